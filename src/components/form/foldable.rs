@@ -0,0 +1,288 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use leptos::*;
+
+use crate::{
+    components::icon::IconArrowRight,
+    core::form::{Expression, FormValue},
+};
+
+use super::FormElement;
+
+const FOLD_THRESHOLD: usize = 6;
+const SUMMARY_MAX_LEN: usize = 40;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenClass {
+    Keyword,
+    String,
+    Number,
+    Function,
+    Operator,
+    Text,
+}
+
+#[component]
+pub fn Foldable(element: FormElement, children: Children) -> impl IntoView {
+    let value = create_memo(move |_| element.data.get().values.get(element.id).cloned());
+    let collapsed = create_rw_signal(
+        value
+            .get_untracked()
+            .is_some_and(|value| value.lines().len() > FOLD_THRESHOLD),
+    );
+    let summary = create_memo(move |_| value.get().map(|value| value.summary()).unwrap_or_default());
+    let lines = create_memo(move |_| value.get().map(|value| value.lines()).unwrap_or_default());
+
+    view! {
+        <div class="space-y-2">
+            <button
+                type="button"
+                class="inline-flex items-center gap-x-1 text-xs font-medium text-gray-500 hover:text-blue-600 dark:text-gray-400"
+                on:click=move |_| collapsed.update(|collapsed| *collapsed = !*collapsed)
+            >
+                <IconArrowRight
+                    size=12
+                    attr:class=move || {
+                        if collapsed.get() {
+                            "flex-shrink-0 size-3 transition-transform"
+                        } else {
+                            "flex-shrink-0 size-3 transition-transform rotate-90"
+                        }
+                    }
+                />
+                {move || if collapsed.get() { summary.get() } else { "Collapse".to_string() }}
+            </button>
+            <div
+                class="rounded-lg border border-gray-200 bg-gray-50 py-2 text-xs font-mono dark:bg-slate-800 dark:border-gray-700"
+                class:hidden=move || !collapsed.get() || lines.get().is_empty()
+                on:click=move |_| collapsed.set(false)
+            >
+                {move || {
+                    lines
+                        .get()
+                        .into_iter()
+                        .enumerate()
+                        .map(|(num, line)| {
+                            view! {
+                                <div class="flex">
+                                    <span class="w-8 pe-2 text-end text-gray-400 select-none">
+                                        {num + 1}
+                                    </span>
+                                    <span class="whitespace-pre-wrap break-all">
+                                        {highlight(&line)
+                                            .into_iter()
+                                            .map(|(class, token)| {
+                                                view! { <span class=class.css()>{token}</span> }
+                                            })
+                                            .collect_view()}
+                                    </span>
+                                </div>
+                            }
+                        })
+                        .collect_view()
+                }}
+
+            </div>
+            <div class:hidden=move || collapsed.get()>{children()}</div>
+        </div>
+    }
+}
+
+impl FormValue {
+    /// Returns a compact, single line description of the value.
+    pub fn summary(&self) -> String {
+        match self {
+            FormValue::Value(value) => truncate(value),
+            FormValue::Array(values) => match values.len() {
+                0 => "No items".to_string(),
+                1 => format!("1 item: {}", truncate(&values[0])),
+                total => format!("{total} items: {}, …", truncate(&values[0])),
+            },
+            FormValue::Expression(expr) => expr.summary(),
+        }
+    }
+
+    /// Returns the value as the lines shown in the folded view.
+    pub fn lines(&self) -> Vec<String> {
+        match self {
+            FormValue::Value(value) => vec![value.clone()],
+            FormValue::Array(values) => values.clone(),
+            FormValue::Expression(expr) => expr
+                .if_thens
+                .iter()
+                .flat_map(|if_then| {
+                    [
+                        format!("if {}", if_then.if_),
+                        format!("then {}", if_then.then_),
+                    ]
+                })
+                .chain(std::iter::once(format!("else {}", expr.else_)))
+                .collect(),
+        }
+    }
+}
+
+impl Expression {
+    pub fn summary(&self) -> String {
+        match self.if_thens.len() {
+            0 => truncate(&self.else_),
+            1 => format!("1 condition, else: {}", truncate(&self.else_)),
+            total => format!("{total} conditions, else: {}", truncate(&self.else_)),
+        }
+    }
+}
+
+impl TokenClass {
+    pub fn css(&self) -> &'static str {
+        match self {
+            TokenClass::Keyword => "text-purple-600 dark:text-purple-400 font-semibold",
+            TokenClass::String => "text-green-700 dark:text-green-400",
+            TokenClass::Number => "text-orange-600 dark:text-orange-400",
+            TokenClass::Function => "text-blue-600 dark:text-blue-400",
+            TokenClass::Operator => "text-gray-500",
+            TokenClass::Text => "text-gray-800 dark:text-gray-200",
+        }
+    }
+}
+
+/// Splits a line into lightly classified tokens for highlighting.
+pub fn highlight(line: &str) -> Vec<(TokenClass, String)> {
+    let mut tokens: Vec<(TokenClass, String)> = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        let (class, token) = match ch {
+            '"' | '\'' => {
+                let mut token = ch.to_string();
+                let mut escaped = false;
+                for next in chars.by_ref() {
+                    token.push(next);
+                    if next == ch && !escaped {
+                        break;
+                    }
+                    escaped = next == '\\' && !escaped;
+                }
+                (TokenClass::String, token)
+            }
+            '0'..='9' => {
+                let mut token = ch.to_string();
+                while let Some(next) = chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+                    token.push(next);
+                }
+                (TokenClass::Number, token)
+            }
+            'a'..='z' | 'A'..='Z' | '_' => {
+                let mut token = ch.to_string();
+                while let Some(next) =
+                    chars.next_if(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+                {
+                    token.push(next);
+                }
+                let class = if matches!(token.as_str(), "if" | "then" | "else") && tokens.is_empty()
+                {
+                    TokenClass::Keyword
+                } else if chars.peek() == Some(&'(') {
+                    TokenClass::Function
+                } else {
+                    TokenClass::Text
+                };
+                (class, token)
+            }
+            '=' | '!' | '<' | '>' | '&' | '|' | '+' | '-' | '*' | '/' | '(' | ')' | ',' => {
+                (TokenClass::Operator, ch.to_string())
+            }
+            _ => (TokenClass::Text, ch.to_string()),
+        };
+
+        match tokens.last_mut() {
+            Some((last_class, last)) if *last_class == class && class != TokenClass::Keyword => {
+                last.push_str(&token);
+            }
+            _ => tokens.push((class, token)),
+        }
+    }
+
+    tokens
+}
+
+fn truncate(value: &str) -> String {
+    if value.chars().count() > SUMMARY_MAX_LEN {
+        format!(
+            "{}…",
+            value.chars().take(SUMMARY_MAX_LEN).collect::<String>()
+        )
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::form::ExpressionIfThen;
+
+    fn expression(conditions: usize, else_: &str) -> Expression {
+        Expression {
+            if_thens: (0..conditions)
+                .map(|idx| ExpressionIfThen {
+                    if_: format!("rcpt_domain == 'example{idx}.org'"),
+                    then_: "true".to_string(),
+                })
+                .collect(),
+            else_: else_.to_string(),
+        }
+    }
+
+    #[test]
+    fn expression_summary() {
+        assert_eq!(expression(5, "false").summary(), "5 conditions, else: false");
+        assert_eq!(expression(1, "'relay'").summary(), "1 condition, else: 'relay'");
+        assert_eq!(expression(0, "10m").summary(), "10m");
+        assert_eq!(
+            expression(2, &"x".repeat(50)).summary(),
+            format!("2 conditions, else: {}…", "x".repeat(SUMMARY_MAX_LEN))
+        );
+    }
+
+    #[test]
+    fn array_summary_and_lines() {
+        let value = FormValue::Array(vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(value.summary(), "2 items: a, …");
+        assert_eq!(FormValue::Array(vec![]).summary(), "No items");
+
+        let value = FormValue::Expression(expression(1, "false"));
+        assert_eq!(
+            value.lines(),
+            vec![
+                "if rcpt_domain == 'example0.org'",
+                "then true",
+                "else false"
+            ]
+        );
+    }
+
+    #[test]
+    fn highlight_tokens() {
+        assert_eq!(
+            highlight("if is_local_domain('', rcpt) == 10"),
+            vec![
+                (TokenClass::Keyword, "if".to_string()),
+                (TokenClass::Text, " ".to_string()),
+                (TokenClass::Function, "is_local_domain".to_string()),
+                (TokenClass::Operator, "(".to_string()),
+                (TokenClass::String, "''".to_string()),
+                (TokenClass::Operator, ",".to_string()),
+                (TokenClass::Text, " rcpt".to_string()),
+                (TokenClass::Operator, ")".to_string()),
+                (TokenClass::Text, " ".to_string()),
+                (TokenClass::Operator, "==".to_string()),
+                (TokenClass::Text, " ".to_string()),
+                (TokenClass::Number, "10".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,421 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use leptos::*;
+
+use crate::components::icon::*;
+
+pub struct IconEntry {
+    pub id: &'static str,
+    pub render: fn() -> View,
+}
+
+impl PartialEq for IconEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+pub static ICON_REGISTRY: &[IconEntry] = &[
+    IconEntry {
+        id: "trash",
+        render: || view! { <IconTrash attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "add",
+        render: || view! { <IconAdd attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "cancel",
+        render: || view! { <IconCancel attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "exclamation-circle",
+        render: || view! { <IconExclamationCircle attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "exclamation-triangle",
+        render: || {
+            view! { <IconExclamationTriangle attr:class="flex-shrink-0 size-4"/> }.into_view()
+        },
+    },
+    IconEntry {
+        id: "refresh",
+        render: || view! { <IconRefresh attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "launch",
+        render: || view! { <IconLaunch attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "envelope",
+        render: || view! { <IconEnvelope attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "clock",
+        render: || view! { <IconClock attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "scale",
+        render: || view! { <IconScale attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "id",
+        render: || view! { <IconId attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "bell",
+        render: || view! { <IconBell attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "alert-triangle",
+        render: || view! { <IconAlertTriangle attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "check-circle",
+        render: || view! { <IconCheckCircle attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "x-mark",
+        render: || view! { <IconXMark attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "arrow-right-circle",
+        render: || view! { <IconArrowRightCircle attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "shield-check",
+        render: || view! { <IconShieldCheck attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "shield-exclamation",
+        render: || view! { <IconShieldExclamation attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "arrow-left",
+        render: || view! { <IconArrowLeft attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "arrow-right",
+        render: || view! { <IconArrowRight attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "document-chart-bar",
+        render: || view! { <IconDocumentChartBar attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "document-text",
+        render: || view! { <IconDocumentText attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "document-magnifying-glass",
+        render: || {
+            view! { <IconDocumentMagnifyingGlass attr:class="flex-shrink-0 size-4"/> }.into_view()
+        },
+    },
+    IconEntry {
+        id: "info",
+        render: || view! { <IconInfo attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "user-group",
+        render: || view! { <IconUserGroup attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "queue-list",
+        render: || view! { <IconQueueList attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "circle-stack",
+        render: || view! { <IconCircleStack attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "plus",
+        render: || view! { <IconPlus attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "code-bracket",
+        render: || view! { <IconCodeBracket attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "server-stack",
+        render: || view! { <IconServerStack attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "paper-airplane",
+        render: || view! { <IconPaperAirplane attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "variable",
+        render: || view! { <IconVariable attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "key",
+        render: || view! { <IconKey attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "inbox",
+        render: || view! { <IconInbox attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "inbox-arrow-down",
+        render: || view! { <IconInboxArrowDown attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "inbox-stack",
+        render: || view! { <IconInboxStack attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "lock-closed",
+        render: || view! { <IconLockClosed attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "adjustments-horizontal",
+        render: || {
+            view! { <IconAdjustmentsHorizontal attr:class="flex-shrink-0 size-4"/> }.into_view()
+        },
+    },
+    IconEntry {
+        id: "user-circle",
+        render: || view! { <IconUserCircle attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "server",
+        render: || view! { <IconServer attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "power",
+        render: || view! { <IconPower attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "computer-desktop",
+        render: || view! { <IconComputerDesktop attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "wrench",
+        render: || view! { <IconWrench attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "heart",
+        render: || view! { <IconHeart attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "square-2x2",
+        render: || view! { <IconSquare2x2 attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "arrow-uturn-left",
+        render: || view! { <IconArrowUTurnLeft attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "three-dots",
+        render: || view! { <IconThreeDots attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "chat-bubble-bottom",
+        render: || view! { <IconChatBubbleBottom attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "chart-bar-square",
+        render: || view! { <IconChartBarSquare attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "building-office",
+        render: || view! { <IconBuildingOffice attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "wifi",
+        render: || view! { <IconWifi attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "phone",
+        render: || view! { <IconPhone attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "phone-arrow-down",
+        render: || view! { <IconPhoneArrowDown attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "no-symbol",
+        render: || view! { <IconNoSymbol attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "signal",
+        render: || view! { <IconSignal attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "hand-raised",
+        render: || view! { <IconHandRaised attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "beaker",
+        render: || view! { <IconBeaker attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "pause-circle",
+        render: || view! { <IconPauseCircle attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "play-circle",
+        render: || view! { <IconPlayCircle attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+    IconEntry {
+        id: "bookmark",
+        render: || view! { <IconBookmark attr:class="flex-shrink-0 size-4"/> }.into_view(),
+    },
+];
+
+pub fn resolve_icon(id: &str) -> Option<&'static IconEntry> {
+    ICON_REGISTRY.iter().find(|icon| icon.id == id)
+}
+
+/// Returns the icons whose identifier contains all the words in `filter`.
+pub fn filter_icons(filter: &str) -> Vec<&'static IconEntry> {
+    let filter = filter.trim().to_lowercase();
+    ICON_REGISTRY
+        .iter()
+        .filter(|icon| {
+            filter
+                .split(|c: char| c.is_whitespace() || c == '-')
+                .filter(|word| !word.is_empty())
+                .all(|word| icon.id.contains(word))
+        })
+        .collect()
+}
+
+#[component]
+pub fn IconPicker(
+    selected: RwSignal<Option<&'static str>>,
+    #[prop(optional, into)] disabled: MaybeSignal<bool>,
+) -> impl IntoView {
+    let filter = create_rw_signal(String::new());
+    let active = create_rw_signal(0usize);
+    let icons = create_memo(move |_| filter_icons(&filter.get()));
+    let select = move |idx: usize| {
+        if let Some(icon) = icons.get_untracked().get(idx) {
+            selected.set(Some(icon.id));
+        }
+    };
+
+    view! {
+        <div class="space-y-2">
+            <input
+                type="text"
+                role="combobox"
+                aria-label="Search icons"
+                aria-expanded="true"
+                class="py-2 px-3 block w-full border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                placeholder="Search icons"
+                disabled=move || disabled.get()
+                prop:value=move || filter.get()
+                on:input=move |ev| {
+                    filter.set(event_target_value(&ev));
+                    active.set(0);
+                }
+                on:keydown=move |ev| {
+                    let total = icons.get_untracked().len();
+                    match ev.key().as_str() {
+                        "ArrowRight" | "ArrowDown" if total > 0 => {
+                            ev.prevent_default();
+                            active.update(|idx| *idx = (*idx + 1) % total);
+                        }
+                        "ArrowLeft" | "ArrowUp" if total > 0 => {
+                            ev.prevent_default();
+                            active.update(|idx| *idx = (*idx + total - 1) % total);
+                        }
+                        "Enter" => {
+                            ev.prevent_default();
+                            select(active.get_untracked());
+                        }
+                        _ => {}
+                    }
+                }
+            />
+            <div role="listbox" class="grid grid-cols-8 gap-1 max-h-48 overflow-y-auto">
+                {move || {
+                    icons
+                        .get()
+                        .into_iter()
+                        .enumerate()
+                        .map(|(idx, icon)| {
+                            let id = icon.id;
+                            view! {
+                                <button
+                                    type="button"
+                                    role="option"
+                                    title=id
+                                    aria-label=id
+                                    aria-selected=move || {
+                                        (selected.get() == Some(id)).to_string()
+                                    }
+                                    class="flex justify-center items-center p-2 rounded-lg border text-gray-800 hover:bg-gray-100 focus:outline-none focus:ring-2 focus:ring-blue-500 dark:text-gray-200 dark:hover:bg-gray-800"
+                                    class:border-blue-600=move || selected.get() == Some(id)
+                                    class:ring-2=move || active.get() == idx
+                                    class:border-transparent=move || selected.get() != Some(id)
+                                    disabled=move || disabled.get()
+                                    on:click=move |_| {
+                                        active.set(idx);
+                                        select(idx);
+                                    }
+                                >
+                                    {(icon.render)()}
+                                </button>
+                            }
+                        })
+                        .collect_view()
+                }}
+
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_icon_registry() {
+        let ids = |filter: &str| {
+            filter_icons(filter)
+                .iter()
+                .map(|icon| icon.id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids("").len(), ICON_REGISTRY.len());
+        assert_eq!(
+            ids("inbox"),
+            vec!["inbox", "inbox-arrow-down", "inbox-stack"]
+        );
+        assert_eq!(
+            ids("Arrow Down"),
+            vec!["inbox-arrow-down", "phone-arrow-down"]
+        );
+        assert_eq!(ids("shield-check"), vec!["shield-check"]);
+        assert!(ids("does-not-exist").is_empty());
+    }
+
+    #[test]
+    fn resolve_selected_icon() {
+        let icon = resolve_icon("lock-closed").unwrap();
+        assert_eq!(icon.id, "lock-closed");
+        assert!(std::ptr::eq(
+            icon,
+            &ICON_REGISTRY[icon_index("lock-closed")]
+        ));
+        assert!(resolve_icon("unknown").is_none());
+
+        // Identifiers are unique so every entry resolves back to itself
+        for (idx, icon) in ICON_REGISTRY.iter().enumerate() {
+            assert_eq!(icon_index(icon.id), idx);
+        }
+    }
+
+    fn icon_index(id: &str) -> usize {
+        ICON_REGISTRY.iter().position(|icon| icon.id == id).unwrap()
+    }
+}
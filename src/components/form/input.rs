@@ -4,12 +4,12 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::str::FromStr;
-
 use leptos::*;
 
 use crate::components::icon::{IconClock, IconExclamationCircle, IconInfo};
 
+pub use crate::core::duration::{Duration, Rate};
+
 use super::FormElement;
 
 #[component]
@@ -728,150 +728,3 @@ pub fn TextArea(
         }}
     }
 }
-
-#[derive(Default, PartialEq, Eq, Clone)]
-pub struct Duration {
-    pub value: String,
-    pub unit: String,
-}
-
-impl FromStr for Duration {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut duration = Duration::default();
-
-        for c in s.chars() {
-            match c {
-                '0'..='9' => duration.value.push(c),
-                'a'..='z' | 'A'..='Z' => duration.unit.push(c.to_ascii_lowercase()),
-                c => {
-                    if !c.is_ascii_whitespace() {
-                        return Err(());
-                    }
-                }
-            }
-        }
-
-        if !duration.value.is_empty()
-            && ["ms", "s", "m", "h", "d"].contains(&duration.unit.as_str())
-        {
-            Ok(duration)
-        } else {
-            Err(())
-        }
-    }
-}
-
-impl std::fmt::Display for Duration {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if !self.value.is_empty() && !self.unit.is_empty() {
-            write!(f, "{}{}", self.value, self.unit)
-        } else {
-            write!(f, "")
-        }
-    }
-}
-
-#[derive(Default, PartialEq, Eq, Clone)]
-pub struct Rate {
-    pub amount: String,
-    pub period: Duration,
-}
-
-impl FromStr for Rate {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (amount_, period) = s.split_once('/').ok_or(())?;
-        let mut amount = String::with_capacity(amount_.len());
-
-        for c in amount_.chars() {
-            if c.is_ascii_digit() {
-                amount.push(c);
-            } else if !c.is_ascii_whitespace() {
-                return Err(());
-            }
-        }
-
-        if !amount.is_empty() {
-            Ok(Rate {
-                amount,
-                period: period.parse()?,
-            })
-        } else {
-            Err(())
-        }
-    }
-}
-
-impl std::fmt::Display for Rate {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if !self.amount.is_empty() && !self.period.value.is_empty() {
-            write!(f, "{}/{}", self.amount, self.period)
-        } else {
-            write!(f, "")
-        }
-    }
-}
-
-impl Duration {
-    pub fn value(mut self, value: u64) -> Self {
-        self.value = value.to_string();
-        self
-    }
-
-    pub fn format(&self) -> Option<String> {
-        if !self.value.is_empty() && !self.unit.is_empty() {
-            Some(format!(
-                "{} {}{}",
-                self.value,
-                match self.unit.as_str() {
-                    "ms" => "millisecond",
-                    "s" => "second",
-                    "m" => "minute",
-                    "h" => "hour",
-                    "d" => "day",
-                    _ => "",
-                },
-                if self.value == "1" { "" } else { "s" }
-            ))
-        } else {
-            None
-        }
-    }
-}
-
-impl Rate {
-    pub fn amount(mut self, amount: u64) -> Self {
-        self.amount = amount.to_string();
-        self
-    }
-
-    pub fn duration_value(mut self, value: u64) -> Self {
-        self.period.value = value.to_string();
-        self
-    }
-
-    pub fn format(&self) -> Option<String> {
-        if !self.amount.is_empty() && !self.period.value.is_empty() && !self.period.unit.is_empty()
-        {
-            Some(format!(
-                "{} every {} {}{}",
-                self.amount,
-                self.period.value,
-                match self.period.unit.as_str() {
-                    "ms" => "millisecond",
-                    "s" => "second",
-                    "m" => "minute",
-                    "h" => "hour",
-                    "d" => "day",
-                    _ => "",
-                },
-                if self.period.value == "1" { "" } else { "s" }
-            ))
-        } else {
-            None
-        }
-    }
-}
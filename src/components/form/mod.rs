@@ -6,6 +6,8 @@
 
 pub mod button;
 pub mod expression;
+pub mod foldable;
+pub mod icon_picker;
 pub mod input;
 pub mod select;
 pub mod stacked_badge;
@@ -13,9 +15,13 @@ pub mod stacked_input;
 pub mod tab;
 
 use leptos::*;
+use web_sys::wasm_bindgen::JsCast;
 
 use crate::{
-    components::{icon::IconInfo, messages::alert::Alerts},
+    components::{
+        icon::{IconBookOpen, IconInfo},
+        messages::alert::Alerts,
+    },
     core::form::FormData,
 };
 
@@ -95,11 +101,32 @@ pub fn FormSection(
 pub fn FormItem(
     #[prop(into)] label: MaybeSignal<String>,
     #[prop(optional)] tooltip: Option<&'static str>,
+    #[prop(optional_no_strip)] doc_url: Option<String>,
+    #[prop(optional_no_strip)] note: Option<&'static str>,
     #[prop(optional, into)] hide: MaybeSignal<bool>,
     #[prop(optional, into)] is_optional: MaybeSignal<bool>,
+    #[prop(optional, into)] modified: MaybeSignal<bool>,
+    #[prop(optional, into)] tab_index: Option<Signal<i32>>,
     #[prop(optional)] stacked: bool,
     children: Children,
 ) -> impl IntoView {
+    let doc_link = doc_url.map(|url| {
+        view! {
+            <a
+                href=url
+                target="_blank"
+                rel="noopener noreferrer"
+                class="inline-block ms-1"
+                title="Documentation"
+            >
+                <IconBookOpen
+                    size=16
+                    attr:class="inline-block size-3 text-gray-400 hover:text-blue-600 dark:text-gray-600 dark:hover:text-blue-500"
+                />
+            </a>
+        }
+    });
+
     let tooltip = tooltip
     .filter(|s| !s.is_empty())
     .map(|tooltip| {
@@ -137,6 +164,10 @@ pub fn FormItem(
         }
     });
 
+    let note = note.map(|note| {
+        view! { <p class="mt-2 text-xs text-yellow-700 dark:text-yellow-500">{note}</p> }
+    });
+
     let is_optional = move || {
         if is_optional.get() {
             Some(
@@ -147,6 +178,38 @@ pub fn FormItem(
         }
     };
 
+    // Apply the tab index to every focusable control inside the item
+    let controls = create_node_ref::<html::Div>();
+    if let Some(tab_index) = tab_index {
+        create_effect(move |_| {
+            let tab_index = tab_index.get().to_string();
+            let Some(controls) = controls.get() else {
+                return;
+            };
+            if let Ok(elements) =
+                controls.query_selector_all("input, select, textarea, button, a[href]")
+            {
+                for element in (0..elements.length())
+                    .filter_map(|i| elements.item(i))
+                    .filter_map(|node| node.dyn_into::<web_sys::Element>().ok())
+                {
+                    let _ = element.set_attribute("tabindex", &tab_index);
+                }
+            }
+        });
+    }
+
+    let modified = move || {
+        modified.get().then(|| {
+            view! {
+                <span
+                    class="inline-block ms-1.5 size-1.5 rounded-full bg-blue-600 align-middle dark:bg-blue-500"
+                    title="Modified"
+                ></span>
+            }
+        })
+    };
+
     if !stacked {
         view! {
             <div class="sm:col-span-3" class:hidden=move || hide.get()>
@@ -154,11 +217,14 @@ pub fn FormItem(
                     {label}
                 </label>
                 {tooltip}
+                {doc_link}
                 {is_optional}
+                {modified}
 
             </div>
-            <div class="sm:col-span-9" class:hidden=move || hide.get()>
+            <div class="sm:col-span-9" class:hidden=move || hide.get() node_ref=controls>
                 {children()}
+                {note}
             </div>
         }
         .into_view()
@@ -166,7 +232,10 @@ pub fn FormItem(
         view! {
             <div class="mb-4 sm:mb-8" class:hidden=move || hide.get()>
                 <label class="block mb-2 text-sm font-medium dark:text-white">{label}</label>
-                <div class="relative">{children()}</div>
+                <div class="relative" node_ref=controls>
+                    {children()}
+                </div>
+                {note}
             </div>
         }
         .into_view()
@@ -9,6 +9,7 @@ use leptos::*;
 use crate::{
     components::icon::{IconPlus, IconXMark},
     core::form::FormErrorType,
+    pages::config::autocomplete::filter_suggestions,
 };
 
 use super::FormElement;
@@ -18,6 +19,7 @@ pub fn StackedInput(
     add_button_text: String,
     element: FormElement,
     #[prop(optional, into)] placeholder: Option<MaybeSignal<String>>,
+    #[prop(optional, into)] suggestions: Option<Signal<Vec<String>>>,
 ) -> impl IntoView {
     let values = create_memo(move |_| {
         let data = element.data.get();
@@ -40,6 +42,21 @@ pub fn StackedInput(
             })
             .collect::<Vec<_>>()
     });
+    let typed = create_rw_signal(String::new());
+    let datalist_id = suggestions.map(|_| format!("{}-suggestions", element.id));
+    let datalist = datalist_id.clone();
+    let matches = create_memo(move |_| {
+        suggestions
+            .map(|suggestions| {
+                let existing = values
+                    .get()
+                    .into_iter()
+                    .map(|(_, value, _)| value)
+                    .collect::<Vec<_>>();
+                filter_suggestions(&suggestions.get(), &typed.get(), &existing)
+            })
+            .unwrap_or_default()
+    });
     let error = create_memo(move |_| {
         element.data.get().error(element.id).and_then(|e| {
             if e.id == FormErrorType::None {
@@ -81,6 +98,8 @@ pub fn StackedInput(
 
                                     prop:value=item
                                     placeholder=placeholder.clone().map(|p| move || p.get())
+                                    list=datalist_id.clone()
+                                    on:input=move |ev| typed.set(event_target_value(&ev))
                                     on:change=move |ev| {
                                         element
                                             .data
@@ -114,6 +133,22 @@ pub fn StackedInput(
                 }
             />
 
+            {datalist
+                .map(|id| {
+                    view! {
+                        <datalist id=id>
+                            {move || {
+                                matches
+                                    .get()
+                                    .into_iter()
+                                    .map(|value| view! { <option value=value></option> })
+                                    .collect_view()
+                            }}
+
+                        </datalist>
+                    }
+                })}
+
         </div>
 
         <p class="mt-3 text-end">
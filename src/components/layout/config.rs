@@ -1,10 +1,14 @@
 use leptos::*;
 use leptos_meta::*;
 use leptos_router::*;
-use web_sys::{FileReader, HtmlInputElement};
+use web_sys::{FileReader, HtmlImageElement, HtmlInputElement};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use js_sys::JsString;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+use base64::Engine;
 use gloo_storage::{LocalStorage, Storage};
 use serde::{Deserialize, Serialize};
 use crate::components::icon::{
@@ -17,15 +21,25 @@ use crate::components::icon::{
     IconArrowPath,
     IconSpinner,
 };
-use crate::utils::storage::LocalStorage;
-use crate::utils::validation::{validate_url, sanitize_input};
-use crate::utils::security::{generate_csrf_token, validate_csrf_token, check_rate_limit};
-use crate::utils::audit::{log_audit, AuditAction};
+use crate::utils::validation::{
+    validate_url, validate_data_url_size, safe_image_src, validate_file_type, sanitize_input,
+    set_strict_security, is_strict_security,
+};
+use crate::utils::security::{
+    generate_csrf_token, validate_csrf_token, check_rate_limit, rate_limit_snapshot,
+    csrf_token_count,
+};
+use crate::utils::audit::{export_audit_logs_between, log_audit, AuditAction};
+use crate::utils::clock;
+use crate::utils::logging::TARGET_CONFIG;
 
 const LAYOUT_CONFIG_KEY: &str = "layout_config";
+const STRICT_SECURITY_KEY: &str = "layout_strict_security";
 const MAX_TITLE_LENGTH: usize = 100;
 const MAX_FILE_SIZE: usize = 5 * 1024 * 1024; // 5MB
-const ALLOWED_IMAGE_TYPES: [&str; 4] = ["image/jpeg", "image/png", "image/svg+xml", "image/gif"];
+// base64 编码后约增大 1/3，需保证 data URL 能写入 LocalStorage
+const MAX_LOGO_DATA_URL_BYTES: usize = 3 * 1024 * 1024; // 3MB
+const LOGO_LOAD_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LayoutConfig {
@@ -33,11 +47,13 @@ pub struct LayoutConfig {
     pub title: String,
 }
 
+const DEFAULT_TITLE: &str = "Stalwart Management";
+
 impl Default for LayoutConfig {
     fn default() -> Self {
         Self {
             logo_url: "/logo.svg".to_string(),
-            title: "Stalwart Management".to_string(),
+            title: DEFAULT_TITLE.to_string(),
         }
     }
 }
@@ -48,7 +64,22 @@ impl LayoutConfig {
     }
 
     fn save(&self) {
-        LocalStorage::set(LAYOUT_CONFIG_KEY, self).unwrap();
+        if let Err(err) = LocalStorage::set(LAYOUT_CONFIG_KEY, self) {
+            log::error!(target: TARGET_CONFIG, "Failed to save layout config: error={err}");
+        }
+    }
+
+    /// 浏览器标签页标题，未配置时使用默认标题
+    pub fn document_title(&self) -> &str {
+        match self.title.trim() {
+            "" => DEFAULT_TITLE,
+            title => title,
+        }
+    }
+
+    /// 可安全绑定到 `<img src>` 的 logo 地址，不合法时退回默认 logo
+    pub fn logo_src(&self) -> String {
+        safe_image_src(&self.logo_url).unwrap_or_else(|| Self::default().logo_url)
     }
 
     fn validate(&self) -> Result<(), String> {
@@ -61,13 +92,136 @@ impl LayoutConfig {
         if !self.logo_url.is_empty() && !validate_url(&self.logo_url) {
             return Err("Invalid logo URL".to_string());
         }
+        if !validate_data_url_size(&self.logo_url, MAX_LOGO_DATA_URL_BYTES) {
+            return Err(logo_too_large());
+        }
         Ok(())
     }
 }
 
+fn logo_too_large() -> String {
+    format!(
+        "Embedded logo must be less than {}MB",
+        MAX_LOGO_DATA_URL_BYTES / 1024 / 1024
+    )
+}
+
+/// 保存前加载 logo 的结果
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogoProbe {
+    Loaded,
+    Failed,
+    TimedOut,
+}
+
+impl LogoProbe {
+    /// 只有 logo 成功加载时才允许保存
+    pub fn commit_decision(self) -> Result<(), String> {
+        match self {
+            LogoProbe::Loaded => Ok(()),
+            LogoProbe::Failed => {
+                Err("The logo could not be loaded, please check that the URL points to an image".to_string())
+            }
+            LogoProbe::TimedOut => Err(format!(
+                "The logo did not load within {} seconds",
+                LOGO_LOAD_TIMEOUT.as_secs()
+            )),
+        }
+    }
+}
+
+/// 解码 data URL 验证其内容，base64 无法解码或内容为空时视为失败
+fn probe_data_url(url: &str) -> Option<LogoProbe> {
+    let (header, payload) = url.strip_prefix("data:")?.split_once(',')?;
+    let is_valid = if header.ends_with(";base64") {
+        base64::engine::general_purpose::STANDARD
+            .decode(payload.trim())
+            .is_ok_and(|data| !data.is_empty())
+    } else {
+        !payload.is_empty()
+    };
+    Some(if is_valid {
+        LogoProbe::Loaded
+    } else {
+        LogoProbe::Failed
+    })
+}
+
+/// 保存前在内存中加载 logo，超时视为失败；data URL 直接解码验证
+pub fn probe_logo(url: &str, on_done: impl FnOnce(LogoProbe) + 'static) {
+    if let Some(probe) = probe_data_url(url) {
+        on_done(probe);
+        return;
+    }
+    let Ok(image) = HtmlImageElement::new() else {
+        on_done(LogoProbe::Failed);
+        return;
+    };
+
+    let on_done = Rc::new(RefCell::new(Some(on_done)));
+    let finish = move |probe: LogoProbe| {
+        if let Some(on_done) = on_done.borrow_mut().take() {
+            on_done(probe);
+        }
+    };
+
+    let on_load = finish.clone();
+    let on_error = finish.clone();
+    image.set_onload(Some(
+        Closure::once_into_js(move || on_load(LogoProbe::Loaded)).unchecked_ref(),
+    ));
+    image.set_onerror(Some(
+        Closure::once_into_js(move || on_error(LogoProbe::Failed)).unchecked_ref(),
+    ));
+    // 回调只会触发一次，先到先得
+    set_timeout(move || finish(LogoProbe::TimedOut), LOGO_LOAD_TIMEOUT);
+    image.set_src(url);
+}
+
+/// 提供全局共享的布局配置，保存后页头、侧边栏及标签页标题立即更新
+pub fn init_layout_config() {
+    provide_context(create_rw_signal(LayoutConfig::load()));
+}
+
+pub fn use_layout_config() -> RwSignal<LayoutConfig> {
+    expect_context::<RwSignal<LayoutConfig>>()
+}
+
+/// 页头显示的 logo 与标题
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeaderBranding {
+    pub logo_src: String,
+    pub title: String,
+}
+
+pub fn use_header_branding() -> Memo<HeaderBranding> {
+    let config = use_layout_config();
+    create_memo(move |_| {
+        config.with(|config| HeaderBranding {
+            logo_src: config.logo_src(),
+            title: config.document_title().to_string(),
+        })
+    })
+}
+
+/// 在配置加载及变更时同步 `document.title`
+pub fn sync_document_title(config: impl Fn() -> LayoutConfig + 'static) {
+    create_effect(move |_| {
+        document().set_title(config().document_title());
+    });
+}
+
+fn invalid_file_type() -> String {
+    if is_strict_security() {
+        "Invalid file type. Please upload a JPEG, PNG, or GIF image.".to_string()
+    } else {
+        "Invalid file type. Please upload a JPEG, PNG, SVG, or GIF image.".to_string()
+    }
+}
+
 #[component]
 pub fn LayoutConfig() -> impl IntoView {
-    let (config, set_config) = create_signal(LayoutConfig::load());
+    let (config, set_config) = use_layout_config().split();
     let (is_editing, set_is_editing) = create_signal(false);
     let (new_logo_url, set_new_logo_url) = create_signal(config.get().logo_url);
     let (new_title, set_new_title) = create_signal(config.get().title);
@@ -76,7 +230,22 @@ pub fn LayoutConfig() -> impl IntoView {
     let (is_uploading, set_is_uploading) = create_signal(false);
     let (auto_save, set_auto_save) = create_signal(true);
     let (is_saving, set_is_saving) = create_signal(false);
+    let probe_generation = store_value(0u64);
     let (csrf_token, set_csrf_token) = create_signal(generate_csrf_token());
+    let (strict_security, set_strict_security_mode) = create_signal(
+        LocalStorage::get::<bool>(STRICT_SECURITY_KEY).unwrap_or_default(),
+    );
+
+    sync_document_title(move || config.get());
+
+    // 严格安全模式下拒绝 SVG 图片
+    create_effect(move |_| {
+        let enabled = strict_security.get();
+        set_strict_security(enabled);
+        if let Err(e) = LocalStorage::set(STRICT_SECURITY_KEY, enabled) {
+            log::error!(target: TARGET_CONFIG, "Failed to save strict security mode: error={e}");
+        }
+    });
 
     // 自动保存功能
     create_effect(move |_| {
@@ -98,19 +267,36 @@ pub fn LayoutConfig() -> impl IntoView {
                 
                 match new_config.validate() {
                     Ok(_) => {
-                        set_config.set(new_config.clone());
-                        new_config.save();
-                        set_error.set(String::new());
-                        log_audit(
-                            AuditAction::ConfigUpdate,
-                            "user", // 这里应该使用实际的用户名
-                            &format!("Updated layout config: title={}", title),
-                            None, // 这里应该使用实际的IP地址
-                            true,
-                        );
+                        let generation = probe_generation.get_value() + 1;
+                        probe_generation.set_value(generation);
+                        let logo_url = new_config.logo_url.clone();
+                        probe_logo(&logo_url, move |probe| {
+                            // 忽略已被后续修改取代的探测结果
+                            if probe_generation.get_value() != generation {
+                                return;
+                            }
+                            if let Err(e) = probe.commit_decision() {
+                                set_error.set(e);
+                                set_is_saving.set(false);
+                                return;
+                            }
+                            set_config.set(new_config.clone());
+                            new_config.save();
+                            set_error.set(String::new());
+                            set_is_saving.set(false);
+                            log_audit(
+                                AuditAction::ConfigUpdate,
+                                "user", // 这里应该使用实际的用户名
+                                &format!("Updated layout config: title={}", new_config.title),
+                                None, // 这里应该使用实际的IP地址
+                                true,
+                            );
+                        });
+                        return;
                     }
                     Err(e) => {
-                        set_error.set(e);
+                        log::warn!(target: TARGET_CONFIG, "Layout config validation failed: error={e}");
+                        set_error.set(e.clone());
                         log_audit(
                             AuditAction::ConfigUpdate,
                             "user",
@@ -135,7 +321,7 @@ pub fn LayoutConfig() -> impl IntoView {
     // Save config to storage when it changes
     create_effect(move |_| {
         if let Err(e) = LocalStorage::set(LAYOUT_CONFIG_KEY, config.get()) {
-            log::error!("Failed to save layout config: {}", e);
+            log::error!(target: TARGET_CONFIG, "Failed to save layout config: error={e}");
         }
     });
 
@@ -148,13 +334,32 @@ pub fn LayoutConfig() -> impl IntoView {
             set_error.set("Title cannot be empty".to_string());
             return;
         }
+        if !validate_data_url_size(&new_logo_url.get(), MAX_LOGO_DATA_URL_BYTES) {
+            set_error.set(logo_too_large());
+            return;
+        }
         set_error.set(String::new());
-        set_config.set(LayoutConfig {
+        set_is_saving.set(true);
+        let new_config = LayoutConfig {
             logo_url: new_logo_url.get(),
             title: new_title.get(),
+        };
+        let generation = probe_generation.get_value() + 1;
+        probe_generation.set_value(generation);
+        probe_logo(&new_config.logo_url.clone(), move |probe| {
+            if probe_generation.get_value() != generation {
+                return;
+            }
+            set_is_saving.set(false);
+            match probe.commit_decision() {
+                Ok(()) => {
+                    set_config.set(new_config);
+                    set_is_editing.set(false);
+                    set_show_preview.set(false);
+                }
+                Err(e) => set_error.set(e),
+            }
         });
-        set_is_editing.set(false);
-        set_show_preview.set(false);
     };
 
     let handle_preview = move |_| {
@@ -179,8 +384,8 @@ pub fn LayoutConfig() -> impl IntoView {
         let input: HtmlInputElement = event_target(&ev).unwrap().dyn_into().unwrap();
         if let Some(file) = input.files().unwrap().get(0) {
             let file_type = file.type_();
-            if !ALLOWED_IMAGE_TYPES.contains(&file_type.as_str()) {
-                set_error.set("Invalid file type. Please upload a JPEG, PNG, SVG, or GIF image.".to_string());
+            if !validate_file_type(&file_type) {
+                set_error.set(invalid_file_type());
                 log_audit(
                     AuditAction::FileUpload,
                     "user",
@@ -213,6 +418,30 @@ pub fn LayoutConfig() -> impl IntoView {
                 let result = reader.result().unwrap();
                 if let Ok(data_url) = result.dyn_into::<js_sys::JsString>() {
                     let data_url = data_url.as_string().unwrap();
+                    if !validate_url(&data_url) {
+                        cloned_set_error.set(invalid_file_type());
+                        cloned_set_is_uploading.set(false);
+                        log_audit(
+                            AuditAction::FileUpload,
+                            "user",
+                            "Attempted to upload invalid file type",
+                            None,
+                            false,
+                        );
+                        return;
+                    }
+                    if !validate_data_url_size(&data_url, MAX_LOGO_DATA_URL_BYTES) {
+                        cloned_set_error.set(logo_too_large());
+                        cloned_set_is_uploading.set(false);
+                        log_audit(
+                            AuditAction::FileUpload,
+                            "user",
+                            "Attempted to upload logo exceeding data URL size limit",
+                            None,
+                            false,
+                        );
+                        return;
+                    }
                     cloned_set_logo_url.set(data_url);
                     cloned_set_error.set(String::new());
                     log_audit(
@@ -400,7 +629,7 @@ pub fn LayoutConfig() -> impl IntoView {
                                     <div class="space-y-4">
                                         <div class="flex items-center gap-x-3">
                                             <img
-                                                src=move || new_logo_url.get()
+                                                src=move || safe_image_src(&new_logo_url.get())
                                                 class="h-8"
                                                 alt="Logo preview"
                                             />
@@ -425,7 +654,7 @@ pub fn LayoutConfig() -> impl IntoView {
                                 </h3>
                                 <div class="p-4 bg-gray-50 rounded-lg dark:bg-gray-800">
                                     <img
-                                        src=move || config.get().logo_url
+                                        src=move || config.get().logo_src()
                                         class="h-12 mx-auto"
                                         alt="Current logo"
                                     />
@@ -460,9 +689,243 @@ pub fn LayoutConfig() -> impl IntoView {
                                 "Auto-save changes"
                             </label>
                         </div>
+                        <div class="flex items-center gap-x-2 mb-4">
+                            <input
+                                type="checkbox"
+                                id="strict-security"
+                                class="size-4 border-gray-300 rounded text-blue-600 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:checked:bg-blue-500 dark:checked:border-blue-500 dark:focus:ring-offset-gray-800"
+                                checked=strict_security
+                                on:change=move |ev| set_strict_security_mode.set(event_target_checked(&ev))
+                            />
+                            <label for="strict-security" class="text-sm text-gray-600 dark:text-gray-400">
+                                "Strict security (reject SVG logos)"
+                            </label>
+                        </div>
                     </div>
                 </div>
             </div>
         </div>
     }
-} 
\ No newline at end of file
+}
+
+/// 安全调试面板：展示速率限制桶及有效CSRF令牌数量，便于排查滥用情况
+#[component]
+pub fn SecurityDebugPanel() -> impl IntoView {
+    let (buckets, set_buckets) = create_signal(rate_limit_snapshot());
+    let (token_count, set_token_count) = create_signal(csrf_token_count());
+
+    let (export_hours, set_export_hours) = create_signal(24i64);
+
+    let handle_refresh = move |_| {
+        set_buckets.set(rate_limit_snapshot());
+        set_token_count.set(csrf_token_count());
+    };
+
+    // 导出所选时间范围内的审计日志
+    let handle_export = move |_| {
+        let until = clock::now();
+        let since = until - chrono::Duration::hours(export_hours.get_untracked());
+        let url = format!(
+            "data:application/json;charset=utf-8,{}",
+            String::from(js_sys::encode_uri_component(&export_audit_logs_between(
+                since, until
+            )))
+        );
+        let link = leptos::html::a();
+        link.set_href(&url);
+        link.set_download(&format!("audit-{}.json", until.format("%Y%m%d%H%M%S")));
+        link.click();
+    };
+
+    view! {
+        <div class="max-w-3xl mx-auto mt-6">
+            <div class="bg-white shadow-sm rounded-xl dark:bg-slate-900 dark:border-gray-700">
+                <div class="p-4 sm:p-7">
+                    <div class="flex justify-between items-center mb-4">
+                        <h2 class="text-xl font-semibold text-gray-800 dark:text-gray-200">
+                            "Security"
+                        </h2>
+                        <button
+                            class="inline-flex items-center gap-x-2 text-sm font-semibold rounded-lg border border-transparent text-blue-600 hover:text-blue-800 dark:text-blue-500 dark:hover:text-blue-400"
+                            on:click=handle_refresh
+                        >
+                            <IconArrowPath class="size-4"/>
+                            "Refresh"
+                        </button>
+                    </div>
+                    <p class="text-sm text-gray-600 dark:text-gray-400 mb-4">
+                        "Active CSRF tokens: "
+                        {move || token_count.get()}
+                    </p>
+                    <div class="flex items-center gap-x-2 mb-4">
+                        <select
+                            class="py-2 px-3 pe-9 block border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                            on:change=move |ev| {
+                                if let Ok(hours) = event_target_value(&ev).parse() {
+                                    set_export_hours.set(hours);
+                                }
+                            }
+                        >
+                            <option value="1">"Last hour"</option>
+                            <option value="24" selected>"Last 24 hours"</option>
+                            <option value="168">"Last 7 days"</option>
+                        </select>
+                        <button
+                            class="inline-flex items-center gap-x-2 text-sm font-semibold rounded-lg border border-transparent text-blue-600 hover:text-blue-800 dark:text-blue-500 dark:hover:text-blue-400"
+                            on:click=handle_export
+                        >
+                            "Export audit log"
+                        </button>
+                    </div>
+                    <Show
+                        when=move || !buckets.get().is_empty()
+                        fallback=|| {
+                            view! {
+                                <p class="text-sm text-gray-500 dark:text-gray-400">
+                                    "No rate-limited requests in the current window."
+                                </p>
+                            }
+                        }
+                    >
+                        <table class="min-w-full divide-y divide-gray-200 dark:divide-gray-700">
+                            <thead>
+                                <tr>
+                                    <th class="px-3 py-2 text-start text-xs font-semibold uppercase text-gray-800 dark:text-gray-200">
+                                        "Bucket"
+                                    </th>
+                                    <th class="px-3 py-2 text-start text-xs font-semibold uppercase text-gray-800 dark:text-gray-200">
+                                        "Requests"
+                                    </th>
+                                    <th class="px-3 py-2 text-start text-xs font-semibold uppercase text-gray-800 dark:text-gray-200">
+                                        "Last request"
+                                    </th>
+                                </tr>
+                            </thead>
+                            <tbody class="divide-y divide-gray-200 dark:divide-gray-700">
+                                {move || {
+                                    buckets
+                                        .get()
+                                        .into_iter()
+                                        .map(|(identifier, count, last)| {
+                                            view! {
+                                                <tr>
+                                                    <td class="px-3 py-2 text-sm text-gray-800 dark:text-gray-200">
+                                                        {identifier}
+                                                    </td>
+                                                    <td class="px-3 py-2 text-sm text-gray-800 dark:text-gray-200">
+                                                        {count}
+                                                    </td>
+                                                    <td class="px-3 py-2 text-sm text-gray-500 dark:text-gray-400">
+                                                        {last.format("%Y-%m-%d %H:%M:%S UTC").to_string()}
+                                                    </td>
+                                                </tr>
+                                            }
+                                        })
+                                        .collect_view()
+                                }}
+                            </tbody>
+                        </table>
+                    </Show>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_logo_src() {
+        let config = |logo_url: &str| LayoutConfig {
+            logo_url: logo_url.to_string(),
+            title: "Stalwart Management".to_string(),
+        };
+
+        assert_eq!(
+            config("https://example.com/logo.png").logo_src(),
+            "https://example.com/logo.png"
+        );
+        assert_eq!(config("javascript:alert(1)").logo_src(), "/logo.svg");
+        assert_eq!(LayoutConfig::default().logo_src(), "/logo.svg");
+    }
+
+    #[test]
+    fn test_header_reflects_config() {
+        let runtime = create_runtime();
+        provide_context(create_rw_signal(LayoutConfig::default()));
+
+        let branding = use_header_branding();
+        assert_eq!(
+            branding.get(),
+            HeaderBranding {
+                logo_src: "/logo.svg".to_string(),
+                title: "Stalwart Management".to_string(),
+            }
+        );
+
+        use_layout_config().set(LayoutConfig {
+            logo_url: "https://example.com/logo.png".to_string(),
+            title: "Example Mail".to_string(),
+        });
+        assert_eq!(
+            branding.get(),
+            HeaderBranding {
+                logo_src: "https://example.com/logo.png".to_string(),
+                title: "Example Mail".to_string(),
+            }
+        );
+
+        runtime.dispose();
+    }
+
+    #[test]
+    fn test_logo_probe_decision() {
+        assert_eq!(LogoProbe::Loaded.commit_decision(), Ok(()));
+        assert!(LogoProbe::Failed
+            .commit_decision()
+            .unwrap_err()
+            .contains("could not be loaded"));
+        assert_eq!(
+            LogoProbe::TimedOut.commit_decision(),
+            Err("The logo did not load within 5 seconds".to_string())
+        );
+
+        assert_eq!(
+            probe_data_url("data:image/png;base64,aGVsbG8="),
+            Some(LogoProbe::Loaded)
+        );
+        assert_eq!(
+            probe_data_url("data:image/png;base64,not base64!"),
+            Some(LogoProbe::Failed)
+        );
+        assert_eq!(probe_data_url("data:image/png;base64,"), Some(LogoProbe::Failed));
+        assert_eq!(
+            probe_data_url("data:image/svg+xml,%3Csvg%3E%3C/svg%3E"),
+            Some(LogoProbe::Loaded)
+        );
+        assert_eq!(probe_data_url("https://example.com/logo.png"), None);
+
+        let result = Rc::new(Cell::new(None));
+        let captured = result.clone();
+        probe_logo("data:image/png;base64,aGVsbG8=", move |probe| {
+            captured.set(Some(probe))
+        });
+        assert_eq!(result.get(), Some(LogoProbe::Loaded));
+    }
+
+    #[test]
+    fn test_document_title() {
+        let config = |title: &str| LayoutConfig {
+            logo_url: "/logo.svg".to_string(),
+            title: title.to_string(),
+        };
+
+        assert_eq!(config("Example Mail").document_title(), "Example Mail");
+        assert_eq!(config("  Example Mail ").document_title(), "Example Mail");
+        assert_eq!(config("").document_title(), "Stalwart Management");
+        assert_eq!(config("   ").document_title(), "Stalwart Management");
+    }
+}
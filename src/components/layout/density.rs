@@ -0,0 +1,105 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use gloo_storage::{LocalStorage, Storage};
+use leptos::*;
+
+const DENSITY_STORAGE_KEY: &str = "webadmin_density";
+const ROOT_CLASS: &str = "bg-gray-50 dark:bg-slate-900";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Density {
+    Compact,
+    #[default]
+    Comfortable,
+}
+
+pub fn init_density() {
+    let density = create_rw_signal(Density::load());
+
+    create_effect(move |prev: Option<Density>| {
+        let density = density.get();
+        if prev.is_some_and(|prev| prev != density) {
+            density.save();
+        }
+        density
+    });
+
+    provide_context(density);
+}
+
+pub fn use_density() -> RwSignal<Density> {
+    expect_context::<RwSignal<Density>>()
+}
+
+impl Density {
+    pub fn load() -> Self {
+        Self::from_stored(LocalStorage::get::<String>(DENSITY_STORAGE_KEY).ok().as_deref())
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = LocalStorage::set(DENSITY_STORAGE_KEY, self.as_str()) {
+            log::error!("Failed to save density to local storage: {}", err);
+        }
+    }
+
+    /// Parses the stored preference, falling back to the default density
+    /// when it is missing or unknown.
+    pub fn from_stored(value: Option<&str>) -> Self {
+        match value {
+            Some("compact") => Density::Compact,
+            _ => Density::Comfortable,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Density::Compact => "compact",
+            Density::Comfortable => "comfortable",
+        }
+    }
+
+    pub fn toggle(&self) -> Self {
+        match self {
+            Density::Compact => Density::Comfortable,
+            Density::Comfortable => Density::Compact,
+        }
+    }
+
+    /// Returns the classes applied to the document body. Compact spacing is
+    /// implemented by the `density-compact` rules in the stylesheet.
+    pub fn root_class(&self) -> String {
+        format!("{ROOT_CLASS} density-{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persist_density_preference() {
+        for density in [Density::Compact, Density::Comfortable] {
+            assert_eq!(Density::from_stored(Some(density.as_str())), density);
+        }
+        assert_eq!(Density::from_stored(None), Density::Comfortable);
+        assert_eq!(Density::from_stored(Some("tiny")), Density::Comfortable);
+        assert_eq!(Density::Compact.toggle(), Density::Comfortable);
+        assert_eq!(Density::Comfortable.toggle(), Density::Compact);
+    }
+
+    #[test]
+    fn resolve_density_class() {
+        assert_eq!(
+            Density::Compact.root_class(),
+            "bg-gray-50 dark:bg-slate-900 density-compact"
+        );
+        assert_eq!(
+            Density::default().root_class(),
+            "bg-gray-50 dark:bg-slate-900 density-comfortable"
+        );
+    }
+}
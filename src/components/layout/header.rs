@@ -4,41 +4,69 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::sync::Arc;
+
 use gloo_storage::{SessionStorage, Storage};
 use leptos::*;
 use leptos_router::use_navigate;
 use leptos_meta::*;
 
 use crate::{
-    components::icon::{
-        IconAdjustmentsHorizontal, IconHeart, IconPower, IconServer, IconUserCircle,
+    components::{
+        icon::{
+            IconAdjustmentsHorizontal, IconHeart, IconPower, IconQueueList, IconServer,
+            IconShieldExclamation, IconSquare2x2, IconUserCircle,
+        },
+        list::page_size::use_page_sizes,
+    },
+    core::{
+        oauth::use_authorization, schema::Schemas, url::UrlBuilder, AccessToken, Permission,
+        Permissions,
+    },
+    pages::config::{
+        edit::DEFAULT_SETTINGS_URL,
+        search::{quick_results, RecentPage},
+    },
+    utils::telemetry::{
+        is_error_reporting_available, is_error_reporting_enabled, set_error_reporting,
     },
-    core::{oauth::use_authorization, url::UrlBuilder, AccessToken, Permission, Permissions},
-    pages::config::edit::DEFAULT_SETTINGS_URL,
     STATE_STORAGE_KEY, VERSION_NAME,
 };
 use web_sys::wasm_bindgen::JsCast;
-use super::config::LayoutConfig;
+use super::{
+    config::{sync_document_title, use_header_branding, use_layout_config},
+    density::{use_density, Density},
+};
 
 #[component]
 pub fn Header(permissions: Memo<Option<Permissions>>) -> impl IntoView {
     let show_action_dropdown = RwSignal::new(false);
     let show_account_dropdown = RwSignal::new(false);
     let auth_token = use_context::<RwSignal<AccessToken>>().unwrap();
-    let (config, set_config) = create_signal(LayoutConfig::default());
-
-    create_effect(move |_| {
-        if let Ok(stored_config) = LocalStorage::get::<LayoutConfig>("layout_config") {
-            set_config.set(stored_config);
-        }
-    });
+    let branding = use_header_branding();
+    let density = use_density();
+    let page_sizes = use_page_sizes();
+    let error_reporting = create_rw_signal(is_error_reporting_enabled());
+    let schemas = expect_context::<Arc<Schemas>>();
+    let search_text = create_rw_signal(String::new());
+    let search_focused = create_rw_signal(false);
+    let recent_pages = create_rw_signal(Vec::<RecentPage>::new());
+    let search_results =
+        create_memo(move |_| quick_results(&search_text.get(), &recent_pages.get(), &schemas));
+
+    let config = use_layout_config();
+    sync_document_title(move || config.get());
 
     view! {
         <header class="sticky top-0 inset-x-0 flex flex-wrap sm:justify-start sm:flex-nowrap z-[48] w-full bg-white border-b text-sm py-2.5 sm:py-4 lg:ps-64 dark:bg-gray-800 dark:border-gray-700">
             <nav class="flex basis-full items-center w-full mx-auto px-4 sm:px-6 md:px-8">
 
                 <div class="me-5 lg:me-0 lg:hidden">
-                    <img src=move || config.get().logo_url title=VERSION_NAME/>
+                    <img
+                        src=move || branding.get().logo_src
+                        alt=move || branding.get().title
+                        title=VERSION_NAME
+                    />
                 </div>
 
                 <div class="w-full flex items-center justify-end sm:justify-between sm:gap-x-3 sm:order-3">
@@ -92,6 +120,13 @@ pub fn Header(permissions: Memo<Option<Permissions>>) -> impl IntoView {
                                     type="text"
                                     class="py-2 px-4 ps-11 block w-full border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400 dark:focus:ring-gray-600"
                                     placeholder="Search settings"
+                                    prop:value=move || search_text.get()
+                                    on:input=move |ev| search_text.set(event_target_value(&ev))
+                                    on:focus=move |_| {
+                                        recent_pages.set(RecentPage::load());
+                                        search_focused.set(true);
+                                    }
+                                    on:blur=move |_| search_focused.set(false)
                                     on:keyup=move |ev| {
                                         let key_code = ev
                                             .unchecked_ref::<web_sys::KeyboardEvent>()
@@ -100,6 +135,7 @@ pub fn Header(permissions: Memo<Option<Permissions>>) -> impl IntoView {
                                             let filter = event_target_value(&ev);
                                             let query = filter.trim();
                                             if !query.is_empty() {
+                                                search_focused.set(false);
                                                 use_navigate()(
                                                     &UrlBuilder::new("/settings/search")
                                                         .with_parameter("query", query)
@@ -110,6 +146,52 @@ pub fn Header(permissions: Memo<Option<Permissions>>) -> impl IntoView {
                                         }
                                     }
                                 />
+                                <div
+                                    class="absolute z-50 w-full mt-1 bg-white border border-gray-200 rounded-lg shadow-lg max-h-96 overflow-y-auto dark:bg-slate-900 dark:border-gray-700"
+                                    class:hidden=move || {
+                                        !search_focused.get()
+                                            || search_results.with(|results| results.is_empty())
+                                    }
+                                >
+                                    {move || {
+                                        search_results
+                                            .get()
+                                            .into_iter()
+                                            .map(|group| {
+                                                let hits = group
+                                                    .hits
+                                                    .into_iter()
+                                                    .map(|hit| {
+                                                        let url = hit.url;
+                                                        view! {
+                                                            <a
+                                                                class="block py-2 px-4 text-sm text-gray-800 hover:bg-gray-100 dark:text-gray-300 dark:hover:bg-gray-800"
+                                                                href=url.clone()
+                                                                on:mousedown=move |ev| {
+                                                                    // Navigate before the input loses focus and hides the list
+                                                                    ev.prevent_default();
+                                                                    search_focused.set(false);
+                                                                    use_navigate()(&url, Default::default());
+                                                                }
+                                                            >
+                                                                {hit.title}
+                                                            </a>
+                                                        }
+                                                    })
+                                                    .collect_view();
+                                                view! {
+                                                    <div class="py-2">
+                                                        <p class="px-4 pb-1 text-xs uppercase text-gray-500 dark:text-gray-500">
+                                                            {group.source.title()}
+                                                        </p>
+                                                        {hits}
+                                                    </div>
+                                                }
+                                            })
+                                            .collect_view()
+                                    }}
+
+                                </div>
 
                             </div>
                         </div>
@@ -299,6 +381,63 @@ pub fn Header(permissions: Memo<Option<Permissions>>) -> impl IntoView {
                                             <IconUserCircle/>
                                             Account
                                         </a>
+                                        <a
+                                            class="flex items-center gap-x-3.5 py-2 px-3 rounded-lg text-sm text-gray-800 hover:bg-gray-100 focus:outline-none focus:bg-gray-100 dark:text-neutral-400 dark:hover:bg-neutral-700 dark:hover:text-neutral-300 dark:focus:bg-neutral-700 dark:focus:text-neutral-300"
+                                            href="#"
+                                            on:click=move |ev| {
+                                                ev.prevent_default();
+                                                density.update(|density| *density = density.toggle());
+                                            }
+                                        >
+
+                                            <IconSquare2x2/>
+                                            {move || match density.get() {
+                                                Density::Compact => "Comfortable view",
+                                                Density::Comfortable => "Compact view",
+                                            }}
+
+                                        </a>
+                                        <a
+                                            class="flex items-center gap-x-3.5 py-2 px-3 rounded-lg text-sm text-gray-800 hover:bg-gray-100 focus:outline-none focus:bg-gray-100 dark:text-neutral-400 dark:hover:bg-neutral-700 dark:hover:text-neutral-300 dark:focus:bg-neutral-700 dark:focus:text-neutral-300"
+                                            href="#"
+                                            title="Used by lists where no page size was picked yet"
+                                            on:click=move |ev| {
+                                                ev.prevent_default();
+                                                page_sizes.update(|page_sizes| page_sizes.next_default());
+                                            }
+                                        >
+
+                                            <IconQueueList/>
+                                            {move || match page_sizes.get().default {
+                                                Some(size) => format!("Default page size: {size}"),
+                                                None => "Default page size: automatic".to_string(),
+                                            }}
+
+                                        </a>
+                                        <Show when=is_error_reporting_available>
+                                            <a
+                                                class="flex items-center gap-x-3.5 py-2 px-3 rounded-lg text-sm text-gray-800 hover:bg-gray-100 focus:outline-none focus:bg-gray-100 dark:text-neutral-400 dark:hover:bg-neutral-700 dark:hover:text-neutral-300 dark:focus:bg-neutral-700 dark:focus:text-neutral-300"
+                                                href="#"
+                                                title="Send details of interface errors to the server operators"
+                                                on:click=move |ev| {
+                                                    ev.prevent_default();
+                                                    let enabled = !error_reporting.get();
+                                                    set_error_reporting(enabled);
+                                                    error_reporting.set(enabled);
+                                                }
+                                            >
+
+                                                <IconShieldExclamation/>
+                                                {move || {
+                                                    if error_reporting.get() {
+                                                        "Error reporting: on"
+                                                    } else {
+                                                        "Error reporting: off"
+                                                    }
+                                                }}
+
+                                            </a>
+                                        </Show>
                                         <a
                                             class="flex items-center gap-x-3.5 py-2 px-3 rounded-lg text-sm text-gray-800 hover:bg-gray-100 focus:outline-none focus:bg-gray-100 dark:text-neutral-400 dark:hover:bg-neutral-700 dark:hover:text-neutral-300 dark:focus:bg-neutral-700 dark:focus:text-neutral-300"
                                             on:click=move |_| {
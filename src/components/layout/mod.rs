@@ -4,6 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod density;
 pub mod header;
 pub mod sidebar;
 pub mod toggle;
@@ -24,6 +25,7 @@ use crate::{
         schema::{Schema, SchemaType},
         Permissions,
     },
+    pages::config::restart::RestartBanner,
 };
 
 use header::Header;
@@ -79,15 +81,37 @@ pub fn Layout(
     #[prop(into)] permissions: Memo<Option<Permissions>>,
 ) -> impl IntoView {
     let menu_items_toggle = menu_items.clone();
+    let density = density::use_density();
+    let menu_items_siblings = menu_items.clone();
     let show_sidebar = create_rw_signal(false);
+    let location = use_location();
+
+    // Move between sibling sub-pages with Alt+Left and Alt+Right
+    let navigate_siblings = window_event_listener(ev::keydown, move |ev| {
+        let forward = match ev.key().as_str() {
+            "ArrowRight" if ev.alt_key() => true,
+            "ArrowLeft" if ev.alt_key() => false,
+            _ => return,
+        };
+        if let Some(route) = sibling_route(
+            &menu_items_siblings,
+            &location.pathname.get_untracked(),
+            forward,
+        ) {
+            ev.prevent_default();
+            use_navigate()(&route, Default::default());
+        }
+    });
+    on_cleanup(move || navigate_siblings.remove());
 
     view! {
-        <Body class="bg-gray-50 dark:bg-slate-900"/>
+        <Body class=move || density.get().root_class()/>
         <Modal/>
         <Header permissions/>
         <ToggleNavigation menu_items show_sidebar/>
         <SideBar menu_items=menu_items_toggle show_sidebar/>
         <div class="w-full pt-10 px-4 sm:px-6 md:px-8 lg:ps-72">
+            <RestartBanner/>
             <Outlet/>
         </div>
     }
@@ -158,6 +182,41 @@ impl LayoutBuilder {
     }
 }
 
+/// Returns the route of the previous or next sibling of the menu item
+/// matching `current`, wrapping around within its section. Routes of nested
+/// pages, such as the edit page of a listed record, match their parent item.
+pub fn sibling_route(menu_items: &[MenuItem], current: &str, forward: bool) -> Option<String> {
+    let siblings = menu_items
+        .iter()
+        .filter_map(|item| item.route.as_deref())
+        .collect::<Vec<_>>();
+    let position = siblings
+        .iter()
+        .position(|route| *route == current)
+        .or_else(|| {
+            siblings.iter().position(|route| {
+                current
+                    .strip_prefix(route)
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+        });
+
+    match position {
+        Some(idx) if siblings.len() > 1 => {
+            let idx = if forward {
+                (idx + 1) % siblings.len()
+            } else {
+                (idx + siblings.len() - 1) % siblings.len()
+            };
+            Some(siblings[idx].to_string())
+        }
+        Some(_) => None,
+        None => menu_items
+            .iter()
+            .find_map(|item| sibling_route(&item.children, current, forward)),
+    }
+}
+
 impl MenuItem {
     pub fn id(&self) -> String {
         let mut hasher = DefaultHasher::new();
@@ -167,3 +226,87 @@ impl MenuItem {
         hasher.finish().to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str, route: Option<&str>, children: Vec<MenuItem>) -> MenuItem {
+        MenuItem {
+            name: name.to_string(),
+            route: route.map(str::to_string),
+            icon: None,
+            children,
+        }
+    }
+
+    fn menu() -> Vec<MenuItem> {
+        vec![
+            item("Server", Some("/settings/network/edit"), vec![]),
+            item(
+                "SMTP",
+                None,
+                vec![
+                    item(
+                        "Inbound",
+                        None,
+                        vec![
+                            item("Connect", Some("/settings/smtp-in-connect/edit"), vec![]),
+                            item("EHLO", Some("/settings/smtp-in-ehlo/edit"), vec![]),
+                            item("RCPT", Some("/settings/smtp-in-rcpt/edit"), vec![]),
+                        ],
+                    ),
+                    item("Milters", Some("/settings/milter"), vec![]),
+                ],
+            ),
+        ]
+    }
+
+    #[test]
+    fn navigate_between_siblings() {
+        let menu = menu();
+        for (current, forward, expected) in [
+            (
+                "/settings/smtp-in-connect/edit",
+                true,
+                Some("/settings/smtp-in-ehlo/edit"),
+            ),
+            (
+                "/settings/smtp-in-ehlo/edit",
+                false,
+                Some("/settings/smtp-in-connect/edit"),
+            ),
+            // Wraps within the section
+            (
+                "/settings/smtp-in-rcpt/edit",
+                true,
+                Some("/settings/smtp-in-connect/edit"),
+            ),
+            (
+                "/settings/smtp-in-connect/edit",
+                false,
+                Some("/settings/smtp-in-rcpt/edit"),
+            ),
+            // Single routed item in the section
+            ("/settings/milter", true, None),
+            // Nested pages match their parent item
+            ("/settings/milter/rspamd/edit", true, None),
+            ("/settings/unknown/edit", true, None),
+        ] {
+            assert_eq!(
+                sibling_route(&menu, current, forward).as_deref(),
+                expected,
+                "{current} {forward}"
+            );
+        }
+
+        let menu = vec![
+            item("Listeners", Some("/settings/listener"), vec![]),
+            item("TLS", Some("/settings/tls/edit"), vec![]),
+        ];
+        assert_eq!(
+            sibling_route(&menu, "/settings/listener/smtp/edit", true).as_deref(),
+            Some("/settings/tls/edit")
+        );
+    }
+}
@@ -7,8 +7,7 @@
 use leptos::*;
 use leptos_meta::*;
 use leptos_router::*;
-use gloo_storage::{LocalStorage, Storage};
-use super::config::LayoutConfig;
+use super::config::use_header_branding;
 
 use crate::VERSION_NAME;
 
@@ -17,13 +16,7 @@ use super::MenuItem;
 #[component]
 pub fn SideBar(menu_items: Vec<MenuItem>, show_sidebar: RwSignal<bool>) -> impl IntoView {
     let current_route = create_memo(move |_| use_location().pathname.get());
-    let (config, set_config) = create_signal(LayoutConfig::default());
-
-    create_effect(move |_| {
-        if let Ok(stored_config) = LocalStorage::get::<LayoutConfig>("layout_config") {
-            set_config.set(stored_config);
-        }
-    });
+    let branding = use_header_branding();
 
     view! {
         <div
@@ -32,7 +25,12 @@ pub fn SideBar(menu_items: Vec<MenuItem>, show_sidebar: RwSignal<bool>) -> impl
             class:open=move || show_sidebar.get()
         >
             <div class="px-8">
-                <img src=move || config.get().logo_url style="height: 25px;" title=VERSION_NAME/>
+                <img
+                    src=move || branding.get().logo_src
+                    alt=move || branding.get().title
+                    style="height: 25px;"
+                    title=VERSION_NAME
+                />
             </div>
 
             <nav
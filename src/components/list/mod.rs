@@ -5,6 +5,7 @@
  */
 
 pub mod header;
+pub mod page_size;
 pub mod pagination;
 pub mod row;
 pub mod table;
@@ -0,0 +1,119 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::AHashMap;
+use gloo_storage::{LocalStorage, Storage};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+const PAGE_SIZES_STORAGE_KEY: &str = "webadmin_page_sizes";
+pub const PAGE_SIZE_OPTIONS: &[u32] = &[10, 20, 50, 100];
+
+/// Page size preferences. The last size picked on a list is remembered for
+/// that list, and lists without one use the global default when it is set.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageSizes {
+    #[serde(default)]
+    pub default: Option<u32>,
+    #[serde(default)]
+    pub lists: AHashMap<String, u32>,
+}
+
+pub fn init_page_sizes() {
+    let page_sizes = create_rw_signal(PageSizes::load());
+
+    create_effect(move |prev: Option<()>| {
+        page_sizes.with(|page_sizes| {
+            if prev.is_some() {
+                page_sizes.save();
+            }
+        });
+    });
+
+    provide_context(page_sizes);
+}
+
+pub fn use_page_sizes() -> RwSignal<PageSizes> {
+    expect_context::<RwSignal<PageSizes>>()
+}
+
+impl PageSizes {
+    pub fn load() -> Self {
+        LocalStorage::get(PAGE_SIZES_STORAGE_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = LocalStorage::set(PAGE_SIZES_STORAGE_KEY, self) {
+            log::error!("Failed to save page sizes to local storage: {}", err);
+        }
+    }
+
+    /// Returns the page size for a list, preferring the size last used on
+    /// it, then the global default and finally the list's own default.
+    pub fn resolve(&self, list_id: &str, fallback: u32) -> u32 {
+        self.lists
+            .get(list_id)
+            .copied()
+            .or(self.default)
+            .unwrap_or(fallback)
+    }
+
+    pub fn set_for(&mut self, list_id: impl Into<String>, size: u32) {
+        self.lists.insert(list_id.into(), size);
+    }
+
+    /// Cycles the global default through the available sizes, starting
+    /// from the smallest one when no default has been chosen.
+    pub fn next_default(&mut self) {
+        let next = self
+            .default
+            .and_then(|size| PAGE_SIZE_OPTIONS.iter().position(|option| *option == size))
+            .map_or(0, |pos| (pos + 1) % PAGE_SIZE_OPTIONS.len());
+        self.default = Some(PAGE_SIZE_OPTIONS[next]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_page_size_precedence() {
+        let mut page_sizes = PageSizes::default();
+        assert_eq!(page_sizes.resolve("listener", 10), 10);
+
+        page_sizes.default = Some(50);
+        assert_eq!(page_sizes.resolve("listener", 10), 50);
+
+        page_sizes.set_for("listener", 20);
+        assert_eq!(page_sizes.resolve("listener", 10), 20);
+        assert_eq!(page_sizes.resolve("signature", 10), 50);
+
+        page_sizes.default = None;
+        assert_eq!(page_sizes.resolve("signature", 25), 25);
+    }
+
+    #[test]
+    fn persist_page_sizes() {
+        let mut page_sizes = PageSizes::default();
+        page_sizes.next_default();
+        assert_eq!(page_sizes.default, Some(10));
+        page_sizes.default = Some(100);
+        page_sizes.next_default();
+        assert_eq!(page_sizes.default, Some(10));
+        page_sizes.set_for("listener", 50);
+
+        let stored = serde_json::to_string(&page_sizes).unwrap();
+        assert_eq!(
+            serde_json::from_str::<PageSizes>(&stored).unwrap(),
+            page_sizes
+        );
+        assert_eq!(
+            serde_json::from_str::<PageSizes>("{}").unwrap(),
+            PageSizes::default()
+        );
+    }
+}
@@ -8,13 +8,46 @@ use leptos::*;
 
 use crate::components::icon::{IconArrowLeft, IconArrowRight};
 
+use super::page_size::PAGE_SIZE_OPTIONS;
+
 #[component]
 pub fn Pagination(
     #[prop(into)] current_page: MaybeSignal<u32>,
     #[prop(into)] total_results: MaybeSignal<Option<u32>>,
     #[prop(into)] page_size: MaybeSignal<u32>,
     #[prop(into)] on_page_change: Callback<u32, ()>,
+    #[prop(optional, into)] on_page_size_change: Option<Callback<u32, ()>>,
 ) -> impl IntoView {
+    let page_size = create_memo(move |_| page_size.get());
+    // Built for each render of the pagination, as it is shown inside <Show>
+    let page_size_select = move || {
+        on_page_size_change.map(|on_page_size_change| {
+            view! {
+                <select
+                    class="py-2 px-3 pe-9 block border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                    title="Results per page"
+                    on:change=move |ev| {
+                        if let Ok(size) = event_target_value(&ev).parse() {
+                            on_page_size_change.call(size);
+                        }
+                    }
+                >
+
+                    {PAGE_SIZE_OPTIONS
+                        .iter()
+                        .map(|size| {
+                            let size = *size;
+                            view! {
+                                <option value=size selected=move || page_size.get() == size>
+                                    {format!("{size} per page")}
+                                </option>
+                            }
+                        })
+                        .collect_view()}
+                </select>
+            }
+        })
+    };
     let total_pages = create_memo(move |_| {
         (total_results.get().unwrap_or(0) as f64 / page_size.get() as f64).ceil() as u32
     });
@@ -106,6 +139,7 @@ pub fn Pagination(
                     </div>
 
                     <p class="text-sm text-gray-600 dark:text-gray-400">"of " {total_pages}</p>
+                    {page_size_select()}
                 </div>
 
             </Show>
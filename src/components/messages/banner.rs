@@ -0,0 +1,38 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use leptos::*;
+
+use crate::components::icon::IconExclamationTriangle;
+
+#[component]
+pub fn Banner(
+    #[prop(into)] message: MaybeSignal<String>,
+    #[prop(optional, into)] hide: MaybeSignal<bool>,
+    #[prop(optional)] children: Option<Children>,
+) -> impl IntoView {
+    view! {
+        <div class="pb-5" class:hidden=move || hide.get()>
+            <div
+                class="bg-yellow-50 border border-yellow-200 text-sm text-yellow-800 rounded-lg p-4 dark:bg-yellow-800/10 dark:border-yellow-900 dark:text-yellow-500"
+                role="status"
+            >
+                <div class="flex items-center">
+                    <div class="flex-shrink-0">
+                        <IconExclamationTriangle
+                            attr:class="flex-shrink-0 size-4 mt-0.5"
+                            attr:stroke="#854d0e"
+                        />
+                    </div>
+                    <div class="ms-4">
+                        <h3 class="text-sm font-semibold">{move || message.get()}</h3>
+                    </div>
+                    <div class="ps-3 ms-auto">{children.map(|children| children())}</div>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,130 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use leptos::*;
+use leptos_router::use_navigate;
+
+use crate::{
+    components::{form::button::Button, icon::IconExclamationTriangle, Color},
+    core::http::{self, ErrorKind},
+};
+
+/// Reload trigger to include in the source of a resource so that
+/// failed loads can be retried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reload(RwSignal<u32>);
+
+impl Reload {
+    pub fn new() -> Self {
+        Reload(create_rw_signal(0))
+    }
+
+    pub fn track(&self) -> u32 {
+        self.0.get()
+    }
+
+    pub fn trigger(&self) {
+        self.0.update(|attempt| *attempt += 1);
+    }
+}
+
+impl Default for Reload {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[component]
+pub fn LoadError(error: http::Error, reload: Reload) -> impl IntoView {
+    let kind = error.kind();
+    let (title, details) = match &error {
+        http::Error::Unauthorized | http::Error::TotpRequired => (
+            "Your session has expired",
+            "Please sign in again to continue.".to_string(),
+        ),
+        http::Error::Forbidden => (
+            "Access denied",
+            "You do not have permission to view this page.".to_string(),
+        ),
+        http::Error::Network(details) => ("Network error", details.clone()),
+        http::Error::Serializer { error, .. } => {
+            ("Failed to deserialize response", error.clone())
+        }
+        http::Error::NotFound => ("Not found", "The requested item was not found.".to_string()),
+        http::Error::Server(error) => ("Failed to load page", format!("{error:?}")),
+    };
+
+    view! {
+        <div
+            class="bg-red-50 border border-red-200 text-sm text-red-800 rounded-lg p-4 dark:bg-red-800/10 dark:border-red-900 dark:text-red-500"
+            role="alert"
+        >
+            <div class="flex items-center">
+                <div class="flex-shrink-0">
+                    <IconExclamationTriangle attr:class="flex-shrink-0 size-4 mt-0.5"/>
+                </div>
+                <div class="ms-4">
+                    <h3 class="text-sm font-semibold">{title}</h3>
+                    <div class="mt-1 text-sm">{details}</div>
+                </div>
+                <div class="ps-3 ms-auto">
+                    {match kind {
+                        ErrorKind::Auth => {
+                            view! {
+                                <Button
+                                    text="Sign in"
+                                    color=Color::Blue
+                                    on_click=move |_| {
+                                        use_navigate()("/login", Default::default());
+                                    }
+                                />
+                            }
+                                .into_view()
+                        }
+                        ErrorKind::Transient => {
+                            view! {
+                                <Button
+                                    text="Retry"
+                                    color=Color::Gray
+                                    on_click=move |_| reload.trigger()
+                                />
+                            }
+                                .into_view()
+                        }
+                        ErrorKind::Permanent => ().into_view(),
+                    }}
+
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_triggers_load() {
+        let runtime = create_runtime();
+        let reload = Reload::new();
+        let id = create_rw_signal("default".to_string());
+        let loads = create_rw_signal(0);
+        let source = create_memo(move |_| {
+            loads.update_untracked(|loads| *loads += 1);
+            (id.get(), reload.track())
+        });
+
+        assert_eq!(source.get(), ("default".to_string(), 0));
+        assert_eq!(loads.get_untracked(), 1);
+
+        reload.trigger();
+        assert_eq!(source.get(), ("default".to_string(), 1));
+        assert_eq!(loads.get_untracked(), 2);
+
+        runtime.dispose();
+    }
+}
@@ -5,4 +5,6 @@
  */
 
 pub mod alert;
+pub mod banner;
+pub mod load_error;
 pub mod modal;
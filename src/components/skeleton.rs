@@ -4,10 +4,131 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::time::Duration;
+
 use leptos::*;
+use web_time::Instant;
+
+use crate::components::icon::IconExclamationTriangle;
+
+pub const LOAD_TIMEOUT: Duration = Duration::from_secs(30);
+const TICK_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SkeletonLayout {
+    #[default]
+    Generic,
+    Form,
+    Table,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadState {
+    Loading { started: Instant },
+    Loaded,
+    TimedOut,
+}
+
+impl LoadState {
+    pub fn start(now: Instant) -> Self {
+        LoadState::Loading { started: now }
+    }
+
+    /// Advances the state machine, switching to `TimedOut` once loading
+    /// has been pending for longer than `timeout`.
+    pub fn tick(self, now: Instant, timeout: Duration) -> Self {
+        match self {
+            LoadState::Loading { started } if now.duration_since(started) >= timeout => {
+                LoadState::TimedOut
+            }
+            state => state,
+        }
+    }
+
+    /// Marks the data as arrived. Late data still replaces the timeout error.
+    pub fn finish(self) -> Self {
+        LoadState::Loaded
+    }
+
+    pub fn is_loading(&self) -> bool {
+        matches!(self, LoadState::Loading { .. })
+    }
+
+    pub fn is_timed_out(&self) -> bool {
+        matches!(self, LoadState::TimedOut)
+    }
+}
 
 #[component]
 pub fn Skeleton() -> impl IntoView {
+    view! { <SkeletonLoader layout=SkeletonLayout::Generic/> }
+}
+
+#[component]
+pub fn SkeletonForm() -> impl IntoView {
+    view! { <SkeletonLoader layout=SkeletonLayout::Form/> }
+}
+
+#[component]
+pub fn SkeletonTable() -> impl IntoView {
+    view! { <SkeletonLoader layout=SkeletonLayout::Table/> }
+}
+
+#[component]
+pub fn SkeletonLoader(
+    #[prop(optional)] layout: SkeletonLayout,
+    #[prop(optional)] timeout: Option<Duration>,
+) -> impl IntoView {
+    let timeout = timeout.unwrap_or(LOAD_TIMEOUT);
+    let state = create_rw_signal(LoadState::start(Instant::now()));
+
+    // The skeleton is unmounted as soon as the data arrives
+    if let Ok(handle) = set_interval_with_handle(
+        move || {
+            let next = state.get_untracked().tick(Instant::now(), timeout);
+            if next != state.get_untracked() {
+                state.set(next);
+            }
+        },
+        TICK_INTERVAL,
+    ) {
+        on_cleanup(move || handle.clear());
+    }
+
+    view! {
+        <Show
+            when=move || state.get().is_timed_out()
+            fallback=move || {
+                match layout {
+                    SkeletonLayout::Generic => view! { <SkeletonGeneric/> }.into_view(),
+                    SkeletonLayout::Form => view! { <SkeletonFormRows/> }.into_view(),
+                    SkeletonLayout::Table => view! { <SkeletonTableRows/> }.into_view(),
+                }
+            }
+        >
+
+            <div
+                class="bg-red-50 border border-red-200 text-sm text-red-800 rounded-lg p-4 dark:bg-red-800/10 dark:border-red-900 dark:text-red-500"
+                role="alert"
+            >
+                <div class="flex">
+                    <div class="flex-shrink-0">
+                        <IconExclamationTriangle attr:class="flex-shrink-0 size-4 mt-0.5"/>
+                    </div>
+                    <div class="ms-4">
+                        <h3 class="text-sm font-semibold">"This page is taking too long to load"</h3>
+                        <div class="mt-1 text-sm">
+                            "The server did not respond in time. Check your connection and reload the page."
+                        </div>
+                    </div>
+                </div>
+            </div>
+        </Show>
+    }
+}
+
+#[component]
+fn SkeletonGeneric() -> impl IntoView {
     view! {
         <div class="flex animate-pulse">
             <div class="ms-4 mt-2 w-full">
@@ -23,3 +144,87 @@ pub fn Skeleton() -> impl IntoView {
         </div>
     }
 }
+
+#[component]
+fn SkeletonFormRows() -> impl IntoView {
+    view! {
+        <div class="grid sm:grid-cols-12 gap-2 sm:gap-6 animate-pulse">
+            {(0..5)
+                .map(|_| {
+                    view! {
+                        <div class="sm:col-span-3">
+                            <div class="mt-3 h-4 w-3/4 bg-gray-200 rounded-full dark:bg-gray-700"></div>
+                        </div>
+                        <div class="sm:col-span-9">
+                            <div class="h-9 w-full bg-gray-200 rounded-lg dark:bg-gray-700"></div>
+                        </div>
+                    }
+                })
+                .collect_view()}
+        </div>
+    }
+}
+
+#[component]
+fn SkeletonTableRows() -> impl IntoView {
+    view! {
+        <div class="animate-pulse">
+            <div class="flex gap-x-4 px-6 py-3 border-b border-gray-200 dark:border-gray-700">
+                <div class="h-3 w-1/4 bg-gray-300 rounded-full dark:bg-gray-600"></div>
+                <div class="h-3 w-1/2 bg-gray-300 rounded-full dark:bg-gray-600"></div>
+                <div class="h-3 w-1/6 bg-gray-300 rounded-full dark:bg-gray-600"></div>
+            </div>
+            {(0..6)
+                .map(|_| {
+                    view! {
+                        <div class="flex gap-x-4 px-6 py-4 border-b border-gray-200 dark:border-gray-700">
+                            <div class="h-4 w-1/4 bg-gray-200 rounded-full dark:bg-gray-700"></div>
+                            <div class="h-4 w-1/2 bg-gray-200 rounded-full dark:bg-gray-700"></div>
+                            <div class="h-4 w-1/6 bg-gray-200 rounded-full dark:bg-gray-700"></div>
+                        </div>
+                    }
+                })
+                .collect_view()}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loading_to_loaded() {
+        let start = Instant::now();
+        let state = LoadState::start(start);
+        assert!(state.is_loading());
+
+        let state = state.tick(start + Duration::from_secs(5), LOAD_TIMEOUT);
+        assert!(state.is_loading());
+        assert_eq!(state.finish(), LoadState::Loaded);
+
+        // Once loaded, time no longer affects the state
+        assert_eq!(
+            LoadState::Loaded.tick(start + Duration::from_secs(60), LOAD_TIMEOUT),
+            LoadState::Loaded
+        );
+    }
+
+    #[test]
+    fn loading_to_timeout() {
+        let start = Instant::now();
+        let timeout = Duration::from_secs(10);
+        let mut state = LoadState::start(start);
+
+        for secs in 0..10 {
+            state = state.tick(start + Duration::from_secs(secs), timeout);
+            assert!(state.is_loading(), "timed out early at {secs}s");
+        }
+        state = state.tick(start + timeout, timeout);
+        assert!(state.is_timed_out());
+        assert_eq!(state.tick(start + timeout * 2, timeout), LoadState::TimedOut);
+
+        // Data arriving late replaces the error
+        assert_eq!(state.finish(), LoadState::Loaded);
+    }
+}
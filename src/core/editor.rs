@@ -0,0 +1,131 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! The schema-driven settings editor, without any of the web admin UI.
+//!
+//! Embedders should only use the items re-exported here. A schema describes
+//! the fields of a settings record, [`open`] loads the stored settings into
+//! a [`FormData`], values are edited with [`FormData::set`] and [`submit`]
+//! validates the form and returns the [`UpdateSettings`] to send to the
+//! `/api/settings` endpoint. Everything else in `core` and `pages` is an
+//! implementation detail of the web admin and may change at any time.
+
+use std::sync::Arc;
+
+pub use super::{
+    form::{Expression, ExpressionIfThen, FormData, FormValue},
+    schema::{
+        InputCheck, NumberType, Schema, SchemaType, Schemas, SelectType, Source, Transformer, Type,
+        Validator,
+    },
+};
+pub use crate::pages::config::{Settings, UpdateSettings};
+
+/// A validation error, as `(field id, message)`.
+pub type FieldError = (String, String);
+
+/// Loads a record into a form. Stored settings are keyed by field id, with
+/// the record id under `_id`. Without stored settings the form creates a new
+/// record, with its fields set to their default values.
+pub fn open(schema: Arc<Schema>, settings: Option<Settings>) -> FormData {
+    FormData::from_settings(schema, settings)
+}
+
+/// Validates the form, applying the schema transformers to its values, and
+/// returns the updates that save it. On failure the errors are returned
+/// sorted by field id and are also kept in the form for display, including
+/// a record id that would produce a malformed setting key.
+pub fn submit(form: &mut FormData) -> Result<Vec<UpdateSettings>, Vec<FieldError>> {
+    if form.validate_form() {
+        form.build_update().map_err(|err| {
+            form.new_error(err.field.clone(), err.error.clone());
+            vec![(err.field, err.error)]
+        })
+    } else {
+        let mut errors = form
+            .errors
+            .iter()
+            .map(|(id, error)| (id.clone(), error.error.clone()))
+            .collect::<Vec<_>>();
+        errors.sort_unstable();
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::editor::{self, Schemas, Transformer, UpdateSettings, Validator};
+
+    fn schemas() -> Schemas {
+        Schemas::builder()
+            .new_schema("store")
+            .prefix("store")
+            .suffix("type")
+            .new_id_field()
+            .build()
+            .new_field("type")
+            .input_check([Transformer::Trim], [Validator::Required])
+            .build()
+            .build()
+            .build()
+    }
+
+    #[test]
+    fn create_record_through_public_api() {
+        let schemas = schemas();
+        let mut form = editor::open(schemas.schemas["store"].clone(), None);
+        form.set("_id", " RocksDB ");
+        form.set("type", "rocksdb ");
+
+        assert_eq!(
+            editor::submit(&mut form),
+            Ok(vec![UpdateSettings::Insert {
+                prefix: Some("store.rocksdb".to_string()),
+                values: vec![("type".to_string(), "rocksdb".to_string())],
+                assert_empty: true,
+            }])
+        );
+    }
+
+    #[test]
+    fn update_record_through_public_api() {
+        let schemas = schemas();
+        let settings = [
+            ("_id".to_string(), "rocksdb".to_string()),
+            ("type".to_string(), "rocksdb".to_string()),
+        ]
+        .into_iter()
+        .collect::<editor::Settings>();
+        let mut form = editor::open(schemas.schemas["store"].clone(), Some(settings));
+        assert_eq!(form.value_as_str("type"), Some("rocksdb"));
+
+        let updates = editor::submit(&mut form).unwrap();
+        assert_eq!(
+            updates.first(),
+            Some(&UpdateSettings::Clear {
+                prefix: "store.rocksdb.".to_string(),
+                filter: None,
+            })
+        );
+    }
+
+    #[test]
+    fn reject_invalid_form_through_public_api() {
+        let schemas = schemas();
+        let mut form = editor::open(schemas.schemas["store"].clone(), None);
+        form.set("_id", "rocks db");
+
+        assert_eq!(
+            editor::submit(&mut form)
+                .unwrap_err()
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>(),
+            vec!["_id".to_string(), "type".to_string()]
+        );
+        assert!(form.has_errors("type"));
+    }
+}
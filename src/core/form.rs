@@ -5,6 +5,7 @@
  */
 
 use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::hash::{DefaultHasher, Hash, Hasher};
 use std::net::SocketAddr;
 use std::str::FromStr;
@@ -16,9 +17,11 @@ use leptos::RwSignal;
 
 use crate::pages::config::{Settings, SettingsValues};
 
+use super::duration::{Duration as DurationValue, Rate};
 use super::expr::parser::ExpressionParser;
 use super::expr::tokenizer::Tokenizer;
 use super::expr::{Constant, ParseValue, Token};
+use super::ipnet::normalize_ip;
 use super::schema::{NumberType, SchemaType, SelectType, Type};
 
 use super::schema::{InputCheck, Schema, Transformer, Validator};
@@ -32,6 +35,8 @@ pub struct FormData {
     pub external_sources: Arc<ExternalSources>,
     pub schema: Arc<Schema>,
     pub is_update: bool,
+    /// Stored key and value of each array element loaded from the settings.
+    pub stored_arrays: AHashMap<String, Vec<(String, String)>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -476,6 +481,7 @@ impl FormData {
                     | Type::Secret
                     | Type::Text
                     | Type::Size
+                    | Type::Number { .. }
                     | Type::Boolean
                     | Type::Duration
                     | Type::Rate
@@ -484,8 +490,12 @@ impl FormData {
                         typ: SelectType::Single,
                         ..
                     } => {
-                        match check.check_value(self.value::<String>(field.id).unwrap_or_default())
-                        {
+                        match check
+                            .check_value(self.value::<String>(field.id).unwrap_or_default())
+                            .and_then(|value| check_number_value(&field.typ_, value))
+                            .and_then(|value| {
+                                check_entry_value(&schema, field.id, &field.typ_, value)
+                            }) {
                             Ok(value) => {
                                 if !value.is_empty() {
                                     self.values.insert(field.id.into(), value.into());
@@ -677,7 +687,8 @@ impl FormData {
                     | Type::Duration
                     | Type::Rate
                     | Type::Cron
-                    | Type::Size => {
+                    | Type::Size
+                    | Type::Number { .. } => {
                         if let Some(value) = settings.remove(field.id) {
                             data.set(field.id, value);
                         }
@@ -689,6 +700,13 @@ impl FormData {
                     } => {
                         let values = settings.array_values(field.id);
                         if !values.is_empty() {
+                            data.stored_arrays.insert(
+                                field.id.to_string(),
+                                values
+                                    .iter()
+                                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                                    .collect(),
+                            );
                             data.array_set(field.id, values.into_iter().map(|(_, value)| value));
                         }
                     }
@@ -812,6 +830,7 @@ impl InputCheck {
                         value
                     }
                 }
+                Transformer::NormalizeIp => normalize_ip(&value),
             };
         }
 
@@ -830,6 +849,12 @@ impl InputCheck {
                         {
                             return Err(format!("Invalid character '{ch}' in this field").into());
                         }
+                        if value.split('.').any(str::is_empty) {
+                            return Err(
+                                "This field cannot start or end with a dot or contain consecutive dots"
+                                    .into(),
+                            );
+                        }
                     }
                     Validator::IsHost => {
                         if value.contains('/') || value.contains(':') {
@@ -1020,8 +1045,223 @@ impl ExpressionIfThen {
     }
 }
 
+/// Checks the sign and range of numeric fields, leaving other values as they are.
+/// Only plain decimal notation is accepted, so values such as `1e3`, `inf` or
+/// `NaN` are rejected, and the integer part must fit in an `i64`.
+fn check_number_value<S, F>(typ: &Type<S, F>, value: String) -> Result<String, Cow<'static, str>> {
+    let Type::Number { signed, min, max } = typ else {
+        return Ok(value);
+    };
+    if value.is_empty() {
+        return Ok(value);
+    }
+
+    let (integer, fraction) = match value.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (value.as_str(), None),
+    };
+    let is_digits =
+        |digits: &str| !digits.is_empty() && digits.bytes().all(|ch| ch.is_ascii_digit());
+    let number = integer
+        .parse::<i64>()
+        .ok()
+        .filter(|_| fraction.into_iter().all(is_digits))
+        .ok_or("This field must be a number")?;
+    if !signed && value.starts_with('-') {
+        return Err("This field cannot be negative".into());
+    }
+    let compare = |bound: &NumberType| match bound {
+        NumberType::Integer(bound) if fraction.is_none() => number.cmp(bound),
+        _ => value
+            .parse::<f64>()
+            .ok()
+            .and_then(|value| value.partial_cmp(&bound.as_f64()))
+            .unwrap_or(Ordering::Equal),
+    };
+    if let Some(min) = min.filter(|min| compare(min) == Ordering::Less) {
+        return Err(format!("This field must be at least {min}").into());
+    }
+    if let Some(max) = max.filter(|max| compare(max) == Ordering::Greater) {
+        return Err(format!("This field must be at most {max}").into());
+    }
+
+    Ok(value)
+}
+
+/// Entry schemas store a single `_value` as plain text, so the type declared
+/// for it is enforced here rather than by a dedicated input.
+fn check_entry_value<S, F>(
+    schema: &Schema,
+    field_id: &str,
+    typ: &Type<S, F>,
+    value: String,
+) -> Result<String, Cow<'static, str>> {
+    if field_id != "_value" || !matches!(schema.typ, SchemaType::Entry { .. }) || value.is_empty() {
+        return Ok(value);
+    }
+
+    let is_valid = match typ {
+        Type::Duration => value.parse::<DurationValue>().is_ok(),
+        Type::Rate => value.parse::<Rate>().is_ok(),
+        Type::Size => value.parse::<u64>().is_ok(),
+        Type::Boolean => matches!(value.as_str(), "true" | "false"),
+        _ => true,
+    };
+
+    if is_valid {
+        Ok(value)
+    } else {
+        Err(match typ {
+            Type::Duration => "Invalid duration, expected a number followed by ms, s, m, h or d",
+            Type::Rate => "Invalid rate, expected an amount and a period such as 10/1m",
+            Type::Size => "Invalid size, expected a number of bytes",
+            _ => "Invalid value",
+        }
+        .into())
+    }
+}
+
 impl Default for FormValue {
     fn default() -> Self {
         FormValue::Value("".to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::schema::Schemas, pages::config::UpdateSettings};
+
+    fn duration_entry() -> Arc<Schema> {
+        Arc::new(
+            Schemas::builder()
+                .new_schema("timeouts")
+                .prefix("test.timeout")
+                .new_id_field()
+                .build()
+                .new_value_field()
+                .typ(Type::Duration)
+                .build()
+                .item,
+        )
+    }
+
+    fn numbers() -> Arc<Schema> {
+        Arc::new(
+            Schemas::builder()
+                .new_schema("numbers")
+                .new_field("score")
+                .typ(Type::Number {
+                    signed: true,
+                    min: Some(NumberType::Integer(-100)),
+                    max: Some(NumberType::Integer(100)),
+                })
+                .input_check([Transformer::Trim], [])
+                .build()
+                .new_field("count")
+                .typ(Type::Number {
+                    signed: false,
+                    min: None,
+                    max: None,
+                })
+                .input_check([Transformer::Trim], [])
+                .build()
+                .item,
+        )
+    }
+
+    #[test]
+    fn validate_numbers() {
+        let mut data = FormData::from(numbers());
+        data.set("score", "-5.5");
+        data.set("count", "10");
+        assert!(data.validate_form());
+
+        let mut data = FormData::from(numbers());
+        data.set("score", "-250");
+        assert!(!data.validate_form());
+        assert_eq!(
+            data.error_string("score"),
+            Some("This field must be at least -100")
+        );
+
+        let mut data = FormData::from(numbers());
+        data.set("count", "-1");
+        assert!(!data.validate_form());
+        assert_eq!(data.error_string("count"), Some("This field cannot be negative"));
+
+        let mut data = FormData::from(numbers());
+        data.set("count", "many");
+        assert!(!data.validate_form());
+        assert_eq!(data.error_string("count"), Some("This field must be a number"));
+
+        for value in [
+            "1e3",
+            "inf",
+            "NaN",
+            "+-1",
+            "1.",
+            ".5",
+            "1.5e2",
+            "99999999999999999999",
+        ] {
+            let mut data = FormData::from(numbers());
+            data.set("count", value);
+            assert!(!data.validate_form(), "{value}");
+            assert_eq!(data.error_string("count"), Some("This field must be a number"));
+        }
+
+        let mut data = FormData::from(numbers());
+        data.set("score", "100.5");
+        assert!(!data.validate_form());
+        assert_eq!(data.error_string("score"), Some("This field must be at most 100"));
+
+        let mut data = FormData::from(numbers());
+        data.set("score", "-100");
+        data.set("count", "9223372036854775807");
+        assert!(data.validate_form());
+    }
+
+    #[test]
+    fn spam_thresholds_are_signed_numbers() {
+        let schemas = crate::build_schemas();
+        let mut data = FormData::from(schemas.schemas["spam-settings"].clone());
+        data.set("spam-filter.score.spam", "-7.5");
+        data.set("spam-filter.score.discard", "0");
+        data.set("spam-filter.score.reject", "1e2");
+        assert!(!data.validate_form());
+        assert_eq!(data.error_string("spam-filter.score.spam"), None);
+        assert_eq!(
+            data.error_string("spam-filter.score.reject"),
+            Some("This field must be a number")
+        );
+    }
+
+    #[test]
+    fn reject_invalid_entry_value() {
+        let mut data = FormData::from(duration_entry());
+        data.set("_id", "connect");
+        data.set("_value", "soon");
+        assert!(!data.validate_form());
+        assert_eq!(
+            data.error_string("_value"),
+            Some("Invalid duration, expected a number followed by ms, s, m, h or d")
+        );
+    }
+
+    #[test]
+    fn accept_valid_entry_value() {
+        let mut data = FormData::from(duration_entry());
+        data.set("_id", "connect");
+        data.set("_value", " 30s ");
+        assert!(data.validate_form());
+        assert_eq!(
+            data.build_update().unwrap(),
+            vec![UpdateSettings::Insert {
+                prefix: None,
+                values: vec![("test.timeout.connect".to_string(), "30s".to_string())],
+                assert_empty: true,
+            }]
+        );
+    }
+}
@@ -9,6 +9,8 @@ use base64::{engine::general_purpose::STANDARD, Engine};
 use gloo_net::http::{Headers, Method, RequestBuilder};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
+use crate::utils::security::csrf_header;
+
 use super::{url::UrlBuilder, AccessToken};
 
 pub struct HttpRequest {
@@ -60,6 +62,30 @@ pub enum Error {
     Server(ManagementApiError),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The session is no longer valid, the user has to sign in again.
+    Auth,
+    /// The request may succeed if retried.
+    Transient,
+    /// Retrying the same request will fail again.
+    Permanent,
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Unauthorized | Error::TotpRequired => ErrorKind::Auth,
+            Error::Network(_) | Error::Server(ManagementApiError::Other { .. }) => {
+                ErrorKind::Transient
+            }
+            Error::Forbidden | Error::NotFound | Error::Serializer { .. } | Error::Server(_) => {
+                ErrorKind::Permanent
+            }
+        }
+    }
+}
+
 pub trait IntoUrlBuilder {
     fn into_url_builder(self) -> UrlBuilder;
 }
@@ -149,6 +175,14 @@ impl HttpRequest {
         )
     }
 
+    /// Attaches the double-submit CSRF token header, if a token is set.
+    pub fn with_csrf_token(self, token: &str) -> Self {
+        match csrf_header(token) {
+            Some((name, value)) => self.with_header(name, value),
+            None => self,
+        }
+    }
+
     pub fn with_header(self, name: impl AsRef<str>, value: impl AsRef<str>) -> Self {
         self.headers.set(name.as_ref(), value.as_ref());
         self
@@ -278,3 +312,40 @@ impl From<serde_json::Error> for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_errors() {
+        assert_eq!(Error::Unauthorized.kind(), ErrorKind::Auth);
+        assert_eq!(Error::TotpRequired.kind(), ErrorKind::Auth);
+        assert_eq!(
+            Error::Network("connection reset".to_string()).kind(),
+            ErrorKind::Transient
+        );
+        assert_eq!(
+            Error::Server(ManagementApiError::Other {
+                details: "Store unavailable".to_string(),
+                reason: None,
+            })
+            .kind(),
+            ErrorKind::Transient
+        );
+        assert_eq!(Error::Forbidden.kind(), ErrorKind::Permanent);
+        assert_eq!(Error::NotFound.kind(), ErrorKind::Permanent);
+        assert_eq!(
+            Error::Server(ManagementApiError::AssertFailed).kind(),
+            ErrorKind::Permanent
+        );
+        assert_eq!(
+            Error::Serializer {
+                error: "invalid type".to_string(),
+                response: "{}".to_string(),
+            }
+            .kind(),
+            ErrorKind::Permanent
+        );
+    }
+}
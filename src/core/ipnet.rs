@@ -0,0 +1,299 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IpNetwork {
+    pub addr: IpAddr,
+    pub prefix: u8,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IpListCleanup {
+    pub entries: Vec<String>,
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+}
+
+impl IpNetwork {
+    pub fn max_prefix(&self) -> u8 {
+        match self.addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+
+    fn bits(&self) -> u128 {
+        match self.addr {
+            IpAddr::V4(addr) => u32::from(addr) as u128,
+            IpAddr::V6(addr) => u128::from(addr),
+        }
+    }
+
+    fn masked_bits(&self, prefix: u8) -> u128 {
+        let host_bits = (self.max_prefix() - prefix) as u32;
+        self.bits()
+            .checked_shr(host_bits)
+            .and_then(|bits| bits.checked_shl(host_bits))
+            .unwrap_or(0)
+    }
+
+    fn with_bits(&self, bits: u128, prefix: u8) -> Self {
+        IpNetwork {
+            addr: match self.addr {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(bits as u32)),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(bits)),
+            },
+            prefix,
+        }
+    }
+
+    /// Returns whether every address in `other` is also part of this network.
+    pub fn contains(&self, other: &IpNetwork) -> bool {
+        self.addr.is_ipv4() == other.addr.is_ipv4()
+            && self.prefix <= other.prefix
+            && self.masked_bits(self.prefix) == other.masked_bits(self.prefix)
+    }
+
+    pub fn is_single_address(&self) -> bool {
+        self.prefix == self.max_prefix()
+    }
+
+    /// Returns the network with its host bits cleared.
+    pub fn normalized(&self) -> Self {
+        self.with_bits(self.masked_bits(self.prefix), self.prefix)
+    }
+
+    /// Returns the network twice the size that contains this one, if any.
+    fn parent(&self) -> Option<Self> {
+        (self.prefix > 0)
+            .then(|| self.with_bits(self.masked_bits(self.prefix - 1), self.prefix - 1))
+    }
+
+    fn sort_key(&self) -> (bool, u128, u8) {
+        (self.addr.is_ipv6(), self.bits(), self.prefix)
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let (addr, prefix) = match value.rsplit_once('/') {
+            Some((addr, prefix)) => (
+                addr.parse::<IpAddr>().map_err(|_| ())?,
+                Some(prefix.parse::<u8>().map_err(|_| ())?),
+            ),
+            None => (value.parse::<IpAddr>().map_err(|_| ())?, None),
+        };
+        let mut network = IpNetwork { addr, prefix: 0 };
+        network.prefix = match prefix {
+            Some(prefix) if prefix <= network.max_prefix() => prefix,
+            Some(_) => return Err(()),
+            None => network.max_prefix(),
+        };
+
+        Ok(network)
+    }
+}
+
+impl Display for IpNetwork {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_single_address() {
+            write!(f, "{}", self.addr)
+        } else {
+            write!(f, "{}/{}", self.addr, self.prefix)
+        }
+    }
+}
+
+/// Returns the canonical notation of an IP address or network, or the
+/// trimmed value unchanged when it cannot be parsed.
+pub fn normalize_ip(value: &str) -> String {
+    value
+        .parse::<IpNetwork>()
+        .map(|network| network.normalized().to_string())
+        .unwrap_or_else(|_| value.trim().to_string())
+}
+
+/// Canonicalizes a list of IP addresses and networks, removing duplicates
+/// and entries already covered by a larger network. When `merge_adjacent`
+/// is set, sibling networks are combined into their parent network.
+/// Entries that cannot be parsed are kept as they are.
+pub fn clean_ip_list<'x>(
+    entries: impl IntoIterator<Item = &'x str>,
+    merge_adjacent: bool,
+) -> IpListCleanup {
+    let entries = entries.into_iter().collect::<Vec<_>>();
+    let mut invalid = Vec::new();
+    let mut networks = Vec::new();
+
+    for entry in &entries {
+        match entry.parse::<IpNetwork>() {
+            Ok(network) => networks.push(network.normalized()),
+            Err(_) => invalid.push(entry.to_string()),
+        }
+    }
+
+    loop {
+        networks.sort_by_key(IpNetwork::sort_key);
+        networks.dedup();
+
+        // Drop networks contained in a larger one
+        let covered = networks
+            .iter()
+            .map(|network| {
+                networks
+                    .iter()
+                    .any(|other| other != network && other.contains(network))
+            })
+            .collect::<Vec<_>>();
+        let mut covered = covered.into_iter();
+        networks.retain(|_| !covered.next().unwrap_or_default());
+
+        if !merge_adjacent {
+            break;
+        }
+
+        // Once sorted, sibling networks are always next to each other
+        let mut merged = false;
+        let mut pos = 0;
+        while pos + 1 < networks.len() {
+            let (a, b) = (networks[pos], networks[pos + 1]);
+            match (a.parent(), b.parent()) {
+                (Some(parent), Some(other)) if a.prefix == b.prefix && parent == other => {
+                    networks[pos] = parent;
+                    networks.remove(pos + 1);
+                    merged = true;
+                }
+                _ => pos += 1,
+            }
+        }
+        if !merged {
+            break;
+        }
+    }
+
+    let mut result = networks
+        .iter()
+        .map(|network| network.to_string())
+        .collect::<Vec<_>>();
+    result.extend(invalid);
+
+    IpListCleanup {
+        removed: entries
+            .iter()
+            .filter(|entry| !result.iter().any(|r| r == *entry))
+            .map(|entry| entry.to_string())
+            .collect(),
+        added: result
+            .iter()
+            .filter(|r| !entries.contains(&r.as_str()))
+            .cloned()
+            .collect(),
+        entries: result,
+    }
+}
+
+impl IpListCleanup {
+    pub fn has_changes(&self) -> bool {
+        !self.removed.is_empty() || !self.added.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_networks() {
+        let network = "10.0.0.0/24".parse::<IpNetwork>().unwrap();
+        assert_eq!(network.prefix, 24);
+        assert!(network.contains(&"10.0.0.77".parse().unwrap()));
+        assert!(!network.contains(&"10.0.1.1".parse().unwrap()));
+        assert!(!network.contains(&"10.0.0.0/16".parse().unwrap()));
+        assert!("::/0"
+            .parse::<IpNetwork>()
+            .unwrap()
+            .contains(&"2001:db8::1".parse().unwrap()));
+        assert!(!"0.0.0.0/0"
+            .parse::<IpNetwork>()
+            .unwrap()
+            .contains(&"2001:db8::1".parse().unwrap()));
+        assert!("10.0.0.1/33".parse::<IpNetwork>().is_err());
+        assert!("example.org".parse::<IpNetwork>().is_err());
+    }
+
+    #[test]
+    fn canonicalize_mixed_notation() {
+        assert_eq!(normalize_ip(" 10.0.0.5/24 "), "10.0.0.0/24");
+        assert_eq!(normalize_ip("192.168.1.1/32"), "192.168.1.1");
+        assert_eq!(normalize_ip("2001:DB8:0:0:0:0:0:1"), "2001:db8::1");
+        assert_eq!(normalize_ip("2001:db8::dead:beef/64"), "2001:db8::/64");
+        assert_eq!(normalize_ip("not-an-ip"), "not-an-ip");
+
+        let cleanup = clean_ip_list(
+            [
+                "2001:DB8:0:0::1",
+                "2001:db8::1",
+                "10.0.0.5/24",
+                "10.0.0.1",
+                "192.168.1.1/32",
+                "192.168.1.1",
+                "bogus",
+            ],
+            false,
+        );
+        assert_eq!(
+            cleanup.entries,
+            vec!["10.0.0.0/24", "192.168.1.1", "2001:db8::1", "bogus"]
+        );
+        assert_eq!(
+            cleanup.removed,
+            vec![
+                "2001:DB8:0:0::1",
+                "10.0.0.5/24",
+                "10.0.0.1",
+                "192.168.1.1/32"
+            ]
+        );
+        assert_eq!(cleanup.added, vec!["10.0.0.0/24"]);
+    }
+
+    #[test]
+    fn merge_adjacent_ranges() {
+        let cleanup = clean_ip_list(["10.0.0.128/25", "10.0.0.0/25"], true);
+        assert_eq!(cleanup.entries, vec!["10.0.0.0/24"]);
+        assert_eq!(cleanup.removed, vec!["10.0.0.128/25", "10.0.0.0/25"]);
+        assert_eq!(cleanup.added, vec!["10.0.0.0/24"]);
+
+        // Merging cascades and non-sibling ranges are left alone
+        assert_eq!(
+            clean_ip_list(
+                [
+                    "10.0.0.0/26",
+                    "10.0.0.64/26",
+                    "10.0.0.128/25",
+                    "10.0.1.128/25"
+                ],
+                true
+            )
+            .entries,
+            vec!["10.0.0.0/24", "10.0.1.128/25"]
+        );
+        assert_eq!(
+            clean_ip_list(["10.0.0.0/25", "10.0.0.128/25"], false).entries,
+            vec!["10.0.0.0/25", "10.0.0.128/25"]
+        );
+        assert!(!clean_ip_list(["10.0.0.0/24"], true).has_changes());
+    }
+}
@@ -0,0 +1,287 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::{AHashMap, AHashSet};
+
+use super::form::{FormData, FormValue};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergeResult {
+    pub merged: FormData,
+    pub resolved: Vec<String>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergeConflict {
+    pub field: String,
+    pub base: Option<FormValue>,
+    pub local: Option<FormValue>,
+    pub remote: Option<FormValue>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    Local,
+    Remote,
+}
+
+impl FormData {
+    /// Performs a 3-way merge between the settings loaded when the form was
+    /// opened (`base`), the edited form (`self`) and the settings currently
+    /// stored on the server (`remote`). Fields changed on a single side are
+    /// merged automatically, fields changed on both sides to different values
+    /// are reported as conflicts and keep the local value until resolved.
+    pub fn merge(&self, base: &FormData, remote: &FormData) -> MergeResult {
+        let mut merged = self.clone();
+        let mut resolved = Vec::new();
+        let mut conflicts = Vec::new();
+
+        let mut fields = self
+            .values
+            .keys()
+            .chain(base.values.keys())
+            .chain(remote.values.keys())
+            .filter(|key| !self.schema.is_reserved_key(key))
+            .collect::<AHashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        fields.sort_unstable();
+
+        for field in fields {
+            let base_value = non_empty(base, field);
+            let local_value = non_empty(self, field);
+            let remote_value = non_empty(remote, field);
+
+            if local_value == remote_value || remote_value == base_value {
+                continue;
+            } else if local_value == base_value {
+                match remote_value {
+                    Some(value) => merged.set(field.as_str(), value.clone()),
+                    None => merged.remove(field),
+                }
+                resolved.push(field.to_string());
+            } else {
+                conflicts.push(MergeConflict {
+                    field: field.to_string(),
+                    base: base_value.cloned(),
+                    local: local_value.cloned(),
+                    remote: remote_value.cloned(),
+                });
+            }
+        }
+
+        MergeResult {
+            merged,
+            resolved,
+            conflicts,
+        }
+    }
+}
+
+impl FormData {
+    /// Whether a field differs from its value when the form was loaded,
+    /// using the same comparison as [`FormData::merge`].
+    pub fn is_modified(&self, base: &FormData, field: &str) -> bool {
+        non_empty(self, field) != non_empty(base, field)
+    }
+
+    /// Whether any field differs from its value when the form was loaded.
+    pub fn has_modifications(&self, base: &FormData) -> bool {
+        self.values
+            .keys()
+            .chain(base.values.keys())
+            .filter(|key| !self.schema.is_reserved_key(key))
+            .any(|key| self.is_modified(base, key))
+    }
+}
+
+impl MergeResult {
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+
+    pub fn resolve(&mut self, field: &str, resolution: Resolution) {
+        if let Some(pos) = self.conflicts.iter().position(|c| c.field == field) {
+            let conflict = self.conflicts.remove(pos);
+            if resolution == Resolution::Remote {
+                match conflict.remote {
+                    Some(value) => self.merged.set(conflict.field.as_str(), value),
+                    None => self.merged.remove(&conflict.field),
+                }
+            }
+            self.resolved.push(conflict.field);
+        }
+    }
+
+    /// Applies the resolution chosen for each conflicting field. Returns
+    /// false, leaving the result unchanged, while any conflict has no choice.
+    pub fn resolve_each(&mut self, choices: &AHashMap<String, Resolution>) -> bool {
+        let Some(resolutions) = self
+            .conflicts
+            .iter()
+            .map(|c| {
+                choices
+                    .get(&c.field)
+                    .map(|choice| (c.field.clone(), *choice))
+            })
+            .collect::<Option<Vec<_>>>()
+        else {
+            return false;
+        };
+        for (field, resolution) in resolutions {
+            self.resolve(&field, resolution);
+        }
+        true
+    }
+}
+
+fn non_empty<'x>(data: &'x FormData, field: &str) -> Option<&'x FormValue> {
+    if !data.value_is_empty(field) {
+        data.values.get(field)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ahash::AHashMap;
+
+    use crate::core::{
+        form::{Expression, ExpressionIfThen, FormData, FormValue},
+        merge::Resolution,
+        schema::Schemas,
+    };
+
+    fn form(values: &[(&str, &str)]) -> FormData {
+        let schemas = Schemas::builder().new_schema("test").build().build();
+        let mut data = schemas.build_form("test");
+        for (key, value) in values {
+            data.set(*key, *value);
+        }
+        data
+    }
+
+    #[test]
+    fn merge_disjoint_edits() {
+        let base = form(&[("a", "1"), ("b", "1"), ("c", "1")]);
+        let local = form(&[("a", "2"), ("b", "1"), ("c", "1")]);
+        let remote = form(&[("a", "1"), ("b", "3"), ("d", "4")]);
+
+        let result = local.merge(&base, &remote);
+        assert!(!result.has_conflicts());
+        assert_eq!(result.resolved, vec!["b", "c", "d"]);
+        assert_eq!(result.merged.value_as_str("a"), Some("2"));
+        assert_eq!(result.merged.value_as_str("b"), Some("3"));
+        assert_eq!(result.merged.value_as_str("c"), None);
+        assert_eq!(result.merged.value_as_str("d"), Some("4"));
+    }
+
+    #[test]
+    fn merge_flags_field_edited_on_both_sides() {
+        let base = form(&[("a", "1"), ("b", "1")]);
+        let local = form(&[("a", "2"), ("b", "5")]);
+        let remote = form(&[("a", "3"), ("b", "5")]);
+
+        let mut result = local.merge(&base, &remote);
+        assert_eq!(result.conflicts.len(), 1);
+        let conflict = &result.conflicts[0];
+        assert_eq!(conflict.field, "a");
+        assert_eq!(conflict.base, Some(FormValue::Value("1".to_string())));
+        assert_eq!(conflict.local, Some(FormValue::Value("2".to_string())));
+        assert_eq!(conflict.remote, Some(FormValue::Value("3".to_string())));
+        assert_eq!(result.merged.value_as_str("a"), Some("2"));
+
+        result.resolve("a", Resolution::Remote);
+        assert!(!result.has_conflicts());
+        assert_eq!(result.merged.value_as_str("a"), Some("3"));
+    }
+
+    #[test]
+    fn resolve_each_conflict_separately() {
+        let base = form(&[("a", "1"), ("b", "1"), ("c", "1")]);
+        let local = form(&[("a", "2"), ("b", "2"), ("c", "2")]);
+        let remote = form(&[("a", "3"), ("b", "3")]);
+
+        let mut result = local.merge(&base, &remote);
+        assert_eq!(result.conflicts.len(), 3);
+
+        let mut choices = AHashMap::from_iter([
+            ("a".to_string(), Resolution::Local),
+            ("b".to_string(), Resolution::Remote),
+        ]);
+        assert!(!result.resolve_each(&choices));
+        assert_eq!(result.conflicts.len(), 3);
+        assert_eq!(result.merged.value_as_str("b"), Some("2"));
+
+        choices.insert("c".to_string(), Resolution::Remote);
+        assert!(result.resolve_each(&choices));
+        assert!(!result.has_conflicts());
+        assert_eq!(result.resolved, vec!["a", "b", "c"]);
+        assert_eq!(result.merged.value_as_str("a"), Some("2"));
+        assert_eq!(result.merged.value_as_str("b"), Some("3"));
+        assert_eq!(result.merged.value_as_str("c"), None);
+    }
+
+    #[test]
+    fn detect_modified_fields() {
+        let base = form(&[("a", "1"), ("b", "1")]);
+
+        let mut local = base.clone();
+        assert!(!local.is_modified(&base, "a"));
+        local.set("a", "2");
+        assert!(local.is_modified(&base, "a"));
+        assert!(!local.is_modified(&base, "b"));
+        assert!(local.has_modifications(&base));
+        local.set("a", "1");
+        assert!(!local.is_modified(&base, "a"));
+        assert!(!local.has_modifications(&base));
+
+        // Clearing a field that was never set is not a change
+        local.set("c", "");
+        assert!(!local.is_modified(&base, "c"));
+        local.set("b", "");
+        assert!(local.is_modified(&base, "b"));
+    }
+
+    #[test]
+    fn detect_modified_arrays_and_expressions() {
+        let mut base = form(&[]);
+        base.set("list", vec!["a".to_string(), "b".to_string()]);
+        base.set(
+            "expr",
+            Expression {
+                if_thens: vec![ExpressionIfThen {
+                    if_: "is_tls".to_string(),
+                    then_: "true".to_string(),
+                }],
+                else_: "false".to_string(),
+            },
+        );
+
+        let mut local = base.clone();
+        local.set("list", vec!["b".to_string(), "a".to_string()]);
+        assert!(local.is_modified(&base, "list"));
+        local.set("list", vec!["a".to_string(), "b".to_string()]);
+        assert!(!local.is_modified(&base, "list"));
+        local.set("list", Vec::<String>::new());
+        assert!(local.is_modified(&base, "list"));
+
+        local.set(
+            "expr",
+            Expression {
+                if_thens: vec![ExpressionIfThen {
+                    if_: "is_tls".to_string(),
+                    then_: "false".to_string(),
+                }],
+                else_: "false".to_string(),
+            },
+        );
+        assert!(local.is_modified(&base, "expr"));
+        assert!(!local.is_modified(&base, "list2"));
+    }
+}
@@ -9,11 +9,16 @@ use std::{fmt::Display, sync::Arc};
 use ahash::AHashSet;
 use serde::{Deserialize, Serialize};
 
+pub mod duration;
+pub mod editor;
 pub mod expr;
 pub mod form;
 pub mod http;
+pub mod ipnet;
+pub mod merge;
 pub mod oauth;
 pub mod schema;
+pub mod schema_json;
 pub mod url;
 
 pub const MINIMUM_API_VERSION: Semver = Semver::new(0, 12, 0);
@@ -32,6 +37,14 @@ pub struct AccessToken {
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Permissions(Arc<AHashSet<Permission>>);
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ControlAccess {
+    pub can_view: bool,
+    pub can_create: bool,
+    pub can_edit: bool,
+    pub can_delete: bool,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum Permission {
@@ -171,6 +184,15 @@ impl Permissions {
         self.0.contains(&permission)
     }
 
+    pub fn settings_access(&self) -> ControlAccess {
+        ControlAccess {
+            can_view: self.has_access(Permission::SettingsList),
+            can_create: self.has_access(Permission::SettingsUpdate),
+            can_edit: self.has_access(Permission::SettingsUpdate),
+            can_delete: self.has_access(Permission::SettingsDelete),
+        }
+    }
+
     pub fn default_url(&self, is_enterprise: bool) -> &'static str {
         if is_enterprise
             && self.0.contains(&Permission::MetricsList)
@@ -224,6 +246,16 @@ impl Permissions {
     }
 }
 
+impl ControlAccess {
+    pub fn is_read_only(&self) -> bool {
+        self.can_view && !self.can_edit
+    }
+
+    pub fn can_modify(&self) -> bool {
+        self.can_create || self.can_edit || self.can_delete
+    }
+}
+
 impl AsRef<AccessToken> for AccessToken {
     fn as_ref(&self) -> &AccessToken {
         self
@@ -378,3 +410,56 @@ impl Display for Semver {
         write!(f, "{major}.{minor}.{patch}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permissions(permissions: &[Permission]) -> Permissions {
+        Permissions::new(permissions.iter().copied().collect())
+    }
+
+    #[test]
+    fn settings_access_view_only() {
+        let access = permissions(&[Permission::SettingsList]).settings_access();
+        assert!(access.can_view);
+        assert!(access.is_read_only());
+        assert!(!access.can_modify());
+    }
+
+    #[test]
+    fn settings_access_full() {
+        let access = permissions(&[
+            Permission::SettingsList,
+            Permission::SettingsUpdate,
+            Permission::SettingsDelete,
+        ])
+        .settings_access();
+        assert!(!access.is_read_only());
+        assert!(access.can_create && access.can_edit && access.can_delete);
+    }
+
+    #[test]
+    fn settings_access_update_only() {
+        let access =
+            permissions(&[Permission::SettingsList, Permission::SettingsUpdate]).settings_access();
+        assert!(access.can_create && access.can_edit);
+        assert!(!access.can_delete);
+    }
+
+    #[test]
+    fn settings_access_delete_only() {
+        let access =
+            permissions(&[Permission::SettingsList, Permission::SettingsDelete]).settings_access();
+        assert!(access.is_read_only());
+        assert!(access.can_delete);
+        assert!(!access.can_create);
+    }
+
+    #[test]
+    fn settings_access_none() {
+        let access = permissions(&[]).settings_access();
+        assert_eq!(access, ControlAccess::default());
+        assert!(!access.is_read_only());
+    }
+}
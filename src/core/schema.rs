@@ -8,7 +8,18 @@ use std::{hash::Hasher, sync::Arc};
 
 use ahash::AHashMap;
 
-use super::form::{FormData, FormValue};
+use super::{
+    form::{FormData, FormValue},
+    Permission, Permissions,
+};
+
+pub const DOCS_BASE_URL: &str = "https://stalw.art/docs/";
+
+/// Form values whose id starts with this prefix hold form state rather than
+/// settings and are not saved, unless the schema allows them explicitly.
+pub const RESERVED_KEY_PREFIX: char = '_';
+/// Reserved ids with a built-in meaning, which can never be saved as-is.
+pub const INTERNAL_KEYS: &[&str] = &["_id", "_value"];
 
 #[derive(Default)]
 pub struct Schemas {
@@ -36,6 +47,13 @@ pub enum Type<S, F> {
     Duration,
     Rate,
     Size,
+    /// A signed or unsigned number, optionally bounded, as used for spam
+    /// scores and other numeric tunables.
+    Number {
+        signed: bool,
+        min: Option<NumberType>,
+        max: Option<NumberType>,
+    },
     Cron,
 }
 
@@ -53,13 +71,20 @@ pub struct Field {
     pub label_form: &'static str,
     pub label_column: &'static str,
     pub help: Option<&'static str>,
+    pub doc_anchor: Option<&'static str>,
     pub checks: Value<InputCheck>,
     pub typ_: Type<Arc<Schema>, Arc<Field>>,
     pub default: Value<FormValue>,
     pub placeholder: Value<&'static str>,
     pub display: Vec<Eval>,
     pub readonly: bool,
+    pub preserve_whitespace: bool,
+    pub requires_restart: bool,
+    pub size_precision: Option<usize>,
+    pub empty_placeholder: Option<&'static str>,
+    pub unit_suffix: Option<&'static str>,
     pub enterprise: bool,
+    pub required_permission: Option<Permission>,
 }
 
 #[derive(Clone, Default, Debug)]
@@ -72,6 +97,9 @@ pub struct Schema {
     pub reload_prefix: Option<&'static str>,
     pub list: List,
     pub form: Form,
+    pub allowed_reserved_keys: Vec<&'static str>,
+    pub partial_array_updates: bool,
+    pub suggested_id: Option<&'static str>,
 }
 
 #[derive(Clone, Default, Debug, PartialEq, Eq)]
@@ -189,6 +217,7 @@ pub enum Transformer {
     Lowercase,
     Uppercase,
     HashSecret,
+    NormalizeIp,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -246,11 +275,17 @@ impl From<Arc<Schema>> for FormData {
             external_sources: Default::default(),
             schema,
             is_update: false,
+            stored_arrays: Default::default(),
         }
     }
 }
 
 impl Schema {
+    /// Whether a form value is kept out of the saved settings.
+    pub fn is_reserved_key(&self, key: &str) -> bool {
+        key.starts_with(RESERVED_KEY_PREFIX) && !self.allowed_reserved_keys.contains(&key)
+    }
+
     pub fn can_edit(&self) -> bool {
         self.has_list_action(Action::Modify)
     }
@@ -303,6 +338,12 @@ impl Schema {
 }
 
 impl Field {
+    /// Returns the link to the section of the documentation describing this
+    /// field, if any.
+    pub fn doc_url(&self) -> Option<String> {
+        self.doc_anchor.map(|anchor| doc_url(DOCS_BASE_URL, anchor))
+    }
+
     pub fn value(&self, settings: &FormData) -> String {
         settings
             .get(self.id)
@@ -334,6 +375,17 @@ impl Field {
                 .unwrap_or_default()
     }
 
+    pub fn can_edit(&self, permissions: &Permissions) -> bool {
+        self.required_permission
+            .is_none_or(|permission| permissions.has_access(permission))
+    }
+
+    /// Whether scalar values are trimmed and have curly quotes straightened
+    /// before saving. Secrets are always stored as typed.
+    pub fn normalizes_value(&self) -> bool {
+        !self.preserve_whitespace && !matches!(self.typ_, Type::Secret)
+    }
+
     pub fn is_multivalue(&self) -> bool {
         matches!(
             self.typ_,
@@ -347,6 +399,16 @@ impl Field {
     }
 }
 
+/// Joins a documentation base URL and a page anchor such as
+/// `server/listener#bind`, tolerating leading or trailing slashes.
+pub fn doc_url(base: &str, anchor: &str) -> String {
+    format!(
+        "{}/{}",
+        base.trim_end_matches('/'),
+        anchor.trim_start_matches('/')
+    )
+}
+
 impl<T> Value<T> {
     pub fn eval(&self, settings: &FormData) -> Option<&T> {
         for if_then in &self.if_thens {
@@ -375,6 +437,25 @@ impl Section {
     }
 }
 
+/// Returns the ids of the fields reachable with the keyboard, in section
+/// order. Fields in hidden sections, hidden fields and fields for which
+/// `is_disabled` returns true are skipped.
+pub fn tab_order(
+    sections: &[Section],
+    settings: &FormData,
+    is_disabled: impl Fn(&Field) -> bool,
+) -> Vec<&'static str> {
+    let mut order: Vec<&'static str> = Vec::new();
+    for section in sections.iter().filter(|section| section.display(settings)) {
+        for field in &section.fields {
+            if field.display(settings) && !is_disabled(field) && !order.contains(&field.id) {
+                order.push(field.id);
+            }
+        }
+    }
+    order
+}
+
 impl Schemas {
     pub fn builder() -> Builder<Schemas, ()> {
         Builder {
@@ -457,6 +538,28 @@ impl Builder<Schemas, Schema> {
         .input_check([Transformer::Trim], [])
     }
 
+    /// Saves the field `id` even though it starts with the reserved prefix.
+    pub fn allow_reserved_key(mut self, id: &'static str) -> Self {
+        if INTERNAL_KEYS.contains(&id) {
+            panic!("Key {id:?} is internal and cannot be saved.");
+        }
+        self.item.allowed_reserved_keys.push(id);
+        self
+    }
+
+    /// Saves changes to array fields of a list schema as inserts and deletes
+    /// of the changed elements only, instead of rewriting the whole array.
+    pub fn partial_array_updates(mut self) -> Self {
+        self.item.partial_array_updates = true;
+        self
+    }
+
+    /// Prefills new records with `<base>-<n>`, the next id not in use.
+    pub fn suggest_id(mut self, base: &'static str) -> Self {
+        self.item.suggested_id = Some(base);
+        self
+    }
+
     pub fn new_form_section(self) -> Builder<(Schemas, Schema), Section> {
         Builder {
             parent: (self.parent, self.item),
@@ -616,16 +719,59 @@ impl Builder<(Schemas, Schema), Field> {
         self
     }
 
+    pub fn doc_anchor(mut self, anchor: &'static str) -> Self {
+        self.item.doc_anchor = Some(anchor);
+        self
+    }
+
     pub fn readonly(mut self) -> Self {
         self.item.readonly = true;
         self
     }
 
+    /// Saves the value exactly as typed, for fields where leading or trailing
+    /// whitespace is significant.
+    pub fn preserve_whitespace(mut self) -> Self {
+        self.item.preserve_whitespace = true;
+        self
+    }
+
+    pub fn requires_restart(mut self) -> Self {
+        self.item.requires_restart = true;
+        self
+    }
+
+    /// Text shown in lists when the setting is not stored at all, telling it
+    /// apart from a setting explicitly stored as empty.
+    pub fn empty_placeholder(mut self, placeholder: &'static str) -> Self {
+        self.item.empty_placeholder = Some(placeholder);
+        self
+    }
+
+    /// Unit appended to numeric values in lists, given in its singular form
+    /// and pluralized as needed, e.g. `connection` for "50 connections".
+    pub fn unit_suffix(mut self, unit: &'static str) -> Self {
+        self.item.unit_suffix = Some(unit);
+        self
+    }
+
+    /// Number of decimal places shown when displaying a size, always
+    /// padded with zeros. Sizes use humansize's default precision otherwise.
+    pub fn size_precision(mut self, decimal_places: usize) -> Self {
+        self.item.size_precision = Some(decimal_places);
+        self
+    }
+
     pub fn enterprise_feature(mut self) -> Self {
         self.item.enterprise = true;
         self
     }
 
+    pub fn required_permission(mut self, permission: Permission) -> Self {
+        self.item.required_permission = Some(permission);
+        self
+    }
+
     pub fn typ(mut self, typ_: Type<&'static str, &'static str>) -> Self {
         self.item.typ_ = match typ_ {
             Type::Select {
@@ -985,6 +1131,7 @@ impl From<Type<&'static str, &'static str>> for Type<Arc<Schema>, Arc<Field>> {
             Type::Secret => Type::Secret,
             Type::Text => Type::Text,
             Type::Size => Type::Size,
+            Type::Number { signed, min, max } => Type::Number { signed, min, max },
             Type::Cron => Type::Cron,
             Type::Rate => Type::Rate,
             Type::Select {
@@ -1017,3 +1164,119 @@ impl From<f64> for NumberType {
         NumberType::Float(value)
     }
 }
+
+impl NumberType {
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            NumberType::Integer(value) => *value as f64,
+            NumberType::Float(value) => *value,
+        }
+    }
+}
+
+impl std::fmt::Display for NumberType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NumberType::Integer(value) => value.fmt(f),
+            NumberType::Float(value) => value.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assemble_doc_url() {
+        for base in ["https://stalw.art/docs", "https://stalw.art/docs/"] {
+            for anchor in ["server/listener#bind", "/server/listener#bind"] {
+                assert_eq!(
+                    doc_url(base, anchor),
+                    "https://stalw.art/docs/server/listener#bind"
+                );
+            }
+        }
+
+        let field = Field {
+            doc_anchor: Some("mta/outbound/queue"),
+            ..Default::default()
+        };
+        assert_eq!(
+            field.doc_url().as_deref(),
+            Some("https://stalw.art/docs/mta/outbound/queue")
+        );
+    }
+
+    #[test]
+    fn tab_order_follows_visible_sections() {
+        let schemas = Schemas::builder()
+            .new_schema("listener")
+            .new_field("protocol")
+            .build()
+            .new_field("bind")
+            .build()
+            .new_field("tls.implicit")
+            .display_if_eq("protocol", ["smtp"])
+            .build()
+            .new_field("proxy.trusted-networks")
+            .build()
+            .new_field("socket.backlog")
+            .readonly()
+            .build()
+            .new_form_section()
+            .title("Network")
+            .fields(["bind", "protocol", "tls.implicit"])
+            .build()
+            .new_form_section()
+            .title("Proxy")
+            .fields(["proxy.trusted-networks"])
+            .display_if_eq("protocol", ["smtp", "lmtp"])
+            .build()
+            .new_form_section()
+            .title("Socket")
+            .fields(["socket.backlog"])
+            .build()
+            .build()
+            .build();
+        let schema = &schemas.schemas["listener"];
+        let sections = &schema.form.sections;
+        let is_disabled = |field: &Field| field.readonly;
+
+        let mut data = FormData::from(schema.clone());
+        data.set("protocol", "smtp");
+        assert_eq!(
+            tab_order(sections, &data, is_disabled),
+            vec!["bind", "protocol", "tls.implicit", "proxy.trusted-networks"]
+        );
+
+        // The dependent field is skipped when its condition no longer holds
+        data.set("protocol", "lmtp");
+        assert_eq!(
+            tab_order(sections, &data, is_disabled),
+            vec!["bind", "protocol", "proxy.trusted-networks"]
+        );
+
+        data.set("protocol", "http");
+        assert_eq!(
+            tab_order(sections, &data, |_| false),
+            vec!["bind", "protocol", "socket.backlog"]
+        );
+    }
+
+    #[test]
+    fn omit_doc_url_without_anchor() {
+        assert_eq!(
+            Schemas::builder()
+                .new_schema("test")
+                .new_field("hostname")
+                .build()
+                .item
+                .fields
+                .get("hostname")
+                .unwrap()
+                .doc_url(),
+            None
+        );
+    }
+}
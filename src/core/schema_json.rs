@@ -0,0 +1,636 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! A portable JSON description of the schemas, for frontends that generate
+//! their own forms. Enums are serialized with an explicit, snake_case tag so
+//! that the format does not depend on the order or Rust names of variants.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    form::FormValue,
+    schema::{
+        Condition, Eval, Field, InputCheck, NumberType, Schema, SchemaType, Section, SelectType,
+        Source, Transformer, Type, Validator, Value,
+    },
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SchemaDef {
+    pub id: String,
+    pub name_singular: String,
+    pub name_plural: String,
+    #[serde(rename = "type")]
+    pub typ: SchemaTypeDef,
+    /// Fields sorted by id.
+    pub fields: Vec<FieldDef>,
+    pub sections: Vec<SectionDef>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SchemaTypeDef {
+    Record { prefix: String, suffix: String },
+    Entry { prefix: String },
+    List,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldDef {
+    pub id: String,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+    #[serde(rename = "type")]
+    pub typ: TypeDef,
+    pub default: ValueDef<FormValueDef>,
+    pub placeholder: ValueDef<String>,
+    pub checks: ValueDef<InputCheckDef>,
+    /// The field is shown when any of these conditions holds, or always
+    /// when there are none.
+    pub display: Vec<EvalDef>,
+    pub readonly: bool,
+    pub enterprise: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TypeDef {
+    Input,
+    Array,
+    Secret,
+    Text,
+    Expression,
+    Select {
+        multiple: SelectTypeDef,
+        source: SourceDef,
+    },
+    Boolean,
+    Duration,
+    Rate,
+    Size,
+    Number {
+        signed: bool,
+        min: Option<NumberDef>,
+        max: Option<NumberDef>,
+    },
+    Cron,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectTypeDef {
+    Single,
+    Many,
+    ManyWithSearch,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SourceDef {
+    /// Options as `(id, label)` pairs.
+    Static {
+        options: Vec<(String, String)>,
+    },
+    StaticId {
+        ids: Vec<String>,
+    },
+    /// The records of another schema, labelled by one of their fields.
+    Dynamic {
+        schema: String,
+        field: String,
+        filter: ValueDef<Vec<String>>,
+    },
+    /// The records of the schema itself, labelled by one of their fields.
+    DynamicSelf {
+        field: String,
+        filter: ValueDef<Vec<String>>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NumberDef {
+    Integer(i64),
+    Float(f64),
+}
+
+/// A value that depends on other fields: the first condition that holds
+/// selects its value, otherwise `default` applies.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValueDef<T> {
+    pub if_thens: Vec<IfThenDef<T>>,
+    pub default: Option<T>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IfThenDef<T> {
+    #[serde(rename = "if")]
+    pub eval: EvalDef,
+    #[serde(rename = "then")]
+    pub value: T,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvalDef {
+    pub field: String,
+    pub values: Vec<String>,
+    pub condition: ConditionDef,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConditionDef {
+    MatchAny,
+    MatchNone,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormValueDef {
+    Value(String),
+    Array(Vec<String>),
+    Expression {
+        if_thens: Vec<(String, String)>,
+        #[serde(rename = "else")]
+        else_: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputCheckDef {
+    pub transformers: Vec<TransformerDef>,
+    pub validators: Vec<ValidatorDef>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransformerDef {
+    Trim,
+    RemoveSpaces,
+    Lowercase,
+    Uppercase,
+    HashSecret,
+    NormalizeIp,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ValidatorDef {
+    Required,
+    IsEmail,
+    IsId,
+    IsHost,
+    IsDomain,
+    IsPort,
+    IsIpOrMask,
+    IsUrl,
+    IsRegex,
+    IsSocketAddr,
+    MinLength {
+        value: usize,
+    },
+    MaxLength {
+        value: usize,
+    },
+    MinValue {
+        value: NumberDef,
+    },
+    MaxValue {
+        value: NumberDef,
+    },
+    MinItems {
+        value: usize,
+    },
+    MaxItems {
+        value: usize,
+    },
+    IsValidExpression {
+        variables: Vec<String>,
+        constants: Vec<String>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SectionDef {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub display: Vec<EvalDef>,
+    pub fields: Vec<String>,
+}
+
+impl From<&Schema> for SchemaDef {
+    fn from(schema: &Schema) -> Self {
+        let mut fields = schema
+            .fields
+            .values()
+            .map(|field| FieldDef::from(field.as_ref()))
+            .collect::<Vec<_>>();
+        fields.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+
+        SchemaDef {
+            id: schema.id.to_string(),
+            name_singular: schema.name_singular.to_string(),
+            name_plural: schema.name_plural.to_string(),
+            typ: match schema.typ {
+                SchemaType::Record { prefix, suffix } => SchemaTypeDef::Record {
+                    prefix: prefix.to_string(),
+                    suffix: suffix.to_string(),
+                },
+                SchemaType::Entry { prefix } => SchemaTypeDef::Entry {
+                    prefix: prefix.to_string(),
+                },
+                SchemaType::List => SchemaTypeDef::List,
+            },
+            fields,
+            sections: schema.form.sections.iter().map(SectionDef::from).collect(),
+        }
+    }
+}
+
+impl From<&Field> for FieldDef {
+    fn from(field: &Field) -> Self {
+        FieldDef {
+            id: field.id.to_string(),
+            label: field.label_form.to_string(),
+            help: field.help.map(str::to_string),
+            typ: TypeDef::from(&field.typ_),
+            default: ValueDef::new(&field.default, |value| FormValueDef::from(value)),
+            placeholder: ValueDef::new(&field.placeholder, |value| value.to_string()),
+            checks: ValueDef::new(&field.checks, |checks| InputCheckDef::from(checks)),
+            display: field.display.iter().map(EvalDef::from).collect(),
+            readonly: field.readonly,
+            enterprise: field.enterprise,
+        }
+    }
+}
+
+impl From<&Type<Arc<Schema>, Arc<Field>>> for TypeDef {
+    fn from(typ: &Type<Arc<Schema>, Arc<Field>>) -> Self {
+        match typ {
+            Type::Input => TypeDef::Input,
+            Type::Array => TypeDef::Array,
+            Type::Secret => TypeDef::Secret,
+            Type::Text => TypeDef::Text,
+            Type::Expression => TypeDef::Expression,
+            Type::Select { typ, source } => TypeDef::Select {
+                multiple: match typ {
+                    SelectType::Single => SelectTypeDef::Single,
+                    SelectType::Many => SelectTypeDef::Many,
+                    SelectType::ManyWithSearch => SelectTypeDef::ManyWithSearch,
+                },
+                source: SourceDef::from(source),
+            },
+            Type::Boolean => TypeDef::Boolean,
+            Type::Duration => TypeDef::Duration,
+            Type::Rate => TypeDef::Rate,
+            Type::Size => TypeDef::Size,
+            Type::Number { signed, min, max } => TypeDef::Number {
+                signed: *signed,
+                min: min.map(NumberDef::from),
+                max: max.map(NumberDef::from),
+            },
+            Type::Cron => TypeDef::Cron,
+        }
+    }
+}
+
+impl From<&Source<Arc<Schema>, Arc<Field>>> for SourceDef {
+    fn from(source: &Source<Arc<Schema>, Arc<Field>>) -> Self {
+        let filter = |filter: &Value<&'static [&'static str]>| {
+            ValueDef::new(filter, |values| {
+                values.iter().map(|value| value.to_string()).collect()
+            })
+        };
+
+        match source {
+            Source::Static(options) => SourceDef::Static {
+                options: options
+                    .iter()
+                    .map(|(id, label)| (id.to_string(), label.to_string()))
+                    .collect(),
+            },
+            Source::StaticId(ids) => SourceDef::StaticId {
+                ids: ids.iter().map(|id| id.to_string()).collect(),
+            },
+            Source::Dynamic {
+                schema,
+                field,
+                filter: values,
+            } => SourceDef::Dynamic {
+                schema: schema.id.to_string(),
+                field: field.id.to_string(),
+                filter: filter(values),
+            },
+            Source::DynamicSelf {
+                field,
+                filter: values,
+            } => SourceDef::DynamicSelf {
+                field: field.id.to_string(),
+                filter: filter(values),
+            },
+        }
+    }
+}
+
+impl From<NumberType> for NumberDef {
+    fn from(number: NumberType) -> Self {
+        match number {
+            NumberType::Integer(value) => NumberDef::Integer(value),
+            NumberType::Float(value) => NumberDef::Float(value),
+        }
+    }
+}
+
+impl<T> ValueDef<T> {
+    fn new<V>(value: &Value<V>, map: impl Fn(&V) -> T) -> Self {
+        ValueDef {
+            if_thens: value
+                .if_thens
+                .iter()
+                .map(|if_then| IfThenDef {
+                    eval: EvalDef::from(&if_then.eval),
+                    value: map(&if_then.value),
+                })
+                .collect(),
+            default: value.default.as_ref().map(map),
+        }
+    }
+}
+
+impl From<&Eval> for EvalDef {
+    fn from(eval: &Eval) -> Self {
+        EvalDef {
+            field: eval.field.id.to_string(),
+            values: eval.values.iter().map(|value| value.to_string()).collect(),
+            condition: match eval.condition {
+                Condition::MatchAny => ConditionDef::MatchAny,
+                Condition::MatchNone => ConditionDef::MatchNone,
+            },
+        }
+    }
+}
+
+impl From<&FormValue> for FormValueDef {
+    fn from(value: &FormValue) -> Self {
+        match value {
+            FormValue::Value(value) => FormValueDef::Value(value.clone()),
+            FormValue::Array(values) => FormValueDef::Array(values.clone()),
+            FormValue::Expression(expr) => FormValueDef::Expression {
+                if_thens: expr
+                    .if_thens
+                    .iter()
+                    .map(|if_then| (if_then.if_.clone(), if_then.then_.clone()))
+                    .collect(),
+                else_: expr.else_.clone(),
+            },
+        }
+    }
+}
+
+impl From<&InputCheck> for InputCheckDef {
+    fn from(check: &InputCheck) -> Self {
+        InputCheckDef {
+            transformers: check
+                .transformers
+                .iter()
+                .map(|transformer| match transformer {
+                    Transformer::Trim => TransformerDef::Trim,
+                    Transformer::RemoveSpaces => TransformerDef::RemoveSpaces,
+                    Transformer::Lowercase => TransformerDef::Lowercase,
+                    Transformer::Uppercase => TransformerDef::Uppercase,
+                    Transformer::HashSecret => TransformerDef::HashSecret,
+                    Transformer::NormalizeIp => TransformerDef::NormalizeIp,
+                })
+                .collect(),
+            validators: check.validators.iter().map(ValidatorDef::from).collect(),
+        }
+    }
+}
+
+impl From<&Validator> for ValidatorDef {
+    fn from(validator: &Validator) -> Self {
+        match *validator {
+            Validator::Required => ValidatorDef::Required,
+            Validator::IsEmail => ValidatorDef::IsEmail,
+            Validator::IsId => ValidatorDef::IsId,
+            Validator::IsHost => ValidatorDef::IsHost,
+            Validator::IsDomain => ValidatorDef::IsDomain,
+            Validator::IsPort => ValidatorDef::IsPort,
+            Validator::IsIpOrMask => ValidatorDef::IsIpOrMask,
+            Validator::IsUrl => ValidatorDef::IsUrl,
+            Validator::IsRegex => ValidatorDef::IsRegex,
+            Validator::IsSocketAddr => ValidatorDef::IsSocketAddr,
+            Validator::MinLength(value) => ValidatorDef::MinLength { value },
+            Validator::MaxLength(value) => ValidatorDef::MaxLength { value },
+            Validator::MinValue(value) => ValidatorDef::MinValue {
+                value: value.into(),
+            },
+            Validator::MaxValue(value) => ValidatorDef::MaxValue {
+                value: value.into(),
+            },
+            Validator::MinItems(value) => ValidatorDef::MinItems { value },
+            Validator::MaxItems(value) => ValidatorDef::MaxItems { value },
+            Validator::IsValidExpression(expr) => ValidatorDef::IsValidExpression {
+                variables: expr.variables.iter().map(|v| v.to_string()).collect(),
+                constants: expr.constants.iter().map(|c| c.to_string()).collect(),
+            },
+        }
+    }
+}
+
+impl From<&Section> for SectionDef {
+    fn from(section: &Section) -> Self {
+        SectionDef {
+            title: section.title.map(str::to_string),
+            display: section.display.iter().map(EvalDef::from).collect(),
+            fields: section
+                .fields
+                .iter()
+                .map(|field| field.id.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Serializes schemas as a JSON array, sorted by schema id.
+pub fn schemas_to_json<'x>(schemas: impl IntoIterator<Item = &'x Schema>) -> String {
+    let mut defs = schemas.into_iter().map(SchemaDef::from).collect::<Vec<_>>();
+    defs.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+    serde_json::to_string_pretty(&defs).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::core::schema::Schemas;
+
+    fn schemas() -> Schemas {
+        Schemas::builder()
+            .new_schema("store")
+            .prefix("store")
+            .suffix("type")
+            .names("store", "stores")
+            .new_id_field()
+            .build()
+            .new_field("type")
+            .label("Type")
+            .typ(Type::Select {
+                typ: SelectType::Single,
+                source: Source::Static(&[("rocksdb", "RocksDB"), ("sql", "SQL")]),
+            })
+            .input_check([], [])
+            .default("rocksdb")
+            .build()
+            .new_field("pool.max-connections")
+            .label("Max connections")
+            .typ(Type::Number {
+                signed: false,
+                min: Some(NumberType::Integer(1)),
+                max: Some(NumberType::Integer(8192)),
+            })
+            .default_if_eq("type", ["sql"], "10")
+            .build()
+            .new_field("lookup")
+            .label("Lookup")
+            .typ(Type::Expression)
+            .build()
+            .new_field("path")
+            .label("Path")
+            .help("Where the data is stored")
+            .typ(Type::Input)
+            .input_check([Transformer::Trim], [Validator::Required])
+            .placeholder("/opt/stalwart/data")
+            .build()
+            .new_field("hosts")
+            .label("Hosts")
+            .typ(Type::Array)
+            .input_check([Transformer::Trim], [Validator::MinItems(1)])
+            .display_if_eq("type", ["sql"])
+            .build()
+            .new_form_section()
+            .title("Configuration")
+            .fields(["_id", "type", "path"])
+            .build()
+            .new_form_section()
+            .fields(["pool.max-connections", "lookup", "hosts"])
+            .display_if_eq("type", ["sql"])
+            .build()
+            .build()
+            .new_schema("directory")
+            .new_field("store")
+            .label("Store")
+            .typ(Type::Select {
+                typ: SelectType::Many,
+                source: Source::Dynamic {
+                    schema: "store",
+                    field: "type",
+                    filter: Default::default(),
+                },
+            })
+            .build()
+            .build()
+            .build()
+    }
+
+    fn field<'x>(def: &'x SchemaDef, id: &str) -> &'x FieldDef {
+        def.fields.iter().find(|field| field.id == id).unwrap()
+    }
+
+    #[test]
+    fn serialize_schema_definition() {
+        let schemas = schemas();
+        let def = SchemaDef::from(schemas.get("store").as_ref());
+
+        assert_eq!(
+            def.fields
+                .iter()
+                .map(|field| field.id.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "_id",
+                "hosts",
+                "lookup",
+                "path",
+                "pool.max-connections",
+                "type"
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&def.typ).unwrap(),
+            json!({"kind": "record", "prefix": "store", "suffix": "type"})
+        );
+        assert_eq!(
+            serde_json::to_value(&field(&def, "type").typ).unwrap(),
+            json!({
+                "kind": "select",
+                "multiple": "single",
+                "source": {"kind": "static", "options": [["rocksdb", "RocksDB"], ["sql", "SQL"]]}
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(&field(&def, "pool.max-connections")).unwrap()["type"],
+            json!({"kind": "number", "signed": false, "min": 1, "max": 8192})
+        );
+        assert_eq!(
+            serde_json::to_value(&field(&def, "pool.max-connections").default).unwrap(),
+            json!({
+                "if_thens": [{
+                    "if": {"field": "type", "values": ["sql"], "condition": "match_any"},
+                    "then": {"value": "10"}
+                }],
+                "default": null
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(&field(&def, "hosts").checks.default).unwrap(),
+            json!({"transformers": ["trim"], "validators": [{"kind": "min_items", "value": 1}]})
+        );
+        assert!(field(&def, "path").display.is_empty());
+        assert_eq!(def.sections[1].display, field(&def, "hosts").display);
+
+        let def = SchemaDef::from(schemas.get("directory").as_ref());
+        assert_eq!(
+            serde_json::to_value(&field(&def, "store").typ).unwrap()["source"],
+            json!({
+                "kind": "dynamic",
+                "schema": "store",
+                "field": "type",
+                "filter": {"if_thens": [], "default": null}
+            })
+        );
+    }
+
+    #[test]
+    fn schema_definition_round_trip() {
+        let schemas = schemas();
+        let json = schemas_to_json(schemas.schemas.values().map(|schema| schema.as_ref()));
+        let defs = serde_json::from_str::<Vec<SchemaDef>>(&json).unwrap();
+
+        assert_eq!(
+            defs,
+            vec![
+                SchemaDef::from(schemas.get("directory").as_ref()),
+                SchemaDef::from(schemas.get("store").as_ref()),
+            ]
+        );
+        assert_eq!(serde_json::to_string_pretty(&defs).unwrap(), json);
+    }
+
+    #[test]
+    fn builtin_schemas_round_trip() {
+        let schemas = crate::build_schemas();
+        let json = schemas_to_json(schemas.schemas.values().map(|schema| schema.as_ref()));
+        let defs = serde_json::from_str::<Vec<SchemaDef>>(&json).unwrap();
+
+        assert_eq!(defs.len(), schemas.schemas.len());
+        assert_eq!(serde_json::to_string_pretty(&defs).unwrap(), json);
+    }
+}
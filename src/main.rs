@@ -46,14 +46,20 @@ pub static VERSION_NAME: &str = concat!("Stalwart Management UI v", env!("CARGO_
 
 use crate::{
     components::{
-        layout::{Layout, LayoutBuilder},
+        layout::{config::init_layout_config, density::init_density, Layout, LayoutBuilder},
+        list::page_size::init_page_sizes,
         messages::{alert::init_alerts, modal::init_modals},
     },
     core::oauth::oauth_refresh_token,
     pages::{
         account::{crypto::ManageCrypto, password::ChangePassword},
         authorize::Authorize,
-        config::{edit::SettingsEdit, list::SettingsList, search::SettingsSearch},
+        config::{
+            bookmarks::init_bookmarks, edit::SettingsEdit, lint::SettingsLint, list::SettingsList,
+            proposal::init_proposals, report::SettingsReport, restart::init_pending_restart,
+            scheduled::init_scheduled_changes, search::SettingsSearch, snapshot::SettingsSnapshots,
+            staging::init_staged_changes, validate::SettingsValidate,
+        },
         login::Login,
         manage::{logs::Logs, maintenance::Maintenance},
         notfound::NotFound,
@@ -68,13 +74,16 @@ use crate::{
 pub mod components;
 pub mod core;
 pub mod pages;
+pub mod utils;
 
 pub const STATE_STORAGE_KEY: &str = "webadmin_state";
 pub const STATE_LOGIN_NAME_KEY: &str = "webadmin_login_name";
 
 fn main() {
-    _ = console_log::init_with_level(log::Level::Debug);
-    console_error_panic_hook::set_once();
+    utils::logging::init_logging();
+    utils::telemetry::init_error_reporting();
+    utils::audit::init_audit_forwarding();
+    utils::security::init_rate_limits();
     leptos::mount_to_body(|| view! { <App/> })
 }
 
@@ -94,6 +103,14 @@ pub fn App() -> impl IntoView {
     provide_context(build_schemas());
     init_alerts();
     init_modals();
+    init_staged_changes();
+    init_proposals();
+    init_scheduled_changes();
+    init_bookmarks();
+    init_density();
+    init_layout_config();
+    init_page_sizes();
+    init_pending_restart();
 
     // Create a resource to refresh the OAuth token
     let _refresh_token_resource = create_resource(
@@ -461,7 +478,7 @@ pub fn App() -> impl IntoView {
                         condition=move || {
                             permissions
                                 .get()
-                                .is_some_and(|p| { p.has_access(Permission::SettingsUpdate) })
+                                .is_some_and(|p| { p.has_access(Permission::SettingsList) })
                         }
                     />
 
@@ -476,6 +493,53 @@ pub fn App() -> impl IntoView {
                         }
                     />
 
+                    <ProtectedRoute
+                        path="/report"
+                        view=SettingsReport
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| { p.has_access(Permission::SettingsList) })
+                        }
+                    />
+
+                    <ProtectedRoute
+                        path="/lint"
+                        view=SettingsLint
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| { p.has_access(Permission::SettingsList) })
+                        }
+                    />
+
+                    <ProtectedRoute
+                        path="/validate"
+                        view=SettingsValidate
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| { p.has_access(Permission::SettingsList) })
+                        }
+                    />
+
+                    <ProtectedRoute
+                        path="/snapshots"
+                        view=SettingsSnapshots
+                        redirect_path="/login"
+                        condition=move || {
+                            permissions
+                                .get()
+                                .is_some_and(|p| {
+                                    p.has_access(Permission::SettingsList)
+                                        && p.has_access(Permission::SettingsUpdate)
+                                })
+                        }
+                    />
+
                     <ProtectedRoute
                         path="/layout"
                         view=LayoutConfigPage
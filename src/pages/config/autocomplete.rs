@@ -0,0 +1,278 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::AHashMap;
+
+use crate::core::{
+    http::{self, HttpRequest},
+    AccessToken,
+};
+
+use super::{edit::FetchSettings, Schema, SchemaType, Settings, UpdateSettings};
+
+const MAX_SUGGESTIONS: usize = 10;
+
+/// Fetches the settings of every record of a schema, keyed relative to the
+/// schema prefix. Only record schemas have sibling records to suggest from.
+pub async fn fetch_record_values(auth: &AccessToken, schema: &Schema) -> http::Result<Settings> {
+    match schema.typ {
+        SchemaType::Record { prefix, .. } => HttpRequest::get("/api/settings/list")
+            .with_authorization(auth)
+            .with_parameter("prefix", prefix)
+            .send::<FetchSettings>()
+            .await
+            .map(|list| list.items),
+        SchemaType::Entry { .. } | SchemaType::List => Ok(Settings::default()),
+    }
+}
+
+/// Collects the values stored for a field across all records, most frequent
+/// first and alphabetically among values used equally often. Keys have the
+/// form `<record>.<field>` or `<record>.<field>.<index>`.
+pub fn rank_values(settings: &Settings, field_id: &str) -> Vec<String> {
+    let suffix = format!(".{field_id}");
+    let mut counts: AHashMap<&str, usize> = AHashMap::new();
+
+    for (key, value) in settings {
+        let key = match key.rsplit_once('.') {
+            Some((key, index)) if index.bytes().all(|ch| ch.is_ascii_digit()) => key,
+            _ => key.as_str(),
+        };
+        let value = value.trim();
+        if key.ends_with(&suffix) && key.len() > suffix.len() && !value.is_empty() {
+            *counts.entry(value).or_default() += 1;
+        }
+    }
+
+    let mut values = counts.into_iter().collect::<Vec<_>>();
+    values.sort_unstable_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    values
+        .into_iter()
+        .map(|(value, _)| value.to_string())
+        .collect()
+}
+
+/// Narrows the ranked suggestions to those starting with the typed text,
+/// ignoring case and leaving out values the field already contains.
+pub fn filter_suggestions(suggestions: &[String], typed: &str, existing: &[String]) -> Vec<String> {
+    let typed = typed.trim().to_lowercase();
+    suggestions
+        .iter()
+        .filter(|value| value.to_lowercase().starts_with(&typed) && !existing.contains(value))
+        .take(MAX_SUGGESTIONS)
+        .cloned()
+        .collect()
+}
+
+/// Returns the ids of the records in settings keyed relative to the schema
+/// prefix, as fetched by [`fetch_record_values`].
+pub fn stored_record_ids(settings: &Settings, schema: &Schema) -> Vec<String> {
+    let SchemaType::Record { suffix, .. } = schema.typ else {
+        return Vec::new();
+    };
+    let suffix = format!(".{suffix}");
+    let mut ids = settings
+        .keys()
+        .filter_map(|key| key.strip_suffix(&suffix))
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// Proposes `<base>-<n>` with `n` one above the highest number in use. Gaps
+/// left by deleted records are not reused, as settings elsewhere may still
+/// refer to them.
+pub fn next_free_id<'x>(base: &str, existing: impl IntoIterator<Item = &'x str>) -> String {
+    let next = existing
+        .into_iter()
+        .filter_map(|id| {
+            let number = id.strip_prefix(base)?;
+            if number.is_empty() {
+                Some(0)
+            } else {
+                number.strip_prefix('-')?.parse::<u64>().ok()
+            }
+        })
+        .max()
+        .map_or(1, |number| number + 1);
+    format!("{base}-{next}")
+}
+
+/// Fetches the records of a schema and proposes the next free id, or `None`
+/// when the schema does not suggest ids.
+pub async fn suggest_record_id(
+    auth: &AccessToken,
+    schema: &Schema,
+) -> http::Result<Option<String>> {
+    let Some(base) = schema.suggested_id else {
+        return Ok(None);
+    };
+    let settings = fetch_record_values(auth, schema).await?;
+    let ids = stored_record_ids(&settings, schema);
+    Ok(Some(next_free_id(base, ids.iter().map(String::as_str))))
+}
+
+/// Moves the inserts of a new record from one id to another, used when the
+/// suggested id was taken by someone else before saving.
+pub fn with_record_id(
+    updates: &[UpdateSettings],
+    schema: &Schema,
+    from: &str,
+    to: &str,
+) -> Vec<UpdateSettings> {
+    let SchemaType::Record { prefix, .. } = schema.typ else {
+        return updates.to_vec();
+    };
+    let from = format!("{prefix}.{from}");
+    updates
+        .iter()
+        .map(|update| match update {
+            UpdateSettings::Insert {
+                prefix: Some(record),
+                values,
+                assert_empty,
+            } if *record == from => UpdateSettings::Insert {
+                prefix: Some(format!("{prefix}.{to}")),
+                values: values.clone(),
+                assert_empty: *assert_empty,
+            },
+            update => update.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(values: &[(&str, &str)]) -> Settings {
+        values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn rank_values_by_frequency() {
+        let settings = settings(&[
+            ("smtp.bind.0", "[::]:25"),
+            ("smtp.protocol", "smtp"),
+            ("submission.bind.0", "[::]:587"),
+            ("submission.bind.1", "[::]:25"),
+            ("submissions.bind.0", "[::]:465"),
+            ("imap.example.org.bind.0", "[::]:25"),
+            ("imap.example.org.bind.1", " "),
+            ("imap.rebind.0", "[::]:143"),
+            ("bind", "0.0.0.0:25"),
+        ]);
+        assert_eq!(
+            rank_values(&settings, "bind"),
+            vec!["[::]:25", "[::]:465", "[::]:587"]
+        );
+        assert_eq!(rank_values(&settings, "protocol"), vec!["smtp"]);
+        assert!(rank_values(&settings, "tls.certificate").is_empty());
+    }
+
+    #[test]
+    fn filter_by_typed_prefix() {
+        let suggestions = vec![
+            "[::]:25".to_string(),
+            "[::]:465".to_string(),
+            "0.0.0.0:25".to_string(),
+        ];
+        assert_eq!(filter_suggestions(&suggestions, "", &[]), suggestions);
+        assert_eq!(
+            filter_suggestions(&suggestions, "[::]:4", &[]),
+            vec!["[::]:465"]
+        );
+        assert_eq!(
+            filter_suggestions(&suggestions, " [", &["[::]:25".to_string()]),
+            vec!["[::]:465"]
+        );
+        assert!(filter_suggestions(&suggestions, "127", &[]).is_empty());
+
+        let uppercase = vec!["MX.Example.org".to_string()];
+        assert_eq!(filter_suggestions(&uppercase, "mx.ex", &[]), uppercase);
+    }
+
+    #[test]
+    fn next_free_id_skips_gaps() {
+        assert_eq!(next_free_id("signature", []), "signature-1");
+        assert_eq!(
+            next_free_id("signature", ["signature-1", "signature-3"]),
+            "signature-4"
+        );
+        assert_eq!(
+            next_free_id("signature", ["signature", "rsa-example.org"]),
+            "signature-1"
+        );
+        assert_eq!(
+            next_free_id(
+                "signature",
+                [
+                    "signature-2",
+                    "signature-10",
+                    "signatures-40",
+                    "signature-x",
+                    "signature-"
+                ]
+            ),
+            "signature-11"
+        );
+    }
+
+    #[test]
+    fn collect_ids_and_move_record() {
+        let schemas = crate::build_schemas();
+        let schema = &schemas.schemas["signature"];
+        let settings = settings(&[
+            ("signature-1.algorithm", "ed25519-sha256"),
+            ("signature-1.domain", "example.org"),
+            ("signature-3.algorithm", "rsa-sha256"),
+        ]);
+        let ids = stored_record_ids(&settings, schema);
+        assert_eq!(ids, vec!["signature-1", "signature-3"]);
+        assert_eq!(
+            next_free_id("signature", ids.iter().map(String::as_str)),
+            "signature-4"
+        );
+
+        let updates = vec![UpdateSettings::Insert {
+            prefix: Some("signature.signature-4".to_string()),
+            values: vec![("algorithm".to_string(), "rsa-sha256".to_string())],
+            assert_empty: true,
+        }];
+        assert_eq!(
+            with_record_id(&updates, schema, "signature-4", "signature-5"),
+            vec![UpdateSettings::Insert {
+                prefix: Some("signature.signature-5".to_string()),
+                values: vec![("algorithm".to_string(), "rsa-sha256".to_string())],
+                assert_empty: true,
+            }]
+        );
+        assert_eq!(
+            with_record_id(&updates, schema, "signature-1", "signature-5"),
+            updates
+        );
+    }
+
+    #[test]
+    fn record_schemas_suggest_ids() {
+        let schemas = crate::build_schemas();
+        for (id, base) in [
+            ("signature", "signature"),
+            ("web-hooks", "webhook"),
+            ("alerts", "alert"),
+        ] {
+            let schema = &schemas.schemas[id];
+            assert_eq!(schema.suggested_id, Some(base));
+            assert!(matches!(schema.typ, SchemaType::Record { .. }));
+        }
+    }
+}
@@ -0,0 +1,380 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use gloo_storage::{LocalStorage, Storage};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{icon::IconBookmark, messages::load_error::Reload},
+    core::oauth::use_authorization,
+    pages::config::edit::fetch_current_settings,
+};
+
+use super::{SchemaType, Schemas, Settings, SettingsValues};
+
+const BOOKMARKS_STORAGE_KEY: &str = "webadmin_bookmarks";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bookmarks {
+    username: String,
+    items: Vec<Bookmark>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub schema_id: String,
+    pub record_id: Option<String>,
+    pub field_id: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BookmarkValue {
+    pub bookmark: Bookmark,
+    pub title: String,
+    pub value: String,
+    pub url: String,
+}
+
+#[derive(Clone, Copy)]
+struct BookmarkRefresh(Reload);
+
+pub fn init_bookmarks() {
+    let auth = use_authorization();
+    let bookmarks = create_rw_signal(Bookmarks::default());
+
+    // Bookmarks are stored per user, reload them when the session changes
+    create_effect(move |_| {
+        let username = auth.with(|auth| auth.username.to_string());
+        if bookmarks.with_untracked(|bookmarks| bookmarks.username != username) {
+            bookmarks.set(Bookmarks::load(&username));
+        }
+    });
+
+    provide_context(bookmarks);
+    provide_context(BookmarkRefresh(Reload::new()));
+}
+
+pub fn use_bookmarks() -> RwSignal<Bookmarks> {
+    expect_context::<RwSignal<Bookmarks>>()
+}
+
+/// Returns the trigger used to refresh bookmarked values after the
+/// settings are saved or reloaded.
+pub fn use_bookmark_refresh() -> Reload {
+    expect_context::<BookmarkRefresh>().0
+}
+
+impl Bookmarks {
+    pub fn load(username: &str) -> Self {
+        Bookmarks {
+            username: username.to_string(),
+            items: if !username.is_empty() {
+                LocalStorage::get(Self::storage_key(username)).unwrap_or_default()
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    pub fn save(&self) {
+        if self.username.is_empty() {
+            return;
+        }
+        if let Err(err) = LocalStorage::set(Self::storage_key(&self.username), &self.items) {
+            log::error!("Failed to save bookmarks to local storage: {}", err);
+        }
+    }
+
+    fn storage_key(username: &str) -> String {
+        format!("{BOOKMARKS_STORAGE_KEY}.{username}")
+    }
+
+    pub fn add(&mut self, bookmark: Bookmark) -> bool {
+        if !self.contains(&bookmark) {
+            self.items.push(bookmark);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn remove(&mut self, bookmark: &Bookmark) -> bool {
+        let len = self.items.len();
+        self.items.retain(|item| item != bookmark);
+        self.items.len() != len
+    }
+
+    pub fn toggle(&mut self, bookmark: Bookmark) {
+        if !self.remove(&bookmark) {
+            self.add(bookmark);
+        }
+    }
+
+    pub fn contains(&self, bookmark: &Bookmark) -> bool {
+        self.items.contains(bookmark)
+    }
+
+    pub fn items(&self) -> &[Bookmark] {
+        &self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl Bookmark {
+    pub fn new(
+        schema_id: impl Into<String>,
+        record_id: Option<impl Into<String>>,
+        field_id: impl Into<String>,
+    ) -> Self {
+        Bookmark {
+            schema_id: schema_id.into(),
+            record_id: record_id.map(Into::into),
+            field_id: field_id.into(),
+        }
+    }
+
+    /// Resolves the bookmarked field against the settings currently stored
+    /// for its schema and record. Returns `None` when the schema or field
+    /// no longer exist.
+    pub fn resolve(&self, schemas: &Schemas, settings: Option<&Settings>) -> Option<BookmarkValue> {
+        let schema = schemas.schemas.get(self.schema_id.as_str())?;
+        let field = schema.fields.get(self.field_id.as_str())?;
+        let title = match (&self.record_id, &schema.typ) {
+            (Some(record_id), SchemaType::Record { .. } | SchemaType::Entry { .. }) => {
+                format!("{} {record_id}: {}", schema.name_singular, field.label_form)
+            }
+            _ => format!("{}: {}", schema.form.title, field.label_form),
+        };
+        let url = match &self.record_id {
+            Some(record_id) => format!("/settings/{}/{record_id}/edit", schema.id),
+            None => format!("/settings/{}/edit", schema.id),
+        };
+
+        Some(BookmarkValue {
+            bookmark: self.clone(),
+            title,
+            value: settings
+                .map(|settings| settings.format(field))
+                .unwrap_or_default(),
+            url,
+        })
+    }
+}
+
+#[component]
+pub fn BookmarkToggle(bookmark: Bookmark) -> impl IntoView {
+    let bookmarks = use_bookmarks();
+    let bookmark = store_value(bookmark);
+    let is_bookmarked =
+        create_memo(move |_| bookmarks.with(|bookmarks| bookmarks.contains(&bookmark.get_value())));
+
+    // Rendered as a link so that it remains usable inside disabled fieldsets
+    view! {
+        <a
+            href="#"
+            class=move || {
+                if is_bookmarked.get() {
+                    "inline-flex mt-2.5 text-blue-600 hover:text-blue-700 dark:text-blue-500"
+                } else {
+                    "inline-flex mt-2.5 text-gray-300 hover:text-gray-500 dark:text-gray-600"
+                }
+            }
+
+            title=move || if is_bookmarked.get() { "Remove bookmark" } else { "Bookmark" }
+            on:click=move |ev| {
+                ev.prevent_default();
+                bookmarks
+                    .update(|bookmarks| {
+                        bookmarks.toggle(bookmark.get_value());
+                        bookmarks.save();
+                    });
+            }
+        >
+
+            <IconBookmark attr:class="flex-shrink-0 size-4"/>
+        </a>
+    }
+}
+
+#[component]
+pub fn BookmarkedSettings() -> impl IntoView {
+    let auth = use_authorization();
+    let bookmarks = use_bookmarks();
+    let refresh = use_bookmark_refresh();
+    let schemas = expect_context::<Arc<Schemas>>();
+
+    let values = create_resource(
+        move || (bookmarks.get().items().to_vec(), refresh.track()),
+        move |(items, _)| {
+            let auth = auth.get_untracked();
+            let schemas = schemas.clone();
+
+            async move {
+                let mut fetched: AHashMap<(String, Option<String>), Option<Settings>> =
+                    AHashMap::new();
+                let mut values = Vec::with_capacity(items.len());
+
+                for bookmark in items {
+                    let Some(schema) = schemas.schemas.get(bookmark.schema_id.as_str()) else {
+                        continue;
+                    };
+                    let key = (bookmark.schema_id.clone(), bookmark.record_id.clone());
+                    if !fetched.contains_key(&key) {
+                        let settings = fetch_current_settings(
+                            &auth,
+                            schema,
+                            bookmark.record_id.as_deref().unwrap_or_default(),
+                        )
+                        .await
+                        .unwrap_or_else(|err| {
+                            log::warn!("Failed to fetch bookmarked settings: {err:?}");
+                            None
+                        });
+                        fetched.insert(key.clone(), settings);
+                    }
+
+                    if let Some(value) =
+                        bookmark.resolve(&schemas, fetched.get(&key).and_then(|s| s.as_ref()))
+                    {
+                        values.push(value);
+                    }
+                }
+
+                values
+            }
+        },
+    );
+
+    view! {
+        <Show when=move || !bookmarks.with(|bookmarks| bookmarks.is_empty())>
+            <div class="flex flex-col bg-white border shadow-sm rounded-xl p-4 md:p-5 mb-4 sm:mb-6 dark:bg-neutral-800 dark:border-neutral-700">
+                <h2 class="text-sm font-semibold text-gray-800 dark:text-neutral-200">
+                    "Bookmarked settings"
+                </h2>
+                <ul class="mt-3 divide-y divide-gray-200 dark:divide-neutral-700">
+                    {move || {
+                        values
+                            .get()
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|value| {
+                                let bookmark = value.bookmark.clone();
+                                view! {
+                                    <li class="flex items-center justify-between gap-x-3 py-2">
+                                        <a
+                                            href=value.url
+                                            class="text-sm text-gray-600 hover:text-blue-600 dark:text-neutral-400"
+                                        >
+                                            {value.title}
+                                        </a>
+                                        <div class="inline-flex items-center gap-x-2">
+                                            <span class="text-sm font-medium text-gray-800 dark:text-neutral-200">
+                                                {if value.value.is_empty() {
+                                                    "Not set".to_string()
+                                                } else {
+                                                    value.value
+                                                }}
+                                            </span>
+                                            <BookmarkToggle bookmark=bookmark/>
+                                        </div>
+                                    </li>
+                                }
+                            })
+                            .collect_view()
+                    }}
+
+                </ul>
+            </div>
+        </Show>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::schema::Type;
+
+    fn schemas() -> Schemas {
+        Schemas::builder()
+            .new_schema("throttle")
+            .names("throttle", "throttles")
+            .prefix("queue.throttle")
+            .suffix("enable")
+            .new_id_field()
+            .build()
+            .new_field("rate")
+            .label("Rate")
+            .typ(Type::Rate)
+            .build()
+            .new_field("enable")
+            .label("Enable")
+            .typ(Type::Boolean)
+            .build()
+            .build()
+            .build()
+    }
+
+    #[test]
+    fn add_and_remove_bookmarks() {
+        let mut bookmarks = Bookmarks::default();
+        let rate = Bookmark::new("throttle", Some("rcpt"), "rate");
+        let enable = Bookmark::new("throttle", Some("rcpt"), "enable");
+
+        assert!(bookmarks.add(rate.clone()));
+        assert!(!bookmarks.add(rate.clone()));
+        assert!(bookmarks.add(enable.clone()));
+        assert_eq!(bookmarks.items(), &[rate.clone(), enable.clone()]);
+
+        assert!(bookmarks.remove(&rate));
+        assert!(!bookmarks.remove(&rate));
+        assert_eq!(bookmarks.items(), &[enable.clone()]);
+
+        bookmarks.toggle(enable.clone());
+        assert!(bookmarks.is_empty());
+        bookmarks.toggle(enable.clone());
+        assert!(bookmarks.contains(&enable));
+    }
+
+    #[test]
+    fn resolve_bookmarks_to_current_values() {
+        let schemas = schemas();
+        let settings = Settings::from_iter([
+            ("_id".to_string(), "rcpt".to_string()),
+            ("rate".to_string(), "5/1s".to_string()),
+            ("enable".to_string(), "true".to_string()),
+        ]);
+
+        let value = Bookmark::new("throttle", Some("rcpt"), "enable")
+            .resolve(&schemas, Some(&settings))
+            .unwrap();
+        assert_eq!(value.title, "throttle rcpt: Enable");
+        assert_eq!(value.value, "Yes");
+        assert_eq!(value.url, "/settings/throttle/rcpt/edit");
+
+        let rate = Bookmark::new("throttle", Some("rcpt"), "rate");
+        assert!(!rate
+            .resolve(&schemas, Some(&settings))
+            .unwrap()
+            .value
+            .is_empty());
+        assert_eq!(rate.resolve(&schemas, None).unwrap().value, "");
+
+        // Stale bookmarks are skipped
+        assert!(Bookmark::new("throttle", Some("rcpt"), "missing")
+            .resolve(&schemas, Some(&settings))
+            .is_none());
+        assert!(Bookmark::new("missing", None::<String>, "rate")
+            .resolve(&schemas, None)
+            .is_none());
+    }
+}
@@ -0,0 +1,188 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::AHashMap;
+use leptos::*;
+
+use crate::{
+    components::{form::button::Button, Color},
+    core::{
+        form::FormValue,
+        merge::{MergeConflict, MergeResult, Resolution},
+        schema::Type,
+    },
+};
+
+use super::REDACTED;
+
+/// Lists the settings changed both in the form and on the server since the
+/// form was loaded, so that the user keeps either value for each of them
+/// before saving.
+#[component]
+pub fn MergeConflicts(
+    merge: RwSignal<Option<MergeResult>>,
+    #[prop(into)] disabled: Signal<bool>,
+    on_save: Callback<MergeResult>,
+) -> impl IntoView {
+    let choices = create_rw_signal(AHashMap::<String, Resolution>::new());
+    let conflicts = create_memo(move |_| {
+        merge.with(|merge| {
+            merge
+                .as_ref()
+                .map(|merge| {
+                    merge
+                        .conflicts
+                        .iter()
+                        .map(|conflict| {
+                            (conflict.field.clone(), describe_conflict(merge, conflict))
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        })
+    });
+    let is_unresolved = move || {
+        conflicts.with(|conflicts| {
+            choices.with(|choices| {
+                conflicts
+                    .iter()
+                    .any(|(field, _)| !choices.contains_key(field))
+            })
+        })
+    };
+    let close = move || {
+        choices.set(AHashMap::new());
+        merge.set(None);
+    };
+
+    view! {
+        <div class="pb-5" class:hidden=move || conflicts.with(|conflicts| conflicts.is_empty())>
+            <div class="bg-white border border-yellow-300 rounded-lg p-4 dark:bg-slate-900 dark:border-yellow-700">
+                <h3 class="text-sm font-semibold text-gray-800 dark:text-gray-200">
+                    "Conflicting changes"
+                </h3>
+                <p class="mt-1 text-xs text-gray-500">
+                    "These settings were modified by another user while you were editing. Choose which value to keep for each of them."
+                </p>
+                <ul class="mt-2 divide-y divide-gray-200 dark:divide-gray-700">
+                    <For
+                        each=move || conflicts.get()
+                        key=|(field, _)| field.clone()
+                        children=move |(field, (label, local, remote))| {
+                            let field = store_value(field);
+                            let is_chosen = move |resolution: Resolution| {
+                                choices
+                                    .with(|choices| {
+                                        choices.get(&field.get_value()) == Some(&resolution)
+                                    })
+                            };
+                            let choose = move |resolution: Resolution| {
+                                choices
+                                    .update(|choices| {
+                                        choices.insert(field.get_value(), resolution);
+                                    });
+                            };
+
+                            view! {
+                                <li class="flex items-center justify-between gap-x-2 py-3">
+                                    <div class="text-sm">
+                                        <p class="font-medium text-gray-800 dark:text-gray-200">
+                                            {label}
+                                        </p>
+                                        <p class="text-gray-500 break-all">
+                                            "Yours: " {local}
+                                        </p>
+                                        <p class="text-gray-500 break-all">
+                                            "Theirs: " {remote}
+                                        </p>
+                                    </div>
+                                    <div class="inline-flex gap-x-2">
+                                        <Button
+                                            text="Keep mine"
+                                            color=Signal::derive(move || {
+                                                if is_chosen(Resolution::Local) {
+                                                    Color::Blue
+                                                } else {
+                                                    Color::Gray
+                                                }
+                                            })
+                                            on_click=move |_| choose(Resolution::Local)
+                                            disabled=disabled
+                                        />
+                                        <Button
+                                            text="Use theirs"
+                                            color=Signal::derive(move || {
+                                                if is_chosen(Resolution::Remote) {
+                                                    Color::Blue
+                                                } else {
+                                                    Color::Gray
+                                                }
+                                            })
+                                            on_click=move |_| choose(Resolution::Remote)
+                                            disabled=disabled
+                                        />
+                                    </div>
+                                </li>
+                            }
+                        }
+                    />
+
+                </ul>
+                <div class="mt-3 flex justify-end gap-x-2">
+                    <Button text="Cancel" color=Color::Gray on_click=move |_| close() />
+                    <Button
+                        text="Save"
+                        color=Color::Blue
+                        on_click=move |_| {
+                            let Some(mut result) = merge.get() else {
+                                return;
+                            };
+                            if choices.with(|choices| result.resolve_each(choices)) {
+                                close();
+                                on_save.call(result);
+                            }
+                        }
+
+                        disabled=Signal::derive(move || disabled.get() || is_unresolved())
+                    />
+                </div>
+            </div>
+        </div>
+    }
+}
+
+/// The label of a conflicting field, and its values as shown to the user
+/// with secrets hidden.
+fn describe_conflict(merge: &MergeResult, conflict: &MergeConflict) -> (String, String, String) {
+    let field = merge.merged.schema.fields.get(conflict.field.as_str());
+    let label = field
+        .map(|field| field.label_form)
+        .filter(|label| !label.is_empty())
+        .unwrap_or(conflict.field.as_str())
+        .to_string();
+    let is_secret = field.is_some_and(|field| matches!(field.typ_, Type::Secret));
+    let describe = |value: &Option<FormValue>| match value {
+        Some(_) if is_secret => REDACTED.to_string(),
+        Some(value) => summarize(value),
+        None => "(not set)".to_string(),
+    };
+
+    (label, describe(&conflict.local), describe(&conflict.remote))
+}
+
+fn summarize(value: &FormValue) -> String {
+    match value {
+        FormValue::Value(value) => value.clone(),
+        FormValue::Array(values) => values.join(", "),
+        FormValue::Expression(expr) => expr
+            .if_thens
+            .iter()
+            .map(|if_then| format!("if {} then {}", if_then.if_, if_then.then_))
+            .chain(std::iter::once(format!("else {}", expr.else_)))
+            .collect::<Vec<_>>()
+            .join("; "),
+    }
+}
@@ -0,0 +1,312 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::collections::BTreeMap;
+
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        badge::Badge,
+        form::button::Button,
+        messages::{
+            alert::{use_alerts, Alert},
+            load_error::{LoadError, Reload},
+        },
+        skeleton::SkeletonTable,
+        Color,
+    },
+    core::{
+        http::HttpRequest,
+        ipnet::{normalize_ip, IpNetwork},
+        oauth::use_authorization,
+    },
+    pages::{config::ip_rules::fetch_ip_list, maybe_plural},
+};
+
+use super::UpdateSettings;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub key: String,
+    pub entries: Vec<String>,
+}
+
+/// Returns the key used to compare list entries. Domain names are compared
+/// case-insensitively and without the trailing dot, and IP addresses or
+/// networks are compared in canonical notation.
+pub fn normalize_entry(entry: &str) -> String {
+    let entry = entry.trim();
+    if entry.parse::<IpNetwork>().is_ok() {
+        normalize_ip(entry)
+    } else {
+        entry.trim_end_matches('.').to_lowercase()
+    }
+}
+
+/// Groups the entries that share the same normalized key. Only groups with
+/// more than one entry are returned, ordered by key. The entry that is kept
+/// by default is listed first, preferring the one already in normalized form.
+pub fn find_duplicates<'x>(entries: impl IntoIterator<Item = &'x str>) -> Vec<DuplicateGroup> {
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for entry in entries {
+        groups
+            .entry(normalize_entry(entry))
+            .or_default()
+            .push(entry.to_string());
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, entries)| entries.len() > 1)
+        .map(|(key, mut entries)| {
+            entries.sort_by(|a, b| (*a != key).cmp(&(*b != key)).then_with(|| a.cmp(b)));
+            DuplicateGroup { key, entries }
+        })
+        .collect()
+}
+
+impl DuplicateGroup {
+    pub fn keep(&self) -> &str {
+        &self.entries[0]
+    }
+
+    pub fn rest(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().skip(1).map(|entry| entry.as_str())
+    }
+}
+
+/// Builds the update that keeps the first entry of each group and deletes
+/// the rest.
+pub fn remove_rest<'x>(
+    prefix: &str,
+    groups: impl IntoIterator<Item = &'x DuplicateGroup>,
+) -> UpdateSettings {
+    UpdateSettings::Delete {
+        keys: groups
+            .into_iter()
+            .flat_map(|group| group.rest())
+            .map(|entry| format!("{prefix}.{entry}"))
+            .collect(),
+    }
+}
+
+#[component]
+pub fn DuplicateEntries(
+    #[prop(into)] prefix: String,
+    #[prop(into)] can_edit: MaybeSignal<bool>,
+    #[prop(into)] on_change: Callback<()>,
+) -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let reload = Reload::new();
+    let prefix = store_value(prefix);
+
+    let duplicates = create_resource(
+        move || reload.track(),
+        move |_| {
+            let auth = auth.get_untracked();
+            let prefix = prefix.get_value();
+
+            async move {
+                fetch_ip_list(&auth, &prefix)
+                    .await
+                    .map(|entries| find_duplicates(entries.iter().map(|entry| entry.as_str())))
+            }
+        },
+    );
+
+    let remove_action = create_action(move |groups: &Vec<DuplicateGroup>| {
+        let auth = auth.get();
+        let update = remove_rest(&prefix.get_value(), groups);
+        let removed = groups.iter().map(|group| group.entries.len() - 1).sum();
+
+        async move {
+            match HttpRequest::post("/api/settings")
+                .with_authorization(&auth)
+                .with_body(vec![update])
+                .unwrap()
+                .send::<serde_json::Value>()
+                .await
+            {
+                Ok(_) => {
+                    reload.trigger();
+                    on_change.call(());
+                    alert.set(Alert::success(format!(
+                        "Removed {}.",
+                        maybe_plural(removed, "duplicate entry", "duplicate entries")
+                    )));
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    view! {
+        <div class="flex flex-col gap-y-3 p-4 mb-4 bg-white border border-gray-200 rounded-xl dark:bg-neutral-800 dark:border-neutral-700">
+            <Transition fallback=SkeletonTable>
+                {move || match duplicates.get() {
+                    None => None,
+                    Some(Err(err)) => {
+                        Some(view! { <LoadError error=err reload=reload/> }.into_view())
+                    }
+                    Some(Ok(groups)) if groups.is_empty() => {
+                        Some(
+                            view! {
+                                <p class="text-sm text-gray-600 dark:text-neutral-400">
+                                    "No duplicate entries were found."
+                                </p>
+                            }
+                                .into_view(),
+                        )
+                    }
+                    Some(Ok(groups)) => {
+                        let all_groups = groups.clone();
+                        let rows = groups
+                            .into_iter()
+                            .map(|group| {
+                                let keep = group.keep().to_string();
+                                let entries = group
+                                    .entries
+                                    .iter()
+                                    .cloned()
+                                    .enumerate()
+                                    .map(|(idx, entry)| {
+                                        view! {
+                                            <Badge color=if idx == 0 {
+                                                Color::Green
+                                            } else {
+                                                Color::Red
+                                            }>{entry}</Badge>
+                                        }
+                                    })
+                                    .collect_view();
+                                view! {
+                                    <li class="flex flex-wrap items-center justify-between gap-2 py-2">
+                                        <div class="flex flex-wrap items-center gap-2">{entries}</div>
+                                        <Show when=move || can_edit.get()>
+                                            <Button
+                                                text=format!("Keep {keep}, remove rest")
+                                                color=Color::Gray
+                                                on_click={
+                                                    let group = group.clone();
+                                                    move |_| {
+                                                        remove_action.dispatch(vec![group.clone()]);
+                                                    }
+                                                }
+
+                                                disabled=remove_action.pending()
+                                            />
+                                        </Show>
+                                    </li>
+                                }
+                            })
+                            .collect_view();
+
+                        Some(
+                            view! {
+                                <div class="flex items-center justify-between">
+                                    <h2 class="text-sm font-semibold text-gray-800 dark:text-neutral-200">
+                                        {format!(
+                                            "Found {}",
+                                            maybe_plural(all_groups.len(), "group of duplicates", "groups of duplicates"),
+                                        )}
+                                    </h2>
+                                    <Show when=move || can_edit.get()>
+                                        <Button
+                                            text="Remove all duplicates"
+                                            color=Color::Red
+                                            on_click={
+                                                let all_groups = all_groups.clone();
+                                                move |_| {
+                                                    remove_action.dispatch(all_groups.clone());
+                                                }
+                                            }
+
+                                            disabled=remove_action.pending()
+                                        />
+                                    </Show>
+                                </div>
+                                <ul class="divide-y divide-gray-200 dark:divide-neutral-700">{rows}</ul>
+                            }
+                                .into_view(),
+                        )
+                    }
+                }}
+
+            </Transition>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_by_normalized_key() {
+        let groups = find_duplicates([
+            "Example.org",
+            "example.org",
+            "example.org.",
+            "other.org",
+            "192.168.1.0/24",
+            "192.168.1.7/24",
+            "10.0.0.1",
+            "10.0.0.1/32",
+            "2001:DB8::1",
+            "2001:db8:0::1",
+        ]);
+        assert_eq!(
+            groups,
+            vec![
+                DuplicateGroup {
+                    key: "10.0.0.1".to_string(),
+                    entries: vec!["10.0.0.1".to_string(), "10.0.0.1/32".to_string()],
+                },
+                DuplicateGroup {
+                    key: "192.168.1.0/24".to_string(),
+                    entries: vec!["192.168.1.0/24".to_string(), "192.168.1.7/24".to_string()],
+                },
+                DuplicateGroup {
+                    key: "2001:db8::1".to_string(),
+                    entries: vec!["2001:DB8::1".to_string(), "2001:db8:0::1".to_string()],
+                },
+                DuplicateGroup {
+                    key: "example.org".to_string(),
+                    entries: vec![
+                        "example.org".to_string(),
+                        "Example.org".to_string(),
+                        "example.org.".to_string(),
+                    ],
+                },
+            ]
+        );
+        assert!(find_duplicates(["a.org", "b.org"]).is_empty());
+    }
+
+    #[test]
+    fn remove_rest_update() {
+        let groups = find_duplicates(["Example.org", "example.org", "EXAMPLE.org.", "x.org"]);
+        assert_eq!(groups[0].keep(), "example.org");
+        assert_eq!(
+            remove_rest("spam-filter.list.trusted-domains", &groups),
+            UpdateSettings::Delete {
+                keys: vec![
+                    "spam-filter.list.trusted-domains.EXAMPLE.org.".to_string(),
+                    "spam-filter.list.trusted-domains.Example.org".to_string(),
+                ]
+            }
+        );
+        assert_eq!(
+            remove_rest("server.blocked-ip", &[]),
+            UpdateSettings::Delete { keys: vec![] }
+        );
+    }
+}
@@ -16,6 +16,7 @@ use crate::{
         form::{
             button::Button,
             expression::InputExpression,
+            foldable::Foldable,
             input::{
                 InputDuration, InputPassword, InputRate, InputSize, InputSwitch, InputText,
                 TextArea,
@@ -28,25 +29,60 @@ use crate::{
         icon::IconRefresh,
         messages::{
             alert::{use_alerts, Alert},
+            banner::Banner,
+            load_error::{LoadError, Reload},
             modal::{use_modals, Modal},
         },
-        skeleton::Skeleton,
+        skeleton::SkeletonForm,
         Color,
     },
     core::{
         form::{ExternalSources, FormData},
-        http::{self, HttpRequest},
+        http::{self, HttpRequest, ManagementApiError},
+        merge::MergeResult,
         oauth::use_authorization,
-        schema::SelectType,
+        schema::{tab_order, Field, SelectType},
+        AccessToken,
     },
     pages::{
-        config::{ReloadSettings, Schema, SchemaType, Schemas, Settings, Type, UpdateSettings},
+        config::{
+            autocomplete::{
+                fetch_record_values, next_free_id, rank_values, stored_record_ids,
+                suggest_record_id, with_record_id,
+            },
+            bookmarks::{use_bookmark_refresh, Bookmark, BookmarkToggle},
+            conflicts::MergeConflicts,
+            endpoint_test::{test_endpoint, EndpointTestRequest, MILTER_SCHEMA, MTA_HOOK_SCHEMA},
+            geoip::{GeoIpStatusPanel, GEOIP_SCHEMA},
+            history::{setting_key, SettingHistory},
+            impact::{impact_warnings, ImpactAcknowledgement},
+            leave::LeavePrompt,
+            mta_sts::{MtaStsPreview, MTA_STS_SCHEMA},
+            proposal::{use_proposals, PendingProposals, Proposal},
+            ranges::SharedGroup,
+            raw::{unmanaged_settings, with_free_form, RawSettings, UnmanagedSettings},
+            relay_test::{test_relay, RELAY_HOST_SCHEMA},
+            restart::{restart_required_fields, use_pending_restart, RESTART_NOTE},
+            scheduled::{use_scheduled_changes, ScheduledChange, ScheduledChanges, UpcomingChanges},
+            search::RecentPage,
+            snapshot::fetch_all_settings,
+            staging::{use_staged_changes, StagedItem},
+            usage::{QueueUsageGauges, QUEUE_SCHEMA},
+            diff_settings, diff_updates, redact_changes, ReloadSettings, Schema, SchemaType,
+            Schemas, Settings, Type, UpdateSettings,
+        },
         List,
     },
+    utils::{
+        audit::{log_audit, log_audit_changes, AuditAction},
+        clock,
+        security::{check_rate_limit_request, csrf_token_for_request, RequestContext},
+        telemetry::report_handled_error,
+    },
 };
 
 #[derive(Clone, Serialize, Deserialize, Default)]
-struct FetchSettings {
+pub(crate) struct FetchSettings {
     pub items: Settings,
     pub total: u64,
 }
@@ -59,10 +95,18 @@ enum FetchResult {
     },
     Create {
         external_sources: ExternalSources,
+        suggested_id: Option<String>,
     },
     NotFound,
 }
 
+#[derive(Clone, PartialEq, Eq)]
+enum SaveSource {
+    Form,
+    Staged,
+    Proposal(String),
+}
+
 pub const DEFAULT_SETTINGS_URL: &str = "/settings/network/edit";
 
 #[component]
@@ -71,8 +115,17 @@ pub fn SettingsEdit() -> impl IntoView {
     let alert = use_alerts();
     let params = use_params_map();
     let modal = use_modals();
+    let staged = use_staged_changes();
+    let proposals = use_proposals();
+    let pending_restart = use_pending_restart();
+    let bookmark_refresh = use_bookmark_refresh();
+    let scheduled = use_scheduled_changes();
+    let apply_at = create_rw_signal(String::new());
+    let access = create_memo(move |_| auth.get().permissions().settings_access());
+    let read_only = create_memo(move |_| access.get().is_read_only());
 
     let schemas = expect_context::<Arc<Schemas>>();
+    let audit_schemas = schemas.clone();
     let current_schema = create_memo(move |_| {
         if let Some(schema) = params
             .get()
@@ -85,157 +138,234 @@ pub fn SettingsEdit() -> impl IntoView {
             Arc::new(Schema::default())
         }
     });
+    create_effect(move |_| {
+        if let Some(page) = RecentPage::for_schema(&current_schema.get()) {
+            page.record();
+        }
+    });
 
+    let reload = Reload::new();
     let fetch_settings = create_resource(
-        move || params.get().get("id").cloned().unwrap_or_default(),
-        move |name| {
+        move || {
+            (
+                params.get().get("id").cloned().unwrap_or_default(),
+                reload.track(),
+            )
+        },
+        move |(name, _)| {
             let auth = auth.get_untracked();
             let current_schema = current_schema.get();
             let is_create = name.is_empty();
 
             async move {
                 // Fetch external sources
-                let mut external_sources = ExternalSources::new();
-                for (schema, field) in current_schema.external_sources() {
-                    let schema = schema.unwrap_or_else(|| current_schema.clone());
-                    let source_key = format!("{}_{}", schema.id, field.id);
-                    if !external_sources.contains_key(&source_key) {
-                        let items = HttpRequest::get("/api/settings/group")
-                            .with_authorization(&auth)
-                            .with_parameter("prefix", schema.unwrap_prefix())
-                            .with_parameter(
-                                "suffix",
-                                schema.try_unwrap_suffix().unwrap_or_default(),
-                            )
-                            .with_parameter("field", field.id)
-                            .send::<List<Settings>>()
-                            .await?
-                            .items;
-
-                        external_sources.insert(
-                            source_key,
-                            items
-                                .into_iter()
-                                .filter_map(|mut item| {
-                                    (
-                                        item.remove("_id")?,
-                                        item.remove(field.id).unwrap_or_default(),
-                                    )
-                                        .into()
-                                })
-                                .collect::<Vec<_>>(),
-                        );
-                    }
-                }
+                let external_sources =
+                    fetch_external_sources(&auth, current_schema.settings_ranges(&name).shared)
+                        .await?;
 
                 // Fetch settings
-                match current_schema.typ {
-                    SchemaType::Record { prefix, .. } => {
-                        if !is_create {
-                            HttpRequest::get("/api/settings/list")
-                                .with_authorization(&auth)
-                                .with_parameter("prefix", format!("{prefix}.{name}"))
-                                .send::<FetchSettings>()
-                                .await
-                                .map(|mut list| {
-                                    if !list.items.is_empty() {
-                                        list.items.insert("_id".to_string(), name.to_string());
-                                        FetchResult::Update {
-                                            settings: list.items,
-                                            external_sources,
-                                        }
-                                    } else {
-                                        FetchResult::NotFound
-                                    }
-                                })
-                        } else {
-                            Ok(FetchResult::Create { external_sources })
-                        }
-                    }
-                    SchemaType::Entry { prefix } => {
-                        if !is_create {
-                            HttpRequest::get("/api/settings/keys")
-                                .with_authorization(&auth)
-                                .with_parameter("keys", format!("{prefix}.{name}"))
-                                .send::<AHashMap<String, Option<String>>>()
-                                .await
-                                .map(|list| {
-                                    if let Some(value) = list.into_values().next().flatten() {
-                                        let mut settings = Settings::new();
-                                        settings.insert("_id".to_string(), name.to_string());
-                                        settings.insert("_value".to_string(), value);
-                                        FetchResult::Update {
-                                            settings,
-                                            external_sources,
-                                        }
-                                    } else {
-                                        FetchResult::NotFound
-                                    }
-                                })
-                        } else {
-                            Ok(FetchResult::Create { external_sources })
-                        }
-                    }
-                    SchemaType::List => {
-                        let mut keys = Vec::new();
-                        let mut prefixes = Vec::new();
-
-                        for field in current_schema.fields.values() {
-                            if field.is_multivalue() {
-                                prefixes.push(field.id);
-                                keys.push(field.id);
-                            } else {
-                                keys.push(field.id);
-                            }
-                        }
-
-                        HttpRequest::get("/api/settings/keys")
-                            .with_authorization(&auth)
-                            .with_parameter("keys", keys.join(","))
-                            .with_parameter("prefixes", prefixes.join(","))
-                            .send::<Settings>()
+                let settings = if !is_create || current_schema.typ == SchemaType::List {
+                    fetch_current_settings(&auth, &current_schema, &name).await?
+                } else {
+                    None
+                };
+                Ok(match (settings, &current_schema.typ) {
+                    (Some(settings), _) => FetchResult::Update {
+                        settings,
+                        external_sources,
+                    },
+                    (None, SchemaType::List) => FetchResult::Create {
+                        external_sources,
+                        suggested_id: None,
+                    },
+                    (None, _) if is_create => FetchResult::Create {
+                        external_sources,
+                        // The suggestion is optional, so a failed fetch leaves the id empty
+                        suggested_id: suggest_record_id(&auth, &current_schema)
                             .await
-                            .map(|mut list| {
-                                let mut settings = Settings::new();
-                                for (name, value) in list.drain() {
-                                    settings.insert(name, value);
-                                }
-
-                                if !settings.is_empty() {
-                                    FetchResult::Update {
-                                        settings,
-                                        external_sources,
-                                    }
-                                } else {
-                                    FetchResult::Create { external_sources }
-                                }
-                            })
-                    }
-                }
+                            .unwrap_or_default(),
+                    },
+                    (None, _) => FetchResult::NotFound,
+                })
             }
         },
     );
     let (pending, set_pending) = create_signal(false);
     let data = FormData::default().into_signal();
+    let base = create_rw_signal(None::<FormData>);
+    let suggested_id = create_rw_signal(None::<String>);
+    let raw_view = create_rw_signal(false);
+    let free_form = create_rw_signal(Vec::<(String, String)>::new());
+    let base_free_form = create_rw_signal(Vec::<(String, String)>::new());
+    let record_values = create_resource(
+        move || current_schema.get(),
+        move |schema| {
+            let auth = auth.get_untracked();
+
+            async move {
+                // Suggestions are optional, so a failed fetch leaves them empty
+                fetch_record_values(&auth, &schema)
+                    .await
+                    .unwrap_or_default()
+            }
+        },
+    );
 
     let save_changes = create_action(
-        move |(changes, reload): &(Arc<Vec<UpdateSettings>>, bool)| {
-            let changes = changes.clone();
-            let reload = *reload;
+        move |(changes, reload, source): &(Arc<Vec<UpdateSettings>>, bool, SaveSource)| {
+            let free_form = free_form.get();
+            let saved_free_form = free_form.clone();
             let auth = auth.get();
+            let changes = if *source == SaveSource::Form && !free_form.is_empty() {
+                Arc::new(with_free_form(
+                    &data.get(),
+                    changes.as_ref().clone(),
+                    free_form,
+                    Some(auth.permissions()),
+                ))
+            } else {
+                changes.clone()
+            };
+            let reload = *reload;
+            let source = source.clone();
             let schema = current_schema.get();
+            let mut setting_changes = Vec::new();
+            let mut restart_fields = Vec::new();
+            // A suggested id can be taken by someone else while the form is open,
+            // ids typed by the user are never changed
+            let retry_id = (source == SaveSource::Form && !data.get().is_update)
+                .then(|| data.get().value_as_str("_id").map(str::to_string))
+                .flatten()
+                .filter(|id| suggested_id.get().as_ref() == Some(id));
+            if source == SaveSource::Form {
+                let previous = base
+                    .get()
+                    .filter(|_| data.get().is_update)
+                    .map(|base| {
+                        with_free_form(
+                            &base,
+                            base.build_update().unwrap_or_default(),
+                            base_free_form.get(),
+                            None,
+                        )
+                    })
+                    .unwrap_or_default();
+                setting_changes = diff_updates(&changes, &previous);
+                restart_fields = restart_required_fields(
+                    &schema,
+                    data.get().value_as_str("_id"),
+                    &setting_changes,
+                );
+                redact_changes(&mut setting_changes, &schema);
+            }
+            // Staged changes and proposals may span other records, their
+            // secrets are redacted using the schema of each one
+            let redact_schemas = match &source {
+                SaveSource::Form => Vec::new(),
+                SaveSource::Staged => staged
+                    .get()
+                    .items()
+                    .iter()
+                    .filter_map(|item| audit_schemas.schemas.get(item.schema_id).cloned())
+                    .collect::<Vec<_>>(),
+                SaveSource::Proposal(id) => proposals
+                    .get()
+                    .get(id)
+                    .and_then(|proposal| audit_schemas.schemas.get(proposal.schema_id.as_str()))
+                    .cloned()
+                    .into_iter()
+                    .collect(),
+            };
 
             async move {
+                let request = RequestContext::new("POST", "/api/settings");
+                if let Err(err) = check_rate_limit_request(&auth.username, Some(&request)) {
+                    alert.set(Alert::error(err));
+                    return;
+                }
                 set_pending.set(true);
-                match HttpRequest::post("/api/settings")
-                    .with_authorization(&auth)
-                    .with_body(changes)
-                    .unwrap()
-                    .send::<Option<String>>()
-                    .await
-                    .map(|_| ())
-                {
+                if source != SaveSource::Form {
+                    // There is no loaded form to compare with, diff against the stored values
+                    let current = fetch_all_settings(&auth).await.unwrap_or_default();
+                    setting_changes = diff_settings(&changes, &current);
+                    for schema in &redact_schemas {
+                        redact_changes(&mut setting_changes, schema);
+                    }
+                }
+                let mut result = post_settings(&auth, changes.clone()).await;
+                let is_taken = matches!(
+                    result,
+                    Err(http::Error::Server(ManagementApiError::AssertFailed))
+                );
+                if let (Some(taken_id), Some(base_id)) = (
+                    retry_id.as_deref().filter(|_| is_taken),
+                    schema.suggested_id,
+                ) {
+                    // Retry once with a new id, unless the rejection was not caused by the id
+                    let ids = fetch_record_values(&auth, &schema)
+                        .await
+                        .map(|settings| stored_record_ids(&settings, &schema))
+                        .unwrap_or_default();
+                    if ids.iter().any(|id| id == taken_id) {
+                        let new_id = next_free_id(base_id, ids.iter().map(String::as_str));
+                        let changes = with_record_id(&changes, &schema, taken_id, &new_id);
+                        setting_changes = diff_updates(&changes, &[]);
+                        restart_fields =
+                            restart_required_fields(&schema, Some(new_id.as_str()), &setting_changes);
+                        redact_changes(&mut setting_changes, &schema);
+                        suggested_id.set(Some(new_id.clone()));
+                        data.update(|data| data.set("_id", new_id));
+                        result = post_settings(&auth, Arc::new(changes)).await;
+                    }
+                }
+                match result {
                     Ok(_) => {
+                        match &source {
+                            SaveSource::Staged => {
+                                log_audit_changes(
+                                    AuditAction::ConfigUpdate,
+                                    &auth.username,
+                                    &format!(
+                                        "Saved {} staged changes",
+                                        staged.get_untracked().len()
+                                    ),
+                                    None,
+                                    true,
+                                    setting_changes,
+                                );
+                                staged.update(|staged| staged.clear());
+                            }
+                            SaveSource::Proposal(id) => {
+                                proposals.update(|proposals| {
+                                    if proposals.approve(id, &auth.username).is_some() {
+                                        proposals.save();
+                                    }
+                                });
+                                log_audit_changes(
+                                    AuditAction::ConfigApprove,
+                                    &auth.username,
+                                    &format!("Approved and applied proposal {id}"),
+                                    None,
+                                    true,
+                                    setting_changes,
+                                );
+                            }
+                            SaveSource::Form => {
+                                // The saved values become the new baseline, clearing the modified dots
+                                base.set(Some(data.get_untracked()));
+                                base_free_form.set(saved_free_form);
+                                pending_restart.update(|pending| pending.add(restart_fields));
+                                log_audit_changes(
+                                    AuditAction::ConfigUpdate,
+                                    &auth.username,
+                                    &format!("Updated {} settings", schema.id),
+                                    None,
+                                    true,
+                                    setting_changes,
+                                );
+                            }
+                        }
+                        bookmark_refresh.trigger();
                         if reload {
                             match HttpRequest::get(format!(
                                 "/api/reload/{}",
@@ -272,6 +402,11 @@ pub fn SettingsEdit() -> impl IntoView {
                                 }
                                 Err(err) => {
                                     set_pending.set(false);
+                                    report_handled_error(
+                                        "settings.reload",
+                                        &err,
+                                        &[("schema", schema.id)],
+                                    );
                                     alert.set(Alert::from(err));
                                 }
                             }
@@ -300,6 +435,11 @@ pub fn SettingsEdit() -> impl IntoView {
                                 use_navigate()("/login", Default::default());
                             }
                             err => {
+                                report_handled_error(
+                                    "settings.save",
+                                    &err,
+                                    &[("schema", schema.id)],
+                                );
                                 alert.set(Alert::from(err));
                             }
                         }
@@ -309,293 +449,969 @@ pub fn SettingsEdit() -> impl IntoView {
         },
     );
 
+    let relay_test = create_action(move |id: &String| {
+        let auth = auth.get();
+        let id = id.clone();
+
+        async move {
+            match test_relay(&auth, &id).await {
+                Ok(response) => {
+                    alert.set(Alert::from(response));
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let endpoint_test = create_action(move |request: &EndpointTestRequest| {
+        let auth = auth.get();
+        let request = request.clone();
+
+        async move {
+            match test_endpoint(&auth, &request).await {
+                Ok(response) => {
+                    alert.set(Alert::from(response));
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    // Builds the update of a form, showing a malformed record id on its field
+    let checked_updates = move |form: &FormData| match form
+        .build_update_for(auth.get_untracked().permissions())
+    {
+        Ok(updates) => Some(Arc::new(updates)),
+        Err(err) => {
+            data.update(|data| data.new_error(err.field, err.error));
+            None
+        }
+    };
+
+    let conflicts = create_rw_signal(None::<MergeResult>);
+    let merge_reload = store_value(false);
+    let merge_and_save = create_action(move |reload: &bool| {
+        let reload = *reload;
+        let auth = auth.get();
+        let schema = current_schema.get();
+        let local = data.get();
+        let loaded = base.get().filter(|_| local.is_update);
+
+        async move {
+            // Merge with any changes made on the server since the form was loaded
+            let Some(loaded) = loaded else {
+                if let Some(updates) = checked_updates(&local) {
+                    save_changes.dispatch((updates, reload, SaveSource::Form));
+                }
+                return;
+            };
+            let name = local.value_as_str("_id").unwrap_or_default().to_string();
+
+            set_pending.set(true);
+            match fetch_current_settings(&auth, &schema, &name).await {
+                Ok(remote) => {
+                    set_pending.set(false);
+                    let remote = FormData::from_settings(schema.clone(), remote);
+                    let result = local.merge(&loaded, &remote);
+                    if !result.resolved.is_empty() {
+                        let merged = result.merged.values.clone();
+                        data.update(|data| data.values = merged);
+                        base.set(Some(remote));
+                    }
+
+                    if !result.has_conflicts() {
+                        if let Some(updates) = checked_updates(&result.merged) {
+                            save_changes.dispatch((updates, reload, SaveSource::Form));
+                        }
+                    } else {
+                        // Let the user pick either value of each conflicting field
+                        merge_reload.set_value(reload);
+                        conflicts.set(Some(result));
+                    }
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    set_pending.set(false);
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    // Warn before saving changes that may interrupt the current session
+    let acknowledged = create_rw_signal(ImpactAcknowledgement::default());
+    let save_form = move |reload: bool| {
+        let local = data.get();
+        let Some(updates) = checked_updates(&local) else {
+            return;
+        };
+        let previous = base
+            .get()
+            .filter(|_| local.is_update)
+            .and_then(|base| base.build_update().ok())
+            .unwrap_or_default();
+        let warnings = impact_warnings(&updates, &previous);
+
+        if acknowledged.with(|ack| ack.is_required(&warnings)) {
+            let mut reasons = Vec::new();
+            for warning in &warnings {
+                if !reasons.contains(&warning.reason) {
+                    reasons.push(warning.reason);
+                }
+            }
+            let reasons = reasons.join(" ");
+            modal.set(
+                Modal::with_title("This change may disconnect you")
+                    .with_message(format!(
+                        "{reasons} Make sure you can still reach the server before saving."
+                    ))
+                    .with_button("Save anyway")
+                    .with_dangerous_callback(move || {
+                        acknowledged.update(|ack| ack.acknowledge(&warnings));
+                        merge_and_save.dispatch(reload);
+                    }),
+            );
+        } else {
+            merge_and_save.dispatch(reload);
+        }
+    };
+
+    // Changes that are not saved yet, and whether they could be saved as they are
+    let leave_prompt = move || {
+        let is_dirty = base.with_untracked(|base| {
+            base.as_ref()
+                .is_some_and(|base| data.with_untracked(|data| data.has_modifications(base)))
+        }) || free_form.get_untracked() != base_free_form.get_untracked();
+        LeavePrompt::select(is_dirty, is_dirty && data.get_untracked().validate_form())
+    };
+    let confirm_unload = window_event_listener(ev::beforeunload, move |ev| {
+        if let Some(prompt) = leave_prompt() {
+            ev.prevent_default();
+            ev.set_return_value(prompt.message());
+        }
+    });
+    on_cleanup(move || confirm_unload.remove());
+
     view! {
         <Form
             title=Signal::derive(move || current_schema.get().form.title.to_string())
             subtitle=Signal::derive(move || current_schema.get().form.subtitle.to_string())
         >
 
-            <Transition fallback=Skeleton set_pending>
+            <Banner
+                message="You have view-only access to these settings. Changes cannot be saved."
+                hide=Signal::derive(move || !read_only.get())
+            />
 
-                {move || match fetch_settings.get() {
-                    None => None,
-                    Some(Err(http::Error::Unauthorized)) => {
-                        use_navigate()("/login", Default::default());
-                        Some(view! { <div></div> }.into_view())
-                    }
-                    Some(Err(http::Error::NotFound) | Ok(FetchResult::NotFound)) => {
-                        let url = format!("/settings/{}", current_schema.get().id);
-                        use_navigate()(&url, Default::default());
-                        Some(view! { <div></div> }.into_view())
+            <MergeConflicts
+                merge=conflicts
+                disabled=pending
+                on_save=Callback::new(move |result: MergeResult| {
+                    let merged = result.merged.values.clone();
+                    data.update(|data| data.values = merged);
+                    if let Some(updates) = checked_updates(&result.merged) {
+                        save_changes
+                            .dispatch((updates, merge_reload.get_value(), SaveSource::Form));
                     }
-                    Some(Err(err)) => {
-                        alert.set(Alert::from(err));
-                        Some(view! { <div></div> }.into_view())
+                })
+            />
+
+            <PendingProposals
+                schema_id=Signal::derive(move || current_schema.get().id)
+                can_review=Signal::derive(move || access.get().can_edit)
+                reviewer=Signal::derive(move || auth.get().username.to_string())
+                disabled=pending
+                on_approve=Callback::new(move |proposal: Proposal| {
+                    if !proposal.can_approve(&auth.get().username) {
+                        alert.set(Alert::error("You cannot approve your own proposal"));
+                        return;
                     }
-                    Some(Ok(result)) => {
-                        let (is_create, settings, external_sources) = match result {
-                            FetchResult::Update { settings, external_sources } => {
-                                (false, Some(settings), external_sources)
+                    modal
+                        .set(
+                            Modal::with_title("Approve proposal")
+                                .with_message(
+                                    format!(
+                                        "Applying {} proposed by {} will overwrite the current settings. Continue?",
+                                        proposal.title,
+                                        proposal.author,
+                                    ),
+                                )
+                                .with_button("Approve")
+                                .with_callback(move || {
+                                    save_changes
+                                        .dispatch((
+                                            Arc::new(proposal.updates.clone()),
+                                            false,
+                                            SaveSource::Proposal(proposal.id.clone()),
+                                        ));
+                                }),
+                        );
+                })
+
+                on_reject=Callback::new(move |proposal: Proposal| {
+                    let reviewer = auth.get().username.to_string();
+                    proposals
+                        .update(|proposals| {
+                            if proposals.reject(&proposal.id, &reviewer) {
+                                proposals.save();
                             }
-                            FetchResult::Create { external_sources } => {
-                                (true, None, external_sources)
+                        });
+                    log_audit(
+                        AuditAction::ConfigReject,
+                        &reviewer,
+                        &format!("Rejected proposal {}", proposal.id),
+                        None,
+                        true,
+                    );
+                    alert.set(Alert::success("Proposal rejected"));
+                })
+            />
+
+            <UpcomingChanges
+                schema_id=Signal::derive(move || current_schema.get().id)
+                can_cancel=Signal::derive(move || access.get().can_modify())
+            />
+
+            <Show when=move || current_schema.get().id == QUEUE_SCHEMA>
+                <QueueUsageGauges schema_id=QUEUE_SCHEMA/>
+            </Show>
+
+            <Show when=move || {
+                cfg!(feature = "asn-lookup") && current_schema.get().id == GEOIP_SCHEMA
+            }>
+                <GeoIpStatusPanel/>
+            </Show>
+
+            <Show when=move || raw_view.get()>
+                <RawSettings data=data free_form=free_form/>
+            </Show>
+
+            <fieldset disabled=move || read_only.get() class:hidden=move || raw_view.get()>
+                <Transition fallback=SkeletonForm set_pending>
+
+                    {move || match fetch_settings.get() {
+                        None => None,
+                        Some(Err(http::Error::NotFound) | Ok(FetchResult::NotFound)) => {
+                            let url = format!("/settings/{}", current_schema.get().id);
+                            use_navigate()(&url, Default::default());
+                            Some(view! { <div></div> }.into_view())
+                        }
+                        Some(Err(err)) => {
+                            Some(view! { <LoadError error=err reload=reload/> }.into_view())
+                        }
+                        Some(Ok(result)) => {
+                            let (is_create, settings, external_sources, suggestion) = match result {
+                                FetchResult::Update { settings, external_sources } => {
+                                    (false, Some(settings), external_sources, None)
+                                }
+                                FetchResult::Create { external_sources, suggested_id } => {
+                                    (true, None, external_sources, suggested_id)
+                                }
+                                FetchResult::NotFound => unreachable!(),
+                            };
+                            let schema = current_schema.get();
+                            let sections = schema.form.sections.iter().cloned();
+                            let is_enterprise = auth.get().is_enterprise();
+                            let permissions = auth.get().permissions().clone();
+                            let record_id = params.get_untracked().get("id").cloned();
+                            let unmanaged = settings
+                                .as_ref()
+                                .map(|settings| unmanaged_settings(&schema, settings))
+                                .unwrap_or_default();
+                            base_free_form.set(unmanaged.clone());
+                            free_form.set(unmanaged);
+                            data.set(
+                                FormData::from_settings(schema.clone(), settings)
+                                    .with_external_sources(external_sources),
+                            );
+                            if let Some(id) = &suggestion {
+                                data.update(|data| data.set("_id", id.clone()));
                             }
-                            FetchResult::NotFound => unreachable!(),
-                        };
-                        let schema = current_schema.get();
-                        let sections = schema.form.sections.iter().cloned();
-                        let is_enterprise = auth.get().is_enterprise();
-                        data.set(
-                            FormData::from_settings(schema.clone(), settings)
-                                .with_external_sources(external_sources),
-                        );
-                        Some(
-                            sections
-                                .map(|section| {
-                                    let title = section.title.map(|s| s.to_string());
-                                    let section_ = section.clone();
-                                    let hide_section = create_memo(move |_| {
-                                        !section_.display(&data.get())
-                                    });
-                                    let components = section
-                                        .fields
-                                        .iter()
-                                        .cloned()
-                                        .map(|field| {
-                                            let is_disabled = (field.readonly && !is_create)
-                                                || (!is_enterprise && field.enterprise);
-                                            let field_label = field.label_form;
-                                            let help = field.help;
-                                            let field_ = field.clone();
-                                            let hide_label = create_memo(move |_| {
-                                                !field_.display(&data.get())
-                                            });
-                                            let field_ = field.clone();
-                                            let is_optional = create_memo(move |_| {
-                                                !field_.is_required(&data.get())
-                                            });
-                                            let is_switch = matches!(field.typ_, Type::Boolean);
-                                            let component = match field.typ_ {
-                                                Type::Input => {
-                                                    view! {
-                                                        <InputText
-                                                            element=FormElement::new(field.id, data)
-                                                            placeholder=create_memo(move |_| {
-                                                                field
-                                                                    .placeholder(&data.get())
-                                                                    .unwrap_or_default()
-                                                                    .to_string()
-                                                            })
-
-                                                            disabled=is_disabled
-                                                        />
+                            suggested_id.set(suggestion);
+                            base.set(Some(data.get_untracked()));
+                            let is_disabled = {
+                                let permissions = permissions.clone();
+                                move |field: &Field| {
+                                    (field.readonly && !is_create)
+                                        || (!is_enterprise && field.enterprise)
+                                        || !field.can_edit(&permissions)
+                                }
+                            };
+                            let schema_ = schema.clone();
+                            let is_disabled_ = is_disabled.clone();
+                            let focus_order = create_memo(move |_| {
+                                data.with(|data| {
+                                    tab_order(&schema_.form.sections, data, &is_disabled_)
+                                })
+                            });
+                            Some(
+                                sections
+                                    .map(|section| {
+                                        let title = section.title.map(|s| s.to_string());
+                                        let section_ = section.clone();
+                                        let hide_section = create_memo(move |_| {
+                                            !section_.display(&data.get())
+                                        });
+                                        let components = section
+                                            .fields
+                                            .iter()
+                                            .cloned()
+                                            .map(|field| {
+                                                let is_disabled = is_disabled(field.as_ref());
+                                                let field_label = field.label_form;
+                                                let help = field.help;
+                                                let doc_url = field.doc_url();
+                                                let note = field
+                                                    .requires_restart
+                                                    .then_some(RESTART_NOTE);
+                                                let field_ = field.clone();
+                                                let hide_label = create_memo(move |_| {
+                                                    !field_.display(&data.get())
+                                                });
+                                                let field_ = field.clone();
+                                                let is_optional = create_memo(move |_| {
+                                                    !field_.is_required(&data.get())
+                                                });
+                                                let field_id = field.id;
+                                                let tab_index = Signal::derive(move || {
+                                                    if focus_order.with(|order| order.contains(&field_id)) {
+                                                        0
+                                                    } else {
+                                                        -1
                                                     }
-                                                        .into_view()
-                                                }
-                                                Type::Array => {
-                                                    view! {
-                                                        <StackedInput
-                                                            add_button_text="Add".to_string()
-                                                            element=FormElement::new(field.id, data)
-                                                            placeholder=create_memo(move |_| {
-                                                                field
-                                                                    .placeholder(&data.get())
-                                                                    .unwrap_or_default()
-                                                                    .to_string()
-                                                            })
-                                                        />
+                                                });
+                                                let is_modified = create_memo(move |_| {
+                                                    base.with(|base| {
+                                                        base.as_ref().is_some_and(|base| {
+                                                            data.with(|data| data.is_modified(base, field_id))
+                                                        })
+                                                    })
+                                                });
+                                                let is_switch = matches!(field.typ_, Type::Boolean);
+                                                let bookmark = (!is_create)
+                                                    .then(|| {
+                                                        Bookmark::new(schema.id, record_id.clone(), field.id)
+                                                    });
+                                                let history_key = setting_key(
+                                                    &schema,
+                                                    record_id.as_deref(),
+                                                    field.id,
+                                                );
+                                                let component = match field.typ_ {
+                                                    Type::Input | Type::Number { .. } => {
+                                                        view! {
+                                                            <InputText
+                                                                element=FormElement::new(field.id, data)
+                                                                placeholder=create_memo(move |_| {
+                                                                    field
+                                                                        .placeholder(&data.get())
+                                                                        .unwrap_or_default()
+                                                                        .to_string()
+                                                                })
+
+                                                                disabled=is_disabled
+                                                            />
+                                                        }
+                                                            .into_view()
                                                     }
-                                                        .into_view()
-                                                }
-                                                Type::Secret => {
-                                                    view! {
-                                                        <InputPassword
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
+                                                    Type::Array => {
+                                                        view! {
+                                                            <Foldable element=FormElement::new(field.id, data)>
+                                                                <StackedInput
+                                                                    add_button_text="Add".to_string()
+                                                                    element=FormElement::new(field.id, data)
+                                                                    placeholder=create_memo(move |_| {
+                                                                        field
+                                                                            .placeholder(&data.get())
+                                                                            .unwrap_or_default()
+                                                                            .to_string()
+                                                                    })
+
+                                                                    suggestions=create_memo(move |_| {
+                                                                        record_values
+                                                                            .get()
+                                                                            .map(|settings| rank_values(&settings, field_id))
+                                                                            .unwrap_or_default()
+                                                                    })
+                                                                />
+                                                            </Foldable>
+                                                        }
+                                                            .into_view()
                                                     }
-                                                        .into_view()
-                                                }
-                                                Type::Select { typ: SelectType::Single, .. } => {
-                                                    view! {
-                                                        <Select
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
+                                                    Type::Secret => {
+                                                        view! {
+                                                            <InputPassword
+                                                                element=FormElement::new(field.id, data)
+                                                                disabled=is_disabled
+                                                            />
+                                                        }
+                                                            .into_view()
                                                     }
-                                                        .into_view()
-                                                }
-                                                Type::Select { typ: SelectType::Many, .. } => {
-                                                    view! {
-                                                        <CheckboxGroup
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
+                                                    Type::Select { typ: SelectType::Single, .. } => {
+                                                        view! {
+                                                            <Select
+                                                                element=FormElement::new(field.id, data)
+                                                                disabled=is_disabled
+                                                            />
+                                                        }
+                                                            .into_view()
                                                     }
-                                                        .into_view()
-                                                }
-                                                Type::Select { typ: SelectType::ManyWithSearch, .. } => {
-                                                    view! {
-                                                        <StackedBadge
-                                                            element=FormElement::new(field.id, data)
-                                                            add_button_text="Add Item"
-                                                            color=Color::Green
-                                                        />
+                                                    Type::Select { typ: SelectType::Many, .. } => {
+                                                        view! {
+                                                            <CheckboxGroup
+                                                                element=FormElement::new(field.id, data)
+                                                                disabled=is_disabled
+                                                            />
+                                                        }
+                                                            .into_view()
                                                     }
-                                                        .into_view()
-                                                }
-                                                Type::Size => {
-                                                    view! {
-                                                        <InputSize
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
+                                                    Type::Select { typ: SelectType::ManyWithSearch, .. } => {
+                                                        view! {
+                                                            <StackedBadge
+                                                                element=FormElement::new(field.id, data)
+                                                                add_button_text="Add Item"
+                                                                color=Color::Green
+                                                            />
+                                                        }
+                                                            .into_view()
                                                     }
-                                                        .into_view()
-                                                }
-                                                Type::Boolean => {
-                                                    view! {
-                                                        <InputSwitch
-                                                            label=field_label
-                                                            tooltip=help.unwrap_or_default()
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
+                                                    Type::Size => {
+                                                        view! {
+                                                            <InputSize
+                                                                element=FormElement::new(field.id, data)
+                                                                disabled=is_disabled
+                                                            />
+                                                        }
+                                                            .into_view()
                                                     }
-                                                        .into_view()
-                                                }
-                                                Type::Duration => {
-                                                    view! {
-                                                        <InputDuration
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
+                                                    Type::Boolean => {
+                                                        view! {
+                                                            <InputSwitch
+                                                                label=field_label
+                                                                tooltip=help.unwrap_or_default()
+                                                                element=FormElement::new(field.id, data)
+                                                                disabled=is_disabled
+                                                            />
+                                                        }
+                                                            .into_view()
                                                     }
-                                                        .into_view()
-                                                }
-                                                Type::Rate => {
-                                                    view! {
-                                                        <InputRate
-                                                            element=FormElement::new(field.id, data)
-                                                            disabled=is_disabled
-                                                        />
+                                                    Type::Duration => {
+                                                        view! {
+                                                            <InputDuration
+                                                                element=FormElement::new(field.id, data)
+                                                                disabled=is_disabled
+                                                            />
+                                                        }
+                                                            .into_view()
                                                     }
-                                                        .into_view()
-                                                }
-                                                Type::Expression => {
-                                                    view! {
-                                                        <InputExpression element=FormElement::new(field.id, data)/>
+                                                    Type::Rate => {
+                                                        view! {
+                                                            <InputRate
+                                                                element=FormElement::new(field.id, data)
+                                                                disabled=is_disabled
+                                                            />
+                                                        }
+                                                            .into_view()
                                                     }
-                                                        .into_view()
-                                                }
-                                                Type::Cron => {
+                                                    Type::Expression => {
+                                                        view! {
+                                                            <Foldable element=FormElement::new(field.id, data)>
+                                                                <InputExpression element=FormElement::new(field.id, data)/>
+                                                            </Foldable>
+                                                        }
+                                                            .into_view()
+                                                    }
+                                                    Type::Cron => {
+                                                        view! {
+                                                            <SelectCron element=FormElement::new(field.id, data)/>
+                                                        }
+                                                            .into_view()
+                                                    }
+                                                    Type::Text => {
+                                                        view! {
+                                                            <TextArea
+                                                                element=FormElement::new(field.id, data)
+                                                                placeholder=create_memo(move |_| {
+                                                                    field
+                                                                        .placeholder(&data.get())
+                                                                        .unwrap_or_default()
+                                                                        .to_string()
+                                                                })
+
+                                                                disabled=is_disabled
+                                                            />
+                                                        }
+                                                            .into_view()
+                                                    }
+                                                };
+                                                let component = match bookmark {
+                                                    Some(bookmark) => {
+                                                        view! {
+                                                            <div class="flex gap-x-2">
+                                                                <div class="grow">{component}</div>
+                                                                <SettingHistory key=history_key/>
+                                                                <BookmarkToggle bookmark=bookmark/>
+                                                            </div>
+                                                        }
+                                                            .into_view()
+                                                    }
+                                                    None => component,
+                                                };
+                                                if !is_switch {
                                                     view! {
-                                                        <SelectCron element=FormElement::new(field.id, data)/>
+                                                        <FormItem
+                                                            label=field_label
+                                                            hide=hide_label
+                                                            is_optional=is_optional
+                                                            modified=is_modified
+                                                            tab_index=tab_index
+                                                            tooltip=help.unwrap_or_default()
+                                                            doc_url=doc_url
+                                                            note=note
+                                                        >
+                                                            {component}
+                                                        </FormItem>
                                                     }
-                                                        .into_view()
-                                                }
-                                                Type::Text => {
+                                                } else {
                                                     view! {
-                                                        <TextArea
-                                                            element=FormElement::new(field.id, data)
-                                                            placeholder=create_memo(move |_| {
-                                                                field
-                                                                    .placeholder(&data.get())
-                                                                    .unwrap_or_default()
-                                                                    .to_string()
-                                                            })
-
-                                                            disabled=is_disabled
-                                                        />
+                                                        <FormItem
+                                                            label=""
+                                                            hide=hide_label
+                                                            is_optional=is_optional
+                                                            modified=is_modified
+                                                            tab_index=tab_index
+                                                            doc_url=doc_url
+                                                            note=note
+                                                        >
+                                                            {component}
+                                                        </FormItem>
                                                     }
-                                                        .into_view()
-                                                }
-                                            };
-                                            if !is_switch {
-                                                view! {
-                                                    <FormItem
-                                                        label=field_label
-                                                        hide=hide_label
-                                                        is_optional=is_optional
-                                                        tooltip=help.unwrap_or_default()
-                                                    >
-                                                        {component}
-                                                    </FormItem>
                                                 }
-                                            } else {
-                                                view! {
-                                                    <FormItem label="" hide=hide_label is_optional=is_optional>
-                                                        {component}
-                                                    </FormItem>
-                                                }
-                                            }
-                                        })
-                                        .collect_view();
-                                    view! {
-                                        <FormSection
-                                            title=title.unwrap_or_default()
-                                            hide=hide_section
-                                        >
-                                            {components}
-                                        </FormSection>
-                                    }
-                                        .into_view()
-                                })
-                                .collect_view(),
-                        )
-                    }
-                }}
+                                            })
+                                            .collect_view();
+                                        view! {
+                                            <FormSection
+                                                title=title.unwrap_or_default()
+                                                hide=hide_section
+                                            >
+                                                {components}
+                                            </FormSection>
+                                        }
+                                            .into_view()
+                                    })
+                                    .collect_view(),
+                            )
+                        }
+                    }}
+
+                </Transition>
 
-            </Transition>
+                <Show when=move || !free_form.get().is_empty()>
+                    <UnmanagedSettings free_form=free_form/>
+                </Show>
+            </fieldset>
+
+            <Show when=move || current_schema.get().id == MTA_STS_SCHEMA && !raw_view.get()>
+                <MtaStsPreview data=data/>
+            </Show>
 
             <FormButtonBar>
+                <Show when=move || !read_only.get()>
+                    <Button
+                        text=Signal::derive(move || {
+                            if raw_view.get() { "Form view" } else { "Advanced view" }.to_string()
+                        })
+                        color=Color::Gray
+                        on_click=move |_| raw_view.update(|raw_view| *raw_view = !*raw_view)
+                    />
+                </Show>
+
+                <Show when=move || {
+                    current_schema.get().id == RELAY_HOST_SCHEMA
+                        && params.with(|params| params.get("id").is_some_and(|id| !id.is_empty()))
+                }>
+                    <Button
+                        text=Signal::derive(move || {
+                            if relay_test.pending().get() { "Testing..." } else { "Test relay" }
+                                .to_string()
+                        })
+                        color=Color::Gray
+                        on_click=Callback::new(move |_| {
+                            if let Some(id) = params.get().get("id").cloned() {
+                                relay_test.dispatch(id);
+                            }
+                        })
+
+                        disabled=relay_test.pending()
+                    />
+                </Show>
+
+                <Show when=move || {
+                    cfg!(feature = "endpoint-test")
+                        && matches!(current_schema.get().id, MILTER_SCHEMA | MTA_HOOK_SCHEMA)
+                }>
+                    <Button
+                        text=Signal::derive(move || {
+                            if endpoint_test.pending().get() { "Testing..." } else { "Test endpoint" }
+                                .to_string()
+                        })
+                        color=Color::Gray
+                        on_click=Callback::new(move |_| {
+                            let request = data
+                                .with(|data| {
+                                    EndpointTestRequest::from_form(current_schema.get().id, data)
+                                });
+                            match request {
+                                Ok(request) => endpoint_test.dispatch(request),
+                                Err(err) => alert.set(Alert::warning(err)),
+                            }
+                        })
+
+                        disabled=endpoint_test.pending()
+                    />
+                </Show>
+
                 <Button
                     text="Cancel"
                     color=Color::Gray
                     on_click=move |_| {
-                        use_navigate()(
-                            &current_schema.get().list_path_or_default(),
-                            Default::default(),
-                        );
+                        let url = current_schema.get().list_path_or_default();
+                        match leave_prompt() {
+                            None => use_navigate()(&url, Default::default()),
+                            Some(prompt @ LeavePrompt::Unsaved) => {
+                                modal.set(
+                                    Modal::with_title(prompt.title())
+                                        .with_message(prompt.message())
+                                        .with_button(prompt.button())
+                                        .with_callback(move || {
+                                            if data
+                                                .try_update(|data| data.validate_form())
+                                                .unwrap_or_default()
+                                            {
+                                                save_form(false);
+                                            }
+                                        }),
+                                );
+                            }
+                            Some(prompt @ LeavePrompt::Invalid) => {
+                                modal.set(
+                                    Modal::with_title(prompt.title())
+                                        .with_message(prompt.message())
+                                        .with_button(prompt.button())
+                                        .with_dangerous_callback(move || {
+                                            use_navigate()(&url, Default::default())
+                                        }),
+                                );
+                            }
+                        }
                     }
                 />
 
-                <Button
-                    text="Save & Reload"
-                    color=Color::Gray
-                    on_click=Callback::new(move |_| {
-                        data.update(|data| {
-                            if data.validate_form() {
-                                save_changes.dispatch((Arc::new(data.build_update()), true));
+                <Show when=move || !read_only.get()>
+                    <input
+                        type="datetime-local"
+                        class="py-2 px-3 block border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                        prop:value=move || apply_at.get()
+                        on:input=move |ev| apply_at.set(event_target_value(&ev))
+                    />
+                    <Button
+                        text="Schedule"
+                        color=Color::Gray
+                        on_click=Callback::new(move |_| {
+                            let Some(apply_at) = ScheduledChanges::parse_apply_at(&apply_at.get()) else {
+                                alert.set(Alert::warning("Select the date and time to apply the changes"));
+                                return;
+                            };
+                            data.update(|data| {
+                                if data.validate_form() {
+                                    let Some(updates) = data
+                                        .checked_update_for(auth.get().permissions()) else {
+                                        return;
+                                    };
+                                    let schema = current_schema.get();
+                                    match ScheduledChange::new(
+                                        schema.id,
+                                        schema.form.title,
+                                        params.get().get("id").cloned(),
+                                        auth.get().username.to_string(),
+                                        updates,
+                                        apply_at,
+                                        clock::now(),
+                                    ) {
+                                        Ok(change) => {
+                                            scheduled
+                                                .update(|scheduled| {
+                                                    scheduled.schedule(change);
+                                                    scheduled.save();
+                                                });
+                                            alert
+                                                .set(
+                                                    Alert::success("Changes scheduled")
+                                                        .with_details(concat!(
+                                                            "They are applied from this browser ",
+                                                            "while it is open and you are signed in."
+                                                        )),
+                                                );
+                                        }
+                                        Err(err) => {
+                                            alert.set(Alert::error(err));
+                                        }
+                                    }
+                                }
+                            });
+                        })
+
+                        disabled=pending
+                    />
+
+                    <Button
+                        text="Stage changes"
+                        color=Color::Gray
+                        on_click=Callback::new(move |_| {
+                            data.update(|data| {
+                                if data.validate_form() {
+                                    let Some(updates) = data
+                                        .checked_update_for(auth.get().permissions()) else {
+                                        return;
+                                    };
+                                    let schema = current_schema.get();
+                                    staged
+                                        .update(|staged| {
+                                            staged
+                                                .stage(StagedItem {
+                                                    schema_id: schema.id,
+                                                    title: schema.form.title,
+                                                    record_id: params.get().get("id").cloned(),
+                                                    updates,
+                                                });
+                                        });
+                                    alert
+                                        .set(
+                                            Alert::success("Changes staged")
+                                                .with_details_list(staged.get().summary()),
+                                        );
+                                }
+                            });
+                        })
+
+                        disabled=pending
+                    />
+
+                    <Button
+                        text="Propose changes"
+                        color=Color::Gray
+                        on_click=Callback::new(move |_| {
+                            data.update(|data| {
+                                if data.validate_form() {
+                                    let Some(updates) = data
+                                        .checked_update_for(auth.get().permissions()) else {
+                                        return;
+                                    };
+                                    let schema = current_schema.get();
+                                    let author = auth.get().username.to_string();
+                                    let proposal = Proposal::new(
+                                        schema.id,
+                                        schema.form.title,
+                                        params.get().get("id").cloned(),
+                                        author.clone(),
+                                        updates,
+                                    );
+                                    log_audit(
+                                        AuditAction::ConfigPropose,
+                                        &author,
+                                        &format!(
+                                            "Proposed {} changes to {}",
+                                            proposal.total_changes(),
+                                            schema.id,
+                                        ),
+                                        None,
+                                        true,
+                                    );
+                                    proposals
+                                        .update(|proposals| {
+                                            proposals.propose(proposal);
+                                            proposals.save();
+                                        });
+                                    alert
+                                        .set(
+                                            Alert::success("Changes proposed")
+                                                .with_details(
+                                                    "Your changes will be applied once a reviewer approves them.",
+                                                ),
+                                        );
+                                }
+                            });
+                        })
+
+                        disabled=pending
+                    />
+
+                    <Show when=move || !staged.get().is_empty()>
+                        <Button
+                            text=Signal::derive(move || format!("Save all ({})", staged.get().len()))
+                            color=Color::Gray
+                            on_click=Callback::new(move |_| {
+                                let summary = staged.get().summary().join(", ");
+                                modal
+                                    .set(
+                                        Modal::with_title("Save all staged changes")
+                                            .with_message(
+                                                format!(
+                                                    "The following changes will be saved in a single update: {summary}.",
+                                                ),
+                                            )
+                                            .with_button("Save all")
+                                            .with_callback(move || {
+                                                save_changes
+                                                    .dispatch((
+                                                        Arc::new(staged.get().build_update()),
+                                                        false,
+                                                        SaveSource::Staged,
+                                                    ));
+                                            }),
+                                    );
+                            })
+
+                            disabled=pending
+                        />
+                    </Show>
+
+                    <Button
+                        text="Save & Reload"
+                        color=Color::Gray
+                        on_click=Callback::new(move |_| {
+                            if data.try_update(|data| data.validate_form()).unwrap_or_default() {
+                                save_form(true);
                             }
-                        });
-                    })
+                        })
 
-                    disabled=pending
-                >
+                        disabled=pending
+                    >
 
-                    <IconRefresh/>
-                </Button>
+                        <IconRefresh/>
+                    </Button>
 
-                <Button
-                    text="Save changes"
-                    color=Color::Blue
-                    on_click=Callback::new(move |_| {
-                        data.update(|data| {
-                            if data.validate_form() {
-                                save_changes.dispatch((Arc::new(data.build_update()), false));
+                    <Button
+                        text="Save changes"
+                        color=Color::Blue
+                        on_click=Callback::new(move |_| {
+                            if data.try_update(|data| data.validate_form()).unwrap_or_default() {
+                                save_form(false);
                             }
-                        });
-                    })
+                        })
 
-                    disabled=pending
-                />
+                        disabled=pending
+                    />
+                </Show>
             </FormButtonBar>
 
         </Form>
     }
 }
 
+async fn post_settings(auth: &AccessToken, changes: Arc<Vec<UpdateSettings>>) -> http::Result<()> {
+    HttpRequest::post("/api/settings")
+        .with_authorization(auth)
+        .with_csrf_token(&csrf_token_for_request())
+        .with_body(changes)
+        .unwrap()
+        .send::<Option<String>>()
+        .await
+        .map(|_| ())
+}
+
+/// Fetches the records referenced by select fields, as pairs of id and label
+/// keyed by the group's source key.
+pub(crate) async fn fetch_external_sources(
+    auth: &AccessToken,
+    groups: Vec<SharedGroup>,
+) -> http::Result<ExternalSources> {
+    let mut external_sources = ExternalSources::new();
+    for group in groups {
+        let items = HttpRequest::get("/api/settings/group")
+            .with_authorization(auth)
+            .with_parameter("prefix", group.prefix)
+            .with_parameter("suffix", group.suffix)
+            .with_parameter("field", group.field)
+            .send::<List<Settings>>()
+            .await?
+            .items;
+
+        external_sources.insert(
+            group.source_key,
+            items
+                .into_iter()
+                .filter_map(|mut item| {
+                    (
+                        item.remove("_id")?,
+                        item.remove(group.field).unwrap_or_default(),
+                    )
+                        .into()
+                })
+                .collect::<Vec<_>>(),
+        );
+    }
+    Ok(external_sources)
+}
+
+/// Fetches the settings currently stored on the server for a schema record,
+/// returning `None` when there are none.
+pub(crate) async fn fetch_current_settings(
+    auth: &AccessToken,
+    schema: &Schema,
+    name: &str,
+) -> http::Result<Option<Settings>> {
+    let ranges = schema.settings_ranges(name);
+
+    match schema.typ {
+        SchemaType::Record { .. } => HttpRequest::get("/api/settings/list")
+            .with_authorization(auth)
+            .with_parameter("prefix", ranges.prefixes.join(","))
+            .send::<FetchSettings>()
+            .await
+            .map(|mut list| {
+                if !list.items.is_empty() {
+                    list.items.insert("_id".to_string(), name.to_string());
+                    Some(list.items)
+                } else {
+                    None
+                }
+            }),
+        SchemaType::Entry { .. } => HttpRequest::get("/api/settings/keys")
+            .with_authorization(auth)
+            .with_parameter("keys", ranges.keys.join(","))
+            .send::<AHashMap<String, Option<String>>>()
+            .await
+            .map(|list| {
+                list.into_values().next().flatten().map(|value| {
+                    let mut settings = Settings::new();
+                    settings.insert("_id".to_string(), name.to_string());
+                    settings.insert("_value".to_string(), value);
+                    settings
+                })
+            }),
+        SchemaType::List => HttpRequest::get("/api/settings/keys")
+            .with_authorization(auth)
+            .with_parameter("keys", ranges.keys.join(","))
+            .with_parameter("prefixes", ranges.prefixes.join(","))
+            .send::<Settings>()
+            .await
+            .map(|settings| (!settings.is_empty()).then_some(settings)),
+    }
+}
+
 impl Schema {
     fn list_path(&self) -> Option<String> {
         if !matches!(self.typ, SchemaType::List) {
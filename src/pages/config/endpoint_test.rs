@@ -0,0 +1,283 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::messages::alert::Alert,
+    core::{
+        form::FormData,
+        http::{self, HttpRequest},
+        AccessToken,
+    },
+};
+
+pub const MILTER_SCHEMA: &str = "milter";
+pub const MTA_HOOK_SCHEMA: &str = "mta-hooks";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "camelCase")]
+pub enum EndpointTestRequest {
+    Milter {
+        hostname: String,
+        port: u16,
+        tls: bool,
+        #[serde(rename = "allowInvalidCerts")]
+        allow_invalid_certs: bool,
+        timeout: Option<String>,
+        version: u32,
+    },
+    MtaHook {
+        url: String,
+        #[serde(rename = "allowInvalidCerts")]
+        allow_invalid_certs: bool,
+        timeout: Option<String>,
+        headers: Vec<String>,
+        username: Option<String>,
+        secret: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointTestResponse {
+    pub status: String,
+    #[serde(default)]
+    pub code: Option<u16>,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub negotiated: Option<String>,
+    #[serde(default)]
+    pub elapsed: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointTestOutcome {
+    Success,
+    Unreachable,
+    Tls,
+    Timeout,
+    Auth,
+    ProtocolMismatch,
+    Unknown,
+}
+
+impl EndpointTestRequest {
+    /// Assembles the test request from the values currently entered in the
+    /// milter or MTA hook form, so that unsaved changes can be tested.
+    pub fn from_form(schema_id: &str, data: &FormData) -> Result<Self, String> {
+        let non_empty = |id: &str| {
+            data.value_as_str(id)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let flag = |id: &str| data.value::<bool>(id).unwrap_or_default();
+
+        match schema_id {
+            MILTER_SCHEMA => Ok(EndpointTestRequest::Milter {
+                hostname: non_empty("hostname").ok_or("Enter the milter hostname")?,
+                port: non_empty("port")
+                    .ok_or("Enter the milter port")?
+                    .parse()
+                    .map_err(|_| "Invalid milter port")?,
+                tls: flag("tls"),
+                allow_invalid_certs: flag("allow-invalid-certs"),
+                timeout: non_empty("timeout.connect"),
+                version: non_empty("options.version")
+                    .and_then(|version| version.parse().ok())
+                    .unwrap_or(6),
+            }),
+            MTA_HOOK_SCHEMA => Ok(EndpointTestRequest::MtaHook {
+                url: non_empty("url").ok_or("Enter the hook URL")?,
+                allow_invalid_certs: flag("allow-invalid-certs"),
+                timeout: non_empty("timeout"),
+                headers: data
+                    .array_value("headers")
+                    .filter(|header| !header.trim().is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                username: non_empty("auth.username"),
+                secret: non_empty("auth.secret"),
+            }),
+            _ => Err(format!("Endpoint tests are not supported for {schema_id}")),
+        }
+    }
+}
+
+impl EndpointTestOutcome {
+    /// Maps the status and protocol code returned by the backend to the
+    /// stage of the handshake that failed. Codes are milter response codes
+    /// or HTTP status codes depending on the endpoint type.
+    pub fn classify(status: &str, code: Option<u16>) -> Self {
+        let (stage, _) = status.split_once('.').unwrap_or((status, ""));
+        match stage {
+            "ok" => EndpointTestOutcome::Success,
+            "dns" | "connect" | "io" => EndpointTestOutcome::Unreachable,
+            "tls" => EndpointTestOutcome::Tls,
+            "timeout" => EndpointTestOutcome::Timeout,
+            "auth" => EndpointTestOutcome::Auth,
+            "protocol" | "version" => EndpointTestOutcome::ProtocolMismatch,
+            _ => match code {
+                Some(401 | 403 | 407) => EndpointTestOutcome::Auth,
+                Some(408 | 504) => EndpointTestOutcome::Timeout,
+                Some(400..=599) => EndpointTestOutcome::ProtocolMismatch,
+                _ => EndpointTestOutcome::Unknown,
+            },
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            EndpointTestOutcome::Success => "Endpoint is reachable",
+            EndpointTestOutcome::Unreachable => "Failed to connect to the endpoint",
+            EndpointTestOutcome::Tls => "TLS negotiation with the endpoint failed",
+            EndpointTestOutcome::Timeout => "The endpoint did not respond in time",
+            EndpointTestOutcome::Auth => "The endpoint rejected the credentials",
+            EndpointTestOutcome::ProtocolMismatch => {
+                "Protocol negotiation with the endpoint failed"
+            }
+            EndpointTestOutcome::Unknown => "Endpoint test failed",
+        }
+    }
+}
+
+impl EndpointTestResponse {
+    pub fn outcome(&self) -> EndpointTestOutcome {
+        EndpointTestOutcome::classify(&self.status, self.code)
+    }
+
+    pub fn details(&self) -> Vec<String> {
+        let mut details = Vec::new();
+        if let Some(message) = &self.message {
+            details.push(match self.code {
+                Some(code) => format!("{code} {message}"),
+                None => message.clone(),
+            });
+        }
+        if let Some(negotiated) = &self.negotiated {
+            details.push(format!("Negotiated: {negotiated}"));
+        }
+        if self.outcome() == EndpointTestOutcome::Success {
+            details.push(format!("Completed in {} ms", self.elapsed));
+        }
+        details
+    }
+}
+
+impl From<EndpointTestResponse> for Alert {
+    fn from(response: EndpointTestResponse) -> Self {
+        let outcome = response.outcome();
+        let alert = if outcome == EndpointTestOutcome::Success {
+            Alert::success(outcome.title())
+        } else {
+            Alert::error(outcome.title()).without_timeout()
+        };
+        alert.with_details_list(response.details())
+    }
+}
+
+/// Asks the server to connect to a milter or MTA hook endpoint and perform
+/// the protocol handshake, without processing any message. The endpoint is
+/// not part of the released server API, so the test is only offered when
+/// built with the `endpoint-test` feature.
+pub async fn test_endpoint(
+    auth: &AccessToken,
+    request: &EndpointTestRequest,
+) -> http::Result<EndpointTestResponse> {
+    HttpRequest::post("/api/troubleshoot/endpoint")
+        .with_authorization(auth)
+        .with_body(request)?
+        .send::<EndpointTestResponse>()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_handshake_results() {
+        for (status, code, expected) in [
+            ("ok", None, EndpointTestOutcome::Success),
+            ("ok", Some(200), EndpointTestOutcome::Success),
+            ("dns.nxdomain", None, EndpointTestOutcome::Unreachable),
+            ("connect.refused", None, EndpointTestOutcome::Unreachable),
+            ("tls.certificate", None, EndpointTestOutcome::Tls),
+            ("timeout", None, EndpointTestOutcome::Timeout),
+            (
+                "protocol.version",
+                None,
+                EndpointTestOutcome::ProtocolMismatch,
+            ),
+            ("http", Some(401), EndpointTestOutcome::Auth),
+            ("http", Some(504), EndpointTestOutcome::Timeout),
+            ("http", Some(404), EndpointTestOutcome::ProtocolMismatch),
+            ("http", Some(302), EndpointTestOutcome::Unknown),
+            ("unexpected", None, EndpointTestOutcome::Unknown),
+        ] {
+            assert_eq!(
+                EndpointTestOutcome::classify(status, code),
+                expected,
+                "{status} {code:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn assemble_milter_request() {
+        let data = FormData::default()
+            .with_value("hostname", " milter.example.org ")
+            .with_value("port", "11332")
+            .with_value("tls", "true")
+            .with_value("timeout.connect", "10s")
+            .with_value("options.version", "2");
+        assert_eq!(
+            EndpointTestRequest::from_form(MILTER_SCHEMA, &data),
+            Ok(EndpointTestRequest::Milter {
+                hostname: "milter.example.org".to_string(),
+                port: 11332,
+                tls: true,
+                allow_invalid_certs: false,
+                timeout: Some("10s".to_string()),
+                version: 2,
+            })
+        );
+
+        assert!(EndpointTestRequest::from_form(
+            MILTER_SCHEMA,
+            &data.clone().with_value("port", "not-a-port")
+        )
+        .is_err());
+        assert!(
+            EndpointTestRequest::from_form(MILTER_SCHEMA, &data.with_value("hostname", ""))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn assemble_hook_request() {
+        let data = FormData::default()
+            .with_value("url", "https://hooks.example.org/mta")
+            .with_value("headers", vec!["X-Key: 1".to_string(), " ".to_string()])
+            .with_value("auth.username", "admin")
+            .with_value("auth.secret", "");
+        assert_eq!(
+            EndpointTestRequest::from_form(MTA_HOOK_SCHEMA, &data),
+            Ok(EndpointTestRequest::MtaHook {
+                url: "https://hooks.example.org/mta".to_string(),
+                allow_invalid_certs: false,
+                timeout: None,
+                headers: vec!["X-Key: 1".to_string()],
+                username: Some("admin".to_string()),
+                secret: None,
+            })
+        );
+        assert!(EndpointTestRequest::from_form("smtp-out-queue", &data).is_err());
+    }
+}
@@ -0,0 +1,338 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        form::button::Button,
+        messages::alert::{use_alerts, Alert},
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+    },
+};
+
+pub const GEOIP_SCHEMA: &str = "smtp-in-asn";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeoIpStatus {
+    #[serde(rename = "type")]
+    pub source: String,
+    #[serde(default)]
+    pub asn: DatabaseStatus,
+    #[serde(default)]
+    pub geo: DatabaseStatus,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseStatus {
+    pub loaded: bool,
+    #[serde(default)]
+    pub records: Option<u64>,
+    #[serde(default)]
+    pub last_update: Option<i64>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IpLookupResult {
+    pub ip: String,
+    #[serde(default)]
+    pub asn: Option<u32>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub network: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatusRow {
+    pub label: &'static str,
+    pub color: Color,
+    pub text: String,
+}
+
+impl GeoIpStatus {
+    /// Describes the state of the ASN and geolocation databases. Databases
+    /// are only downloaded when the URL resource source is selected.
+    pub fn rows(&self) -> Vec<StatusRow> {
+        match self.source.as_str() {
+            "resource" => vec![
+                self.asn.row("ASN database"),
+                self.geo.row("Geolocation database"),
+            ],
+            "dns" => vec![StatusRow {
+                label: "ASN/Geo data",
+                color: Color::Green,
+                text: "Resolved on demand through DNS lookups".to_string(),
+            }],
+            _ => vec![StatusRow {
+                label: "ASN/Geo data",
+                color: Color::Gray,
+                text: "Disabled".to_string(),
+            }],
+        }
+    }
+}
+
+impl DatabaseStatus {
+    fn row(&self, label: &'static str) -> StatusRow {
+        let last_update = self
+            .last_update
+            .and_then(|ts| DateTime::<Utc>::from_timestamp(ts, 0))
+            .map(|ts| format!("updated {}", ts.format("%Y-%m-%d %H:%M UTC")));
+
+        let (color, text) = if self.loaded {
+            (
+                Color::Green,
+                [
+                    Some(format!(
+                        "Loaded, {} records",
+                        self.records.unwrap_or_default()
+                    )),
+                    last_update,
+                ]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(", "),
+            )
+        } else if let Some(error) = &self.error {
+            (Color::Red, format!("Failed to load: {error}"))
+        } else {
+            (Color::Yellow, "Not loaded yet".to_string())
+        };
+
+        StatusRow { label, color, text }
+    }
+}
+
+impl IpLookupResult {
+    pub fn summary(&self) -> String {
+        let asn = match (self.asn, &self.name) {
+            (Some(asn), Some(name)) => format!("AS{asn} {name}"),
+            (Some(asn), None) => format!("AS{asn}"),
+            (None, _) => "no ASN".to_string(),
+        };
+        let network = self
+            .network
+            .as_ref()
+            .map(|network| format!(" ({network})"))
+            .unwrap_or_default();
+        let country = self.country.as_deref().unwrap_or("unknown country");
+
+        format!("{}: {asn}{network}, {country}", self.ip)
+    }
+}
+
+/// Shows whether the ASN and GeoIP databases are loaded and looks up test
+/// addresses. The status and lookup endpoints are not part of the released
+/// server API, so the panel is only shown when built with the `asn-lookup`
+/// feature.
+#[component]
+pub fn GeoIpStatusPanel() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let ip = create_rw_signal(String::new());
+    let result = create_rw_signal(None::<IpLookupResult>);
+
+    let status = create_resource(
+        || (),
+        move |_| {
+            let auth = auth.get_untracked();
+
+            async move {
+                HttpRequest::get("/api/troubleshoot/asn/status")
+                    .with_authorization(&auth)
+                    .send::<GeoIpStatus>()
+                    .await
+            }
+        },
+    );
+
+    let lookup = create_action(move |ip: &IpAddr| {
+        let auth = auth.get();
+        let ip = ip.to_string();
+
+        async move {
+            match HttpRequest::get("/api/troubleshoot/asn")
+                .with_authorization(&auth)
+                .with_parameter("ip", ip)
+                .send::<IpLookupResult>()
+                .await
+            {
+                Ok(lookup) => result.set(Some(lookup)),
+                Err(err) => {
+                    result.set(None);
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    view! {
+        <div class="flex flex-col gap-y-3 p-4 mb-4 bg-white border border-gray-200 rounded-xl dark:bg-neutral-800 dark:border-neutral-700">
+            <Transition>
+                {move || match status.get() {
+                    Some(Ok(status)) => {
+                        Some(
+                            status
+                                .rows()
+                                .into_iter()
+                                .map(|row| {
+                                    let dot = match row.color {
+                                        Color::Green => "size-2 rounded-full bg-teal-500",
+                                        Color::Red => "size-2 rounded-full bg-red-500",
+                                        Color::Yellow => "size-2 rounded-full bg-yellow-500",
+                                        Color::Gray | Color::Blue => {
+                                            "size-2 rounded-full bg-gray-400"
+                                        }
+                                    };
+                                    view! {
+                                        <div class="flex items-center gap-x-2 text-sm">
+                                            <span class=dot></span>
+                                            <span class="font-medium text-gray-800 dark:text-neutral-200">
+                                                {row.label}
+                                            </span>
+                                            <span class="text-gray-600 dark:text-neutral-400">
+                                                {row.text}
+                                            </span>
+                                        </div>
+                                    }
+                                })
+                                .collect_view(),
+                        )
+                    }
+                    Some(Err(http::Error::NotFound)) | None => None,
+                    Some(Err(err)) => {
+                        log::warn!("Failed to fetch ASN/Geo status: {err:?}");
+                        None
+                    }
+                }}
+
+            </Transition>
+            <div class="flex gap-x-2">
+                <input
+                    type="text"
+                    class="py-2 px-3 block w-full border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                    placeholder="IP address"
+                    prop:value=move || ip.get()
+                    on:input=move |ev| ip.set(event_target_value(&ev))
+                />
+                <Button
+                    text="Lookup IP"
+                    color=Color::Gray
+                    on_click=move |_| {
+                        match ip.get().trim().parse::<IpAddr>() {
+                            Ok(addr) => {
+                                lookup.dispatch(addr);
+                            }
+                            Err(_) => {
+                                alert.set(Alert::warning("Enter a valid IPv4 or IPv6 address"));
+                            }
+                        }
+                    }
+
+                    disabled=lookup.pending()
+                />
+            </div>
+            {move || {
+                result
+                    .get()
+                    .map(|result| {
+                        view! {
+                            <p class="text-sm text-gray-800 dark:text-neutral-200">
+                                {result.summary()}
+                            </p>
+                        }
+                    })
+            }}
+
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_database_status() {
+        let status: GeoIpStatus = serde_json::from_str(
+            r#"{
+                "type": "resource",
+                "asn": {"loaded": true, "records": 512000, "lastUpdate": 1700000000},
+                "geo": {"loaded": false, "error": "HTTP 404"}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            status.rows(),
+            vec![
+                StatusRow {
+                    label: "ASN database",
+                    color: Color::Green,
+                    text: "Loaded, 512000 records, updated 2023-11-14 22:13 UTC".to_string(),
+                },
+                StatusRow {
+                    label: "Geolocation database",
+                    color: Color::Red,
+                    text: "Failed to load: HTTP 404".to_string(),
+                },
+            ]
+        );
+
+        let status: GeoIpStatus =
+            serde_json::from_str(r#"{"type": "resource", "geo": {"loaded": false}}"#).unwrap();
+        assert_eq!(status.rows()[1].color, Color::Yellow);
+        assert_eq!(status.rows()[0].text, "Not loaded yet");
+
+        for (source, color) in [("dns", Color::Green), ("disable", Color::Gray)] {
+            let status = GeoIpStatus {
+                source: source.to_string(),
+                ..Default::default()
+            };
+            assert_eq!(status.rows().len(), 1);
+            assert_eq!(status.rows()[0].color, color);
+        }
+    }
+
+    #[test]
+    fn format_lookup_result() {
+        let result = IpLookupResult {
+            ip: "8.8.8.8".to_string(),
+            asn: Some(15169),
+            name: Some("GOOGLE".to_string()),
+            network: Some("8.8.8.0/24".to_string()),
+            country: Some("US".to_string()),
+        };
+        assert_eq!(result.summary(), "8.8.8.8: AS15169 GOOGLE (8.8.8.0/24), US");
+
+        let result = IpLookupResult {
+            ip: "192.0.2.1".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(result.summary(), "192.0.2.1: no ASN, unknown country");
+
+        let result = IpLookupResult {
+            ip: "2001:db8::1".to_string(),
+            asn: Some(64496),
+            ..Default::default()
+        };
+        assert_eq!(result.summary(), "2001:db8::1: AS64496, unknown country");
+    }
+}
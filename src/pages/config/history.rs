@@ -0,0 +1,135 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use leptos::*;
+
+use crate::{
+    components::icon::IconClock,
+    utils::audit::{get_audit_logs, key_history, SettingChange},
+};
+
+use super::{Schema, SchemaType};
+
+const HISTORY_LIMIT: usize = 5;
+
+/// Returns the full key under which a field is stored, which is the key
+/// recorded in the audit log.
+pub fn setting_key(schema: &Schema, record_id: Option<&str>, field_id: &str) -> String {
+    match (&schema.typ, record_id) {
+        (SchemaType::Record { prefix, .. }, Some(record_id)) => {
+            format!("{prefix}.{record_id}.{field_id}")
+        }
+        (SchemaType::Entry { prefix }, Some(record_id)) => format!("{prefix}.{record_id}"),
+        _ => field_id.to_string(),
+    }
+}
+
+pub fn format_change(change: &SettingChange) -> String {
+    format!(
+        "{} → {}",
+        change.old.as_deref().unwrap_or("(unset)"),
+        change.new.as_deref().unwrap_or("(unset)")
+    )
+}
+
+#[component]
+pub fn SettingHistory(key: String) -> impl IntoView {
+    let show = create_rw_signal(false);
+    let key = store_value(key);
+    let history = create_memo(move |_| {
+        show.get()
+            .then(|| key_history(&get_audit_logs(), &key.get_value(), HISTORY_LIMIT))
+            .unwrap_or_default()
+    });
+
+    // Rendered as a link so that it remains usable inside disabled fieldsets
+    view! {
+        <div class="relative inline-flex">
+            <a
+                href="#"
+                class="inline-flex mt-2.5 text-gray-300 hover:text-gray-500 dark:text-gray-600"
+                title="Change history"
+                on:click=move |ev| {
+                    ev.prevent_default();
+                    show.update(|show| *show = !*show);
+                }
+            >
+
+                <IconClock attr:class="flex-shrink-0 size-4"/>
+            </a>
+            <Show when=move || show.get()>
+                <div class="absolute end-0 top-8 z-20 w-80 p-3 bg-white border border-gray-200 rounded-lg shadow-lg dark:bg-slate-900 dark:border-gray-700">
+                    <p class="mb-2 text-xs font-semibold uppercase text-gray-500">
+                        "Recent changes"
+                    </p>
+                    {move || {
+                        let history = history.get();
+                        if history.is_empty() {
+                            view! {
+                                <p class="text-sm text-gray-600 dark:text-gray-400">
+                                    "No changes were recorded in this session."
+                                </p>
+                            }
+                                .into_view()
+                        } else {
+                            history
+                                .into_iter()
+                                .map(|entry| {
+                                    view! {
+                                        <div class="py-1.5 text-sm border-t first:border-t-0 border-gray-100 dark:border-gray-800">
+                                            <p class="text-xs text-gray-500">
+                                                {format!(
+                                                    "{} by {}",
+                                                    entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                                                    entry.user,
+                                                )}
+                                            </p>
+                                            <p class="font-mono text-gray-800 break-all dark:text-gray-200">
+                                                {format_change(&entry.change)}
+                                            </p>
+                                        </div>
+                                    }
+                                })
+                                .collect_view()
+                        }
+                    }}
+
+                </div>
+            </Show>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_schemas;
+
+    #[test]
+    fn resolve_setting_keys() {
+        let schemas = build_schemas();
+        assert_eq!(
+            setting_key(&schemas.schemas["listener"], Some("smtp"), "bind"),
+            "server.listener.smtp.bind"
+        );
+        assert_eq!(
+            setting_key(&schemas.schemas["blocked-ip"], Some("10.0.0.1"), "_id"),
+            "server.blocked-ip.10.0.0.1"
+        );
+        assert_eq!(
+            setting_key(&schemas.schemas["network"], None, "server.hostname"),
+            "server.hostname"
+        );
+        assert_eq!(
+            format_change(&SettingChange {
+                key: "server.hostname".to_string(),
+                old: None,
+                new: Some("mx.example.org".to_string()),
+            }),
+            "(unset) → mx.example.org"
+        );
+    }
+}
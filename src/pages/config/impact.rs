@@ -0,0 +1,211 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::AHashSet;
+
+use super::{diff_updates, UpdateSettings};
+
+/// Settings that can interrupt the current session or the server's
+/// connectivity when changed. `*` matches a single key segment and any key
+/// below a pattern is also matched.
+const IMPACT_PATTERNS: &[(&str, &str)] = &[
+    (
+        "server.listener.*.bind",
+        "Changing listener addresses can make the server unreachable on its current ports.",
+    ),
+    (
+        "server.listener.*.protocol",
+        "Changing a listener protocol can disconnect clients using it, including this session.",
+    ),
+    (
+        "server.listener.*.tls",
+        "Changing listener TLS settings can break connections to the web admin.",
+    ),
+    (
+        "server.tls",
+        "Changing the default TLS settings can break connections to the web admin.",
+    ),
+    (
+        "certificate",
+        "Changing certificates can break TLS connections to the web admin.",
+    ),
+    (
+        "server.http",
+        "Changing HTTP settings can make the web admin unreachable.",
+    ),
+    (
+        "server.blocked-ip",
+        "Blocking addresses can lock you out if your own address is included.",
+    ),
+    (
+        "authentication",
+        "Changing authentication settings can sign you out or prevent you from signing in again.",
+    ),
+    (
+        "storage.directory",
+        "Changing the directory can prevent you from signing in again.",
+    ),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImpactWarning {
+    pub key: String,
+    pub reason: &'static str,
+}
+
+/// Tracks which impact warnings were explicitly acknowledged, so that the
+/// same change does not require confirmation twice.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImpactAcknowledgement {
+    keys: AHashSet<String>,
+}
+
+/// Returns a warning for every flagged key modified by `updates`, compared
+/// to the values currently stored as described by `previous`.
+pub fn impact_warnings(
+    updates: &[UpdateSettings],
+    previous: &[UpdateSettings],
+) -> Vec<ImpactWarning> {
+    diff_updates(updates, previous)
+        .into_iter()
+        .filter_map(|change| {
+            IMPACT_PATTERNS
+                .iter()
+                .find(|(pattern, _)| matches_pattern(pattern, &change.key))
+                .map(|(_, reason)| ImpactWarning {
+                    key: change.key,
+                    reason,
+                })
+        })
+        .collect()
+}
+
+impl ImpactAcknowledgement {
+    pub fn is_required(&self, warnings: &[ImpactWarning]) -> bool {
+        warnings
+            .iter()
+            .any(|warning| !self.keys.contains(&warning.key))
+    }
+
+    pub fn acknowledge(&mut self, warnings: &[ImpactWarning]) {
+        self.keys
+            .extend(warnings.iter().map(|warning| warning.key.clone()));
+    }
+}
+
+fn matches_pattern(pattern: &str, key: &str) -> bool {
+    let mut key = key.split('.');
+    pattern.split('.').all(|segment| {
+        key.next()
+            .is_some_and(|part| segment == "*" || segment == part)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(prefix: Option<&str>, values: &[(&str, &str)]) -> UpdateSettings {
+        UpdateSettings::Insert {
+            prefix: prefix.map(str::to_string),
+            values: values
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            assert_empty: false,
+        }
+    }
+
+    #[test]
+    fn detect_flagged_fields() {
+        let previous = vec![insert(
+            Some("server.listener.https"),
+            &[
+                ("bind.0", "[::]:443"),
+                ("protocol", "http"),
+                ("tls.implicit", "true"),
+            ],
+        )];
+
+        // Unchanged values do not trigger warnings
+        let updates = vec![
+            UpdateSettings::Clear {
+                prefix: "server.listener.https.".to_string(),
+                filter: None,
+            },
+            previous[0].clone(),
+        ];
+        assert_eq!(impact_warnings(&updates, &previous), vec![]);
+
+        // Changed and removed flagged keys are reported
+        let updates = vec![
+            UpdateSettings::Clear {
+                prefix: "server.listener.https.".to_string(),
+                filter: None,
+            },
+            insert(
+                Some("server.listener.https"),
+                &[("bind.0", "[::]:8443"), ("protocol", "http")],
+            ),
+        ];
+        assert_eq!(
+            impact_warnings(&updates, &previous)
+                .into_iter()
+                .map(|warning| warning.key)
+                .collect::<Vec<_>>(),
+            vec![
+                "server.listener.https.bind.0",
+                "server.listener.https.tls.implicit"
+            ]
+        );
+
+        // Unflagged keys are ignored
+        let updates = vec![
+            insert(None, &[("session.rcpt.relay", "false")]),
+            UpdateSettings::Delete {
+                keys: vec!["authentication.fallback-admin.user".to_string()],
+            },
+        ];
+        assert_eq!(
+            impact_warnings(&updates, &[]),
+            vec![ImpactWarning {
+                key: "authentication.fallback-admin.user".to_string(),
+                reason: IMPACT_PATTERNS[7].1,
+            }]
+        );
+    }
+
+    #[test]
+    fn match_key_patterns() {
+        assert!(matches_pattern(
+            "server.listener.*.tls",
+            "server.listener.imap.tls.implicit"
+        ));
+        assert!(matches_pattern("certificate", "certificate.default.cert"));
+        assert!(!matches_pattern(
+            "server.listener.*.tls",
+            "server.listener.imap"
+        ));
+        assert!(!matches_pattern("server.http", "server.hostname"));
+    }
+
+    #[test]
+    fn acknowledgement_gate() {
+        let warning = |key: &str| ImpactWarning {
+            key: key.to_string(),
+            reason: "",
+        };
+        let mut ack = ImpactAcknowledgement::default();
+        assert!(!ack.is_required(&[]));
+        assert!(ack.is_required(&[warning("server.http.url")]));
+
+        ack.acknowledge(&[warning("server.http.url")]);
+        assert!(!ack.is_required(&[warning("server.http.url")]));
+
+        // A new flagged change requires a new acknowledgement
+        assert!(ack.is_required(&[warning("server.http.url"), warning("server.tls.timeout")]));
+    }
+}
@@ -0,0 +1,203 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::messages::banner::Banner,
+    core::{
+        http::{self, HttpRequest},
+        ipnet::IpNetwork,
+        oauth::use_authorization,
+        AccessToken,
+    },
+    pages::config::edit::FetchSettings,
+};
+
+pub const ALLOWED_IP_PREFIX: &str = "server.allowed-ip";
+pub const BLOCKED_IP_PREFIX: &str = "server.blocked-ip";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IpRuleConflict {
+    pub allowed: String,
+    pub blocked: String,
+    pub kind: IpConflictKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IpConflictKind {
+    Duplicate,
+    BlockedContainsAllowed,
+    AllowedContainsBlocked,
+}
+
+impl IpRuleConflict {
+    pub fn description(&self) -> String {
+        match self.kind {
+            IpConflictKind::Duplicate => {
+                format!("{} is listed as both allowed and blocked.", self.allowed)
+            }
+            IpConflictKind::BlockedContainsAllowed => format!(
+                "Allowed address {} falls within blocked network {}, exempting it from the block.",
+                self.allowed, self.blocked
+            ),
+            IpConflictKind::AllowedContainsBlocked => format!(
+                "Blocked address {} falls within allowed network {}, so the two rules contradict each other.",
+                self.blocked, self.allowed
+            ),
+        }
+    }
+}
+
+/// Compares the Allowed and Blocked IP lists, reporting entries present in
+/// both lists and networks that contain an entry of the other list.
+/// Entries that cannot be parsed are ignored.
+pub fn check_ip_rules<'x>(
+    allowed: impl IntoIterator<Item = &'x str>,
+    blocked: impl IntoIterator<Item = &'x str>,
+) -> Vec<IpRuleConflict> {
+    let allowed = parse_entries(allowed);
+    let blocked = parse_entries(blocked);
+    let mut conflicts = Vec::new();
+
+    for (allowed_entry, allowed_net) in &allowed {
+        for (blocked_entry, blocked_net) in &blocked {
+            let kind = match (
+                blocked_net.contains(allowed_net),
+                allowed_net.contains(blocked_net),
+            ) {
+                (true, true) => IpConflictKind::Duplicate,
+                (true, false) => IpConflictKind::BlockedContainsAllowed,
+                (false, true) => IpConflictKind::AllowedContainsBlocked,
+                (false, false) => continue,
+            };
+            conflicts.push(IpRuleConflict {
+                allowed: allowed_entry.to_string(),
+                blocked: blocked_entry.to_string(),
+                kind,
+            });
+        }
+    }
+
+    conflicts
+}
+
+fn parse_entries<'x>(entries: impl IntoIterator<Item = &'x str>) -> Vec<(&'x str, IpNetwork)> {
+    entries
+        .into_iter()
+        .filter_map(|entry| Some((entry, entry.parse::<IpNetwork>().ok()?)))
+        .collect()
+}
+
+pub async fn fetch_ip_list(auth: &AccessToken, prefix: &str) -> http::Result<Vec<String>> {
+    HttpRequest::get("/api/settings/list")
+        .with_authorization(auth)
+        .with_parameter("prefix", prefix)
+        .send::<FetchSettings>()
+        .await
+        .map(|list| list.items.into_keys().collect())
+}
+
+#[component]
+pub fn IpRuleConflicts(#[prop(into)] reload: Signal<u32>) -> impl IntoView {
+    let auth = use_authorization();
+    let conflicts = create_resource(
+        move || reload.get(),
+        move |_| {
+            let auth = auth.get_untracked();
+
+            async move {
+                let allowed = fetch_ip_list(&auth, ALLOWED_IP_PREFIX).await?;
+                let blocked = fetch_ip_list(&auth, BLOCKED_IP_PREFIX).await?;
+                Ok::<_, http::Error>(check_ip_rules(
+                    allowed.iter().map(|s| s.as_str()),
+                    blocked.iter().map(|s| s.as_str()),
+                ))
+            }
+        },
+    );
+
+    view! {
+        <Transition>
+            {move || {
+                let conflicts = match conflicts.get() {
+                    Some(Ok(conflicts)) if !conflicts.is_empty() => conflicts,
+                    _ => return None,
+                };
+                let total = conflicts.len();
+
+                Some(
+                    view! {
+                        <Banner message=format!(
+                            "{total} conflicting allowed and blocked IP {}",
+                            if total == 1 { "rule" } else { "rules" },
+                        )>
+                            <ul class="list-disc ps-5 text-sm">
+                                {conflicts
+                                    .iter()
+                                    .map(|conflict| view! { <li>{conflict.description()}</li> })
+                                    .collect_view()}
+                            </ul>
+                        </Banner>
+                    },
+                )
+            }}
+
+        </Transition>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_duplicate_across_lists() {
+        assert_eq!(
+            check_ip_rules(["192.168.1.1", "10.0.0.1"], ["10.0.0.1"]),
+            vec![IpRuleConflict {
+                allowed: "10.0.0.1".to_string(),
+                blocked: "10.0.0.1".to_string(),
+                kind: IpConflictKind::Duplicate,
+            }]
+        );
+        assert_eq!(
+            check_ip_rules(["10.0.0.1/32"], ["10.0.0.1"])[0].kind,
+            IpConflictKind::Duplicate
+        );
+    }
+
+    #[test]
+    fn detect_network_containing_listed_ip() {
+        assert_eq!(
+            check_ip_rules(["10.0.0.5"], ["10.0.0.0/24"]),
+            vec![IpRuleConflict {
+                allowed: "10.0.0.5".to_string(),
+                blocked: "10.0.0.0/24".to_string(),
+                kind: IpConflictKind::BlockedContainsAllowed,
+            }]
+        );
+        assert_eq!(
+            check_ip_rules(["2001:db8::/32"], ["2001:db8::dead:beef"])[0].kind,
+            IpConflictKind::AllowedContainsBlocked
+        );
+    }
+
+    #[test]
+    fn non_overlapping_ranges_are_clean() {
+        assert!(check_ip_rules(
+            ["10.0.0.0/25", "192.168.0.1", "2001:db8::1"],
+            [
+                "10.0.0.128/25",
+                "192.168.0.2",
+                "172.16.0.0/12",
+                "2001:db9::/32"
+            ],
+        )
+        .is_empty());
+    }
+}
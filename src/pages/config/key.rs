@@ -0,0 +1,146 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::fmt::{self, Display};
+
+/// A dot-separated setting key such as `server.listener.smtp.bind`.
+///
+/// Every segment is guaranteed to be non-empty and free of whitespace, so
+/// joining a prefix with an id can never produce keys like `store..id` or
+/// `store.id.` that would silently target the wrong settings.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SettingKey(String);
+
+impl SettingKey {
+    /// Parses a full key, validating each of its segments.
+    pub fn parse(key: &str) -> Result<Self, String> {
+        validate_segment(key).map(|_| SettingKey(key.to_string()))
+    }
+
+    /// Appends a segment. The segment may itself contain dots, as record
+    /// ids often do, as long as none of the resulting segments is empty.
+    pub fn child(&self, segment: impl Display) -> Result<Self, String> {
+        let segment = segment.to_string();
+        validate_segment(&segment).map(|_| SettingKey(format!("{}.{segment}", self.0)))
+    }
+
+    /// Appends a zero-padded array index.
+    pub fn index(&self, index: usize, pad_len: usize) -> Self {
+        SettingKey(format!("{}.{index:0>pad_len$}", self.0))
+    }
+
+    /// Returns the full key of a fixed, known-valid last segment such as
+    /// `if` or `then`.
+    pub fn leaf(&self, segment: &'static str) -> String {
+        debug_assert!(validate_segment(segment).is_ok());
+        format!("{}.{segment}", self.0)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.0.split('.')
+    }
+
+    /// The prefix matching every subkey of this key, with its trailing dot.
+    pub fn subkeys(&self) -> String {
+        format!("{}.", self.0)
+    }
+
+    /// Whether a key is this key or one of its subkeys.
+    pub fn contains(&self, key: &str) -> bool {
+        key.strip_prefix(self.0.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+    }
+}
+
+fn validate_segment(segment: &str) -> Result<(), String> {
+    if segment.is_empty() {
+        Err("Setting key segments cannot be empty".to_string())
+    } else if let Some(ch) = segment
+        .chars()
+        .find(|ch| ch.is_whitespace() || ch.is_control())
+    {
+        Err(format!(
+            "Invalid character {ch:?} in setting key {segment:?}"
+        ))
+    } else if segment.split('.').any(str::is_empty) {
+        Err(format!("Setting key {segment:?} contains an empty segment"))
+    } else {
+        Ok(())
+    }
+}
+
+impl Display for SettingKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<SettingKey> for String {
+    fn from(key: SettingKey) -> Self {
+        key.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_key_segments() {
+        assert!(SettingKey::parse("server.listener").is_ok());
+        assert!(SettingKey::parse("").is_err());
+        assert!(SettingKey::parse("store..type").is_err());
+        assert!(SettingKey::parse(".store").is_err());
+        assert!(SettingKey::parse("store.").is_err());
+        assert!(SettingKey::parse("store.my id").is_err());
+
+        let store = SettingKey::parse("store").unwrap();
+        assert!(store.child("mail.example.org").is_ok());
+        assert!(store.child("").is_err());
+        assert!(store.child(".rocksdb").is_err());
+        assert!(store.child("rocksdb.").is_err());
+        assert!(store.child("rocks\ndb").is_err());
+    }
+
+    #[test]
+    fn join_key_segments() {
+        let listener = SettingKey::parse("server.listener")
+            .unwrap()
+            .child("smtp")
+            .unwrap();
+        assert_eq!(listener.as_str(), "server.listener.smtp");
+        assert_eq!(listener.subkeys(), "server.listener.smtp.");
+
+        let bind = listener.child("bind").unwrap();
+        assert_eq!(bind.index(3, 2).as_str(), "server.listener.smtp.bind.03");
+        assert_eq!(
+            bind.index(3, 1).leaf("if"),
+            "server.listener.smtp.bind.3.if"
+        );
+        assert!(bind.contains("server.listener.smtp.bind"));
+        assert!(bind.contains("server.listener.smtp.bind.0"));
+        assert!(!bind.contains("server.listener.smtp.binder"));
+        assert!(!bind.contains("server.listener.smtp"));
+    }
+
+    #[test]
+    fn split_key_round_trip() {
+        let key = SettingKey::parse("queue.strategy.route.0.then").unwrap();
+        let segments = key.segments().collect::<Vec<_>>();
+        assert_eq!(segments, vec!["queue", "strategy", "route", "0", "then"]);
+
+        let mut rebuilt = SettingKey::parse(segments[0]).unwrap();
+        for segment in &segments[1..] {
+            rebuilt = rebuilt.child(segment).unwrap();
+        }
+        assert_eq!(rebuilt, key);
+        assert_eq!(String::from(rebuilt), "queue.strategy.route.0.then");
+    }
+}
@@ -0,0 +1,76 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+/// What to tell the user when they try to leave an edit form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LeavePrompt {
+    /// The form has changes that pass validation and can be saved.
+    Unsaved,
+    /// The form has changes that cannot be saved until errors are fixed.
+    Invalid,
+}
+
+impl LeavePrompt {
+    /// Selects the prompt for a form, or `None` when it can be left freely.
+    pub fn select(is_dirty: bool, is_valid: bool) -> Option<Self> {
+        match (is_dirty, is_valid) {
+            (false, _) => None,
+            (true, true) => Some(LeavePrompt::Unsaved),
+            (true, false) => Some(LeavePrompt::Invalid),
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            LeavePrompt::Unsaved => "Unsaved changes",
+            LeavePrompt::Invalid => "Changes cannot be saved",
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            LeavePrompt::Unsaved => {
+                "You have unsaved changes. Do you want to save them before leaving?"
+            }
+            LeavePrompt::Invalid => concat!(
+                "You have unsaved changes that contain errors and cannot be saved as they are. ",
+                "Leaving now will discard them."
+            ),
+        }
+    }
+
+    pub fn button(&self) -> &'static str {
+        match self {
+            LeavePrompt::Unsaved => "Save and leave",
+            LeavePrompt::Invalid => "Discard and leave",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_prompt_from_form_state() {
+        assert_eq!(LeavePrompt::select(false, true), None);
+        assert_eq!(LeavePrompt::select(false, false), None);
+        assert_eq!(LeavePrompt::select(true, true), Some(LeavePrompt::Unsaved));
+        assert_eq!(LeavePrompt::select(true, false), Some(LeavePrompt::Invalid));
+    }
+
+    #[test]
+    fn invalid_prompt_does_not_offer_save() {
+        let unsaved = LeavePrompt::Unsaved;
+        assert!(unsaved.message().contains("save them"));
+        assert_eq!(unsaved.button(), "Save and leave");
+
+        let invalid = LeavePrompt::Invalid;
+        assert!(invalid.message().contains("cannot be saved"));
+        assert!(!invalid.button().contains("Save"));
+        assert_ne!(unsaved.title(), invalid.title());
+    }
+}
@@ -0,0 +1,400 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        badge::Badge,
+        messages::load_error::{LoadError, Reload},
+        skeleton::SkeletonTable,
+        Color,
+    },
+    core::{
+        form::{Expression, FormData, FormValue},
+        oauth::use_authorization,
+        url::UrlBuilder,
+    },
+    pages::config::snapshot::fetch_all_settings,
+};
+
+use super::{Schemas, Settings, SettingsValues};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum LintSeverity {
+    Critical,
+    Warning,
+    Info,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LintFinding {
+    pub rule: String,
+    pub severity: LintSeverity,
+    pub message: String,
+    pub suggestion: String,
+    pub route: String,
+}
+
+impl LintSeverity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LintSeverity::Critical => "Critical",
+            LintSeverity::Warning => "Warning",
+            LintSeverity::Info => "Info",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            LintSeverity::Critical => Color::Red,
+            LintSeverity::Warning => Color::Yellow,
+            LintSeverity::Info => Color::Gray,
+        }
+    }
+}
+
+/// Looks for risky or suboptimal combinations of settings. Settings that
+/// are not present are evaluated using their schema defaults. Findings are
+/// returned with the most severe first.
+pub fn lint_config(settings: &Settings, schemas: &Schemas) -> Vec<LintFinding> {
+    let mut findings = Vec::new();
+    let mut finding = |rule: &str, severity, message: String, suggestion: &str, route: String| {
+        findings.push(LintFinding {
+            rule: rule.to_string(),
+            severity,
+            message,
+            suggestion: suggestion.to_string(),
+            route,
+        })
+    };
+
+    // Relaying
+    if let Some(relay) = expression(settings, schemas, "smtp-in-rcpt", "session.rcpt.relay") {
+        if relay.else_ == "true"
+            || relay
+                .if_thens
+                .iter()
+                .any(|it| it.if_ == "true" && it.then_ == "true")
+        {
+            finding(
+                "relay-without-auth",
+                LintSeverity::Critical,
+                "Relaying is allowed for unauthenticated senders, making this server an open relay."
+                    .to_string(),
+                "Only allow relaying for authenticated senders, for example with the condition !is_empty(authenticated_as).",
+                "/settings/smtp-in-rcpt/edit".to_string(),
+            );
+        }
+    }
+
+    // Authentication
+    if let Some(require) = expression(settings, schemas, "smtp-in-auth", "session.auth.require") {
+        if !grants(&require, "true") {
+            finding(
+                "auth-disabled",
+                LintSeverity::Warning,
+                "SMTP authentication is not required on any listener.".to_string(),
+                "Require authentication on submission ports, for example when local_port != 25.",
+                "/settings/smtp-in-auth/edit".to_string(),
+            );
+        }
+    }
+
+    // TLS
+    if settings
+        .array_values("server.tls.disable-protocols")
+        .iter()
+        .any(|(_, protocol)| *protocol == "TLSv1.3")
+    {
+        finding(
+            "tls13-disabled",
+            LintSeverity::Warning,
+            "TLS 1.3 is disabled, clients are limited to TLS 1.2.".to_string(),
+            "Remove TLS 1.3 from the disabled protocols.",
+            "/settings/tls/edit".to_string(),
+        );
+    }
+    let mut insecure = settings
+        .iter()
+        .filter(|(key, value)| key.ends_with("tls.allow-invalid-certs") && value.as_str() == "true")
+        .map(|(key, _)| key.as_str())
+        .collect::<Vec<_>>();
+    insecure.sort_unstable();
+    for key in insecure {
+        finding(
+            "tls-invalid-certs",
+            LintSeverity::Warning,
+            format!("Certificate validation is disabled by {key}."),
+            "Install a valid certificate on the remote server and stop accepting invalid certificates.",
+            UrlBuilder::new("/settings/search")
+                .with_parameter("query", key)
+                .finish(),
+        );
+    }
+
+    // DKIM
+    if let Some(sign) = expression(settings, schemas, "dkim", "auth.dkim.sign") {
+        if sign.if_thens.iter().all(|it| it.then_ == "false") && sign.else_ == "false" {
+            finding(
+                "dkim-disabled",
+                LintSeverity::Warning,
+                "Outgoing messages are not DKIM signed.".to_string(),
+                "Create a DKIM signature for each local domain and enable signing.",
+                "/settings/dkim/edit".to_string(),
+            );
+        }
+
+        let mut missing = sign
+            .if_thens
+            .iter()
+            .map(|it| it.then_.as_str())
+            .chain([sign.else_.as_str()])
+            .filter(|value| !value.contains('+'))
+            .flat_map(quoted_strings)
+            .filter(|id| {
+                let prefix = format!("signature.{id}.");
+                !settings.keys().any(|key| key.starts_with(&prefix))
+            })
+            .collect::<Vec<_>>();
+        missing.sort_unstable();
+        missing.dedup();
+        for id in missing {
+            finding(
+                "dkim-missing-signature",
+                LintSeverity::Critical,
+                format!("DKIM signing uses signature {id:?}, which does not exist."),
+                "Create the missing signature or remove it from the signing expression.",
+                "/settings/signature".to_string(),
+            );
+        }
+    }
+
+    findings.sort_by_key(|finding| finding.severity);
+    findings
+}
+
+/// Returns the value of an expression setting, falling back to the field
+/// default when it is not set.
+fn expression(
+    settings: &Settings,
+    schemas: &Schemas,
+    schema_id: &str,
+    field_id: &str,
+) -> Option<Expression> {
+    let schema = schemas.schemas.get(schema_id)?.clone();
+    let values = settings
+        .array_values(field_id)
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect::<Settings>();
+    let data = FormData::from_settings(schema, Some(values));
+
+    match data.values.get(field_id)? {
+        FormValue::Expression(expr) => Some(expr.clone()),
+        FormValue::Value(value) => Some(Expression {
+            if_thens: vec![],
+            else_: value.clone(),
+        }),
+        FormValue::Array(_) => None,
+    }
+}
+
+fn grants(expr: &Expression, value: &str) -> bool {
+    expr.else_ == value || expr.if_thens.iter().any(|it| it.then_ == value)
+}
+
+fn quoted_strings(value: &str) -> Vec<&str> {
+    value.split('\'').skip(1).step_by(2).collect()
+}
+
+#[component]
+pub fn SettingsLint() -> impl IntoView {
+    let auth = use_authorization();
+    let schemas = expect_context::<Arc<Schemas>>();
+    let reload = Reload::new();
+
+    let findings = create_resource(
+        move || reload.track(),
+        move |_| {
+            let auth = auth.get_untracked();
+            let schemas = schemas.clone();
+
+            async move {
+                fetch_all_settings(&auth)
+                    .await
+                    .map(|settings| lint_config(&settings, &schemas))
+            }
+        },
+    );
+
+    view! {
+        <div class="max-w-4xl px-4 py-10 sm:px-6 lg:px-8 lg:py-14 mx-auto">
+            <div class="bg-white rounded-xl shadow p-4 sm:p-7 dark:bg-slate-900">
+                <div class="mb-8">
+                    <h2 class="text-xl font-bold text-gray-800 dark:text-gray-200">
+                        "Configuration lint"
+                    </h2>
+                    <p class="text-sm text-gray-600 dark:text-gray-400">
+                        "Risky or suboptimal combinations of settings, with suggested fixes."
+                    </p>
+                </div>
+                <Transition fallback=SkeletonTable>
+                    {move || match findings.get() {
+                        None => None,
+                        Some(Err(err)) => {
+                            Some(view! { <LoadError error=err reload=reload/> }.into_view())
+                        }
+                        Some(Ok(findings)) if findings.is_empty() => {
+                            Some(
+                                view! {
+                                    <p class="text-sm text-gray-600 dark:text-gray-400">
+                                        "No issues were found."
+                                    </p>
+                                }
+                                    .into_view(),
+                            )
+                        }
+                        Some(Ok(findings)) => {
+                            Some(
+                                view! {
+                                    <ul class="divide-y divide-gray-200 dark:divide-gray-700">
+                                        {findings
+                                            .into_iter()
+                                            .map(|finding| {
+                                                view! {
+                                                    <li class="flex flex-col gap-y-1 py-3">
+                                                        <div class="flex items-center gap-x-2">
+                                                            <Badge color=finding.severity.color()>
+                                                                {finding.severity.label()}
+                                                            </Badge>
+                                                            <span class="text-sm font-medium text-gray-800 dark:text-gray-200">
+                                                                {finding.message}
+                                                            </span>
+                                                        </div>
+                                                        <p class="text-sm text-gray-600 dark:text-gray-400">
+                                                            {finding.suggestion}
+                                                            " "
+                                                            <a
+                                                                href=finding.route
+                                                                class="text-blue-600 hover:underline dark:text-blue-500"
+                                                            >
+                                                                "Fix"
+                                                            </a>
+                                                        </p>
+                                                    </li>
+                                                }
+                                            })
+                                            .collect_view()}
+                                    </ul>
+                                }
+                                    .into_view(),
+                            )
+                        }
+                    }}
+
+                </Transition>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_schemas;
+
+    fn settings(values: &[(&str, &str)]) -> Settings {
+        values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn rules(findings: &[LintFinding]) -> Vec<&str> {
+        findings
+            .iter()
+            .map(|finding| finding.rule.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn lint_relay_without_auth() {
+        let schemas = build_schemas();
+        let findings = lint_config(
+            &settings(&[
+                ("session.rcpt.relay", "true"),
+                ("session.auth.require", "false"),
+            ]),
+            &schemas,
+        );
+        assert_eq!(
+            rules(&findings),
+            vec!["relay-without-auth", "auth-disabled"]
+        );
+        assert_eq!(findings[0].severity, LintSeverity::Critical);
+        assert_eq!(findings[0].route, "/settings/smtp-in-rcpt/edit");
+
+        // Relaying granted by an unconditional rule
+        let findings = lint_config(
+            &settings(&[
+                ("session.rcpt.relay.0.if", "true"),
+                ("session.rcpt.relay.0.then", "true"),
+                ("session.rcpt.relay.1.else", "false"),
+            ]),
+            &schemas,
+        );
+        assert_eq!(rules(&findings), vec!["relay-without-auth"]);
+    }
+
+    #[test]
+    fn lint_tls_and_dkim() {
+        let schemas = build_schemas();
+        let findings = lint_config(
+            &settings(&[
+                ("server.tls.disable-protocols.0", "TLSv1.3"),
+                ("remote.relay.tls.allow-invalid-certs", "true"),
+                ("auth.dkim.sign", "['rsa-example.org']"),
+            ]),
+            &schemas,
+        );
+        assert_eq!(
+            rules(&findings),
+            vec![
+                "dkim-missing-signature",
+                "tls13-disabled",
+                "tls-invalid-certs"
+            ]
+        );
+        assert!(findings[0].message.contains("rsa-example.org"));
+
+        let findings = lint_config(&settings(&[("auth.dkim.sign", "false")]), &schemas);
+        assert_eq!(rules(&findings), vec!["dkim-disabled"]);
+    }
+
+    #[test]
+    fn safe_config_passes() {
+        let schemas = build_schemas();
+        assert_eq!(lint_config(&Settings::default(), &schemas), vec![]);
+        assert_eq!(
+            lint_config(
+                &settings(&[
+                    ("session.rcpt.relay.0.if", "!is_empty(authenticated_as)"),
+                    ("session.rcpt.relay.0.then", "true"),
+                    ("session.rcpt.relay.1.else", "false"),
+                    ("auth.dkim.sign", "['rsa-example.org']"),
+                    ("signature.rsa-example.org.algorithm", "rsa-sha256"),
+                    ("remote.relay.tls.allow-invalid-certs", "false"),
+                ]),
+                &schemas,
+            ),
+            vec![]
+        );
+    }
+}
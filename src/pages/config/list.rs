@@ -11,9 +11,10 @@ use leptos_router::*;
 
 use crate::{
     components::{
-        icon::{IconAdd, IconRefresh, IconTrash},
+        icon::{IconAdd, IconDocumentMagnifyingGlass, IconRefresh, IconTrash, IconWrench},
         list::{
             header::ColumnList,
+            page_size::use_page_sizes,
             pagination::Pagination,
             row::SelectItem,
             toolbar::{SearchBox, ToolbarButton},
@@ -22,20 +23,34 @@ use crate::{
         },
         messages::{
             alert::{use_alerts, Alert},
+            load_error::{LoadError, Reload},
             modal::{use_modals, Modal},
         },
-        skeleton::Skeleton,
+        skeleton::SkeletonTable,
         Color,
     },
     core::{
+        form::ExternalSources,
         http::{self, HttpRequest},
+        ipnet::clean_ip_list,
         oauth::use_authorization,
         url::UrlBuilder,
     },
     pages::{
-        config::{ReloadSettings, SchemaType, Schemas, SettingsValues},
+        config::{
+            bookmarks::use_bookmark_refresh,
+            duplicates::DuplicateEntries,
+            edit::fetch_external_sources,
+            ip_rules::{fetch_ip_list, IpRuleConflicts},
+            references::{find_references, record_ids, reference_warning},
+            search::RecentPage,
+            snapshot::fetch_all_settings,
+            usage::{QueueUsageGauges, QUOTA_SCHEMA},
+            format_row, ReloadSettings, SchemaType, Schemas,
+        },
         maybe_plural, List,
     },
+    utils::telemetry::report_handled_error,
 };
 
 use super::{Schema, Settings, UpdateSettings};
@@ -63,6 +78,7 @@ pub fn SettingsList() -> impl IntoView {
         })
     });
     let selected = create_rw_signal::<ItemSelection>(ItemSelection::None);
+    let show_duplicates = create_rw_signal(false);
     let params = use_params_map();
     let current_schema = create_memo(move |_| {
         if let Some(schema) = params
@@ -71,34 +87,60 @@ pub fn SettingsList() -> impl IntoView {
             .and_then(|id| schemas.schemas.get(id.as_str()))
         {
             selected.set(ItemSelection::None);
+            show_duplicates.set(false);
             schema.clone()
         } else {
             use_navigate()("/404", Default::default());
             Arc::new(Schema::default())
         }
     });
+    create_effect(move |_| {
+        if let Some(page) = RecentPage::for_schema(&current_schema.get()) {
+            page.record();
+        }
+    });
 
     let auth = use_authorization();
     let alert = use_alerts();
     let modal = use_modals();
+    let bookmark_refresh = use_bookmark_refresh();
+    let access = create_memo(move |_| auth.get().permissions().settings_access());
     provide_context(selected);
 
+    let page_sizes = use_page_sizes();
+    let page_size = create_memo(move |_| {
+        let schema = current_schema.get();
+        page_sizes.with(|page_sizes| page_sizes.resolve(schema.id, schema.list.page_size))
+    });
+
+    let reload = Reload::new();
     let settings = create_resource(
-        move || (page.get(), filter.get()),
-        move |(page, filter)| {
+        move || (page.get(), page_size.get(), filter.get(), reload.track()),
+        move |(page, page_size, filter, _)| {
             let auth = auth.get_untracked();
             let schema = current_schema.get();
 
             async move {
-                HttpRequest::get("/api/settings/group")
+                let settings = HttpRequest::get("/api/settings/group")
                     .with_authorization(&auth)
                     .with_parameter("page", page.to_string())
-                    .with_parameter("limit", schema.list.page_size.to_string())
+                    .with_parameter("limit", page_size.to_string())
                     .with_parameter("prefix", schema.unwrap_prefix())
                     .with_parameter("suffix", schema.try_unwrap_suffix().unwrap_or_default())
                     .with_optional_parameter("filter", filter)
                     .send::<List<Settings>>()
-                    .await
+                    .await?;
+
+                // Labels are optional, the stored ids are shown when they cannot be loaded
+                let sources = if !settings.items.is_empty() {
+                    fetch_external_sources(&auth, schema.list_sources())
+                        .await
+                        .unwrap_or_default()
+                } else {
+                    ExternalSources::default()
+                };
+
+                Ok((settings, sources))
             }
         },
     );
@@ -117,6 +159,7 @@ pub fn SettingsList() -> impl IntoView {
             .await
             {
                 Ok(result) => {
+                    bookmark_refresh.trigger();
                     alert.set(Alert::from(result));
                 }
                 Err(http::Error::Unauthorized) => {
@@ -193,6 +236,68 @@ pub fn SettingsList() -> impl IntoView {
                     )));
                 }
                 Err(err) => {
+                    report_handled_error("settings.delete", &err, &[("schema", schema.id)]);
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    let cleanup_action = create_action(move |merge_adjacent: &bool| {
+        let merge_adjacent = *merge_adjacent;
+        let auth = auth.get();
+        let schema = current_schema.get();
+
+        async move {
+            let prefix = schema.unwrap_prefix();
+            let cleanup = match fetch_ip_list(&auth, prefix).await {
+                Ok(entries) => clean_ip_list(entries.iter().map(|s| s.as_str()), merge_adjacent),
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                    return;
+                }
+            };
+            if !cleanup.has_changes() {
+                alert.set(Alert::success("The list is already clean."));
+                return;
+            }
+
+            let updates = vec![
+                UpdateSettings::Delete {
+                    keys: cleanup
+                        .removed
+                        .iter()
+                        .map(|entry| format!("{prefix}.{entry}"))
+                        .collect(),
+                },
+                UpdateSettings::Insert {
+                    prefix: None,
+                    values: cleanup
+                        .added
+                        .iter()
+                        .map(|entry| (format!("{prefix}.{entry}"), String::new()))
+                        .collect(),
+                    assert_empty: false,
+                },
+            ];
+
+            match HttpRequest::post("/api/settings")
+                .with_authorization(&auth)
+                .with_body(updates)
+                .unwrap()
+                .send::<serde_json::Value>()
+                .await
+            {
+                Ok(_) => {
+                    settings.refetch();
+                    alert.set(Alert::success(format!(
+                        "Cleaned up list: removed {}, added {}.",
+                        maybe_plural(cleanup.removed.len(), "entry", "entries"),
+                        maybe_plural(cleanup.added.len(), "entry", "entries"),
+                    )));
+                }
+                Err(err) => {
+                    report_handled_error("settings.cleanup", &err, &[("schema", schema.id)]);
                     alert.set(Alert::from(err));
                 }
             }
@@ -201,6 +306,27 @@ pub fn SettingsList() -> impl IntoView {
 
     view! {
         <ListSection>
+            <Show when=move || matches!(current_schema.get().id, "allowed-ip" | "blocked-ip")>
+                <IpRuleConflicts reload=Signal::derive(move || {
+                    settings.track();
+                    reload.track()
+                })/>
+            </Show>
+            <Show when=move || current_schema.get().id == QUOTA_SCHEMA>
+                <QueueUsageGauges schema_id=QUOTA_SCHEMA/>
+            </Show>
+            <Show when=move || show_duplicates.get()>
+                {move || {
+                    view! {
+                        <DuplicateEntries
+                            prefix=current_schema.get().unwrap_prefix().to_string()
+                            can_edit=Signal::derive(move || access.get().can_edit)
+                            on_change=move |_| settings.refetch()
+                        />
+                    }
+                }}
+
+            </Show>
             <ListTable
                 title=Signal::derive(move || { current_schema.get().list.title.to_string() })
                 subtitle=Signal::derive(move || { current_schema.get().list.subtitle.to_string() })
@@ -219,46 +345,139 @@ pub fn SettingsList() -> impl IntoView {
                         }
                     />
 
-                    <ToolbarButton
-                        text=Signal::derive(move || {
-                            let ns = selected.get().total_selected(total_results.get());
-                            if ns > 0 { format!("Delete ({ns})") } else { "Delete".to_string() }
-                        })
-
-                        color=Color::Red
-                        on_click=Callback::new(move |_| {
-                            let to_delete = selected.get().total_selected(total_results.get());
-                            if to_delete > 0 {
-                                let schema = current_schema.get();
-                                let text = maybe_plural(
-                                    to_delete,
-                                    schema.name_singular,
-                                    schema.name_plural,
-                                );
+                    <Show when=move || access.get().can_delete>
+                        <ToolbarButton
+                            text=Signal::derive(move || {
+                                let ns = selected.get().total_selected(total_results.get());
+                                if ns > 0 { format!("Delete ({ns})") } else { "Delete".to_string() }
+                            })
+
+                            color=Color::Red
+                            on_click=Callback::new(move |_| {
+                                let to_delete = selected.get().total_selected(total_results.get());
+                                if to_delete > 0 {
+                                    let schema = current_schema.get();
+                                    let text = maybe_plural(
+                                        to_delete,
+                                        schema.name_singular,
+                                        schema.name_plural,
+                                    );
+                                    let auth = auth.get();
+                                    let selection = selected.get();
+                                    spawn_local(async move {
+                                        // Warn about settings left pointing at the deleted records
+                                        let warning = match fetch_all_settings(&auth).await {
+                                            Ok(all_settings) => {
+                                                let ids = match &selection {
+                                                    ItemSelection::All => record_ids(&all_settings, &schema),
+                                                    ItemSelection::Some(items) => {
+                                                        items.iter().map(|item| item.as_str()).collect()
+                                                    }
+                                                    ItemSelection::None => Vec::new(),
+                                                };
+                                                reference_warning(&find_references(&all_settings, &schema, &ids))
+                                            }
+                                            Err(err) => {
+                                                log::warn!("Failed to check references before delete: {err:?}");
+                                                None
+                                            }
+                                        };
+                                        let mut message = format!(
+                                            "Are you sure you want to delete {text}? This action cannot be undone.",
+                                        );
+                                        if let Some(warning) = warning {
+                                            message = format!("{warning} {message}");
+                                        }
+                                        modal
+                                            .set(
+                                                Modal::with_title("Confirm deletion")
+                                                    .with_message(message)
+                                                    .with_button(format!("Delete {text}"))
+                                                    .with_dangerous_callback(move || {
+                                                        delete_action
+                                                            .dispatch(
+                                                                Arc::new(
+                                                                    selected.try_update(std::mem::take).unwrap_or_default(),
+                                                                ),
+                                                            );
+                                                    }),
+                                            )
+                                    });
+                                }
+                            })
+                        >
+
+                            <IconTrash/>
+                        </ToolbarButton>
+                    </Show>
+
+                    <Show when=move || {
+                        access.get().can_edit
+                            && matches!(current_schema.get().id, "allowed-ip" | "blocked-ip")
+                    }>
+                        <ToolbarButton
+                            text="Clean up list"
+                            color=Color::Gray
+                            on_click=Callback::new(move |_| {
                                 modal
                                     .set(
-                                        Modal::with_title("Confirm deletion")
+                                        Modal::with_title("Clean up list")
                                             .with_message(
-                                                format!(
-                                                    "Are you sure you want to delete {text}? This action cannot be undone.",
-                                                ),
+                                                "Entries will be rewritten in canonical notation, host bits will be removed from networks and duplicate or redundant entries will be deleted.",
                                             )
-                                            .with_button(format!("Delete {text}"))
-                                            .with_dangerous_callback(move || {
-                                                delete_action
-                                                    .dispatch(
-                                                        Arc::new(
-                                                            selected.try_update(std::mem::take).unwrap_or_default(),
-                                                        ),
-                                                    );
+                                            .with_button("Clean up")
+                                            .with_callback(move || {
+                                                cleanup_action.dispatch(false);
                                             }),
                                     )
-                            }
-                        })
-                    >
+                            })
+                        >
+
+                            <IconWrench/>
+                        </ToolbarButton>
+                        <ToolbarButton
+                            text="Merge ranges"
+                            color=Color::Gray
+                            on_click=Callback::new(move |_| {
+                                modal
+                                    .set(
+                                        Modal::with_title("Merge ranges")
+                                            .with_message(
+                                                "In addition to cleaning up the list, adjacent networks will be merged into larger networks covering the same addresses.",
+                                            )
+                                            .with_button("Merge ranges")
+                                            .with_callback(move || {
+                                                cleanup_action.dispatch(true);
+                                            }),
+                                    )
+                            })
+                        >
+
+                            <IconWrench/>
+                        </ToolbarButton>
+                    </Show>
+
+                    <Show when=move || {
+                        matches!(current_schema.get().typ, SchemaType::Entry { .. })
+                    }>
+                        <ToolbarButton
+                            text=Signal::derive(move || {
+                                if show_duplicates.get() {
+                                    "Hide duplicates".to_string()
+                                } else {
+                                    "Find duplicates".to_string()
+                                }
+                            })
 
-                        <IconTrash/>
-                    </ToolbarButton>
+                            color=Color::Gray
+                            on_click=Callback::new(move |_| {
+                                show_duplicates.update(|show| *show = !*show);
+                            })
+                        >
+
+                            <IconDocumentMagnifyingGlass/>
+                        </ToolbarButton>
+                    </Show>
 
                     <ToolbarButton
                         text="Reload config"
@@ -272,39 +491,37 @@ pub fn SettingsList() -> impl IntoView {
                         <IconRefresh/>
                     </ToolbarButton>
 
-                    <ToolbarButton
-                        text=Signal::derive(move || {
-                            format!("Create {}", current_schema.get().name_singular)
-                        })
-
-                        color=Color::Blue
-                        on_click=move |_| {
-                            use_navigate()(
-                                &format!("/settings/{}/edit", current_schema.get().id),
-                                Default::default(),
-                            );
-                        }
-                    >
+                    <Show when=move || access.get().can_create>
+                        <ToolbarButton
+                            text=Signal::derive(move || {
+                                format!("Create {}", current_schema.get().name_singular)
+                            })
+
+                            color=Color::Blue
+                            on_click=move |_| {
+                                use_navigate()(
+                                    &format!("/settings/{}/edit", current_schema.get().id),
+                                    Default::default(),
+                                );
+                            }
+                        >
 
-                        <IconAdd size=16 attr:class="flex-shrink-0 size-3"/>
-                    </ToolbarButton>
+                            <IconAdd size=16 attr:class="flex-shrink-0 size-3"/>
+                        </ToolbarButton>
+                    </Show>
 
                 </Toolbar>
 
-                <Transition fallback=Skeleton>
+                <Transition fallback=SkeletonTable>
                     {move || match settings.get() {
                         None => None,
-                        Some(Err(http::Error::Unauthorized)) => {
-                            use_navigate()("/login", Default::default());
-                            Some(view! { <div></div> }.into_view())
-                        }
                         Some(Err(err)) => {
                             total_results.set(Some(0));
-                            alert.set(Alert::from(err));
-                            Some(view! { <Skeleton/> }.into_view())
+                            Some(view! { <LoadError error=err reload=reload/> }.into_view())
                         }
-                        Some(Ok(settings)) if !settings.items.is_empty() => {
+                        Some(Ok((settings, sources))) if !settings.items.is_empty() => {
                             total_results.set(Some(settings.total as u32));
+                            let sources = Arc::new(sources);
                             let schema = current_schema.get();
                             let mut headers = schema
                                 .list
@@ -330,7 +547,12 @@ pub fn SettingsList() -> impl IntoView {
 
                                             let:settings
                                         >
-                                            <SettingsItem settings schema=schema.clone()/>
+                                            <SettingsItem
+                                                settings
+                                                sources=sources.clone()
+                                                schema=schema.clone()
+                                                read_only=access.get_untracked().is_read_only()
+                                            />
                                         </For>
 
                                     </ColumnList>
@@ -372,7 +594,7 @@ pub fn SettingsList() -> impl IntoView {
                     <Pagination
                         current_page=page
                         total_results=total_results.read_only()
-                        page_size=Signal::derive(move || current_schema.get().list.page_size)
+                        page_size=page_size
                         on_page_change=move |page: u32| {
                             use_navigate()(
                                 &UrlBuilder::new("/settings")
@@ -383,6 +605,18 @@ pub fn SettingsList() -> impl IntoView {
                                 Default::default(),
                             );
                         }
+
+                        on_page_size_change=move |size: u32| {
+                            let schema_id = current_schema.get().id;
+                            page_sizes.update(|page_sizes| page_sizes.set_for(schema_id, size));
+                            use_navigate()(
+                                &UrlBuilder::new("/settings")
+                                    .with_subpath(schema_id)
+                                    .with_optional_parameter("filter", filter.get())
+                                    .finish(),
+                                Default::default(),
+                            );
+                        }
                     />
 
                 </Footer>
@@ -392,15 +626,15 @@ pub fn SettingsList() -> impl IntoView {
 }
 
 #[component]
-fn SettingsItem(settings: Settings, schema: Arc<Schema>) -> impl IntoView {
-    let columns = schema
-        .list
-        .fields
-        .iter()
-        .map(|field| {
-            let value = settings.format(field);
-            view! { <ListTextItem>{value}</ListTextItem> }
-        })
+fn SettingsItem(
+    settings: Settings,
+    sources: Arc<ExternalSources>,
+    schema: Arc<Schema>,
+    read_only: bool,
+) -> impl IntoView {
+    let columns = format_row(&schema.list.fields, &settings, &sources)
+        .into_iter()
+        .map(|value| view! { <ListTextItem>{value}</ListTextItem> })
         .collect_view();
     let setting_id = settings
         .get("_id")
@@ -414,7 +648,7 @@ fn SettingsItem(settings: Settings, schema: Arc<Schema>) -> impl IntoView {
                     class="inline-flex items-center gap-x-1 text-sm text-blue-600 decoration-2 hover:underline font-medium dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600"
                     href=edit_url
                 >
-                    Edit
+                    {if read_only { "View" } else { "Edit" }}
                 </a>
             </ListItem>
         })
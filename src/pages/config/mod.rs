@@ -4,57 +4,91 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod autocomplete;
+pub mod bookmarks;
+pub mod conflicts;
+pub mod duplicates;
 pub mod edit;
+pub mod endpoint_test;
+pub mod geoip;
+pub mod history;
+pub mod impact;
+pub mod ip_rules;
+pub mod key;
+pub mod leave;
+pub mod lint;
 pub mod list;
+pub mod mta_sts;
+pub mod proposal;
+pub mod ranges;
+pub mod raw;
+pub mod references;
+pub mod relay_test;
+pub mod report;
+pub mod restart;
+#[cfg(test)]
+pub mod round_trip;
+pub mod scheduled;
 pub mod schema;
 pub mod search;
+pub mod snapshot;
+pub mod staging;
+pub mod usage;
+pub mod validate;
 
-use std::{collections::BTreeMap, str::FromStr};
+use std::{collections::BTreeMap, str::FromStr, sync::Arc};
 
 use crate::{
     components::{
         form::input::{Duration, Rate},
         icon::{
-            IconAdjustmentsHorizontal, IconBeaker, IconChartBarSquare, IconCircleStack,
-            IconCodeBracket, IconHandRaised, IconInbox, IconInboxArrowDown, IconInboxStack,
+            IconAdjustmentsHorizontal, IconCheckCircle, IconCircleStack, IconCodeBracket,
+            IconDocumentText, IconHandRaised, IconInbox, IconInboxArrowDown, IconInboxStack,
             IconKey, IconServer, IconServerStack, IconShieldCheck, IconSignal,
         },
-        layout::{LayoutBuilder, MenuItem, LayoutConfig},
+        layout::{config::SecurityDebugPanel, LayoutBuilder, LayoutConfig, MenuItem},
     },
     core::{
-        form::{FormData, FormValue},
+        form::{ExternalSources, FormData, FormValue},
         schema::*,
+        Permissions,
+    },
+    utils::{
+        audit::SettingChange,
+        locale::{format_bytes, format_signed_number},
     },
 };
 use ahash::AHashMap;
-use humansize::{format_size, DECIMAL};
-use leptos::view;
-use serde::{Deserialize, Serialize};
+use key::SettingKey;
 use leptos::*;
-use leptos_meta::*;
 use leptos_router::*;
-use crate::components::icon::{
-    IconCircleStack,
-    IconCog6Tooth,
-    IconCommandLine,
-    IconCube,
-    IconDocumentText,
-    IconFolder,
-    IconGlobeAlt,
-    IconLockClosed,
-    IconMail,
-    IconQueueList,
-    IconServer,
-    IconShieldCheck,
-    IconUser,
-    IconUsers,
-    IconWrenchScrewdriver,
-};
-use crate::types::Permissions;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 pub type Settings = AHashMap<String, String>;
 
-#[derive(Debug, Serialize, Deserialize)]
+pub const REDACTED: &str = "***";
+
+/// Returns a stable SHA-256 hex digest of a settings map, suitable for drift
+/// detection and etags. Keys are hashed in sorted order, line endings in
+/// values are normalized and every key and value is length-prefixed so that
+/// no two distinct maps share the same canonical form.
+pub fn settings_hash(settings: &Settings) -> String {
+    let mut entries = settings.iter().collect::<Vec<_>>();
+    entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut hasher = Sha256::new();
+    for (key, value) in entries {
+        let value = value.replace("\r\n", "\n");
+        for part in [key.as_bytes(), value.as_bytes()] {
+            hasher.update((part.len() as u64).to_be_bytes());
+            hasher.update(part);
+        }
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 #[serde(rename_all = "camelCase")]
 pub enum UpdateSettings {
@@ -99,48 +133,141 @@ pub enum ConfigError {
     Macro { error: String },
 }
 
+/// A value that cannot be saved because it would produce a malformed
+/// setting key, reported against the field it was entered in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyError {
+    pub field: String,
+    pub error: String,
+}
+
 impl FormData {
-    pub fn build_update(&self) -> Vec<UpdateSettings> {
+    pub fn build_update(&self) -> Result<Vec<UpdateSettings>, KeyError> {
+        self.build_update_inner(None)
+    }
+
+    /// Builds the update omitting any field gated behind a permission
+    /// the user does not hold, so their stored values are left untouched.
+    pub fn build_update_for(
+        &self,
+        permissions: &Permissions,
+    ) -> Result<Vec<UpdateSettings>, KeyError> {
+        self.build_update_inner(Some(permissions))
+    }
+
+    /// Builds the update for saving, showing a malformed key as an error on
+    /// its field instead.
+    pub fn checked_update_for(&mut self, permissions: &Permissions) -> Option<Vec<UpdateSettings>> {
+        match self.build_update_for(permissions) {
+            Ok(updates) => Some(updates),
+            Err(err) => {
+                self.new_error(err.field, err.error);
+                None
+            }
+        }
+    }
+
+    fn build_update_inner(
+        &self,
+        permissions: Option<&Permissions>,
+    ) -> Result<Vec<UpdateSettings>, KeyError> {
         let mut updates = Vec::new();
+        let mut partial_values = Vec::new();
         let mut insert_prefix = None;
         let mut assert_empty = false;
+        let can_edit = |key: &str| {
+            permissions.is_none_or(|permissions| {
+                self.schema
+                    .fields
+                    .get(key)
+                    .is_none_or(|field| field.can_edit(permissions))
+            })
+        };
 
         match &self.schema.typ {
             SchemaType::Record { prefix, .. } => {
+                let record = self.record_key(prefix)?;
                 if self.is_update {
-                    updates.push(UpdateSettings::Clear {
-                        prefix: format!("{prefix}.{}.", self.value_as_str("_id").unwrap()),
-                        filter: None,
-                    });
+                    // Underscore keys are not swept by clearing the whole record
+                    let has_reserved_keys = !self.schema.allowed_reserved_keys.is_empty()
+                        || self.schema.fields.keys().any(|key| {
+                            key.starts_with(RESERVED_KEY_PREFIX) && !INTERNAL_KEYS.contains(key)
+                        });
+                    if !has_reserved_keys && self.schema.fields.keys().all(|key| can_edit(key)) {
+                        updates.push(UpdateSettings::Clear {
+                            prefix: record.subkeys(),
+                            filter: None,
+                        });
+                    } else {
+                        // Clear editable fields only, preserving restricted ones
+                        let mut delete_keys = Vec::new();
+                        for field in self.schema.fields.values() {
+                            if self.schema.is_reserved_key(field.id)
+                                || !can_edit(field.id)
+                                || (field.id.starts_with(RESERVED_KEY_PREFIX)
+                                    && !self.value_is_empty(field.id))
+                            {
+                                continue;
+                            }
+                            let Ok(key) = record.child(field.id) else {
+                                continue;
+                            };
+                            if field.is_multivalue() {
+                                updates.push(UpdateSettings::Clear {
+                                    prefix: key.subkeys(),
+                                    filter: None,
+                                });
+                            }
+                            delete_keys.push(key.into());
+                        }
+
+                        if !delete_keys.is_empty() {
+                            updates.push(UpdateSettings::Delete { keys: delete_keys });
+                        }
+                    }
                 } else {
                     assert_empty = true;
                 }
 
-                insert_prefix = format!("{prefix}.{}", self.value_as_str("_id").unwrap()).into();
+                insert_prefix = Some(record.into());
             }
             SchemaType::Entry { prefix } => {
+                if !can_edit("_value") {
+                    return Ok(updates);
+                }
+                let entry = self.record_key(prefix)?;
                 updates.push(UpdateSettings::Insert {
                     prefix: None,
                     assert_empty: !self.is_update,
                     values: vec![(
-                        format!("{prefix}.{}", self.value_as_str("_id").unwrap()),
-                        self.value_as_str("_value").unwrap_or_default().to_string(),
+                        entry.into(),
+                        self.normalized_value("_value").unwrap_or_default(),
                     )],
                 });
-                return updates;
+                return Ok(updates);
             }
             SchemaType::List => {
                 if self.is_update {
                     let mut delete_keys = Vec::new();
                     for field in self.schema.fields.values() {
-                        if field.is_multivalue() {
+                        if !can_edit(field.id) {
+                            continue;
+                        }
+                        let Ok(key) = SettingKey::parse(field.id) else {
+                            continue;
+                        };
+                        if self.is_partial_array(field) {
+                            let (removed, added) = self.array_changes(&key);
+                            delete_keys.extend(removed);
+                            partial_values.extend(added);
+                        } else if field.is_multivalue() {
                             updates.push(UpdateSettings::Clear {
-                                prefix: format!("{}.", field.id),
+                                prefix: key.subkeys(),
                                 filter: None,
                             });
-                            delete_keys.push(field.id.to_string());
+                            delete_keys.push(key.into());
                         } else if self.value_is_empty(field.id) {
-                            delete_keys.push(field.id.to_string());
+                            delete_keys.push(key.into());
                         }
                     }
 
@@ -153,54 +280,38 @@ impl FormData {
 
         let mut key_values = Vec::new();
         for (key, value) in &self.values {
-            if key.starts_with('_') {
+            if self.schema.is_reserved_key(key) {
+                if !INTERNAL_KEYS.contains(&key.as_str()) {
+                    log::debug!(
+                        "Not saving {key:?} in schema {:?}: keys starting with {RESERVED_KEY_PREFIX:?} are reserved",
+                        self.schema.id
+                    );
+                }
+                continue;
+            } else if !can_edit(key)
+                || self
+                    .schema
+                    .fields
+                    .get(key.as_str())
+                    .is_some_and(|field| self.is_partial_array(field))
+            {
                 continue;
             }
 
-            match value {
-                FormValue::Value(value) if !value.is_empty() => {
-                    key_values.push((key.to_string(), value.to_string()));
-                }
-                FormValue::Array(values) if !values.is_empty() => {
-                    let total_values = values.len();
-                    if total_values > 1 {
-                        let pad_len = (total_values - 1).to_string().len();
-
-                        for (idx, value) in values.iter().enumerate() {
-                            key_values.push((format!("{key}.{idx:0>pad_len$}"), value.to_string()));
-                        }
-                    } else {
-                        key_values.push((key.to_string(), values.first().unwrap().to_string()));
-                    }
-                }
-                FormValue::Expression(expr) if !expr.is_empty() => {
-                    if !expr.if_thens.is_empty() {
-                        let total_values = expr.if_thens.len();
-                        let pad_len = total_values.to_string().len();
-
-                        for (idx, if_then) in expr.if_thens.iter().enumerate() {
-                            key_values.push((
-                                format!("{key}.{idx:0>pad_len$}.if"),
-                                if_then.if_.to_string(),
-                            ));
-                            key_values.push((
-                                format!("{key}.{idx:0>pad_len$}.then"),
-                                if_then.then_.to_string(),
-                            ));
-                        }
-
-                        key_values.push((
-                            format!("{key}.{total_values:0>pad_len$}.else"),
-                            expr.else_.to_string(),
-                        ));
-                    } else {
-                        key_values.push((key.to_string(), expr.else_.to_string()));
-                    }
+            let setting_key = SettingKey::parse(key).map_err(|error| KeyError {
+                field: key.to_string(),
+                error,
+            })?;
+            match (value, self.normalized_value(key)) {
+                (FormValue::Value(_), Some(value)) => {
+                    flatten_value(&setting_key, &FormValue::Value(value), &mut key_values);
                 }
-                _ => (),
+                _ => flatten_value(&setting_key, value, &mut key_values),
             }
         }
 
+        key_values.extend(partial_values);
+
         if !key_values.is_empty() {
             updates.push(UpdateSettings::Insert {
                 prefix: insert_prefix,
@@ -209,28 +320,183 @@ impl FormData {
             });
         }
 
-        updates
+        Ok(updates)
+    }
+
+    /// Whether an array field is saved as changes to its stored elements.
+    /// Arrays stored as a single value under the bare key are rewritten in
+    /// full, as appending `key.<index>` would mix both layouts.
+    fn is_partial_array(&self, field: &Field) -> bool {
+        self.is_update
+            && self.schema.partial_array_updates
+            && matches!(self.schema.typ, SchemaType::List)
+            && field.is_multivalue()
+            && !matches!(field.typ_, Type::Expression)
+            && !self
+                .stored_arrays
+                .get(field.id)
+                .is_some_and(|stored| stored.iter().any(|(key, _)| key == field.id))
+    }
+
+    /// Compares an array with the elements it was loaded with, returning the
+    /// stored keys of the removed elements and the keys and values of the
+    /// appended ones. New elements are keyed after the highest stored index,
+    /// so that stored elements are never rewritten.
+    fn array_changes(&self, key: &SettingKey) -> (Vec<String>, Vec<(String, String)>) {
+        let stored = self
+            .stored_arrays
+            .get(key.as_str())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let mut added = self
+            .array_value(key.as_str())
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>();
+        let mut removed = Vec::new();
+        for (stored_key, value) in stored {
+            match added.iter().position(|added| added == value) {
+                Some(pos) => {
+                    added.remove(pos);
+                }
+                None => removed.push(stored_key.clone()),
+            }
+        }
+
+        let indexes = stored
+            .iter()
+            .filter_map(|(stored_key, _)| {
+                let index = stored_key.strip_prefix(&key.subkeys())?;
+                index
+                    .parse::<usize>()
+                    .ok()
+                    .map(|value| (value, index.len()))
+            })
+            .collect::<Vec<_>>();
+        let next = indexes
+            .iter()
+            .map(|(index, _)| index + 1)
+            .max()
+            .unwrap_or(stored.len());
+        let pad_len = indexes.iter().map(|(_, len)| *len).max().unwrap_or(1);
+        let added = added
+            .into_iter()
+            .enumerate()
+            .map(|(offset, value)| (key.index(next + offset, pad_len).into(), value.to_string()))
+            .collect();
+
+        (removed, added)
+    }
+
+    /// Returns the key a record or entry is stored under, failing when its
+    /// id would produce a malformed key.
+    fn record_key(&self, prefix: &str) -> Result<SettingKey, KeyError> {
+        let id = self.value_as_str("_id").unwrap_or_default();
+        SettingKey::parse(prefix)
+            .and_then(|prefix| prefix.child(id))
+            .map_err(|error| KeyError {
+                field: "_id".to_string(),
+                error,
+            })
+    }
+
+    /// Returns the scalar value of a field as it should be saved, normalized
+    /// unless the field keeps its value as typed.
+    fn normalized_value(&self, id: &str) -> Option<String> {
+        let value = self.value_as_str(id)?;
+        if self
+            .schema
+            .fields
+            .get(id)
+            .is_none_or(|field| field.normalizes_value())
+        {
+            Some(normalize_value(value))
+        } else {
+            Some(value.to_string())
+        }
+    }
+}
+
+/// Trims surrounding whitespace and replaces typographic quotes, which are
+/// often introduced when values are pasted from documents, with straight ones.
+pub fn normalize_value(value: &str) -> String {
+    value
+        .trim()
+        .chars()
+        .map(|ch| match ch {
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' => '\'',
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' => '"',
+            ch => ch,
+        })
+        .collect()
+}
+
+/// Flattens a form value into the setting keys it is stored under.
+pub(crate) fn flatten_value(
+    key: &SettingKey,
+    value: &FormValue,
+    key_values: &mut Vec<(String, String)>,
+) {
+    match value {
+        FormValue::Value(value) if !value.is_empty() => {
+            key_values.push((key.to_string(), value.to_string()));
+        }
+        FormValue::Array(values) if !values.is_empty() => {
+            let total_values = values.len();
+            if total_values > 1 {
+                let pad_len = (total_values - 1).to_string().len();
+
+                for (idx, value) in values.iter().enumerate() {
+                    key_values.push((key.index(idx, pad_len).into(), value.to_string()));
+                }
+            } else {
+                key_values.push((key.to_string(), values.first().unwrap().to_string()));
+            }
+        }
+        FormValue::Expression(expr) if !expr.is_empty() => {
+            if !expr.if_thens.is_empty() {
+                let total_values = expr.if_thens.len();
+                let pad_len = total_values.to_string().len();
+
+                for (idx, if_then) in expr.if_thens.iter().enumerate() {
+                    let item = key.index(idx, pad_len);
+                    key_values.push((item.leaf("if"), if_then.if_.to_string()));
+                    key_values.push((item.leaf("then"), if_then.then_.to_string()));
+                }
+
+                key_values.push((
+                    key.index(total_values, pad_len).leaf("else"),
+                    expr.else_.to_string(),
+                ));
+            } else {
+                key_values.push((key.to_string(), expr.else_.to_string()));
+            }
+        }
+        _ => (),
     }
 }
 
 pub trait SettingsValues {
     fn array_values(&self, prefix: &str) -> Vec<(&str, &str)>;
     fn format(&self, field: &Field) -> String;
+    fn format_with_sources(&self, field: &Field, sources: &ExternalSources) -> String;
 }
 
+/// Appended to stored values that cannot be parsed as their field's type, so
+/// that misconfigurations are shown rather than hidden.
+pub const INVALID_MARKER: &str = "(invalid)";
+
 impl SettingsValues for Settings {
     fn array_values(&self, key: &str) -> Vec<(&str, &str)> {
-        let full_prefix = key;
-        let prefix = format!("{key}.");
+        let Ok(parent) = SettingKey::parse(key) else {
+            return Vec::new();
+        };
 
         let mut results = self
             .iter()
-            .filter_map(move |(key, value)| {
-                if key.starts_with(&prefix) || key == full_prefix {
-                    (key.as_str(), value.as_str()).into()
-                } else {
-                    None
-                }
+            .filter_map(|(key, value)| {
+                parent
+                    .contains(key)
+                    .then_some((key.as_str(), value.as_str()))
             })
             .collect::<Vec<_>>();
 
@@ -240,7 +506,48 @@ impl SettingsValues for Settings {
     }
 
     fn format(&self, field: &Field) -> String {
+        self.format_with_sources(field, &ExternalSources::default())
+    }
+
+    /// Formats a value, resolving the labels of selects backed by records
+    /// from the loaded sources, keyed as `<schema>_<field>`. Values missing
+    /// from the sources are shown as stored.
+    fn format_with_sources(&self, field: &Field, sources: &ExternalSources) -> String {
+        if let Some(placeholder) = field.empty_placeholder {
+            let is_missing = if field.is_multivalue() {
+                self.array_values(field.id).is_empty()
+            } else {
+                !self.contains_key(field.id)
+            };
+            if is_missing {
+                return placeholder.to_string();
+            }
+        }
+
         match &field.typ_ {
+            Type::Select {
+                source:
+                    Source::Dynamic {
+                        schema,
+                        field: source_field,
+                        ..
+                    },
+                typ: SelectType::Single,
+            } => {
+                let value = self
+                    .get(field.id)
+                    .map(|s| s.as_str())
+                    .unwrap_or_default();
+                sources
+                    .get(&format!("{}_{}", schema.id, source_field.id))
+                    .and_then(|items| {
+                        items
+                            .iter()
+                            .find(|(id, label)| id == value && !label.is_empty())
+                    })
+                    .map(|(_, label)| label.clone())
+                    .unwrap_or_else(|| value.to_string())
+            }
             Type::Select {
                 source: Source::Static(items),
                 typ: SelectType::Single,
@@ -268,28 +575,207 @@ impl SettingsValues for Settings {
                     "No".to_string()
                 }
             }
-            Type::Duration => self
-                .get(field.id)
-                .and_then(|s| Duration::from_str(s).ok())
-                .and_then(|d| d.format())
-                .unwrap_or_default(),
-            Type::Rate => self
-                .get(field.id)
-                .and_then(|s| Rate::from_str(s).ok())
-                .and_then(|d| d.format())
-                .unwrap_or_default(),
-            Type::Size => self
-                .get(field.id)
-                .and_then(|s| s.parse::<u64>().ok())
-                .map(|s| format_size(s, DECIMAL))
-                .unwrap_or_default(),
+            Type::Duration => {
+                format_or_invalid(self, field, |s| Duration::from_str(s).ok()?.format())
+            }
+            Type::Rate => format_or_invalid(self, field, |s| Rate::from_str(s).ok()?.format()),
+            Type::Size => format_or_invalid(self, field, |s| {
+                s.parse::<u64>()
+                    .ok()
+                    .map(|size| format_bytes(size, field.size_precision))
+            }),
+            Type::Number { .. } => format_or_invalid(self, field, |s| {
+                format_signed_number(s).map(|number| with_unit_suffix(field, s, number))
+            }),
             _ => self
                 .get(field.id)
-                .map(|s| s.as_str())
-                .unwrap_or_default()
-                .to_string(),
+                .map(|s| with_unit_suffix(field, s, s.to_string()))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Formats the cells of a list row. Labels of selects backed by records are
+/// resolved from `sources`, loaded once for the whole list.
+pub fn format_row(
+    fields: &[Arc<Field>],
+    settings: &Settings,
+    sources: &ExternalSources,
+) -> Vec<String> {
+    fields
+        .iter()
+        .map(|field| settings.format_with_sources(field, sources))
+        .collect()
+}
+
+/// Appends the field's unit to numeric values, in singular form for a value
+/// of one and plural form otherwise. Non-numeric values are left as they are.
+fn with_unit_suffix(field: &Field, value: &str, formatted: String) -> String {
+    match (field.unit_suffix, value.trim().parse::<f64>()) {
+        (Some(unit), Ok(number)) if number.abs() == 1.0 => format!("{formatted} {unit}"),
+        (Some(unit), Ok(_)) => format!("{formatted} {}", pluralize(unit)),
+        _ => formatted,
+    }
+}
+
+/// English plural of a unit name, covering the regular cases.
+fn pluralize(unit: &str) -> String {
+    if unit.ends_with('s') || unit.ends_with('x') || unit.ends_with("ch") || unit.ends_with("sh")
+    {
+        format!("{unit}es")
+    } else if let Some(stem) = unit
+        .strip_suffix('y')
+        .filter(|stem| !stem.ends_with(['a', 'e', 'i', 'o', 'u']))
+    {
+        format!("{stem}ies")
+    } else {
+        format!("{unit}s")
+    }
+}
+
+/// Formats a stored value, falling back to the raw value flagged as invalid
+/// when it cannot be parsed.
+fn format_or_invalid(
+    settings: &Settings,
+    field: &Field,
+    format: impl Fn(&str) -> Option<String>,
+) -> String {
+    match settings.get(field.id).map(|value| value.trim()) {
+        Some(value) if !value.is_empty() => {
+            format(value).unwrap_or_else(|| format!("{value} {INVALID_MARKER}"))
+        }
+        _ => String::new(),
+    }
+}
+
+/// Returns a copy of `settings` with the values of all secret fields
+/// replaced by `***`. Keys are expected to be relative to the schema prefix.
+pub fn redact_secrets(settings: &Settings, schema: &Schema) -> Settings {
+    let secrets = schema
+        .fields
+        .values()
+        .filter(|field| matches!(field.typ_, Type::Secret))
+        .map(|field| field.id)
+        .collect::<Vec<_>>();
+
+    settings
+        .iter()
+        .map(|(key, value)| {
+            let is_secret = secrets.iter().any(|id| {
+                key.strip_prefix(id)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+            });
+            (
+                key.clone(),
+                if is_secret {
+                    REDACTED.to_string()
+                } else {
+                    value.clone()
+                },
+            )
+        })
+        .collect()
+}
+
+/// Lists the settings modified by `updates`, with their values before and
+/// after the update. `previous` describes the values currently stored and
+/// is usually built from the form as it was loaded. Keys are sorted.
+pub fn diff_updates(updates: &[UpdateSettings], previous: &[UpdateSettings]) -> Vec<SettingChange> {
+    let previous = inserted_values(previous);
+    let inserted = inserted_values(updates);
+    let mut changes = inserted
+        .iter()
+        .filter(|(key, value)| previous.get(*key) != Some(*value))
+        .map(|(key, value)| SettingChange {
+            key: key.clone(),
+            old: previous.get(key).cloned(),
+            new: Some(value.clone()),
+        })
+        .collect::<Vec<_>>();
+
+    let mut removed = Vec::new();
+    for update in updates {
+        match update {
+            UpdateSettings::Delete { keys } => {
+                removed.extend(keys.iter().filter(|key| !inserted.contains_key(*key)));
+            }
+            UpdateSettings::Clear { prefix, .. } => {
+                removed.extend(
+                    previous
+                        .keys()
+                        .filter(|key| key.starts_with(prefix) && !inserted.contains_key(*key)),
+                );
+            }
+            UpdateSettings::Insert { .. } => (),
+        }
+    }
+    changes.extend(removed.into_iter().map(|key| SettingChange {
+        key: key.clone(),
+        old: previous.get(key).cloned(),
+        new: None,
+    }));
+
+    changes.sort_by(|a, b| a.key.cmp(&b.key));
+    changes.dedup_by(|a, b| a.key == b.key);
+    changes
+}
+
+/// Lists the settings modified by `updates` compared with `current`, the
+/// settings stored on the server. Used for updates that were not built from
+/// a loaded form, such as staged changes and approved proposals.
+pub fn diff_settings(updates: &[UpdateSettings], current: &Settings) -> Vec<SettingChange> {
+    diff_updates(
+        updates,
+        &[UpdateSettings::Insert {
+            prefix: None,
+            values: current
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            assert_empty: false,
+        }],
+    )
+}
+
+/// Replaces the values of secret fields in `changes` by `***`.
+pub fn redact_changes(changes: &mut [SettingChange], schema: &Schema) {
+    let secrets = schema
+        .fields
+        .values()
+        .filter(|field| matches!(field.typ_, Type::Secret))
+        .map(|field| field.id)
+        .collect::<Vec<_>>();
+
+    for change in changes {
+        let key = format!(".{}.", change.key);
+        let is_secret = secrets.iter().any(|id| key.contains(&format!(".{id}.")));
+        if is_secret {
+            for value in [&mut change.old, &mut change.new].into_iter().flatten() {
+                *value = REDACTED.to_string();
+            }
+        }
+    }
+}
+
+fn inserted_values(updates: &[UpdateSettings]) -> AHashMap<String, String> {
+    let mut values = AHashMap::new();
+    for update in updates {
+        if let UpdateSettings::Insert {
+            prefix,
+            values: items,
+            ..
+        } = update
+        {
+            for (key, value) in items {
+                let key = match prefix {
+                    Some(prefix) => format!("{prefix}.{key}"),
+                    None => key.clone(),
+                };
+                values.insert(key, value.clone());
+            }
         }
     }
+    values
 }
 
 impl LayoutBuilder {
@@ -650,6 +1136,90 @@ pub fn Config() -> impl IntoView {
                         </a>
                     </div>
                 </div>
+                <div class="group flex flex-col h-full bg-white border border-gray-200 shadow-sm rounded-xl dark:bg-slate-900 dark:border-gray-700 dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600">
+                    <div class="h-52 flex flex-col justify-center items-center bg-gradient-to-br from-gray-600 to-gray-700 rounded-t-xl">
+                        <IconDocumentText attr:class="size-28 text-white"/>
+                    </div>
+                    <div class="p-4 md:p-6">
+                        <h3 class="text-xl font-semibold text-gray-800 dark:text-gray-300">
+                            Configuration Report
+                        </h3>
+                        <p class="mt-3 text-gray-500 dark:text-gray-400">
+                            Generate a printable snapshot of the current settings, with secrets excluded
+                        </p>
+                    </div>
+                    <div class="mt-auto flex border-t border-gray-200 divide-x divide-gray-200 dark:border-gray-700 dark:divide-gray-700">
+                        <a
+                            class="w-full py-3 px-4 inline-flex justify-center items-center gap-x-2 text-sm font-medium rounded-es-xl bg-white text-gray-800 shadow-sm hover:bg-gray-50 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-white dark:hover:bg-gray-800 dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600"
+                            href="/settings/report"
+                        >
+                            Generate report
+                        </a>
+                    </div>
+                </div>
+                <div class="group flex flex-col h-full bg-white border border-gray-200 shadow-sm rounded-xl dark:bg-slate-900 dark:border-gray-700 dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600">
+                    <div class="h-52 flex flex-col justify-center items-center bg-gradient-to-br from-yellow-500 to-yellow-600 rounded-t-xl">
+                        <IconCircleStack attr:class="size-28 text-white"/>
+                    </div>
+                    <div class="p-4 md:p-6">
+                        <h3 class="text-xl font-semibold text-gray-800 dark:text-gray-300">
+                            Configuration Snapshots
+                        </h3>
+                        <p class="mt-3 text-gray-500 dark:text-gray-400">
+                            Save the current configuration and roll back to it if an experiment goes wrong
+                        </p>
+                    </div>
+                    <div class="mt-auto flex border-t border-gray-200 divide-x divide-gray-200 dark:border-gray-700 dark:divide-gray-700">
+                        <a
+                            class="w-full py-3 px-4 inline-flex justify-center items-center gap-x-2 text-sm font-medium rounded-es-xl bg-white text-gray-800 shadow-sm hover:bg-gray-50 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-white dark:hover:bg-gray-800 dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600"
+                            href="/settings/snapshots"
+                        >
+                            Manage snapshots
+                        </a>
+                    </div>
+                </div>
+                <div class="group flex flex-col h-full bg-white border border-gray-200 shadow-sm rounded-xl dark:bg-slate-900 dark:border-gray-700 dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600">
+                    <div class="h-52 flex flex-col justify-center items-center bg-gradient-to-br from-red-500 to-red-600 rounded-t-xl">
+                        <IconShieldCheck attr:class="size-28 text-white"/>
+                    </div>
+                    <div class="p-4 md:p-6">
+                        <h3 class="text-xl font-semibold text-gray-800 dark:text-gray-300">
+                            Configuration Lint
+                        </h3>
+                        <p class="mt-3 text-gray-500 dark:text-gray-400">
+                            Find risky settings such as open relaying, disabled authentication or weak TLS
+                        </p>
+                    </div>
+                    <div class="mt-auto flex border-t border-gray-200 divide-x divide-gray-200 dark:border-gray-700 dark:divide-gray-700">
+                        <a
+                            class="w-full py-3 px-4 inline-flex justify-center items-center gap-x-2 text-sm font-medium rounded-es-xl bg-white text-gray-800 shadow-sm hover:bg-gray-50 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-white dark:hover:bg-gray-800 dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600"
+                            href="/settings/lint"
+                        >
+                            Lint configuration
+                        </a>
+                    </div>
+                </div>
+                <div class="group flex flex-col h-full bg-white border border-gray-200 shadow-sm rounded-xl dark:bg-slate-900 dark:border-gray-700 dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600">
+                    <div class="h-52 flex flex-col justify-center items-center bg-gradient-to-br from-green-500 to-green-600 rounded-t-xl">
+                        <IconCheckCircle attr:class="size-28 text-white"/>
+                    </div>
+                    <div class="p-4 md:p-6">
+                        <h3 class="text-xl font-semibold text-gray-800 dark:text-gray-300">
+                            Validate All
+                        </h3>
+                        <p class="mt-3 text-gray-500 dark:text-gray-400">
+                            Check every settings section at once and review the problems grouped by section
+                        </p>
+                    </div>
+                    <div class="mt-auto flex border-t border-gray-200 divide-x divide-gray-200 dark:border-gray-700 dark:divide-gray-700">
+                        <a
+                            class="w-full py-3 px-4 inline-flex justify-center items-center gap-x-2 text-sm font-medium rounded-es-xl bg-white text-gray-800 shadow-sm hover:bg-gray-50 disabled:opacity-50 disabled:pointer-events-none dark:bg-slate-900 dark:border-gray-700 dark:text-white dark:hover:bg-gray-800 dark:focus:outline-none dark:focus:ring-1 dark:focus:ring-gray-600"
+                            href="/settings/validate"
+                        >
+                            Validate all
+                        </a>
+                    </div>
+                </div>
             </div>
         </div>
     }
@@ -660,6 +1230,916 @@ pub fn LayoutConfigPage() -> impl IntoView {
     view! {
         <div class="max-w-[85rem] px-4 py-10 sm:px-6 lg:px-8 lg:py-14 mx-auto">
             <LayoutConfig/>
+            <SecurityDebugPanel/>
         </div>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Permission;
+
+    #[test]
+    fn format_size_with_precision() {
+        let schemas = Schemas::builder()
+            .new_schema("sizes")
+            .new_field("whole")
+            .typ(Type::Size)
+            .size_precision(0)
+            .build()
+            .new_field("two-decimals")
+            .typ(Type::Size)
+            .size_precision(2)
+            .build()
+            .build()
+            .build();
+        let schema = &schemas.schemas["sizes"];
+        let settings = [
+            ("whole".to_string(), "1234000".to_string()),
+            ("two-decimals".to_string(), "1000000".to_string()),
+        ]
+        .into_iter()
+        .collect::<Settings>();
+
+        crate::utils::locale::set_deterministic_formatting(Some(Default::default()));
+        assert_eq!(settings.format(&schema.fields["whole"]), "1 MB");
+        assert_eq!(settings.format(&schema.fields["two-decimals"]), "1.00 MB");
+
+        // Queue quotas are shown in whole units
+        let quota = &crate::build_schemas().schemas["smtp-out-quota"];
+        let settings = [("size".to_string(), "1234000".to_string())]
+            .into_iter()
+            .collect::<Settings>();
+        assert_eq!(quota.fields["size"].size_precision, Some(0));
+        assert_eq!(settings.format(&quota.fields["size"]), "1 MB");
+
+        let schemas = Schemas::builder()
+            .new_schema("numbers")
+            .new_field("score")
+            .typ(Type::Number {
+                signed: true,
+                min: None,
+                max: None,
+            })
+            .build()
+            .build()
+            .build();
+        let settings = [("score".to_string(), "-12500.5".to_string())]
+            .into_iter()
+            .collect::<Settings>();
+        assert_eq!(
+            settings.format(&schemas.schemas["numbers"].fields["score"]),
+            "-12,500.5"
+        );
+        crate::utils::locale::set_deterministic_formatting(None);
+    }
+
+    #[test]
+    fn format_dynamic_select_labels() {
+        let schemas = Schemas::builder()
+            .new_schema("store")
+            .prefix("store")
+            .suffix("type")
+            .new_id_field()
+            .build()
+            .new_field("type")
+            .build()
+            .build()
+            .new_schema("directory")
+            .new_field("store")
+            .typ(Type::Select {
+                source: Source::Dynamic {
+                    schema: "store",
+                    field: "type",
+                    filter: Default::default(),
+                },
+                typ: SelectType::Single,
+            })
+            .build()
+            .build()
+            .build();
+        let field = &schemas.schemas["directory"].fields["store"];
+        let sources = ExternalSources::from_iter([(
+            "store_type".to_string(),
+            vec![
+                ("rocksdb".to_string(), "RocksDB".to_string()),
+                ("blank".to_string(), String::new()),
+            ],
+        )]);
+        let settings = |value: &str| {
+            [("store".to_string(), value.to_string())]
+                .into_iter()
+                .collect::<Settings>()
+        };
+
+        assert_eq!(
+            settings("rocksdb").format_with_sources(field, &sources),
+            "RocksDB"
+        );
+        assert_eq!(
+            settings("postgresql").format_with_sources(field, &sources),
+            "postgresql"
+        );
+        assert_eq!(settings("blank").format_with_sources(field, &sources), "blank");
+        assert_eq!(settings("rocksdb").format(field), "rocksdb");
+        assert_eq!(
+            format_row(&[field.clone()], &settings("rocksdb"), &sources),
+            vec!["RocksDB"]
+        );
+    }
+
+    #[test]
+    fn format_row_matches_cells() {
+        let schemas = Schemas::builder()
+            .new_schema("row")
+            .new_field("name")
+            .build()
+            .new_field("enabled")
+            .typ(Type::Boolean)
+            .build()
+            .new_field("timeout")
+            .typ(Type::Duration)
+            .build()
+            .new_field("size")
+            .typ(Type::Size)
+            .build()
+            .new_field("bind")
+            .typ(Type::Array)
+            .build()
+            .new_field("protocol")
+            .typ(Type::Select {
+                source: Source::Static(&[("smtp", "SMTP"), ("imap", "IMAP")]),
+                typ: SelectType::Single,
+            })
+            .build()
+            .build()
+            .build();
+        let schema = &schemas.schemas["row"];
+        let fields = ["name", "enabled", "timeout", "size", "bind", "protocol", "name"]
+            .iter()
+            .map(|id| schema.fields[id].clone())
+            .collect::<Vec<_>>();
+        let settings = [
+            ("name", "smtp"),
+            ("enabled", "true"),
+            ("timeout", "bad"),
+            ("size", "2000"),
+            ("bind.0", "[::]:25"),
+            ("bind.1", "[::]:587"),
+            ("protocol", "smtp"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect::<Settings>();
+
+        assert_eq!(
+            format_row(&fields, &settings, &ExternalSources::default()),
+            fields
+                .iter()
+                .map(|field| settings.format(field))
+                .collect::<Vec<_>>()
+        );
+        assert!(format_row(&[], &settings, &ExternalSources::default()).is_empty());
+    }
+
+    #[test]
+    fn format_empty_placeholder() {
+        let schemas = Schemas::builder()
+            .new_schema("placeholders")
+            .new_field("hostname")
+            .empty_placeholder("(default)")
+            .build()
+            .new_field("bind")
+            .typ(Type::Array)
+            .empty_placeholder("\u{2014}")
+            .build()
+            .new_field("banner")
+            .build()
+            .build()
+            .build();
+        let fields = &schemas.schemas["placeholders"].fields;
+
+        let missing = Settings::default();
+        assert_eq!(missing.format(&fields["hostname"]), "(default)");
+        assert_eq!(missing.format(&fields["bind"]), "\u{2014}");
+        assert_eq!(missing.format(&fields["banner"]), "");
+
+        let present = [
+            ("hostname".to_string(), String::new()),
+            ("bind.0".to_string(), "[::]:25".to_string()),
+        ]
+        .into_iter()
+        .collect::<Settings>();
+        assert_eq!(present.format(&fields["hostname"]), "");
+        assert_eq!(present.format(&fields["bind"]), "[::]:25");
+
+        // Queue quotas without a limit say so
+        let quota = &crate::build_schemas().schemas["smtp-out-quota"];
+        assert_eq!(missing.format(&quota.fields["messages"]), "No limit");
+        assert_eq!(missing.format(&quota.fields["size"]), "No limit");
+    }
+
+    #[test]
+    fn format_unit_suffix() {
+        let schemas = Schemas::builder()
+            .new_schema("units")
+            .new_field("connections")
+            .typ(Type::Number {
+                signed: false,
+                min: None,
+                max: None,
+            })
+            .unit_suffix("connection")
+            .build()
+            .new_field("retries")
+            .unit_suffix("retry")
+            .build()
+            .new_field("matches")
+            .unit_suffix("match")
+            .build()
+            .build()
+            .build();
+        let fields = &schemas.schemas["units"].fields;
+        let format = |id: &str, value: &str| {
+            [(id.to_string(), value.to_string())]
+                .into_iter()
+                .collect::<Settings>()
+                .format(&fields[id])
+        };
+
+        assert_eq!(format("connections", "1"), "1 connection");
+        assert_eq!(format("connections", "50"), "50 connections");
+        assert_eq!(format("connections", "0"), "0 connections");
+        assert_eq!(format("connections", "many"), "many (invalid)");
+        assert_eq!(format("retries", "1"), "1 retry");
+        assert_eq!(format("retries", "3"), "3 retries");
+        assert_eq!(format("matches", "2"), "2 matches");
+        assert_eq!(format("matches", "unlimited"), "unlimited");
+        assert_eq!(pluralize("day"), "days");
+        assert_eq!(pluralize("message"), "messages");
+
+        let quota = &crate::build_schemas().schemas["smtp-out-quota"];
+        let format = |value: &str| {
+            [("messages".to_string(), value.to_string())]
+                .into_iter()
+                .collect::<Settings>()
+                .format(&quota.fields["messages"])
+        };
+        assert_eq!(format("1"), "1 message");
+        assert_eq!(format("5000"), "5000 messages");
+    }
+
+    #[test]
+    fn format_malformed_values() {
+        let schemas = Schemas::builder()
+            .new_schema("malformed")
+            .new_field("timeout")
+            .typ(Type::Duration)
+            .build()
+            .new_field("rate")
+            .typ(Type::Rate)
+            .build()
+            .new_field("size")
+            .typ(Type::Size)
+            .build()
+            .build()
+            .build();
+        let fields = &schemas.schemas["malformed"].fields;
+        let settings = [
+            ("timeout".to_string(), "soon".to_string()),
+            ("rate".to_string(), "often".to_string()),
+            ("size".to_string(), "-1".to_string()),
+        ]
+        .into_iter()
+        .collect::<Settings>();
+
+        assert_eq!(settings.format(&fields["timeout"]), "soon (invalid)");
+        assert_eq!(settings.format(&fields["rate"]), "often (invalid)");
+        assert_eq!(settings.format(&fields["size"]), "-1 (invalid)");
+        assert_eq!(Settings::default().format(&fields["timeout"]), "");
+
+        let settings = [("timeout".to_string(), "30s".to_string())]
+            .into_iter()
+            .collect::<Settings>();
+        assert_eq!(settings.format(&fields["timeout"]), "30 seconds");
+    }
+
+    #[test]
+    fn stable_settings_hash() {
+        let entries = [
+            ("server.hostname", "mx.example.org"),
+            ("server.listener.smtp.bind.0", "[::]:25"),
+            ("server.listener.smtp.protocol", "smtp"),
+            ("sieve.trusted.script", "require \"fileinto\";\nkeep;"),
+        ];
+        let forward = entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<Settings>();
+        let mut reverse = Settings::default();
+        for (key, value) in entries.iter().rev() {
+            reverse.insert(key.to_string(), value.to_string());
+        }
+
+        let hash = settings_hash(&forward);
+        assert_eq!(hash, settings_hash(&reverse));
+        assert_eq!(hash.len(), 64);
+        assert_eq!(
+            settings_hash(&Settings::default()),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+
+        reverse.insert(
+            "sieve.trusted.script".to_string(),
+            "require \"fileinto\";\r\nkeep;".to_string(),
+        );
+        assert_eq!(hash, settings_hash(&reverse));
+
+        reverse.insert("server.listener.smtp.bind.0".to_string(), "[::]:2525".to_string());
+        assert_ne!(hash, settings_hash(&reverse));
+
+        let joined = [("a.b".to_string(), "c".to_string())].into_iter().collect();
+        let split = [("a".to_string(), "b.c".to_string())].into_iter().collect();
+        assert_ne!(settings_hash(&joined), settings_hash(&split));
+    }
+
+    fn schemas() -> Schemas {
+        Schemas::builder()
+            .new_schema("list")
+            .new_field("public")
+            .build()
+            .new_field("restricted")
+            .required_permission(Permission::SettingsDelete)
+            .build()
+            .build()
+            .new_schema("record")
+            .prefix("record")
+            .suffix("public")
+            .new_id_field()
+            .build()
+            .new_field("public")
+            .build()
+            .new_field("restricted")
+            .required_permission(Permission::SettingsDelete)
+            .build()
+            .build()
+            .build()
+    }
+
+    fn form(schemas: &Schemas, id: &str) -> FormData {
+        schemas
+            .build_form(id)
+            .with_value("_id", "test")
+            .with_value("public", "a")
+            .with_value("restricted", "b")
+    }
+
+    #[test]
+    fn build_update_omits_restricted_fields() {
+        let schemas = schemas();
+        let permissions = Permissions::new([Permission::SettingsUpdate].into_iter().collect());
+
+        let mut data = form(&schemas, "list");
+        assert_eq!(
+            data.build_update_for(&permissions).unwrap(),
+            vec![UpdateSettings::Insert {
+                prefix: None,
+                values: vec![("public".to_string(), "a".to_string())],
+                assert_empty: false,
+            }]
+        );
+
+        data.is_update = true;
+        data.set("public", "");
+        data.set("restricted", "");
+        assert_eq!(
+            data.build_update_for(&permissions).unwrap(),
+            vec![UpdateSettings::Delete {
+                keys: vec!["public".to_string()],
+            }]
+        );
+
+        // Records are cleared field by field so restricted values survive
+        let mut data = form(&schemas, "record");
+        data.is_update = true;
+        assert_eq!(
+            data.build_update_for(&permissions).unwrap(),
+            vec![
+                UpdateSettings::Delete {
+                    keys: vec!["record.test.public".to_string()],
+                },
+                UpdateSettings::Insert {
+                    prefix: Some("record.test".to_string()),
+                    values: vec![("public".to_string(), "a".to_string())],
+                    assert_empty: false,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn master_user_requires_impersonation() {
+        let schemas = crate::build_schemas();
+        let data = schemas
+            .build_form("authentication")
+            .with_value("storage.directory", "internal")
+            .with_value("authentication.master.user", "master")
+            .with_value("authentication.master.secret", "secret");
+        let keys = |permissions: &[Permission]| match data
+            .build_update_for(&Permissions::new(permissions.iter().cloned().collect()))
+            .unwrap()
+            .pop()
+        {
+            Some(UpdateSettings::Insert { values, .. }) => {
+                values.into_iter().map(|(key, _)| key).collect::<Vec<_>>()
+            }
+            update => panic!("Expected an insert, got {update:?}"),
+        };
+
+        let master = "authentication.master.user".to_string();
+        assert!(!keys(&[Permission::SettingsUpdate]).contains(&master));
+        assert!(keys(&[Permission::SettingsUpdate, Permission::Impersonate]).contains(&master));
+    }
+
+    #[test]
+    fn build_update_includes_permitted_fields() {
+        let schemas = schemas();
+        let permissions = Permissions::new(
+            [Permission::SettingsUpdate, Permission::SettingsDelete]
+                .into_iter()
+                .collect(),
+        );
+
+        let mut data = form(&schemas, "record");
+        data.is_update = true;
+        let updates = data.build_update_for(&permissions).unwrap();
+        assert_eq!(updates, data.build_update().unwrap());
+        assert_eq!(
+            updates.first(),
+            Some(&UpdateSettings::Clear {
+                prefix: "record.test.".to_string(),
+                filter: None,
+            })
+        );
+    }
+
+    #[test]
+    fn build_update_rejects_malformed_record_id() {
+        let schemas = schemas();
+        let permissions = Permissions::new([Permission::SettingsUpdate].into_iter().collect());
+        let mut data = form(&schemas, "record").with_value("_id", "test.");
+
+        assert_eq!(data.build_update().unwrap_err().field, "_id");
+        assert_eq!(data.checked_update_for(&permissions), None);
+        assert!(data.has_errors("_id"));
+    }
+
+    #[test]
+    fn build_update_with_reserved_keys() {
+        let schemas = Schemas::builder()
+            .new_schema("hook")
+            .prefix("mta-hook")
+            .suffix("url")
+            .allow_reserved_key("_meta")
+            .new_id_field()
+            .build()
+            .new_field("url")
+            .build()
+            .new_field("_meta")
+            .build()
+            .new_field("_draft")
+            .build()
+            .build()
+            .build();
+        let schema = &schemas.schemas["hook"];
+        assert!(!schema.is_reserved_key("_meta"));
+        assert!(schema.is_reserved_key("_draft"));
+        assert!(schema.is_reserved_key("_id"));
+
+        let data = schemas
+            .build_form("hook")
+            .with_value("_id", "audit")
+            .with_value("url", "https://hooks.example.org")
+            .with_value("_meta", "owner=ops")
+            .with_value("_draft", "true");
+        let Some(UpdateSettings::Insert { prefix, values, .. }) =
+            data.build_update().unwrap().pop()
+        else {
+            panic!("Expected an insert");
+        };
+        let mut values = values;
+        values.sort();
+        assert_eq!(prefix.as_deref(), Some("mta-hook.audit"));
+        assert_eq!(
+            values,
+            vec![
+                ("_meta".to_string(), "owner=ops".to_string()),
+                ("url".to_string(), "https://hooks.example.org".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn update_preserves_reserved_keys() {
+        let schemas = Schemas::builder()
+            .new_schema("hook")
+            .prefix("mta-hook")
+            .suffix("url")
+            .allow_reserved_key("_meta")
+            .new_id_field()
+            .build()
+            .new_field("url")
+            .build()
+            .new_field("_meta")
+            .build()
+            .new_field("_draft")
+            .build()
+            .build()
+            .build();
+        let mut data = schemas
+            .build_form("hook")
+            .with_value("_id", "audit")
+            .with_value("url", "https://hooks.example.org")
+            .with_value("_meta", "owner=ops")
+            .with_value("_draft", "true");
+        data.is_update = true;
+
+        let updates = data.build_update().unwrap();
+        assert!(!updates
+            .iter()
+            .any(|update| matches!(update, UpdateSettings::Clear { .. })));
+        assert_eq!(
+            updates.first(),
+            Some(&UpdateSettings::Delete {
+                keys: vec!["mta-hook.audit.url".to_string()]
+            })
+        );
+
+        // An emptied allowed key is deleted, a reserved one never is
+        data.set("_meta", "");
+        assert!(matches!(
+            data.build_update().unwrap().first(),
+            Some(UpdateSettings::Delete { keys }) if keys.contains(&"mta-hook.audit._meta".to_string())
+                && !keys.contains(&"mta-hook.audit._draft".to_string())
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "internal")]
+    fn internal_keys_cannot_be_allowed() {
+        Schemas::builder()
+            .new_schema("hook")
+            .allow_reserved_key("_id");
+    }
+
+    fn partial_form(stored: &[&str]) -> FormData {
+        let schemas = Schemas::builder()
+            .new_schema("allowed")
+            .partial_array_updates()
+            .new_field("server.allowed-ip")
+            .typ(Type::Array)
+            .build()
+            .build()
+            .build();
+        let settings = stored
+            .iter()
+            .enumerate()
+            .map(|(idx, value)| (format!("server.allowed-ip.{idx}"), value.to_string()))
+            .collect::<Settings>();
+        FormData::from_settings(schemas.get("allowed"), Some(settings))
+    }
+
+    #[test]
+    fn partial_array_update_appends_element() {
+        let mut data = partial_form(&["10.0.0.1", "10.0.0.2"]);
+        data.array_set("server.allowed-ip", ["10.0.0.1", "10.0.0.2", "10.0.0.3"]);
+
+        assert_eq!(
+            data.build_update().unwrap(),
+            vec![UpdateSettings::Insert {
+                prefix: None,
+                values: vec![("server.allowed-ip.2".to_string(), "10.0.0.3".to_string())],
+                assert_empty: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn partial_array_update_removes_element() {
+        let mut data = partial_form(&["10.0.0.1", "10.0.0.2", "10.0.0.3"]);
+        data.array_set("server.allowed-ip", ["10.0.0.1", "10.0.0.3"]);
+
+        assert_eq!(
+            data.build_update().unwrap(),
+            vec![UpdateSettings::Delete {
+                keys: vec!["server.allowed-ip.1".to_string()],
+            }]
+        );
+
+        // Unchanged arrays produce no updates at all
+        let data = partial_form(&["10.0.0.1"]);
+        assert_eq!(data.build_update().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn partial_array_update_rewrites_bare_key() {
+        let schemas = Schemas::builder()
+            .new_schema("allowed")
+            .partial_array_updates()
+            .new_field("server.allowed-ip")
+            .typ(Type::Array)
+            .build()
+            .build()
+            .build();
+        let settings =
+            Settings::from_iter([("server.allowed-ip".to_string(), "10.0.0.1".to_string())]);
+        let mut data = FormData::from_settings(schemas.get("allowed"), Some(settings));
+        data.array_set("server.allowed-ip", ["10.0.0.1", "10.0.0.2"]);
+
+        assert_eq!(
+            data.build_update().unwrap(),
+            vec![
+                UpdateSettings::Clear {
+                    prefix: "server.allowed-ip.".to_string(),
+                    filter: None,
+                },
+                UpdateSettings::Delete {
+                    keys: vec!["server.allowed-ip".to_string()],
+                },
+                UpdateSettings::Insert {
+                    prefix: None,
+                    values: vec![
+                        ("server.allowed-ip.0".to_string(), "10.0.0.1".to_string()),
+                        ("server.allowed-ip.1".to_string(), "10.0.0.2".to_string()),
+                    ],
+                    assert_empty: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn list_schemas_use_partial_array_updates() {
+        let schemas = crate::build_schemas();
+        for id in ["system", "auto-ban", "metrics"] {
+            assert!(schemas.schemas[id].partial_array_updates, "{id}");
+        }
+    }
+
+    #[test]
+    fn update_settings_round_trip() {
+        let updates = vec![
+            UpdateSettings::Delete {
+                keys: vec!["store.rocksdb.path".to_string()],
+            },
+            UpdateSettings::Delete { keys: vec![] },
+            UpdateSettings::Clear {
+                prefix: "store.rocksdb.".to_string(),
+                filter: None,
+            },
+            UpdateSettings::Clear {
+                prefix: "spam-filter.list.".to_string(),
+                filter: Some("block".to_string()),
+            },
+            UpdateSettings::Insert {
+                prefix: None,
+                values: vec![("server.hostname".to_string(), "mx.example.org".to_string())],
+                assert_empty: false,
+            },
+            UpdateSettings::Insert {
+                prefix: Some("store.rocksdb".to_string()),
+                values: vec![
+                    ("type".to_string(), "rocksdb".to_string()),
+                    ("path".to_string(), "/opt/stalwart/data".to_string()),
+                ],
+                assert_empty: true,
+            },
+        ];
+
+        for update in updates {
+            let json = serde_json::to_string(&update).unwrap();
+            assert_eq!(
+                serde_json::from_str::<UpdateSettings>(&json).unwrap(),
+                update,
+                "{json}"
+            );
+        }
+    }
+
+    #[test]
+    fn update_settings_wire_format() {
+        assert_eq!(
+            serde_json::to_value(UpdateSettings::Clear {
+                prefix: "store.rocksdb.".to_string(),
+                filter: None,
+            })
+            .unwrap(),
+            serde_json::json!({"type": "clear", "prefix": "store.rocksdb."})
+        );
+        assert_eq!(
+            serde_json::to_value(UpdateSettings::Insert {
+                prefix: None,
+                values: vec![("a".to_string(), "1".to_string())],
+                assert_empty: false,
+            })
+            .unwrap(),
+            serde_json::json!({
+                "type": "insert",
+                "prefix": null,
+                "values": [["a", "1"]],
+                "assert_empty": false
+            })
+        );
+
+        // Optional fields may be omitted or null when importing
+        assert_eq!(
+            serde_json::from_str::<UpdateSettings>(
+                r#"{"type": "clear", "prefix": "a.", "filter": null}"#
+            )
+            .unwrap(),
+            UpdateSettings::Clear {
+                prefix: "a.".to_string(),
+                filter: None,
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<UpdateSettings>(
+                r#"{"type": "insert", "values": [], "assert_empty": true}"#
+            )
+            .unwrap(),
+            UpdateSettings::Insert {
+                prefix: None,
+                values: vec![],
+                assert_empty: true,
+            }
+        );
+        assert!(serde_json::from_str::<UpdateSettings>(r#"{"type": "clear"}"#).is_err());
+    }
+
+    #[test]
+    fn normalize_values_on_save() {
+        let schemas = Schemas::builder()
+            .new_schema("sieve")
+            .new_field("hostname")
+            .build()
+            .new_field("subject-prefix")
+            .preserve_whitespace()
+            .build()
+            .build()
+            .build();
+        let data = schemas
+            .build_form("sieve")
+            .with_value("hostname", "  \u{201C}mx.example.org\u{201D}\t")
+            .with_value("subject-prefix", " \u{2018}Auto\u{2019}: ");
+
+        let mut updates = data.build_update().unwrap();
+        let Some(UpdateSettings::Insert { values, .. }) = updates.pop() else {
+            panic!("Expected an insert, got {updates:?}");
+        };
+        let values = values.into_iter().collect::<AHashMap<_, _>>();
+        assert_eq!(values["hostname"], "\"mx.example.org\"");
+        assert_eq!(values["subject-prefix"], " \u{2018}Auto\u{2019}: ");
+        assert_eq!(normalize_value("  "), "");
+    }
+
+    #[test]
+    fn redact_secret_fields() {
+        let schemas = Schemas::builder()
+            .new_schema("store")
+            .new_field("host")
+            .build()
+            .new_field("password")
+            .typ(Type::Secret)
+            .build()
+            .new_field("keys")
+            .typ(Type::Secret)
+            .build()
+            .build()
+            .build();
+        let settings = Settings::from_iter(
+            [
+                ("host", "localhost"),
+                ("password", "s3cr3t"),
+                ("password-file", "/etc/secret"),
+                ("keys.0", "abc"),
+                ("keys.1", "def"),
+            ]
+            .map(|(k, v)| (k.to_string(), v.to_string())),
+        );
+
+        let redacted = redact_secrets(&settings, &schemas.schemas["store"]);
+        assert_eq!(redacted.len(), settings.len());
+        assert_eq!(redacted["host"], "localhost");
+        assert_eq!(redacted["password"], REDACTED);
+        assert_eq!(redacted["keys.0"], REDACTED);
+        assert_eq!(redacted["keys.1"], REDACTED);
+        assert_eq!(redacted["password-file"], "/etc/secret");
+    }
+
+    #[test]
+    fn diff_against_stored_settings() {
+        let current = Settings::from_iter(
+            [
+                ("queue.quota.a.enable", "true"),
+                ("queue.quota.a.messages", "10"),
+                ("queue.quota.b.messages", "20"),
+                ("server.hostname", "mx.example.org"),
+            ]
+            .map(|(k, v)| (k.to_string(), v.to_string())),
+        );
+        let updates = vec![
+            UpdateSettings::Clear {
+                prefix: "queue.quota.a.".to_string(),
+                filter: None,
+            },
+            UpdateSettings::Insert {
+                prefix: Some("queue.quota.a".to_string()),
+                values: vec![("messages".to_string(), "15".to_string())],
+                assert_empty: false,
+            },
+            UpdateSettings::Insert {
+                prefix: None,
+                values: vec![("server.hostname".to_string(), "mx.example.org".to_string())],
+                assert_empty: false,
+            },
+        ];
+
+        assert_eq!(
+            diff_settings(&updates, &current),
+            vec![
+                SettingChange {
+                    key: "queue.quota.a.enable".to_string(),
+                    old: Some("true".to_string()),
+                    new: None,
+                },
+                SettingChange {
+                    key: "queue.quota.a.messages".to_string(),
+                    old: Some("10".to_string()),
+                    new: Some("15".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_and_redact_changes() {
+        let insert = |values: &[(&str, &str)]| UpdateSettings::Insert {
+            prefix: Some("store.pg".to_string()),
+            values: values
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            assert_empty: false,
+        };
+        let previous = vec![insert(&[
+            ("host", "localhost"),
+            ("password", "old"),
+            ("port", "5432"),
+        ])];
+        let updates = vec![
+            UpdateSettings::Clear {
+                prefix: "store.pg.".to_string(),
+                filter: None,
+            },
+            insert(&[("host", "db.example.org"), ("password", "new")]),
+        ];
+
+        let mut changes = diff_updates(&updates, &previous);
+        assert_eq!(
+            changes,
+            vec![
+                SettingChange {
+                    key: "store.pg.host".to_string(),
+                    old: Some("localhost".to_string()),
+                    new: Some("db.example.org".to_string()),
+                },
+                SettingChange {
+                    key: "store.pg.password".to_string(),
+                    old: Some("old".to_string()),
+                    new: Some("new".to_string()),
+                },
+                SettingChange {
+                    key: "store.pg.port".to_string(),
+                    old: Some("5432".to_string()),
+                    new: None,
+                },
+            ]
+        );
+        assert_eq!(diff_updates(&previous, &previous), vec![]);
+
+        let schemas = Schemas::builder()
+            .new_schema("store")
+            .new_field("host")
+            .build()
+            .new_field("password")
+            .typ(Type::Secret)
+            .build()
+            .build()
+            .build();
+        redact_changes(&mut changes, &schemas.schemas["store"]);
+        assert_eq!(changes[0].new.as_deref(), Some("db.example.org"));
+        assert_eq!(changes[1].old.as_deref(), Some(REDACTED));
+        assert_eq!(changes[1].new.as_deref(), Some(REDACTED));
+    }
+}
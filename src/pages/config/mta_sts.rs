@@ -0,0 +1,301 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use leptos::*;
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::{
+    components::{
+        form::{button::Button, input::Duration},
+        messages::alert::{use_alerts, Alert},
+        Color,
+    },
+    core::form::FormData,
+};
+
+pub const MTA_STS_SCHEMA: &str = "smtp-in-mta-sts";
+
+// RFC 8461 section 3.2
+const MAX_AGE_LIMIT: u64 = 31_557_600;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MtaStsPolicy {
+    pub mode: &'static str,
+    pub mx: Vec<String>,
+    pub max_age: u64,
+}
+
+impl MtaStsPolicy {
+    /// Builds the policy published by the server from the MTA-STS form,
+    /// returning every validation error found.
+    pub fn from_form(data: &FormData) -> Result<Self, Vec<String>> {
+        let mut errors = Vec::new();
+
+        let mode = match data
+            .value_as_str("session.mta-sts.mode")
+            .unwrap_or_default()
+        {
+            "enforce" => "enforce",
+            "testing" => "testing",
+            "disable" => "none",
+            mode => {
+                errors.push(format!("Invalid policy mode {mode:?}"));
+                "none"
+            }
+        };
+
+        let max_age = match data
+            .value_as_str("session.mta-sts.max-age")
+            .and_then(|value| value.parse::<Duration>().ok())
+            .and_then(|duration| duration.as_secs())
+        {
+            Some(0) => {
+                errors.push("Max lifetime must be greater than zero".to_string());
+                0
+            }
+            Some(max_age) if max_age > MAX_AGE_LIMIT => {
+                errors.push(format!(
+                    "Max lifetime cannot exceed {MAX_AGE_LIMIT} seconds (about one year)"
+                ));
+                max_age
+            }
+            Some(max_age) => max_age,
+            None => {
+                errors.push("Max lifetime is not a valid duration".to_string());
+                0
+            }
+        };
+
+        let mx = data
+            .array_value("session.mta-sts.mx")
+            .map(str::trim)
+            .filter(|mx| !mx.is_empty())
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+        for pattern in &mx {
+            if !is_valid_mx_pattern(pattern) {
+                errors.push(format!("Invalid MX pattern {pattern:?}"));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(MtaStsPolicy { mode, mx, max_age })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Returns the policy file served at
+    /// `https://mta-sts.<domain>/.well-known/mta-sts.txt`.
+    pub fn policy_file(&self) -> String {
+        let mut policy = format!("version: STSv1\r\nmode: {}\r\n", self.mode);
+        for mx in &self.mx {
+            policy.push_str(&format!("mx: {mx}\r\n"));
+        }
+        policy.push_str(&format!("max_age: {}\r\n", self.max_age));
+        policy
+    }
+
+    /// Returns the policy identifier, which changes whenever the contents of
+    /// the policy file change.
+    pub fn policy_id(&self) -> String {
+        let hash = self
+            .policy_file()
+            .bytes()
+            .fold(0xcbf29ce484222325u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            });
+        format!("{hash:016x}")
+    }
+
+    pub fn txt_record(&self) -> String {
+        format!("_mta-sts IN TXT \"v=STSv1; id={}\"", self.policy_id())
+    }
+}
+
+fn is_valid_mx_pattern(pattern: &str) -> bool {
+    let host = pattern.strip_prefix("*.").unwrap_or(pattern);
+    host.contains('.')
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+fn copy_to_clipboard(text: &str) -> bool {
+    let get = |target: &JsValue, key: &str| {
+        js_sys::Reflect::get(target, &JsValue::from_str(key))
+            .ok()
+            .filter(|value| !value.is_undefined())
+    };
+    let clipboard = get(&window(), "navigator").and_then(|navigator| get(&navigator, "clipboard"));
+    let write_text = clipboard.as_ref().and_then(|clipboard| {
+        get(clipboard, "writeText")?
+            .dyn_into::<js_sys::Function>()
+            .ok()
+    });
+
+    match (clipboard, write_text) {
+        (Some(clipboard), Some(write_text)) => write_text
+            .call1(&clipboard, &JsValue::from_str(text))
+            .is_ok(),
+        _ => false,
+    }
+}
+
+#[component]
+pub fn MtaStsPreview(data: RwSignal<FormData>) -> impl IntoView {
+    let alert = use_alerts();
+    let policy = create_memo(move |_| data.with(MtaStsPolicy::from_form));
+
+    let copy = move |text: String| {
+        if copy_to_clipboard(&text) {
+            alert.set(Alert::success("Copied to clipboard"));
+        } else {
+            alert.set(Alert::warning(
+                "Copying to the clipboard is not supported by this browser",
+            ));
+        }
+    };
+
+    view! {
+        <div class="flex flex-col gap-y-3 p-4 mt-4 bg-white border border-gray-200 rounded-xl dark:bg-neutral-800 dark:border-neutral-700">
+            <h2 class="text-sm font-semibold text-gray-800 dark:text-neutral-200">
+                "Policy preview"
+            </h2>
+            {move || match policy.get() {
+                Ok(policy) => {
+                    let file = policy.policy_file();
+                    let record = policy.txt_record();
+                    let file_copy = file.clone();
+                    let record_copy = record.clone();
+                    view! {
+                        <p class="text-sm text-gray-600 dark:text-neutral-400">
+                            "Policy file served at https://mta-sts.<domain>/.well-known/mta-sts.txt"
+                        </p>
+                        <pre class="p-3 text-xs bg-gray-50 rounded-lg overflow-x-auto dark:bg-neutral-900 dark:text-neutral-300">
+                            {file}
+                        </pre>
+                        {policy
+                            .mx
+                            .is_empty()
+                            .then(|| {
+                                view! {
+                                    <p class="text-xs text-gray-500 dark:text-neutral-500">
+                                        "No MX patterns are set, the server will list the hostnames of its TLS certificates."
+                                    </p>
+                                }
+                            })}
+
+                        <div>
+                            <Button
+                                text="Copy policy"
+                                color=Color::Gray
+                                on_click=move |_| copy(file_copy.clone())
+                            />
+                        </div>
+                        <p class="text-sm text-gray-600 dark:text-neutral-400">"DNS record"</p>
+                        <pre class="p-3 text-xs bg-gray-50 rounded-lg overflow-x-auto dark:bg-neutral-900 dark:text-neutral-300">
+                            {record}
+                        </pre>
+                        <div>
+                            <Button
+                                text="Copy record"
+                                color=Color::Gray
+                                on_click=move |_| copy(record_copy.clone())
+                            />
+                        </div>
+                    }
+                        .into_view()
+                }
+                Err(errors) => {
+                    view! {
+                        <ul class="list-disc ps-5 text-sm text-red-600 dark:text-red-500">
+                            {errors
+                                .into_iter()
+                                .map(|error| view! { <li>{error}</li> })
+                                .collect_view()}
+                        </ul>
+                    }
+                        .into_view()
+                }
+            }}
+
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form(mode: &str, max_age: &str, mx: &[&str]) -> FormData {
+        FormData::default()
+            .with_value("session.mta-sts.mode", mode)
+            .with_value("session.mta-sts.max-age", max_age)
+            .with_value(
+                "session.mta-sts.mx",
+                mx.iter().map(|mx| mx.to_string()).collect::<Vec<_>>(),
+            )
+    }
+
+    #[test]
+    fn synthesize_policy_file() {
+        let policy = MtaStsPolicy::from_form(&form(
+            "enforce",
+            "7d",
+            &["mail.example.org", " *.MX.example.org ", ""],
+        ))
+        .unwrap();
+        assert_eq!(
+            policy.policy_file(),
+            concat!(
+                "version: STSv1\r\n",
+                "mode: enforce\r\n",
+                "mx: mail.example.org\r\n",
+                "mx: *.mx.example.org\r\n",
+                "max_age: 604800\r\n"
+            )
+        );
+        assert_eq!(policy.policy_id().len(), 16);
+        assert!(policy
+            .txt_record()
+            .starts_with("_mta-sts IN TXT \"v=STSv1; id="));
+
+        let disabled = MtaStsPolicy::from_form(&form("disable", "1h", &[])).unwrap();
+        assert_eq!(
+            disabled.policy_file(),
+            "version: STSv1\r\nmode: none\r\nmax_age: 3600\r\n"
+        );
+        assert_ne!(policy.policy_id(), disabled.policy_id());
+    }
+
+    #[test]
+    fn reject_invalid_policies() {
+        assert_eq!(
+            MtaStsPolicy::from_form(&form("enforce", "400d", &[])),
+            Err(vec![
+                "Max lifetime cannot exceed 31557600 seconds (about one year)".to_string()
+            ])
+        );
+        assert_eq!(
+            MtaStsPolicy::from_form(&form("strict", "7d", &[])),
+            Err(vec!["Invalid policy mode \"strict\"".to_string()])
+        );
+        assert_eq!(
+            MtaStsPolicy::from_form(&form("testing", "0s", &["mail..example.org", "mx"])),
+            Err(vec![
+                "Max lifetime must be greater than zero".to_string(),
+                "Invalid MX pattern \"mail..example.org\"".to_string(),
+                "Invalid MX pattern \"mx\"".to_string(),
+            ])
+        );
+        assert!(MtaStsPolicy::from_form(&form("testing", "soon", &[])).is_err());
+    }
+}
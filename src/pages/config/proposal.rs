@@ -0,0 +1,327 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use chrono::{DateTime, Utc};
+use gloo_storage::{LocalStorage, Storage};
+use leptos::*;
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{form::button::Button, Color},
+    utils::clock,
+};
+
+use super::UpdateSettings;
+
+const PROPOSALS_STORAGE_KEY: &str = "webadmin_proposals";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proposals {
+    items: Vec<Proposal>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proposal {
+    pub id: String,
+    pub schema_id: String,
+    pub title: String,
+    pub record_id: Option<String>,
+    pub author: String,
+    pub created: DateTime<Utc>,
+    pub updates: Vec<UpdateSettings>,
+    pub status: ProposalStatus,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "camelCase")]
+pub enum ProposalStatus {
+    Pending,
+    Approved {
+        reviewer: String,
+        reviewed: DateTime<Utc>,
+    },
+    Rejected {
+        reviewer: String,
+        reviewed: DateTime<Utc>,
+    },
+}
+
+pub fn init_proposals() {
+    provide_context(create_rw_signal(Proposals::load()));
+}
+
+pub fn use_proposals() -> RwSignal<Proposals> {
+    expect_context::<RwSignal<Proposals>>()
+}
+
+#[component]
+pub fn PendingProposals(
+    #[prop(into)] schema_id: Signal<&'static str>,
+    #[prop(into)] can_review: Signal<bool>,
+    #[prop(into)] reviewer: Signal<String>,
+    #[prop(into)] disabled: Signal<bool>,
+    on_approve: Callback<Proposal>,
+    on_reject: Callback<Proposal>,
+) -> impl IntoView {
+    let proposals = use_proposals();
+    let pending = create_memo(move |_| {
+        proposals
+            .get()
+            .pending(schema_id.get())
+            .cloned()
+            .collect::<Vec<_>>()
+    });
+
+    view! {
+        <div class="pb-5" class:hidden=move || pending.get().is_empty()>
+            <div class="bg-white border border-gray-200 rounded-lg p-4 dark:bg-slate-900 dark:border-gray-700">
+                <h3 class="text-sm font-semibold text-gray-800 dark:text-gray-200">
+                    "Pending proposals"
+                </h3>
+                <p class="mt-1 text-xs text-gray-500">
+                    "Proposals are kept in this browser. Another administrator must sign in here to approve them."
+                </p>
+                <ul class="mt-2 divide-y divide-gray-200 dark:divide-gray-700">
+                    <For
+                        each=move || pending.get()
+                        key=|proposal| proposal.id.clone()
+                        children=move |proposal| {
+                            let description = format!(
+                                "{} change{} proposed by {} on {}",
+                                proposal.total_changes(),
+                                if proposal.total_changes() == 1 { "" } else { "s" },
+                                proposal.author,
+                                proposal.created.format("%Y-%m-%d %H:%M"),
+                            );
+                            let title = match &proposal.record_id {
+                                Some(record_id) => format!("{} ({record_id})", proposal.title),
+                                None => proposal.title.clone(),
+                            };
+                            let author = store_value(proposal.author.clone());
+                            let proposal = store_value(proposal);
+
+                            view! {
+                                <li class="flex items-center justify-between gap-x-2 py-3">
+                                    <div class="text-sm">
+                                        <p class="font-medium text-gray-800 dark:text-gray-200">
+                                            {title}
+                                        </p>
+                                        <p class="text-gray-500">{description}</p>
+                                    </div>
+                                    <Show when=move || can_review.get()>
+                                        <div class="inline-flex gap-x-2">
+                                            <Button
+                                                text="Reject"
+                                                color=Color::Red
+                                                on_click=move |_| on_reject.call(proposal.get_value())
+
+                                                disabled=disabled
+                                            />
+                                            <Show when=move || {
+                                                reviewer
+                                                    .with(|reviewer| {
+                                                        author.with_value(|author| reviewer != author)
+                                                    })
+                                            }>
+                                                <Button
+                                                    text="Approve"
+                                                    color=Color::Blue
+                                                    on_click=move |_| on_approve.call(proposal.get_value())
+
+                                                    disabled=disabled
+                                                />
+                                            </Show>
+                                        </div>
+                                    </Show>
+                                </li>
+                            }
+                        }
+                    />
+
+                </ul>
+            </div>
+        </div>
+    }
+}
+
+impl Proposal {
+    pub fn new(
+        schema_id: impl Into<String>,
+        title: impl Into<String>,
+        record_id: Option<String>,
+        author: impl Into<String>,
+        updates: Vec<UpdateSettings>,
+    ) -> Self {
+        Proposal {
+            id: thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(12)
+                .map(char::from)
+                .collect(),
+            schema_id: schema_id.into(),
+            title: title.into(),
+            record_id,
+            author: author.into(),
+            created: clock::now(),
+            updates,
+            status: ProposalStatus::Pending,
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        matches!(self.status, ProposalStatus::Pending)
+    }
+
+    /// Proposals must be approved by someone other than their author.
+    pub fn can_approve(&self, reviewer: &str) -> bool {
+        self.is_pending() && self.author != reviewer
+    }
+
+    pub fn total_changes(&self) -> usize {
+        self.updates.iter().map(UpdateSettings::total_changes).sum()
+    }
+}
+
+impl Proposals {
+    pub fn load() -> Self {
+        LocalStorage::get(PROPOSALS_STORAGE_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = LocalStorage::set(PROPOSALS_STORAGE_KEY, self) {
+            log::warn!("Failed to persist proposals: {err}");
+        }
+    }
+
+    pub fn propose(&mut self, proposal: Proposal) {
+        self.items.push(proposal);
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Proposal> {
+        self.items.iter().find(|p| p.id == id)
+    }
+
+    /// Returns the pending proposals for a section, oldest first.
+    pub fn pending<'x>(&'x self, schema_id: &'x str) -> impl Iterator<Item = &'x Proposal> + 'x {
+        self.items
+            .iter()
+            .filter(move |p| p.is_pending() && p.schema_id == schema_id)
+    }
+
+    /// Marks a pending proposal as approved, returning the updates to apply.
+    /// Authors cannot approve their own proposals.
+    pub fn approve(&mut self, id: &str, reviewer: &str) -> Option<Vec<UpdateSettings>> {
+        let proposal = self
+            .items
+            .iter_mut()
+            .find(|p| p.id == id && p.can_approve(reviewer))?;
+        proposal.status = ProposalStatus::Approved {
+            reviewer: reviewer.to_string(),
+            reviewed: clock::now(),
+        };
+        Some(proposal.updates.clone())
+    }
+
+    /// Marks a pending proposal as rejected, discarding its updates.
+    pub fn reject(&mut self, id: &str, reviewer: &str) -> bool {
+        if let Some(proposal) = self.items.iter_mut().find(|p| p.id == id && p.is_pending()) {
+            proposal.status = ProposalStatus::Rejected {
+                reviewer: reviewer.to_string(),
+                reviewed: clock::now(),
+            };
+            proposal.updates.clear();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proposal(schema_id: &str, key: &str) -> Proposal {
+        Proposal::new(
+            schema_id,
+            "Connect stage",
+            None,
+            "alice",
+            vec![UpdateSettings::Insert {
+                prefix: None,
+                values: vec![(key.to_string(), "true".to_string())],
+                assert_empty: false,
+            }],
+        )
+    }
+
+    #[test]
+    fn serialize_proposal() {
+        let proposal = proposal("smtp-in-connect", "session.connect.greeting");
+        let json = serde_json::to_string(&proposal).unwrap();
+        assert!(json.contains(r#""status":{"type":"pending"}"#));
+        assert_eq!(serde_json::from_str::<Proposal>(&json).unwrap(), proposal);
+    }
+
+    #[test]
+    fn list_pending_proposals() {
+        let mut proposals = Proposals::default();
+        proposals.propose(proposal("smtp-in-connect", "a"));
+        proposals.propose(proposal("smtp-in-ehlo", "b"));
+        proposals.propose(proposal("smtp-in-connect", "c"));
+
+        assert_eq!(proposals.pending("smtp-in-connect").count(), 2);
+        assert_eq!(proposals.pending("smtp-in-ehlo").count(), 1);
+        assert_eq!(proposals.pending("smtp-in-rcpt").count(), 0);
+    }
+
+    #[test]
+    fn approve_and_reject_transitions() {
+        let mut proposals = Proposals::default();
+        let approved = proposal("smtp-in-connect", "a");
+        let rejected = proposal("smtp-in-connect", "b");
+        let (approved_id, rejected_id) = (approved.id.clone(), rejected.id.clone());
+        let approved_updates = approved.updates.clone();
+        proposals.propose(approved);
+        proposals.propose(rejected);
+
+        // Approving returns the updates to apply
+        assert_eq!(
+            proposals.approve(&approved_id, "bob"),
+            Some(approved_updates)
+        );
+        assert!(matches!(
+            proposals.get(&approved_id).unwrap().status,
+            ProposalStatus::Approved { .. }
+        ));
+        assert_eq!(proposals.approve(&approved_id, "bob"), None);
+
+        // Rejecting discards the updates
+        assert!(proposals.reject(&rejected_id, "bob"));
+        let rejected = proposals.get(&rejected_id).unwrap();
+        assert!(matches!(rejected.status, ProposalStatus::Rejected { .. }));
+        assert!(rejected.updates.is_empty());
+        assert_eq!(proposals.approve(&rejected_id, "bob"), None);
+
+        assert_eq!(proposals.pending("smtp-in-connect").count(), 0);
+    }
+
+    #[test]
+    fn authors_cannot_approve_own_proposals() {
+        let mut proposals = Proposals::default();
+        let proposal = proposal("smtp-in-connect", "a");
+        let id = proposal.id.clone();
+        assert!(!proposal.can_approve("alice"));
+        assert!(proposal.can_approve("bob"));
+        proposals.propose(proposal);
+
+        assert_eq!(proposals.approve(&id, "alice"), None);
+        assert!(proposals.get(&id).unwrap().is_pending());
+        assert!(proposals.approve(&id, "bob").is_some());
+    }
+}
@@ -0,0 +1,211 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::core::schema::{Source, Type};
+
+use super::{Schema, SchemaType};
+
+/// The parts of the stored configuration a settings page reads, so that
+/// only those are fetched instead of the whole configuration.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SettingsRanges {
+    /// Keys fetched by exact name.
+    pub keys: Vec<String>,
+    /// Prefixes whose keys are fetched together with all their subkeys.
+    pub prefixes: Vec<String>,
+    /// Records of other schemas (or of this one) referenced by select
+    /// fields, fetched as groups to populate their options.
+    pub shared: Vec<SharedGroup>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SharedGroup {
+    pub source_key: String,
+    pub prefix: &'static str,
+    pub suffix: &'static str,
+    pub field: &'static str,
+}
+
+impl Schema {
+    /// Returns the key ranges needed to edit the record `name` of this
+    /// schema. Records are stored under `<prefix>.<name>`, entries are a
+    /// single `<prefix>.<name>` key and list schemas read each field's key,
+    /// plus its subkeys for multi-value fields.
+    pub fn settings_ranges(&self, name: &str) -> SettingsRanges {
+        let mut ranges = SettingsRanges::default();
+
+        match self.typ {
+            SchemaType::Record { prefix, .. } => {
+                ranges.prefixes.push(format!("{prefix}.{name}"));
+            }
+            SchemaType::Entry { prefix } => {
+                ranges.keys.push(format!("{prefix}.{name}"));
+            }
+            SchemaType::List => {
+                for field in self.fields.values() {
+                    ranges.keys.push(field.id.to_string());
+                    if field.is_multivalue() {
+                        ranges.prefixes.push(field.id.to_string());
+                    }
+                }
+            }
+        }
+
+        for (schema, field) in self.external_sources() {
+            let schema = schema.as_deref().unwrap_or(self);
+            let source_key = format!("{}_{}", schema.id, field.id);
+            let (prefix, suffix) = match schema.typ {
+                SchemaType::Record { prefix, suffix } => (prefix, suffix),
+                SchemaType::Entry { prefix } => (prefix, ""),
+                SchemaType::List => continue,
+            };
+            if !ranges
+                .shared
+                .iter()
+                .any(|group| group.source_key == source_key)
+            {
+                ranges.shared.push(SharedGroup {
+                    source_key,
+                    prefix,
+                    suffix,
+                    field: field.id,
+                });
+            }
+        }
+
+        ranges.keys.sort();
+        ranges.prefixes.sort();
+        ranges
+    }
+
+    /// Returns the groups holding the labels of the select fields shown as
+    /// list columns, leaving out those only used by the form.
+    pub fn list_sources(&self) -> Vec<SharedGroup> {
+        let keys = self
+            .list
+            .fields
+            .iter()
+            .filter_map(|field| match &field.typ_ {
+                Type::Select {
+                    source: Source::Dynamic { schema, field, .. },
+                    ..
+                } => Some(format!("{}_{}", schema.id, field.id)),
+                Type::Select {
+                    source: Source::DynamicSelf { field, .. },
+                    ..
+                } => Some(format!("{}_{}", self.id, field.id)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        self.settings_ranges("")
+            .shared
+            .into_iter()
+            .filter(|group| keys.contains(&group.source_key))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::schema::{Schemas, SelectType, Source, Type, Value};
+
+    use super::*;
+
+    fn schemas() -> Schemas {
+        Schemas::builder()
+            .new_schema("store")
+            .prefix("store")
+            .suffix("type")
+            .new_id_field()
+            .build()
+            .new_field("type")
+            .build()
+            .build()
+            .new_schema("listener")
+            .prefix("server.listener")
+            .suffix("protocol")
+            .new_id_field()
+            .build()
+            .new_field("bind")
+            .typ(Type::Array)
+            .build()
+            .new_field("store")
+            .typ(Type::Select {
+                source: Source::Dynamic {
+                    schema: "store",
+                    field: "type",
+                    filter: Value::default(),
+                },
+                typ: SelectType::Single,
+            })
+            .build()
+            .new_field("fallback-store")
+            .typ(Type::Select {
+                source: Source::Dynamic {
+                    schema: "store",
+                    field: "type",
+                    filter: Value::default(),
+                },
+                typ: SelectType::Single,
+            })
+            .build()
+            .list_fields(["bind", "store"])
+            .build()
+            .new_schema("alias")
+            .prefix("alias")
+            .new_id_field()
+            .build()
+            .build()
+            .new_schema("settings")
+            .new_field("server.hostname")
+            .build()
+            .new_field("server.allowed-ip")
+            .typ(Type::Array)
+            .build()
+            .build()
+            .build()
+    }
+
+    #[test]
+    fn extract_ranges_from_schema() {
+        let schemas = schemas();
+
+        let listener = schemas.schemas["listener"].settings_ranges("smtp");
+        assert_eq!(listener.prefixes, vec!["server.listener.smtp"]);
+        assert!(listener.keys.is_empty());
+        assert_eq!(
+            listener.shared,
+            vec![SharedGroup {
+                source_key: "store_type".to_string(),
+                prefix: "store",
+                suffix: "type",
+                field: "type",
+            }]
+        );
+
+        let alias = schemas.schemas["alias"].settings_ranges("postmaster");
+        assert_eq!(alias.keys, vec!["alias.postmaster"]);
+        assert!(alias.prefixes.is_empty());
+        assert!(alias.shared.is_empty());
+
+        let settings = schemas.schemas["settings"].settings_ranges("");
+        assert_eq!(settings.keys, vec!["server.allowed-ip", "server.hostname"]);
+        assert_eq!(settings.prefixes, vec!["server.allowed-ip"]);
+    }
+
+    #[test]
+    fn list_sources_follow_columns() {
+        let schemas = schemas();
+
+        assert_eq!(
+            schemas.schemas["listener"].list_sources(),
+            schemas.schemas["listener"].settings_ranges("").shared
+        );
+        assert!(schemas.schemas["alias"].list_sources().is_empty());
+        assert!(schemas.schemas["store"].list_sources().is_empty());
+    }
+}
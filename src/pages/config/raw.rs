@@ -0,0 +1,678 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use leptos::*;
+
+use crate::{
+    components::{
+        form::{button::Button, FormSection},
+        icon::{IconAdd, IconTrash},
+        messages::alert::{use_alerts, Alert},
+        Color,
+    },
+    core::{form::FormData, oauth::use_authorization, Permissions},
+};
+
+use super::{flatten_value, key::SettingKey, Schema, SchemaType, Settings, UpdateSettings};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawValues {
+    pub data: FormData,
+    pub free_form: Vec<(String, String)>,
+    /// Keys that cannot be saved with this form, either because they are
+    /// outside the record being edited or because the user may not edit them.
+    pub rejected: Vec<String>,
+}
+
+impl FormData {
+    /// Returns the full setting keys and values this form would be saved as.
+    pub fn raw_values(&self) -> Vec<(String, String)> {
+        let mut key_values = Vec::new();
+
+        match &self.schema.typ {
+            SchemaType::Entry { prefix } => {
+                if let Some(value) = self.value_as_str("_value").filter(|v| !v.is_empty()) {
+                    key_values.push((
+                        format!("{prefix}.{}", self.value_as_str("_id").unwrap_or_default()),
+                        value.to_string(),
+                    ));
+                }
+            }
+            SchemaType::Record { .. } | SchemaType::List => {
+                let key_prefix = self.raw_key_prefix();
+                for (key, value) in &self.values {
+                    if self.schema.is_reserved_key(key) {
+                        continue;
+                    }
+                    if let Ok(key) = SettingKey::parse(key) {
+                        flatten_value(&key, value, &mut key_values);
+                    }
+                }
+                if let Some(key_prefix) = key_prefix {
+                    for (key, _) in &mut key_values {
+                        key.insert_str(0, &key_prefix);
+                    }
+                }
+            }
+        }
+
+        key_values.sort_unstable();
+        key_values
+    }
+
+    /// Maps raw key/value pairs back into a form. Pairs that do not belong
+    /// to any field of the schema are returned as free-form settings when
+    /// they can be saved with this form, and as rejected keys otherwise.
+    pub fn apply_raw_values(
+        &self,
+        pairs: &[(String, String)],
+        permissions: Option<&Permissions>,
+    ) -> RawValues {
+        let mut settings = Settings::new();
+        let mut free_form = Vec::new();
+        let mut rejected = Vec::new();
+
+        for (key, value) in pairs {
+            let key = key.trim();
+            if key.is_empty() {
+                continue;
+            }
+
+            match self.raw_key_to_field_key(key) {
+                Some(field_key) => {
+                    settings.insert(field_key, value.to_string());
+                }
+                None if self.is_allowed_free_form(key, permissions) => {
+                    free_form.push((key.to_string(), value.to_string()));
+                }
+                None => {
+                    rejected.push(key.to_string());
+                }
+            }
+        }
+
+        if let Some(id) = self.value_as_str("_id") {
+            settings.insert("_id".to_string(), id.to_string());
+        }
+
+        let mut data = FormData::from_settings(self.schema.clone(), Some(settings))
+            .with_external_sources(self.external_sources.clone());
+        data.is_update = self.is_update;
+        // Keep the loaded elements as the baseline for partial array updates
+        data.stored_arrays = self.stored_arrays.clone();
+
+        RawValues {
+            data,
+            free_form,
+            rejected,
+        }
+    }
+
+    pub fn is_known_raw_key(&self, key: &str) -> bool {
+        self.raw_key_to_field_key(key.trim()).is_some()
+    }
+
+    /// Whether a key that no field manages can be saved with this form. Only
+    /// records hold free-form settings, under `<prefix>.<id>.`, and keys
+    /// nested under a field follow the same permission checks as
+    /// `build_update_for`.
+    pub fn is_allowed_free_form(&self, key: &str, permissions: Option<&Permissions>) -> bool {
+        let Some(rest) = self
+            .raw_key_prefix()
+            .and_then(|prefix| key.strip_prefix(&prefix).map(str::to_string))
+        else {
+            return false;
+        };
+
+        !rest.is_empty()
+            && !self.schema.is_reserved_key(&rest)
+            && SettingKey::parse(key).is_ok()
+            && permissions.is_none_or(|permissions| {
+                self.schema
+                    .fields
+                    .values()
+                    .filter(|field| is_under(&rest, field.id))
+                    .all(|field| field.can_edit(permissions))
+            })
+    }
+
+    fn raw_key_prefix(&self) -> Option<String> {
+        match &self.schema.typ {
+            SchemaType::Record { prefix, .. } => Some(format!(
+                "{prefix}.{}.",
+                self.value_as_str("_id").unwrap_or_default()
+            )),
+            SchemaType::Entry { .. } | SchemaType::List => None,
+        }
+    }
+
+    fn raw_key_to_field_key(&self, key: &str) -> Option<String> {
+        if let SchemaType::Entry { prefix } = &self.schema.typ {
+            let id = self.value_as_str("_id").unwrap_or_default();
+            return (key.strip_prefix(*prefix)?.strip_prefix('.')? == id)
+                .then(|| "_value".to_string());
+        }
+
+        let key = match self.raw_key_prefix() {
+            Some(prefix) => key.strip_prefix(&prefix)?,
+            None => key,
+        };
+        is_field_key(&self.schema, key).then(|| key.to_string())
+    }
+}
+
+fn is_field_key(schema: &Schema, key: &str) -> bool {
+    schema
+        .fields
+        .keys()
+        .any(|id| !schema.is_reserved_key(id) && is_under(key, id))
+}
+
+/// Whether `key` is `id` or one of its subkeys.
+fn is_under(key: &str, id: &str) -> bool {
+    key.strip_prefix(id)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// Returns the settings stored under a record that no field of its schema
+/// covers, with their full keys. These are kept as free-form settings so
+/// that saving the form, which clears the record first, does not drop them.
+pub fn unmanaged_settings(schema: &Schema, settings: &Settings) -> Vec<(String, String)> {
+    let (prefix, id) = match (&schema.typ, settings.get("_id")) {
+        (SchemaType::Record { prefix, .. }, Some(id)) => (prefix, id),
+        (SchemaType::Record { .. }, None) => return vec![],
+        // Entries are a single key and lists are fetched field by field, so
+        // neither loads settings that no field covers
+        (SchemaType::Entry { .. } | SchemaType::List, _) => return vec![],
+    };
+
+    let mut unmanaged = settings
+        .iter()
+        .filter(|(key, _)| !schema.is_reserved_key(key) && !is_field_key(schema, key))
+        .map(|(key, value)| (format!("{prefix}.{id}.{key}"), value.to_string()))
+        .collect::<Vec<_>>();
+    unmanaged.sort_unstable();
+    unmanaged
+}
+
+/// Appends the free-form settings to the updates built from the form, so
+/// they are inserted after any clear of the record. Settings that `data`
+/// does not allow as free-form, such as keys of other records, are left out.
+pub fn with_free_form(
+    data: &FormData,
+    mut updates: Vec<UpdateSettings>,
+    mut free_form: Vec<(String, String)>,
+    permissions: Option<&Permissions>,
+) -> Vec<UpdateSettings> {
+    free_form.retain(|(key, _)| {
+        let is_allowed = data.is_allowed_free_form(key, permissions);
+        if !is_allowed {
+            log::warn!(
+                "Not saving free-form setting {key:?} outside of {:?}",
+                data.schema.id
+            );
+        }
+        is_allowed
+    });
+    if !free_form.is_empty() {
+        updates.push(UpdateSettings::Insert {
+            prefix: None,
+            values: free_form,
+            assert_empty: false,
+        });
+    }
+    updates
+}
+
+#[component]
+pub fn RawSettings(
+    data: RwSignal<FormData>,
+    free_form: RwSignal<Vec<(String, String)>>,
+) -> impl IntoView {
+    let alert = use_alerts();
+    let auth = use_authorization();
+    let next_id = store_value(0usize);
+    let rows = create_rw_signal(Vec::<(usize, String, String)>::new());
+    let add_rows = move |pairs: Vec<(String, String)>| {
+        rows.update(|rows| {
+            for (key, value) in pairs {
+                next_id.update_value(|id| *id += 1);
+                rows.push((next_id.get_value(), key, value));
+            }
+        });
+    };
+    add_rows(
+        data.get_untracked()
+            .raw_values()
+            .into_iter()
+            .chain(free_form.get_untracked())
+            .collect(),
+    );
+
+    view! {
+        <div class="flex flex-col">
+            <table class="min-w-full divide-y divide-gray-200 dark:divide-gray-700">
+                <thead>
+                    <tr>
+                        <th class="px-3 py-2 text-start text-xs font-semibold uppercase text-gray-800 dark:text-gray-200">
+                            "Key"
+                        </th>
+                        <th class="px-3 py-2 text-start text-xs font-semibold uppercase text-gray-800 dark:text-gray-200">
+                            "Value"
+                        </th>
+                        <th></th>
+                    </tr>
+                </thead>
+                <tbody class="divide-y divide-gray-200 dark:divide-gray-700">
+                    <For
+                        each=move || rows.get()
+                        key=|(id, _, _)| *id
+                        children=move |(id, _, _)| {
+                            let get = move |value: fn(&(usize, String, String)) -> String| {
+                                rows.with(|rows| {
+                                    rows.iter().find(|row| row.0 == id).map(value).unwrap_or_default()
+                                })
+                            };
+                            let set = move |key: Option<String>, value: Option<String>| {
+                                rows.update(|rows| {
+                                    if let Some(row) = rows.iter_mut().find(|row| row.0 == id) {
+                                        if let Some(key) = key {
+                                            row.1 = key;
+                                        }
+                                        if let Some(value) = value {
+                                            row.2 = value;
+                                        }
+                                    }
+                                });
+                            };
+                            let is_free_form = move || {
+                                let key = get(|row| row.1.clone());
+                                !key.is_empty() && !data.with(|data| data.is_known_raw_key(&key))
+                            };
+
+                            view! {
+                                <tr>
+                                    <td class="px-3 py-2">
+                                        <input
+                                            type="text"
+                                            class="py-2 px-3 block w-full border-gray-200 rounded-lg text-sm font-mono focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                                            prop:value=move || get(|row| row.1.clone())
+                                            on:input=move |ev| set(Some(event_target_value(&ev)), None)
+                                        />
+                                        <p class="mt-1 text-xs text-yellow-600" class:hidden=move || !is_free_form()>
+                                            "Free-form key not managed by this form"
+                                        </p>
+                                    </td>
+                                    <td class="px-3 py-2">
+                                        <input
+                                            type="text"
+                                            class="py-2 px-3 block w-full border-gray-200 rounded-lg text-sm font-mono focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                                            prop:value=move || get(|row| row.2.clone())
+                                            on:input=move |ev| set(None, Some(event_target_value(&ev)))
+                                        />
+                                    </td>
+                                    <td class="px-3 py-2 text-end">
+                                        <button
+                                            type="button"
+                                            class="inline-flex items-center text-gray-500 hover:text-red-600"
+                                            on:click=move |_| {
+                                                rows.update(|rows| rows.retain(|row| row.0 != id));
+                                            }
+                                        >
+                                            <IconTrash attr:class="flex-shrink-0 size-4"/>
+                                        </button>
+                                    </td>
+                                </tr>
+                            }
+                        }
+                    />
+
+                </tbody>
+            </table>
+            <div class="mt-3 flex justify-end gap-x-2">
+                <Button
+                    text="Add setting"
+                    color=Color::Gray
+                    on_click=move |_| add_rows(vec![(String::new(), String::new())])
+                >
+                    <IconAdd attr:class="flex-shrink-0 size-4"/>
+                </Button>
+                <Button
+                    text="Apply to form"
+                    color=Color::Blue
+                    on_click=move |_| {
+                        let pairs = rows
+                            .get()
+                            .into_iter()
+                            .map(|(_, key, value)| (key, value))
+                            .collect::<Vec<_>>();
+                        let result = data
+                            .get()
+                            .apply_raw_values(&pairs, Some(auth.get().permissions()));
+                        data.set(result.data);
+                        if !result.rejected.is_empty() {
+                            alert
+                                .set(
+                                    Alert::error("Settings outside this record were discarded")
+                                        .with_details_list(result.rejected),
+                                );
+                        } else if !result.free_form.is_empty() {
+                            alert
+                                .set(
+                                    Alert::warning("Free-form settings")
+                                        .with_details_list(
+                                            result.free_form.iter().map(|(key, _)| key.clone()),
+                                        ),
+                                );
+                        }
+                        free_form.set(result.free_form);
+                    }
+                />
+
+            </div>
+        </div>
+    }
+}
+
+#[component]
+pub fn UnmanagedSettings(free_form: RwSignal<Vec<(String, String)>>) -> impl IntoView {
+    view! {
+        <FormSection title="Unmanaged settings".to_string()>
+            <div class="sm:col-span-12 space-y-3">
+                <p class="text-sm text-gray-600 dark:text-gray-400">
+                    "These settings are stored with this record but are not managed by any field of this form. They are kept when saving unless removed here."
+                </p>
+                <For
+                    each=move || free_form.get()
+                    key=|(key, _)| key.clone()
+                    children=move |(key, value)| {
+                        let key = store_value(key);
+                        view! {
+                            <div class="flex items-center gap-x-2">
+                                <span class="w-1/2 text-sm font-mono text-gray-800 break-all dark:text-gray-200">
+                                    {key.get_value()}
+                                </span>
+                                <input
+                                    type="text"
+                                    class="py-2 px-3 block w-full border-gray-200 rounded-lg text-sm font-mono focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                                    prop:value=value
+                                    on:change=move |ev| {
+                                        let value = event_target_value(&ev);
+                                        free_form
+                                            .update(|free_form| {
+                                                if let Some(item) = free_form
+                                                    .iter_mut()
+                                                    .find(|(k, _)| *k == key.get_value())
+                                                {
+                                                    item.1 = value;
+                                                }
+                                            });
+                                    }
+                                />
+
+                                <button
+                                    type="button"
+                                    class="inline-flex items-center text-gray-500 hover:text-red-600"
+                                    title="Remove setting"
+                                    on:click=move |_| {
+                                        free_form
+                                            .update(|free_form| {
+                                                free_form.retain(|(k, _)| *k != key.get_value());
+                                            });
+                                    }
+                                >
+
+                                    <IconTrash attr:class="flex-shrink-0 size-4"/>
+                                </button>
+                            </div>
+                        }
+                    }
+                />
+
+            </div>
+        </FormSection>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        schema::{Schemas, Type},
+        Permission,
+    };
+
+    fn schemas() -> Schemas {
+        Schemas::builder()
+            .new_schema("record")
+            .prefix("queue.throttle")
+            .suffix("enable")
+            .new_id_field()
+            .build()
+            .new_field("enable")
+            .typ(Type::Boolean)
+            .build()
+            .new_field("key")
+            .typ(Type::Array)
+            .build()
+            .build()
+            .build()
+    }
+
+    fn pairs(values: &[(&str, &str)]) -> Vec<(String, String)> {
+        values
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn render_raw_pairs() {
+        let data = schemas()
+            .build_form("record")
+            .with_value("_id", "rcpt")
+            .with_value("enable", "true")
+            .with_value("key", ["sender", "rcpt"].as_slice());
+
+        assert_eq!(
+            data.raw_values(),
+            pairs(&[
+                ("queue.throttle.rcpt.enable", "true"),
+                ("queue.throttle.rcpt.key.0", "sender"),
+                ("queue.throttle.rcpt.key.1", "rcpt"),
+            ])
+        );
+    }
+
+    #[test]
+    fn round_trip_raw_edits() {
+        let mut data = schemas()
+            .build_form("record")
+            .with_value("_id", "rcpt")
+            .with_value("enable", "true");
+        data.is_update = true;
+
+        let mut raw = data.raw_values();
+        raw[0].1 = "false".to_string();
+        raw.extend(pairs(&[
+            ("queue.throttle.rcpt.key.0", "sender"),
+            ("queue.throttle.rcpt.rate", "5/1s"),
+            ("queue.other", "x"),
+        ]));
+
+        let result = data.apply_raw_values(&raw, None);
+        assert!(result.data.is_update);
+        assert_eq!(result.data.value_as_str("_id"), Some("rcpt"));
+        assert_eq!(result.data.value_as_str("enable"), Some("false"));
+        assert_eq!(
+            result.data.array_value("key").collect::<Vec<_>>(),
+            vec!["sender"]
+        );
+        assert_eq!(
+            result.free_form,
+            pairs(&[("queue.throttle.rcpt.rate", "5/1s")])
+        );
+        assert_eq!(result.rejected, vec!["queue.other"]);
+        assert_eq!(
+            result.data.raw_values(),
+            pairs(&[
+                ("queue.throttle.rcpt.enable", "false"),
+                ("queue.throttle.rcpt.key", "sender"),
+            ])
+        );
+    }
+
+    #[test]
+    fn reject_raw_keys_outside_record() {
+        let data = schemas()
+            .build_form("record")
+            .with_value("_id", "rcpt")
+            .with_value("enable", "true");
+
+        let result = data.apply_raw_values(
+            &pairs(&[
+                ("queue.throttle.rcpt.rate", "5/1s"),
+                ("queue.throttle.other.rate", "1/1s"),
+                ("queue.throttle.rcpt._meta", "x"),
+                ("queue.throttle.rcpt.bad key", "x"),
+                ("authentication.fallback-admin.secret", "x"),
+            ]),
+            None,
+        );
+        assert_eq!(
+            result.free_form,
+            pairs(&[("queue.throttle.rcpt.rate", "5/1s")])
+        );
+        assert_eq!(
+            result.rejected,
+            vec![
+                "queue.throttle.other.rate",
+                "queue.throttle.rcpt._meta",
+                "queue.throttle.rcpt.bad key",
+                "authentication.fallback-admin.secret",
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_raw_keys_of_restricted_fields() {
+        let schemas = Schemas::builder()
+            .new_schema("record")
+            .prefix("queue.throttle")
+            .suffix("key")
+            .new_id_field()
+            .build()
+            .new_field("key")
+            .typ(Type::Array)
+            .required_permission(Permission::SettingsDelete)
+            .build()
+            .build()
+            .build();
+        let data = schemas.build_form("record").with_value("_id", "rcpt");
+        let permissions = Permissions::default();
+
+        // Keys nested under a field the user cannot edit are never free-form
+        assert!(!data.is_allowed_free_form("queue.throttle.rcpt.key.0", Some(&permissions)));
+        assert!(data.is_allowed_free_form("queue.throttle.rcpt.key.0", None));
+        assert!(data.is_allowed_free_form("queue.throttle.rcpt.rate", Some(&permissions)));
+    }
+
+    fn record_settings() -> Settings {
+        pairs(&[
+            ("_id", "rcpt"),
+            ("enable", "true"),
+            ("key.0", "sender"),
+            ("key.1", "rcpt"),
+            ("rate", "5/1s"),
+            ("legacy.concurrency", "10"),
+        ])
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn detect_unmanaged_settings() {
+        let schemas = schemas();
+        assert_eq!(
+            unmanaged_settings(&schemas.get("record"), &record_settings()),
+            pairs(&[
+                ("queue.throttle.rcpt.legacy.concurrency", "10"),
+                ("queue.throttle.rcpt.rate", "5/1s"),
+            ])
+        );
+
+        let mut settings = record_settings();
+        settings.retain(|key, _| !matches!(key.as_str(), "rate" | "legacy.concurrency"));
+        assert!(unmanaged_settings(&schemas.get("record"), &settings).is_empty());
+    }
+
+    #[test]
+    fn preserve_unmanaged_settings_on_save() {
+        let schema = schemas().get("record");
+        let settings = record_settings();
+        let free_form = unmanaged_settings(&schema, &settings);
+        let mut data = FormData::from_settings(schema, Some(settings));
+        data.is_update = true;
+
+        let updates = with_free_form(&data, data.build_update().unwrap(), free_form.clone(), None);
+        assert_eq!(
+            updates.first(),
+            Some(&UpdateSettings::Clear {
+                prefix: "queue.throttle.rcpt.".to_string(),
+                filter: None,
+            })
+        );
+        assert_eq!(
+            updates.last(),
+            Some(&UpdateSettings::Insert {
+                prefix: None,
+                values: free_form,
+                assert_empty: false,
+            })
+        );
+        assert_eq!(with_free_form(&data, vec![], vec![], None), vec![]);
+
+        // Keys of other records are never written through the free-form list
+        assert_eq!(
+            with_free_form(
+                &data,
+                vec![],
+                pairs(&[
+                    ("queue.throttle.other.rate", "1/1s"),
+                    ("server.hostname", "x")
+                ]),
+                None,
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn unmanaged_settings_need_a_record() {
+        let record = schemas().get("record");
+        let schemas = Schemas::builder()
+            .new_schema("entry")
+            .prefix("queue.quota")
+            .new_id_field()
+            .build()
+            .new_value_field()
+            .build()
+            .build()
+            .new_schema("list")
+            .new_field("server.hostname")
+            .build()
+            .build()
+            .build();
+        let settings = pairs(&[("_id", "rcpt"), ("_value", "10"), ("other", "x")])
+            .into_iter()
+            .collect::<Settings>();
+
+        assert!(unmanaged_settings(&schemas.get("entry"), &settings).is_empty());
+        assert!(unmanaged_settings(&schemas.get("list"), &settings).is_empty());
+        let without_id = pairs(&[("rate", "1/1s")]).into_iter().collect::<Settings>();
+        assert!(unmanaged_settings(&record, &without_id).is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{Schema, SchemaType, Settings};
+
+/// A setting outside a record that refers to it by id.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordReference {
+    pub id: String,
+    pub key: String,
+}
+
+/// Scans the settings for values referring to any of the records about to
+/// be deleted. A value refers to a record when it is the record id itself
+/// or contains it quoted, as expressions do. Keys belonging to the deleted
+/// records are skipped. Results are sorted by key.
+pub fn find_references(settings: &Settings, schema: &Schema, ids: &[&str]) -> Vec<RecordReference> {
+    let prefix = match schema.typ {
+        SchemaType::Record { prefix, .. } | SchemaType::Entry { prefix } => prefix,
+        SchemaType::List => return Vec::new(),
+    };
+    let owned_keys = ids
+        .iter()
+        .map(|id| format!("{prefix}.{id}"))
+        .collect::<Vec<_>>();
+
+    let mut references = settings
+        .iter()
+        .filter(|(key, _)| {
+            !owned_keys.iter().any(|owned| {
+                key.strip_prefix(owned.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+            })
+        })
+        .filter_map(|(key, value)| {
+            let value = value.trim();
+            ids.iter()
+                .find(|id| {
+                    value == **id
+                        || value.contains(&format!("'{id}'"))
+                        || value.contains(&format!("\"{id}\""))
+                })
+                .map(|id| RecordReference {
+                    id: id.to_string(),
+                    key: key.clone(),
+                })
+        })
+        .collect::<Vec<_>>();
+    references.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+    references
+}
+
+/// Returns the ids of all stored records of a schema, used when every
+/// record is selected for deletion.
+pub fn record_ids<'x>(settings: &'x Settings, schema: &Schema) -> Vec<&'x str> {
+    let mut ids = settings
+        .keys()
+        .filter_map(|key| match schema.typ {
+            SchemaType::Record { prefix, suffix } => key
+                .strip_prefix(prefix)?
+                .strip_prefix('.')?
+                .strip_suffix(suffix)?
+                .strip_suffix('.'),
+            SchemaType::Entry { prefix } => key.strip_prefix(prefix)?.strip_prefix('.'),
+            SchemaType::List => None,
+        })
+        .filter(|id| !id.is_empty())
+        .collect::<Vec<_>>();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// Describes the references found, or `None` when the delete is clean.
+pub fn reference_warning(references: &[RecordReference]) -> Option<String> {
+    if references.is_empty() {
+        return None;
+    }
+
+    let locations = references
+        .iter()
+        .map(|reference| format!("{} (references {:?})", reference.key, reference.id))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(format!(
+        "The following settings still reference the selected items and will be left dangling: {locations}."
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_schemas;
+
+    fn settings(values: &[(&str, &str)]) -> Settings {
+        values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn detect_references_to_deleted_record() {
+        let schemas = build_schemas();
+        let store = &schemas.schemas["store"];
+        let settings = settings(&[
+            ("store.rocksdb.type", "rocksdb"),
+            ("store.rocksdb.path", "/opt/stalwart/data"),
+            ("store.sqlite.type", "sqlite"),
+            ("directory.internal.type", "internal"),
+            ("directory.internal.store", "rocksdb"),
+            ("storage.data", " rocksdb "),
+            ("storage.blob", "sqlite"),
+            ("queue.strategy.route.0.then", "'rocksdb'"),
+            ("spam-filter.lookup", "rocksdb-backup"),
+        ]);
+
+        let references = find_references(&settings, store, &["rocksdb"]);
+        assert_eq!(
+            references
+                .iter()
+                .map(|reference| reference.key.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "directory.internal.store",
+                "queue.strategy.route.0.then",
+                "storage.data"
+            ]
+        );
+        assert!(references.iter().all(|reference| reference.id == "rocksdb"));
+        assert_eq!(
+            reference_warning(&references[..1]).unwrap(),
+            "The following settings still reference the selected items and will be left dangling: directory.internal.store (references \"rocksdb\")."
+        );
+    }
+
+    #[test]
+    fn clean_delete_without_references() {
+        let schemas = build_schemas();
+        let store = &schemas.schemas["store"];
+        let settings = settings(&[
+            ("store.rocksdb.type", "rocksdb"),
+            ("store.unused.type", "sqlite"),
+            ("store.unused.path", "unused"),
+            ("storage.data", "rocksdb"),
+        ]);
+
+        assert_eq!(record_ids(&settings, store), vec!["rocksdb", "unused"]);
+
+        let references = find_references(&settings, store, &["unused"]);
+        assert!(references.is_empty());
+        assert_eq!(reference_warning(&references), None);
+    }
+}
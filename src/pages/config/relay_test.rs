@@ -0,0 +1,175 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::messages::alert::Alert,
+    core::{
+        http::{self, HttpRequest},
+        AccessToken,
+    },
+};
+
+pub const RELAY_HOST_SCHEMA: &str = "smtp-out-remote";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelayTestResponse {
+    pub status: String,
+    #[serde(default)]
+    pub code: Option<u16>,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub banner: Option<String>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    #[serde(default)]
+    pub tls: bool,
+    #[serde(default)]
+    pub elapsed: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayTestOutcome {
+    Success,
+    Dns,
+    Connection,
+    Tls,
+    Auth,
+    Rejected,
+    Unknown,
+}
+
+impl RelayTestOutcome {
+    /// Maps the status and SMTP reply code returned by the backend to the
+    /// stage of the session that failed.
+    pub fn classify(status: &str, code: Option<u16>) -> Self {
+        let (stage, _) = status.split_once('.').unwrap_or((status, ""));
+        match stage {
+            "ok" => RelayTestOutcome::Success,
+            "dns" => RelayTestOutcome::Dns,
+            "connect" | "timeout" | "io" => RelayTestOutcome::Connection,
+            "tls" | "starttls" => RelayTestOutcome::Tls,
+            "auth" => RelayTestOutcome::Auth,
+            _ => match code {
+                Some(530 | 534 | 535 | 538 | 454) => RelayTestOutcome::Auth,
+                Some(400..=599) => RelayTestOutcome::Rejected,
+                _ => RelayTestOutcome::Unknown,
+            },
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            RelayTestOutcome::Success => "Relay host is reachable",
+            RelayTestOutcome::Dns => "Failed to resolve the relay host",
+            RelayTestOutcome::Connection => "Failed to connect to the relay host",
+            RelayTestOutcome::Tls => "TLS negotiation with the relay host failed",
+            RelayTestOutcome::Auth => "Authentication with the relay host failed",
+            RelayTestOutcome::Rejected => "The relay host rejected the session",
+            RelayTestOutcome::Unknown => "Relay test failed",
+        }
+    }
+}
+
+impl RelayTestResponse {
+    pub fn outcome(&self) -> RelayTestOutcome {
+        RelayTestOutcome::classify(&self.status, self.code)
+    }
+
+    pub fn details(&self) -> Vec<String> {
+        let mut details = Vec::new();
+        if let Some(message) = &self.message {
+            details.push(match self.code {
+                Some(code) => format!("{code} {message}"),
+                None => message.clone(),
+            });
+        }
+        if let Some(banner) = &self.banner {
+            details.push(format!("Banner: {banner}"));
+        }
+        if !self.capabilities.is_empty() {
+            details.push(format!("EHLO: {}", self.capabilities.join(", ")));
+        }
+        if self.outcome() == RelayTestOutcome::Success {
+            details.push(format!(
+                "{} in {} ms",
+                if self.tls {
+                    "Connected over TLS"
+                } else {
+                    "Connected without TLS"
+                },
+                self.elapsed
+            ));
+        }
+        details
+    }
+}
+
+impl From<RelayTestResponse> for Alert {
+    fn from(response: RelayTestResponse) -> Self {
+        let outcome = response.outcome();
+        let alert = if outcome == RelayTestOutcome::Success {
+            Alert::success(outcome.title())
+        } else {
+            Alert::error(outcome.title()).without_timeout()
+        };
+        alert.with_details_list(response.details())
+    }
+}
+
+/// Asks the server to open an SMTP session with a configured relay host,
+/// reading its banner, sending EHLO and, when configured, upgrading the
+/// connection with STARTTLS and authenticating.
+pub async fn test_relay(auth: &AccessToken, id: &str) -> http::Result<RelayTestResponse> {
+    HttpRequest::post(("/api/troubleshoot/relay", id))
+        .with_authorization(auth)
+        .send::<RelayTestResponse>()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_relay_test_results() {
+        for (status, code, expected) in [
+            ("ok", Some(250), RelayTestOutcome::Success),
+            ("dns.nxdomain", None, RelayTestOutcome::Dns),
+            ("dns", None, RelayTestOutcome::Dns),
+            ("connect.refused", None, RelayTestOutcome::Connection),
+            ("timeout", None, RelayTestOutcome::Connection),
+            ("tls.handshake", None, RelayTestOutcome::Tls),
+            ("starttls.unsupported", Some(502), RelayTestOutcome::Tls),
+            ("auth.failed", Some(535), RelayTestOutcome::Auth),
+            ("smtp.reply", Some(535), RelayTestOutcome::Auth),
+            ("smtp.reply", Some(454), RelayTestOutcome::Auth),
+            ("smtp.reply", Some(421), RelayTestOutcome::Rejected),
+            ("smtp.reply", Some(554), RelayTestOutcome::Rejected),
+            ("smtp.reply", None, RelayTestOutcome::Unknown),
+            ("", None, RelayTestOutcome::Unknown),
+        ] {
+            assert_eq!(
+                RelayTestOutcome::classify(status, code),
+                expected,
+                "{status} {code:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_relay_test_response() {
+        let response: RelayTestResponse = serde_json::from_str(
+            r#"{"status":"auth.failed","code":535,"message":"5.7.8 Invalid credentials"}"#,
+        )
+        .unwrap();
+        assert_eq!(response.outcome(), RelayTestOutcome::Auth);
+        assert_eq!(response.details(), vec!["535 5.7.8 Invalid credentials"]);
+    }
+}
@@ -0,0 +1,765 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use leptos::*;
+use leptos_meta::Style;
+use leptos_router::{use_navigate, use_query_map};
+
+use crate::{
+    components::{
+        form::button::Button,
+        icon::IconDocumentText,
+        messages::{
+            load_error::{LoadError, Reload},
+            modal::{use_modals, Modal},
+        },
+        report::{ReportItem, ReportSection, ReportTextValue, ReportView},
+        skeleton::Skeleton,
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        schema_json::schemas_to_json,
+        url::UrlBuilder,
+        AccessToken,
+    },
+    pages::config::edit::{fetch_current_settings, FetchSettings},
+    utils::audit::{log_audit, AuditAction},
+};
+
+use super::{redact_secrets, Schema, SchemaType, Schemas, Settings, SettingsValues};
+
+const PRINT_STYLE: &str = r#"
+@media print {
+    body * { visibility: hidden; }
+    #config-report, #config-report * { visibility: visible; }
+    #config-report { position: absolute; left: 0; top: 0; width: 100%; }
+}
+"#;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigReport {
+    pub generated: DateTime<Utc>,
+    pub sections: Vec<ConfigReportSection>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigReportSection {
+    pub title: String,
+    pub rows: Vec<ConfigReportRow>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigReportRow {
+    pub label: String,
+    pub value: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl ConfigReport {
+    pub fn new(generated: DateTime<Utc>) -> Self {
+        ConfigReport {
+            generated,
+            sections: Vec::new(),
+        }
+    }
+
+    /// Adds one section per form section and record of `schema`, skipping
+    /// settings that are not set. Secret values are never included.
+    pub fn add_schema(&mut self, schema: &Schema, records: &[Settings]) {
+        for settings in records {
+            let settings = redact_secrets(settings, schema);
+            let record_title = match schema.typ {
+                SchemaType::Record { .. } | SchemaType::Entry { .. } => format!(
+                    "{} ({})",
+                    schema.form.title,
+                    settings.get("_id").map(|s| s.as_str()).unwrap_or_default()
+                ),
+                SchemaType::List => schema.form.title.to_string(),
+            };
+
+            for section in &schema.form.sections {
+                let rows = section
+                    .fields
+                    .iter()
+                    .filter(|field| {
+                        (!schema.is_reserved_key(field.id) || field.id == "_value")
+                            && !settings.array_values(field.id).is_empty()
+                    })
+                    .map(|field| ConfigReportRow {
+                        label: field.label_form.to_string(),
+                        value: settings.format(field),
+                    })
+                    .collect::<Vec<_>>();
+
+                if !rows.is_empty() {
+                    self.sections.push(ConfigReportSection {
+                        title: match section.title {
+                            Some(title) if !title.is_empty() => {
+                                format!("{record_title} › {title}")
+                            }
+                            _ => record_title.clone(),
+                        },
+                        rows,
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Csv => "text/csv",
+        }
+    }
+}
+
+/// Serializes the settings of each schema, redacting secret values unless
+/// `include_secrets` is explicitly set.
+pub fn export_settings(
+    records: &[(Arc<Schema>, Vec<Settings>)],
+    format: ExportFormat,
+    include_secrets: bool,
+) -> String {
+    let exported = records
+        .iter()
+        .map(|(schema, records)| {
+            (
+                schema.id,
+                records
+                    .iter()
+                    .map(|settings| {
+                        if include_secrets {
+                            settings.clone()
+                        } else {
+                            redact_secrets(settings, schema)
+                        }
+                        .into_iter()
+                        .collect::<BTreeMap<_, _>>()
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&exported).unwrap_or_default(),
+        ExportFormat::Csv => {
+            let mut csv = String::from("schema,id,key,value\n");
+            for (schema_id, records) in exported {
+                for settings in records {
+                    let id = settings.get("_id").cloned().unwrap_or_default();
+                    for (key, value) in settings.iter().filter(|(key, _)| *key != "_id") {
+                        csv.push_str(&format!(
+                            "{},{},{},{}\n",
+                            csv_escape(schema_id),
+                            csv_escape(&id),
+                            csv_escape(key),
+                            csv_escape(value)
+                        ));
+                    }
+                }
+            }
+            csv
+        }
+    }
+}
+
+/// Settings per schema as read back from an export, in the same shape
+/// `export_settings` writes them.
+pub type ImportedSettings = BTreeMap<String, Vec<Settings>>;
+
+/// Parses the output of `export_settings`. Records without an id, such as
+/// those of list schemas, are exported with an empty id column in CSV.
+pub fn import_settings(contents: &str, format: ExportFormat) -> Result<ImportedSettings, String> {
+    match format {
+        ExportFormat::Json => serde_json::from_str::<BTreeMap<String, Vec<Settings>>>(contents)
+            .map_err(|err| format!("Invalid JSON export: {err}")),
+        ExportFormat::Csv => {
+            let mut rows = csv_rows(contents)?.into_iter();
+            if rows
+                .next()
+                .is_none_or(|header| header != ["schema", "id", "key", "value"])
+            {
+                return Err("Invalid CSV export: missing header".to_string());
+            }
+
+            let mut imported = ImportedSettings::new();
+            for (line, row) in rows.enumerate() {
+                let [schema_id, id, key, value]: [String; 4] = row.try_into().map_err(|_| {
+                    format!(
+                        "Invalid CSV export: row {} has wrong column count",
+                        line + 2
+                    )
+                })?;
+                let records = imported.entry(schema_id).or_default();
+                let record = match records
+                    .iter()
+                    .position(|record| record.get("_id").map_or(id.is_empty(), |rid| *rid == id))
+                {
+                    Some(pos) => &mut records[pos],
+                    None => {
+                        let mut record = Settings::new();
+                        if !id.is_empty() {
+                            record.insert("_id".to_string(), id);
+                        }
+                        records.push(record);
+                        records.last_mut().unwrap()
+                    }
+                };
+                record.insert(key, value);
+            }
+            Ok(imported)
+        }
+    }
+}
+
+fn csv_rows(contents: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if in_quotes => {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            }
+            '"' if field.is_empty() => in_quotes = true,
+            ',' if !in_quotes => row.push(std::mem::take(&mut field)),
+            '\n' if !in_quotes => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            ch => field.push(ch),
+        }
+    }
+
+    if in_quotes {
+        return Err("Invalid CSV export: unterminated quoted value".to_string());
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn download(filename: &str, mime_type: &str, contents: &str) {
+    let url = format!(
+        "data:{mime_type};charset=utf-8,{}",
+        String::from(js_sys::encode_uri_component(contents))
+    );
+    let link = leptos::html::a();
+    link.set_href(&url);
+    link.set_download(filename);
+    link.click();
+}
+
+/// Splits the settings stored under a schema prefix into one settings map
+/// per record, keyed by field identifier.
+pub fn split_records(schema: &Schema, settings: Settings) -> Vec<Settings> {
+    let mut records = match schema.typ {
+        SchemaType::Record { suffix, .. } => {
+            let suffix = format!(".{suffix}");
+            let ids = settings
+                .keys()
+                .filter_map(|key| key.strip_suffix(&suffix))
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>();
+            let mut records = ids
+                .iter()
+                .map(|id| Settings::from_iter([("_id".to_string(), id.clone())]))
+                .collect::<Vec<_>>();
+
+            for (key, value) in settings {
+                // Use the longest matching id in case ids contain dots
+                if let Some((pos, field)) = ids
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(pos, id)| {
+                        key.strip_prefix(id.as_str())?
+                            .strip_prefix('.')
+                            .map(|field| (pos, id.len(), field))
+                    })
+                    .max_by_key(|(_, len, _)| *len)
+                    .map(|(pos, _, field)| (pos, field.to_string()))
+                {
+                    records[pos].insert(field, value);
+                }
+            }
+
+            records
+        }
+        SchemaType::Entry { .. } => settings
+            .into_iter()
+            .map(|(id, value)| {
+                Settings::from_iter([("_id".to_string(), id), ("_value".to_string(), value)])
+            })
+            .collect(),
+        SchemaType::List => vec![settings],
+    };
+
+    records.sort_by(|a, b| a.get("_id").cmp(&b.get("_id")));
+    records
+}
+
+async fn fetch_records(auth: &AccessToken, schema: &Schema) -> http::Result<Vec<Settings>> {
+    match schema.typ {
+        SchemaType::Record { prefix, .. } | SchemaType::Entry { prefix } => {
+            HttpRequest::get("/api/settings/list")
+                .with_authorization(auth)
+                .with_parameter("prefix", prefix)
+                .send::<FetchSettings>()
+                .await
+                .map(|list| split_records(schema, list.items))
+        }
+        SchemaType::List => fetch_current_settings(auth, schema, "")
+            .await
+            .map(|settings| settings.into_iter().collect()),
+    }
+}
+
+#[component]
+pub fn SettingsReport() -> impl IntoView {
+    let auth = use_authorization();
+    let modal = use_modals();
+    let query = use_query_map();
+    let schemas = expect_context::<Arc<Schemas>>();
+    let include_secrets = create_rw_signal(false);
+    let selected = create_memo(move |_| query.with(|q| q.get("schema").cloned()));
+
+    let mut report_schemas = schemas
+        .schemas
+        .values()
+        .filter(|schema| !schema.form.title.is_empty() && !schema.form.sections.is_empty())
+        .cloned()
+        .collect::<Vec<_>>();
+    report_schemas.sort_by(|a, b| a.form.title.cmp(b.form.title).then(a.id.cmp(b.id)));
+    let report_schemas = store_value(report_schemas);
+
+    let reload = Reload::new();
+    let report = create_resource(
+        move || (selected.get(), reload.track()),
+        move |(selected, _)| {
+            let auth = auth.get_untracked();
+            let schemas = report_schemas
+                .get_value()
+                .into_iter()
+                .filter(|schema| selected.as_deref().is_none_or(|id| schema.id == id))
+                .collect::<Vec<_>>();
+
+            async move {
+                let mut results = Vec::with_capacity(schemas.len());
+                for schema in schemas {
+                    let records = fetch_records(&auth, &schema).await?;
+                    results.push((schema.id.to_string(), records));
+                }
+                Ok::<_, http::Error>(Arc::new(results))
+            }
+        },
+    );
+    // Resources only hold serializable values, schemas are looked up by id
+    let with_schemas = move |records: &[(String, Vec<Settings>)]| {
+        report_schemas.with_value(|schemas| {
+            records
+                .iter()
+                .filter_map(|(id, records)| {
+                    schemas
+                        .iter()
+                        .find(|schema| schema.id == id.as_str())
+                        .map(|schema| (schema.clone(), records.clone()))
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+    let export = move |format: ExportFormat| {
+        let Some(Ok(records)) = report.get() else {
+            return;
+        };
+        let include_secrets = include_secrets.get_untracked();
+        download(
+            &format!("settings.{}", format.extension()),
+            format.mime_type(),
+            &export_settings(&with_schemas(&records), format, include_secrets),
+        );
+        log_audit(
+            AuditAction::ConfigExport,
+            &auth.get_untracked().username,
+            &format!(
+                "Exported {} setting groups as {} ({})",
+                records.len(),
+                format.extension().to_uppercase(),
+                if include_secrets {
+                    "secrets included"
+                } else {
+                    "secrets redacted"
+                }
+            ),
+            None,
+            true,
+        );
+    };
+    let export_schema = move |_| {
+        let selected = selected.get_untracked();
+        download(
+            "schema.json",
+            ExportFormat::Json.mime_type(),
+            &schemas_to_json(
+                report_schemas
+                    .get_value()
+                    .iter()
+                    .filter(|schema| selected.as_deref().is_none_or(|id| schema.id == id))
+                    .map(|schema| schema.as_ref()),
+            ),
+        );
+    };
+
+    view! {
+        <Style>{PRINT_STYLE}</Style>
+        <div class="max-w-[85rem] px-4 pt-5 sm:px-6 lg:px-8 mx-auto print:hidden">
+            <div class="flex flex-wrap items-center justify-between gap-3">
+                <select
+                    class="py-2 px-3 pe-9 block border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                    on:change=move |ev| {
+                        let value = event_target_value(&ev);
+                        let mut url = UrlBuilder::new("/settings/report");
+                        if !value.is_empty() {
+                            url = url.with_parameter("schema", value);
+                        }
+                        use_navigate()(&url.finish(), Default::default());
+                    }
+                >
+
+                    <option value="" selected=move || selected.get().is_none()>
+                        "All settings"
+                    </option>
+                    {report_schemas
+                        .get_value()
+                        .into_iter()
+                        .map(|schema| {
+                            let id = schema.id;
+                            view! {
+                                <option
+                                    value=id
+                                    selected=move || selected.get().as_deref() == Some(id)
+                                >
+                                    {schema.form.title}
+                                </option>
+                            }
+                        })
+                        .collect_view()}
+                </select>
+                <div class="inline-flex items-center gap-x-2">
+                    <label class="inline-flex items-center gap-x-2 text-sm text-gray-600 dark:text-gray-400">
+                        <input
+                            type="checkbox"
+                            class="shrink-0 border-gray-200 rounded text-red-600 focus:ring-red-500 dark:bg-slate-900 dark:border-gray-700"
+                            prop:checked=move || include_secrets.get()
+                            on:change=move |ev| {
+                                if event_target_checked(&ev) {
+                                    // Keep the box unchecked until the export is confirmed
+                                    event_target::<web_sys::HtmlInputElement>(&ev)
+                                        .set_checked(false);
+                                    modal
+                                        .set(
+                                            Modal::with_title("Include secrets")
+                                                .with_message(concat!(
+                                                    "Exports will contain passwords and other secret ",
+                                                    "values in plain text. Make sure the exported ",
+                                                    "file is stored securely."
+                                                ))
+                                                .with_button("Include secrets")
+                                                .with_dangerous_callback(move || {
+                                                    include_secrets.set(true);
+                                                }),
+                                        );
+                                } else {
+                                    include_secrets.set(false);
+                                }
+                            }
+                        />
+
+                        "Include secrets"
+                    </label>
+                    <Button
+                        text="Export JSON"
+                        color=Color::Gray
+                        on_click=move |_| export(ExportFormat::Json)
+                    />
+                    <Button
+                        text="Export CSV"
+                        color=Color::Gray
+                        on_click=move |_| export(ExportFormat::Csv)
+                    />
+                    <Button text="Export schema" color=Color::Gray on_click=export_schema/>
+                    <Button
+                        text="Print report"
+                        color=Color::Blue
+                        on_click=move |_| {
+                            if let Err(err) = window().print() {
+                                log::warn!("Failed to open print dialog: {err:?}");
+                            }
+                        }
+                    >
+
+                        <IconDocumentText attr:class="flex-shrink-0 size-4"/>
+                    </Button>
+                </div>
+            </div>
+        </div>
+        <Transition fallback=Skeleton>
+            {move || match report.get() {
+                None => None,
+                Some(Err(err)) => {
+                    Some(
+                        view! {
+                            <div class="max-w-[85rem] px-4 py-5 sm:px-6 lg:px-8 mx-auto">
+                                <LoadError error=err reload=reload/>
+                            </div>
+                        }
+                            .into_view(),
+                    )
+                }
+                Some(Ok(records)) => {
+                    let mut report = ConfigReport::new(Utc::now());
+                    for (schema, records) in with_schemas(&records) {
+                        report.add_schema(&schema, &records);
+                    }
+                    let generated = format!(
+                        "Generated on {}",
+                        report.generated.format("%Y-%m-%d %H:%M:%S UTC"),
+                    );
+                    let sections = report
+                        .sections
+                        .into_iter()
+                        .map(|section| {
+                            view! {
+                                <ReportSection title=section.title>
+                                    {section
+                                        .rows
+                                        .into_iter()
+                                        .map(|row| {
+                                            view! {
+                                                <ReportItem label=row.label>
+                                                    <ReportTextValue value=row.value/>
+                                                </ReportItem>
+                                            }
+                                        })
+                                        .collect_view()}
+                                </ReportSection>
+                            }
+                        })
+                        .collect_view();
+
+                    Some(
+                        view! {
+                            <ReportView attr:id="config-report">
+                                <div class="mb-8">
+                                    <h2 class="text-xl font-bold text-gray-800 dark:text-gray-200">
+                                        "Configuration report"
+                                    </h2>
+                                    <p class="text-sm text-gray-600 dark:text-gray-400">
+                                        {generated}
+                                    </p>
+                                </div>
+                                {sections}
+                            </ReportView>
+                        }
+                            .into_view(),
+                    )
+                }
+            }}
+
+        </Transition>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::schema::{Schemas, Type},
+        pages::config::REDACTED,
+    };
+
+    fn schemas() -> Schemas {
+        Schemas::builder()
+            .new_schema("store")
+            .prefix("store")
+            .suffix("type")
+            .new_id_field()
+            .build()
+            .new_field("type")
+            .label("Type")
+            .typ(Type::Input)
+            .build()
+            .new_field("host")
+            .label("Host")
+            .typ(Type::Input)
+            .build()
+            .new_field("password")
+            .label("Password")
+            .typ(Type::Secret)
+            .build()
+            .new_form_section()
+            .title("Store")
+            .fields(["_id", "type"])
+            .build()
+            .new_form_section()
+            .title("Connection")
+            .fields(["host", "password"])
+            .build()
+            .form_title("Store")
+            .build()
+            .build()
+    }
+
+    fn settings(values: &[(&str, &str)]) -> Settings {
+        values
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn split_records_by_id() {
+        let schemas = schemas();
+        let records = split_records(
+            &schemas.schemas["store"],
+            settings(&[
+                ("pg.type", "postgresql"),
+                ("pg.host", "localhost"),
+                ("example.org.type", "redis"),
+                ("example.org.host", "redis.example.org"),
+            ]),
+        );
+
+        assert_eq!(
+            records,
+            vec![
+                settings(&[
+                    ("_id", "example.org"),
+                    ("type", "redis"),
+                    ("host", "redis.example.org")
+                ]),
+                settings(&[("_id", "pg"), ("type", "postgresql"), ("host", "localhost")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn assemble_report_sections() {
+        let schemas = schemas();
+        let mut report = ConfigReport::new(Utc::now());
+        report.add_schema(
+            &schemas.schemas["store"],
+            &[
+                settings(&[
+                    ("_id", "pg"),
+                    ("type", "postgresql"),
+                    ("host", "localhost"),
+                    ("password", "s3cr3t"),
+                ]),
+                settings(&[("_id", "fs"), ("type", "fs")]),
+            ],
+        );
+
+        assert_eq!(
+            report.sections,
+            vec![
+                ConfigReportSection {
+                    title: "Store (pg) › Store".to_string(),
+                    rows: vec![ConfigReportRow {
+                        label: "Type".to_string(),
+                        value: "postgresql".to_string()
+                    }],
+                },
+                ConfigReportSection {
+                    title: "Store (pg) › Connection".to_string(),
+                    rows: vec![
+                        ConfigReportRow {
+                            label: "Host".to_string(),
+                            value: "localhost".to_string()
+                        },
+                        ConfigReportRow {
+                            label: "Password".to_string(),
+                            value: REDACTED.to_string()
+                        }
+                    ],
+                },
+                ConfigReportSection {
+                    title: "Store (fs) › Store".to_string(),
+                    rows: vec![ConfigReportRow {
+                        label: "Type".to_string(),
+                        value: "fs".to_string()
+                    }],
+                },
+            ]
+        );
+        assert!(!format!("{report:?}").contains("s3cr3t"));
+    }
+
+    #[test]
+    fn export_redacts_secrets_unless_requested() {
+        let schemas = schemas();
+        let records = vec![(
+            schemas.schemas["store"].clone(),
+            vec![settings(&[
+                ("_id", "pg"),
+                ("host", "localhost"),
+                ("password", "s3cr3t"),
+            ])],
+        )];
+
+        let json = export_settings(&records, ExportFormat::Json, false);
+        assert!(!json.contains("s3cr3t"));
+        assert!(json.contains(r#""password": "***""#));
+        assert_eq!(
+            export_settings(&records, ExportFormat::Csv, false),
+            "schema,id,key,value\nstore,pg,host,localhost\nstore,pg,password,***\n"
+        );
+
+        assert!(export_settings(&records, ExportFormat::Json, true).contains("s3cr3t"));
+        assert_eq!(
+            export_settings(&records, ExportFormat::Csv, true),
+            "schema,id,key,value\nstore,pg,host,localhost\nstore,pg,password,s3cr3t\n"
+        );
+    }
+}
@@ -0,0 +1,229 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use leptos::*;
+use leptos_router::use_navigate;
+
+use crate::{
+    components::{
+        form::button::Button,
+        messages::{
+            alert::{use_alerts, Alert},
+            banner::Banner,
+        },
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        Permission, Permissions,
+    },
+    utils::audit::SettingChange,
+};
+
+use super::{history::setting_key, Schema};
+
+pub const RESTART_NOTE: &str = "Requires a server restart to take effect";
+/// Endpoint of the "Restart server" maintenance action, which requires the
+/// `Restart` permission and replies with an optional message.
+pub const RESTART_URL: &str = "/api/restart";
+pub const RESTART_MESSAGE: &str = "Restarting server, try reloading this page in a few seconds.";
+
+/// Settings saved during this session that only take effect once the
+/// server has been restarted.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PendingRestart {
+    pub fields: Vec<String>,
+}
+
+pub fn init_pending_restart() {
+    provide_context(create_rw_signal(PendingRestart::default()));
+}
+
+pub fn use_pending_restart() -> RwSignal<PendingRestart> {
+    expect_context::<RwSignal<PendingRestart>>()
+}
+
+/// Returns the labels of the restart-requiring fields touched by a set of
+/// changes, sorted and without duplicates.
+pub fn restart_required_fields(
+    schema: &Schema,
+    record_id: Option<&str>,
+    changes: &[SettingChange],
+) -> Vec<String> {
+    let mut fields = schema
+        .fields
+        .values()
+        .filter(|field| field.requires_restart)
+        .filter(|field| {
+            let key = setting_key(schema, record_id, field.id);
+            changes.iter().any(|change| {
+                change
+                    .key
+                    .strip_prefix(&key)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+            })
+        })
+        .map(|field| field.label_form.to_string())
+        .collect::<Vec<_>>();
+    fields.sort();
+    fields.dedup();
+    fields
+}
+
+impl PendingRestart {
+    pub fn add(&mut self, fields: impl IntoIterator<Item = String>) {
+        for field in fields {
+            if !self.fields.contains(&field) {
+                self.fields.push(field);
+            }
+        }
+    }
+
+    pub fn is_required(&self) -> bool {
+        !self.fields.is_empty()
+    }
+
+    /// The restart action is only offered to users allowed to restart the
+    /// server; everyone else sees the notice alone.
+    pub fn can_restart_now(&self, permissions: &Permissions) -> bool {
+        self.is_required() && permissions.has_access(Permission::Restart)
+    }
+
+    pub fn message(&self) -> String {
+        format!(
+            "Changes to {} require a server restart to take effect.",
+            self.fields.join(", ")
+        )
+    }
+}
+
+#[component]
+pub fn RestartBanner() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let pending_restart = use_pending_restart();
+    let can_restart = create_memo(move |_| {
+        pending_restart
+            .get()
+            .can_restart_now(auth.get().permissions())
+    });
+
+    let restart_action = create_action(move |_: &()| {
+        let auth = auth.get();
+
+        async move {
+            match HttpRequest::get(RESTART_URL)
+                .with_authorization(&auth)
+                .send::<Option<String>>()
+                .await
+            {
+                Ok(_) => {
+                    pending_restart.set(PendingRestart::default());
+                    alert.set(Alert::success(RESTART_MESSAGE).without_timeout());
+                }
+                Err(http::Error::Unauthorized) => {
+                    use_navigate()("/login", Default::default());
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+        }
+    });
+
+    view! {
+        <Banner
+            message=Signal::derive(move || pending_restart.get().message())
+            hide=Signal::derive(move || !pending_restart.get().is_required())
+        >
+            <div class="flex gap-x-2">
+                <Show when=move || can_restart.get()>
+                    <Button
+                        text="Restart now"
+                        color=Color::Red
+                        on_click=move |_| {
+                            restart_action.dispatch(());
+                        }
+
+                        disabled=restart_action.pending()
+                    />
+                </Show>
+                <Button
+                    text="Dismiss"
+                    color=Color::Gray
+                    on_click=move |_| {
+                        pending_restart.set(PendingRestart::default());
+                    }
+                />
+            </div>
+        </Banner>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_schemas;
+
+    fn change(key: &str) -> SettingChange {
+        SettingChange {
+            key: key.to_string(),
+            old: None,
+            new: Some("value".to_string()),
+        }
+    }
+
+    #[test]
+    fn detect_restart_required_changes() {
+        let schemas = build_schemas();
+        let listener = &schemas.schemas["listener"];
+
+        assert_eq!(
+            restart_required_fields(
+                listener,
+                Some("smtp"),
+                &[
+                    change("server.listener.smtp.bind.0"),
+                    change("server.listener.smtp.bind.1"),
+                    change("server.listener.smtp.protocol"),
+                    change("server.listener.smtp.tls.implicit"),
+                ]
+            ),
+            vec!["Bind addresses", "Protocol"]
+        );
+        assert!(restart_required_fields(
+            listener,
+            Some("smtp"),
+            &[
+                change("server.listener.smtp.tls.implicit"),
+                change("server.listener.submission.bind.0"),
+                change("server.listener.smtp.binder"),
+            ]
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn offer_restart_after_save() {
+        let admin = Permissions::new([Permission::Restart].into_iter().collect());
+        let operator = Permissions::new([Permission::SettingsUpdate].into_iter().collect());
+
+        let mut pending = PendingRestart::default();
+        assert!(!pending.is_required());
+        assert!(!pending.can_restart_now(&admin));
+
+        pending.add(["Protocol".to_string()]);
+        pending.add(["Bind addresses".to_string(), "Protocol".to_string()]);
+        assert_eq!(pending.fields, vec!["Protocol", "Bind addresses"]);
+        assert!(pending.can_restart_now(&admin));
+        assert!(!pending.can_restart_now(&operator));
+        assert_eq!(
+            pending.message(),
+            "Changes to Protocol, Bind addresses require a server restart to take effect."
+        );
+    }
+}
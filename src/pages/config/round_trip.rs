@@ -0,0 +1,160 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+//! Test utilities asserting that exported settings can be imported back
+//! without losing or altering any value.
+
+use std::sync::Arc;
+
+use super::{
+    redact_secrets,
+    report::{export_settings, import_settings, ExportFormat},
+    Schema, Settings,
+};
+
+pub const FORMATS: [ExportFormat; 2] = [ExportFormat::Json, ExportFormat::Csv];
+
+/// Exports the records of each schema in every format and asserts that
+/// importing the result reproduces them exactly. Secret values are expected
+/// to come back redacted unless `include_secrets` is set.
+pub fn assert_round_trip(records: &[(Arc<Schema>, Vec<Settings>)], include_secrets: bool) {
+    for format in FORMATS {
+        let exported = export_settings(records, format, include_secrets);
+        let imported = import_settings(&exported, format)
+            .unwrap_or_else(|err| panic!("{format:?} import failed: {err}\n{exported}"));
+
+        for (schema, expected) in records {
+            let mut expected = expected
+                .iter()
+                .filter(|settings| format == ExportFormat::Json || has_values(settings))
+                .map(|settings| {
+                    if include_secrets {
+                        settings.clone()
+                    } else {
+                        redact_secrets(settings, schema)
+                    }
+                })
+                .collect::<Vec<_>>();
+            let mut actual = imported.get(schema.id).cloned().unwrap_or_default();
+            sort_records(&mut expected);
+            sort_records(&mut actual);
+
+            assert_eq!(
+                actual, expected,
+                "{format:?} round trip of schema {:?} changed its settings",
+                schema.id
+            );
+        }
+    }
+}
+
+/// CSV rows carry one value each, so records holding nothing but an id
+/// cannot be represented.
+fn has_values(settings: &Settings) -> bool {
+    settings.keys().any(|key| key != "_id")
+}
+
+fn sort_records(records: &mut [Settings]) {
+    records.sort_by(|a, b| a.get("_id").cmp(&b.get("_id")));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::schema::{Schemas, Type};
+
+    fn schemas() -> Schemas {
+        Schemas::builder()
+            .new_schema("store")
+            .prefix("store")
+            .suffix("type")
+            .new_id_field()
+            .build()
+            .new_field("type")
+            .typ(Type::Input)
+            .build()
+            .new_field("hosts")
+            .typ(Type::Array)
+            .build()
+            .new_field("lookup")
+            .typ(Type::Expression)
+            .build()
+            .new_field("max-size")
+            .typ(Type::Size)
+            .build()
+            .new_field("timeout")
+            .typ(Type::Duration)
+            .build()
+            .new_field("password")
+            .typ(Type::Secret)
+            .build()
+            .build()
+            .new_schema("hostname")
+            .new_field("server.hostname")
+            .typ(Type::Input)
+            .build()
+            .build()
+            .build()
+    }
+
+    fn settings(values: &[(&str, &str)]) -> Settings {
+        values
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn records() -> Vec<(Arc<Schema>, Vec<Settings>)> {
+        let schemas = schemas();
+        vec![
+            (
+                schemas.schemas["store"].clone(),
+                vec![
+                    settings(&[
+                        ("_id", "pg"),
+                        ("type", "postgresql"),
+                        ("hosts.0", "db1.example.org"),
+                        ("hosts.1", "db2.example.org:5432"),
+                        ("lookup.0.if", "key == 'a,b'"),
+                        ("lookup.0.then", "\"quoted\""),
+                        ("lookup.else", "'multi\nline'\r\nvalue"),
+                        ("max-size", "104857600"),
+                        ("timeout", "15s"),
+                        ("password", "s3cr3t,\"pass\""),
+                    ]),
+                    settings(&[("_id", "example.org"), ("type", "redis"), ("timeout", "1d")]),
+                    settings(&[("_id", "empty")]),
+                ],
+            ),
+            (
+                schemas.schemas["hostname"].clone(),
+                vec![settings(&[("server.hostname", "mx.exämple.org")])],
+            ),
+        ]
+    }
+
+    #[test]
+    fn round_trip_with_secrets() {
+        assert_round_trip(&records(), true);
+    }
+
+    #[test]
+    fn round_trip_redacts_secrets() {
+        assert_round_trip(&records(), false);
+    }
+
+    #[test]
+    fn reject_malformed_imports() {
+        assert!(import_settings("schema,id,key\nstore,pg,type\n", ExportFormat::Csv).is_err());
+        assert!(import_settings("id,key,value\n", ExportFormat::Csv).is_err());
+        assert!(import_settings(
+            "schema,id,key,value\nstore,pg,type,\"open\n",
+            ExportFormat::Csv
+        )
+        .is_err());
+        assert!(import_settings("[]", ExportFormat::Json).is_err());
+    }
+}
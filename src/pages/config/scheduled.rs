@@ -0,0 +1,410 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDateTime, Utc};
+use gloo_storage::{LocalStorage, Storage};
+use leptos::*;
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        form::button::Button,
+        messages::alert::{use_alerts, Alert},
+        Color,
+    },
+    core::{http::HttpRequest, oauth::use_authorization},
+    utils::{
+        audit::{log_audit, AuditAction},
+        clock,
+    },
+};
+
+use super::UpdateSettings;
+
+const SCHEDULED_STORAGE_KEY: &str = "webadmin_scheduled";
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledChanges {
+    items: Vec<ScheduledChange>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledChange {
+    pub id: String,
+    pub schema_id: String,
+    pub title: String,
+    pub record_id: Option<String>,
+    pub author: String,
+    pub apply_at: DateTime<Utc>,
+    pub updates: Vec<UpdateSettings>,
+}
+
+pub fn init_scheduled_changes() {
+    let scheduled = create_rw_signal(ScheduledChanges::load());
+    provide_context(scheduled);
+
+    // Scheduled changes live in this browser and are only applied while it is
+    // open and their author is signed in, so they always run with the
+    // credentials of whoever scheduled them.
+    let auth = use_authorization();
+    let alert = use_alerts();
+    set_interval(
+        move || {
+            let auth = auth.get_untracked();
+            let now = clock::now();
+            if !auth.is_logged_in()
+                || !scheduled.with_untracked(|s| s.has_due_for(&auth.username, now))
+            {
+                return;
+            }
+
+            let mut due = Vec::new();
+            scheduled.update(|scheduled| {
+                due = scheduled.take_due_for(&auth.username, now);
+                scheduled.save();
+            });
+            let updates = ScheduledChanges::build_payload(&due);
+
+            spawn_local(async move {
+                let result = HttpRequest::post("/api/settings")
+                    .with_authorization(&auth)
+                    .with_body(updates)
+                    .unwrap()
+                    .send::<Option<String>>()
+                    .await;
+                for change in &due {
+                    log_audit(
+                        AuditAction::ConfigUpdate,
+                        &auth.username,
+                        &change.audit_details(),
+                        None,
+                        result.is_ok(),
+                    );
+                }
+                match result {
+                    Ok(_) => {
+                        alert.set(
+                            Alert::success("Scheduled changes applied")
+                                .with_details_list(due.iter().map(|change| change.title.clone())),
+                        );
+                    }
+                    Err(err) => {
+                        // Put the changes back so they are retried on the next tick
+                        scheduled.update(|scheduled| {
+                            for change in due {
+                                scheduled.schedule(change);
+                            }
+                            scheduled.save();
+                        });
+                        alert.set(Alert::from(err));
+                    }
+                }
+            });
+        },
+        SCHEDULER_INTERVAL,
+    );
+}
+
+pub fn use_scheduled_changes() -> RwSignal<ScheduledChanges> {
+    expect_context::<RwSignal<ScheduledChanges>>()
+}
+
+#[component]
+pub fn UpcomingChanges(
+    #[prop(into)] schema_id: Signal<&'static str>,
+    #[prop(into)] can_cancel: Signal<bool>,
+) -> impl IntoView {
+    let scheduled = use_scheduled_changes();
+    let upcoming = create_memo(move |_| {
+        scheduled
+            .get()
+            .upcoming(schema_id.get())
+            .cloned()
+            .collect::<Vec<_>>()
+    });
+
+    view! {
+        <div class="pb-5" class:hidden=move || upcoming.get().is_empty()>
+            <div class="bg-white border border-gray-200 rounded-lg p-4 dark:bg-slate-900 dark:border-gray-700">
+                <h3 class="text-sm font-semibold text-gray-800 dark:text-gray-200">
+                    "Scheduled changes"
+                </h3>
+                <ul class="mt-2 divide-y divide-gray-200 dark:divide-gray-700">
+                    <For
+                        each=move || upcoming.get()
+                        key=|change| change.id.clone()
+                        children=move |change| {
+                            let description = format!(
+                                "Applies on {} while this browser is open and {} is signed in",
+                                change.apply_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                                change.author,
+                            );
+                            let title = match &change.record_id {
+                                Some(record_id) => format!("{} ({record_id})", change.title),
+                                None => change.title.clone(),
+                            };
+                            let id = change.id;
+
+                            view! {
+                                <li class="flex items-center justify-between gap-x-2 py-3">
+                                    <div class="text-sm">
+                                        <p class="font-medium text-gray-800 dark:text-gray-200">
+                                            {title}
+                                        </p>
+                                        <p class="text-gray-500">{description}</p>
+                                    </div>
+                                    <Show when=move || can_cancel.get()>
+                                        <Button
+                                            text="Cancel"
+                                            color=Color::Gray
+                                            on_click={
+                                                let id = id.clone();
+                                                move |_| {
+                                                    scheduled
+                                                        .update(|scheduled| {
+                                                            if scheduled.cancel(&id) {
+                                                                scheduled.save();
+                                                            }
+                                                        });
+                                                }
+                                            }
+                                        />
+
+                                    </Show>
+                                </li>
+                            }
+                        }
+                    />
+
+                </ul>
+            </div>
+        </div>
+    }
+}
+
+impl ScheduledChange {
+    /// Assembles a scheduled change, rejecting windows that have already started.
+    pub fn new(
+        schema_id: impl Into<String>,
+        title: impl Into<String>,
+        record_id: Option<String>,
+        author: impl Into<String>,
+        updates: Vec<UpdateSettings>,
+        apply_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<Self, &'static str> {
+        if apply_at <= now {
+            return Err("The scheduled time must be in the future");
+        } else if updates.is_empty() {
+            return Err("There are no changes to schedule");
+        }
+
+        Ok(ScheduledChange {
+            id: thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(12)
+                .map(char::from)
+                .collect(),
+            schema_id: schema_id.into(),
+            title: title.into(),
+            record_id,
+            author: author.into(),
+            apply_at,
+            updates,
+        })
+    }
+
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.apply_at <= now
+    }
+
+    pub fn audit_details(&self) -> String {
+        let title = match &self.record_id {
+            Some(record_id) => format!("{} ({record_id})", self.title),
+            None => self.title.clone(),
+        };
+        format!(
+            "Applied {title} as scheduled by {} for {}",
+            self.author,
+            self.apply_at.format("%Y-%m-%d %H:%M UTC")
+        )
+    }
+}
+
+impl ScheduledChanges {
+    pub fn load() -> Self {
+        LocalStorage::get(SCHEDULED_STORAGE_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = LocalStorage::set(SCHEDULED_STORAGE_KEY, self) {
+            log::warn!("Failed to persist scheduled changes: {err}");
+        }
+    }
+
+    /// Adds a change, keeping the list ordered by application time.
+    pub fn schedule(&mut self, change: ScheduledChange) {
+        let pos = self
+            .items
+            .partition_point(|item| item.apply_at <= change.apply_at);
+        self.items.insert(pos, change);
+    }
+
+    pub fn cancel(&mut self, id: &str) -> bool {
+        let len = self.items.len();
+        self.items.retain(|item| item.id != id);
+        self.items.len() != len
+    }
+
+    pub fn upcoming<'x>(
+        &'x self,
+        schema_id: &'x str,
+    ) -> impl Iterator<Item = &'x ScheduledChange> + 'x {
+        self.items
+            .iter()
+            .filter(move |item| item.schema_id == schema_id)
+    }
+
+    pub fn has_due_for(&self, author: &str, now: DateTime<Utc>) -> bool {
+        self.items
+            .iter()
+            .any(|item| item.author == author && item.is_due(now))
+    }
+
+    /// Removes and returns the changes of `author` whose window has started,
+    /// leaving those scheduled by other users for their own sessions.
+    pub fn take_due_for(&mut self, author: &str, now: DateTime<Utc>) -> Vec<ScheduledChange> {
+        let mut due = Vec::new();
+        self.items.retain(|item| {
+            if item.author == author && item.is_due(now) {
+                due.push(item.clone());
+                false
+            } else {
+                true
+            }
+        });
+        due
+    }
+
+    /// Flattens due changes into a single update, applied in schedule order.
+    pub fn build_payload(changes: &[ScheduledChange]) -> Vec<UpdateSettings> {
+        changes
+            .iter()
+            .flat_map(|change| change.updates.iter().cloned())
+            .collect()
+    }
+
+    /// Parses the value of a `datetime-local` input as local time.
+    pub fn parse_apply_at(value: &str) -> Option<DateTime<Utc>> {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+            .ok()?
+            .and_local_timezone(Local)
+            .earliest()
+            .map(|dt| dt.with_timezone(&Utc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn time(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, hour, 0, 0).unwrap()
+    }
+
+    fn insert(key: &str) -> Vec<UpdateSettings> {
+        vec![UpdateSettings::Insert {
+            prefix: None,
+            values: vec![(key.to_string(), "true".to_string())],
+            assert_empty: false,
+        }]
+    }
+
+    fn change(key: &str, hour: u32) -> ScheduledChange {
+        change_by("alice", key, hour)
+    }
+
+    fn change_by(author: &str, key: &str, hour: u32) -> ScheduledChange {
+        ScheduledChange::new(
+            "smtp-in-connect",
+            "Connect stage",
+            None,
+            author,
+            insert(key),
+            time(hour),
+            time(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn assemble_scheduling_payload() {
+        assert!(ScheduledChange::new("s", "t", None, "a", insert("a"), time(1), time(1)).is_err());
+        assert!(ScheduledChange::new("s", "t", None, "a", vec![], time(2), time(1)).is_err());
+
+        let mut scheduled = ScheduledChanges::default();
+        scheduled.schedule(change("late", 5));
+        scheduled.schedule(change("early", 2));
+        scheduled.schedule(change("middle", 3));
+
+        let json = serde_json::to_string(&scheduled).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ScheduledChanges>(&json).unwrap(),
+            scheduled
+        );
+
+        let due = scheduled.take_due_for("alice", time(4));
+        assert_eq!(
+            ScheduledChanges::build_payload(&due),
+            [insert("early"), insert("middle")].concat()
+        );
+        assert_eq!(scheduled.upcoming("smtp-in-connect").count(), 1);
+    }
+
+    #[test]
+    fn is_due_given_current_time() {
+        let change = change("a", 3);
+        assert!(!change.is_due(time(2)));
+        assert!(change.is_due(time(3)));
+        assert!(change.is_due(time(4)));
+
+        let mut scheduled = ScheduledChanges::default();
+        scheduled.schedule(change.clone());
+        assert!(!scheduled.has_due_for("alice", time(2)));
+        assert!(scheduled.take_due_for("alice", time(2)).is_empty());
+        assert!(scheduled.has_due_for("alice", time(3)));
+
+        assert!(scheduled.cancel(&change.id));
+        assert!(!scheduled.has_due_for("alice", time(3)));
+    }
+
+    #[test]
+    fn apply_only_changes_of_signed_in_author() {
+        let clock = clock::install_fake_clock(time(1));
+        let mut scheduled = ScheduledChanges::default();
+        scheduled.schedule(change_by("alice", "a", 2));
+        scheduled.schedule(change_by("bob", "b", 2));
+        assert!(!scheduled.has_due_for("alice", clock::now()));
+
+        clock.advance(chrono::Duration::hours(1));
+        assert!(!scheduled.has_due_for("carol", clock::now()));
+        let due = scheduled.take_due_for("alice", clock::now());
+        assert_eq!(ScheduledChanges::build_payload(&due), insert("a"));
+        assert_eq!(
+            due[0].audit_details(),
+            "Applied Connect stage as scheduled by alice for 2024-06-01 02:00 UTC"
+        );
+        assert!(!scheduled.has_due_for("alice", clock::now()));
+        assert!(scheduled.has_due_for("bob", clock::now()));
+        clock::set_clock(None);
+    }
+}
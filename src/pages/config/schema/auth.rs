@@ -18,6 +18,7 @@ impl Builder<Schemas, ()> {
             .prefix("signature")
             .suffix("algorithm")
             .names("signature", "signatures")
+            .suggest_id("signature")
             .new_id_field()
             .label("Signature ID")
             .help("Unique identifier for the signature")
@@ -4,6 +4,8 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use crate::core::Permission;
+
 use super::*;
 
 impl Builder<Schemas, ()> {
@@ -52,6 +54,7 @@ impl Builder<Schemas, ()> {
             ))
             .typ(Type::Input)
             .input_check([Transformer::Trim], [])
+            .required_permission(Permission::Impersonate)
             .build()
             .new_field("authentication.master.secret")
             .label("Password")
@@ -60,6 +63,7 @@ impl Builder<Schemas, ()> {
             ))
             .typ(Type::Secret)
             .input_check([Transformer::Trim, Transformer::HashSecret], [])
+            .required_permission(Permission::Impersonate)
             .build()
             .new_form_section()
             .title("Authentication")
@@ -32,6 +32,8 @@ impl Builder<Schemas, ()> {
             })
             .label("Protocol")
             .help("The protocol used by the listener")
+            .doc_anchor("server/listener#protocol")
+            .requires_restart()
             .input_check([], [Validator::Required])
             .default("smtp")
             .build()
@@ -39,6 +41,8 @@ impl Builder<Schemas, ()> {
             .new_field("bind")
             .label("Bind addresses")
             .help("The addresses the listener will bind to")
+            .doc_anchor("server/listener#bind-address")
+            .requires_restart()
             .typ(Type::Array)
             .input_check(
                 [Transformer::Trim],
@@ -60,6 +60,7 @@ impl Builder<Schemas, ()> {
             .build()
             // Common settings
             .new_schema("system")
+            .partial_array_updates()
             // Local keys
             .new_field("config.local-keys")
             .label("Local settings")
@@ -229,7 +230,7 @@ impl Builder<Schemas, ()> {
             .label("IP Address(es)")
             .help("The IP address or mask to block")
             .input_check(
-                [Transformer::Trim],
+                [Transformer::Trim, Transformer::NormalizeIp],
                 [Validator::Required, Validator::IsIpOrMask],
             )
             .build()
@@ -250,7 +251,7 @@ impl Builder<Schemas, ()> {
             .label("IP Address(es)")
             .help("The IP address or mask to allow")
             .input_check(
-                [Transformer::Trim],
+                [Transformer::Trim, Transformer::NormalizeIp],
                 [Validator::Required, Validator::IsIpOrMask],
             )
             .build()
@@ -264,6 +265,7 @@ impl Builder<Schemas, ()> {
             .build()
             // Auto-ban settings
             .new_schema("auto-ban")
+            .partial_array_updates()
             .new_field("server.auto-ban.auth.rate")
             .label("Auth failures")
             .help("The maximum number of failed login attempts before the IP is banned")
@@ -417,6 +419,7 @@ impl Builder<Schemas, ()> {
             .prefix("webhook")
             .suffix("url")
             .names("webhook", "webhooks")
+            .suggest_id("webhook")
             .new_id_field()
             .label("Webhook Id")
             .help("Unique identifier for this webhook")
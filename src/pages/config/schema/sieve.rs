@@ -56,6 +56,7 @@ impl Builder<Schemas, ()> {
             .help(concat!("Default subject prefix of vacation responses"))
             .default("Auto: ")
             .typ(Type::Input)
+            .preserve_whitespace()
             .build()
             .new_field("sieve.untrusted.default-expiry.vacation")
             .label("Default Expiry")
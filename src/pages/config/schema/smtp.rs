@@ -718,6 +718,8 @@ impl Builder<Schemas, ()> {
             ))
             .typ(Type::Input)
             .input_check([Transformer::Trim], [Validator::MinValue(1.into())])
+            .unit_suffix("message")
+            .empty_placeholder("No limit")
             .build()
             .new_field("size")
             .label("Max Size")
@@ -726,6 +728,8 @@ impl Builder<Schemas, ()> {
                 "this quota will allow"
             ))
             .typ(Type::Size)
+            .size_precision(0)
+            .empty_placeholder("No limit")
             .build()
             .new_form_section()
             .title("Quota")
@@ -80,43 +80,34 @@ impl Builder<Schemas, ()> {
             .label("Spam threshold")
             .help("Mark as Spam messages with a score above this threshold")
             .default("5.0")
-            .typ(Type::Input)
-            .input_check(
-                [Transformer::Trim],
-                [
-                    Validator::Required,
-                    Validator::MinValue((-100.0).into()),
-                    Validator::MaxValue(100.0.into()),
-                ],
-            )
+            .typ(Type::Number {
+                signed: true,
+                min: Some(NumberType::Integer(-100)),
+                max: Some(NumberType::Integer(100)),
+            })
+            .input_check([Transformer::Trim], [Validator::Required])
             .build()
             .new_field("spam-filter.score.discard")
             .label("Discard threshold")
             .help("Discard messages with a score above this threshold")
             .default("0")
-            .typ(Type::Input)
-            .input_check(
-                [Transformer::Trim],
-                [
-                    Validator::Required,
-                    Validator::MinValue((-100.0).into()),
-                    Validator::MaxValue(100.0.into()),
-                ],
-            )
+            .typ(Type::Number {
+                signed: true,
+                min: Some(NumberType::Integer(-100)),
+                max: Some(NumberType::Integer(100)),
+            })
+            .input_check([Transformer::Trim], [Validator::Required])
             .build()
             .new_field("spam-filter.score.reject")
             .label("Reject threshold")
             .help("Reject messages with a score above this threshold")
             .default("0")
-            .typ(Type::Input)
-            .input_check(
-                [Transformer::Trim],
-                [
-                    Validator::Required,
-                    Validator::MinValue((-100.0).into()),
-                    Validator::MaxValue(100.0.into()),
-                ],
-            )
+            .typ(Type::Number {
+                signed: true,
+                min: Some(NumberType::Integer(-100)),
+                max: Some(NumberType::Integer(100)),
+            })
+            .input_check([Transformer::Trim], [Validator::Required])
             .build()
             .new_field("spam-filter.grey-list.duration")
             .label("Duration")
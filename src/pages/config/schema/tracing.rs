@@ -279,6 +279,7 @@ impl Builder<Schemas, ()> {
             .build()
             // Metrics
             .new_schema("metrics")
+            .partial_array_updates()
             // OT Transport
             .new_field("metrics.open-telemetry.transport")
             .typ(Type::Select {
@@ -504,6 +505,7 @@ impl Builder<Schemas, ()> {
             // Alerts
             .new_schema("alerts")
             .names("alert", "alerts")
+            .suggest_id("alert")
             .prefix("metrics.alerts")
             .suffix("condition")
             // Id
@@ -6,42 +6,202 @@
 
 use std::sync::Arc;
 
+use gloo_storage::{LocalStorage, Storage};
 use leptos::*;
 use leptos_router::use_query_map;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     components::{list::ZeroResults, report::ReportView},
-    pages::config::Schemas,
+    core::{http::HttpRequest, oauth::use_authorization, url::UrlBuilder},
+    pages::{config::Schemas, List},
+    utils::audit::get_audit_logs,
 };
 
-use super::{Field, Form, Section};
+use super::{Field, Form, Schema, SchemaType, Section, Settings, SettingsValues};
+
+pub const GROUP_LIMIT: usize = 5;
+
+const RECENT_PAGES_STORAGE_KEY: &str = "webadmin_recent_pages";
+const RECENT_PAGES_LIMIT: usize = 5;
+
+/// Commonly edited settings, suggested when the search query is empty.
+const SUGGESTED_SCHEMAS: &[&str] = &[
+    "network",
+    "listener",
+    "tls",
+    "storage",
+    "authentication",
+    "smtp-in-rcpt",
+    "dkim",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecentPage {
+    pub title: String,
+    pub url: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum SearchSource {
+    Recent,
+    Suggested,
+    Settings,
+    Records { schema_id: String, name: String },
+    Audit,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub source: SearchSource,
+    pub title: String,
+    pub details: String,
+    pub url: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchGroup {
+    pub source: SearchSource,
+    pub hits: Vec<SearchHit>,
+    pub see_all: Option<String>,
+}
 
 #[component]
 pub fn SettingsSearch() -> impl IntoView {
     let query = use_query_map();
     let schemas = expect_context::<Arc<Schemas>>();
-
-    let results = create_memo(move |_| {
-        let params = query.with(|q| {
+    let auth = use_authorization();
+    let text = create_memo(move |_| {
+        query.with(|q| {
             q.get("query")
-                .map(|s| {
-                    s.split_whitespace()
-                        .map(|s| s.to_lowercase())
-                        .collect::<Vec<_>>()
-                })
+                .map(|s| s.trim().to_string())
                 .unwrap_or_default()
-        });
+        })
+    });
+    let group = create_memo(move |_| query.with(|q| q.get("group").cloned()));
 
-        schemas
-            .schemas
-            .values()
-            .filter_map(|s| {
-                let (title, matches) = s.form.contains_string(&params)?;
+    let schemas_ = schemas.clone();
+    let setting_hits = create_memo(move |_| setting_hits(&schemas_, &text.get()));
+    let schemas_ = schemas.clone();
+    let suggestions = create_memo(move |_| quick_results("", &RecentPage::load(), &schemas_));
+    let audit_hits = create_memo(move |_| {
+        let text = text.get().to_lowercase();
+        if text.is_empty() {
+            return vec![];
+        }
 
-                Some((s.id, title, matches))
+        get_audit_logs()
+            .into_iter()
+            .rev()
+            .filter(|log| {
+                log.details.to_lowercase().contains(&text)
+                    || log.user.to_lowercase().contains(&text)
+                    || format!("{:?}", log.action).to_lowercase().contains(&text)
+            })
+            .map(|log| SearchHit {
+                source: SearchSource::Audit,
+                title: format!("{:?} by {}", log.action, log.user),
+                details: format!(
+                    "{} - {}",
+                    log.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                    log.details
+                ),
+                url: String::new(),
             })
             .collect::<Vec<_>>()
     });
+    let record_hits = create_resource(
+        move || text.get(),
+        move |text| {
+            let auth = auth.get_untracked();
+            let mut record_schemas = schemas
+                .schemas
+                .values()
+                .filter(|schema| {
+                    !matches!(schema.typ, SchemaType::List) && !schema.list.title.is_empty()
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            record_schemas.sort_by_key(|schema| schema.id);
+
+            async move {
+                let mut hits = Vec::new();
+                if text.is_empty() {
+                    return hits;
+                }
+
+                for schema in record_schemas {
+                    // Request one extra item to find out whether there are more results
+                    let Ok(list) = HttpRequest::get("/api/settings/group")
+                        .with_authorization(&auth)
+                        .with_parameter("page", "1")
+                        .with_parameter("limit", (GROUP_LIMIT + 1).to_string())
+                        .with_parameter("prefix", schema.unwrap_prefix())
+                        .with_parameter("suffix", schema.try_unwrap_suffix().unwrap_or_default())
+                        .with_parameter("filter", text.clone())
+                        .send::<List<Settings>>()
+                        .await
+                    else {
+                        continue;
+                    };
+
+                    hits.extend(list.items.into_iter().filter_map(|mut item| {
+                        let id = item.remove("_id")?;
+                        let details = schema
+                            .list
+                            .fields
+                            .iter()
+                            .filter(|field| field.id != "_id")
+                            .map(|field| item.format(field))
+                            .filter(|value| !value.is_empty())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+
+                        Some(SearchHit {
+                            source: SearchSource::Records {
+                                schema_id: schema.id.to_string(),
+                                name: schema.name_plural.to_string(),
+                            },
+                            url: format!("/settings/{}/{id}/edit", schema.id),
+                            title: id,
+                            details,
+                        })
+                    }));
+                }
+
+                hits
+            }
+        },
+    );
+
+    let results = create_memo(move |_| {
+        let group = group.get();
+        if text.with(|text| text.is_empty()) {
+            return suggestions.get();
+        }
+
+        let hits = setting_hits
+            .get()
+            .into_iter()
+            .chain(record_hits.get().unwrap_or_default())
+            .chain(audit_hits.get())
+            .filter(|hit| {
+                group
+                    .as_deref()
+                    .is_none_or(|group| hit.source.id() == group)
+            })
+            .collect::<Vec<_>>();
+
+        group_results(
+            hits,
+            if group.is_none() {
+                Some(GROUP_LIMIT)
+            } else {
+                None
+            },
+            &text.get(),
+        )
+    });
 
     view! {
         {move || {
@@ -49,51 +209,78 @@ pub fn SettingsSearch() -> impl IntoView {
             let has_results = !results.is_empty();
             let results = results
                 .into_iter()
-                .map(|(id, title, matches)| {
-                    let url = format!("/settings/{id}/edit");
-                    view! {
-                        <a
-                            class="group flex flex-col bg-white border shadow-sm rounded-xl hover:shadow-md transition dark:bg-slate-900 dark:border-gray-800"
-                            href=url
-                        >
-                            <div class="p-4 md:p-5">
-                                <div class="flex justify-between items-center">
-                                    <div>
-                                        <h3 class="group-hover:text-blue-600 font-semibold text-gray-800 dark:group-hover:text-gray-400 dark:text-gray-200">
-                                            {title}
-                                        </h3>
-                                        <p class="text-sm text-gray-500">{matches}</p>
-                                    </div>
-                                    <div class="ps-3">
-                                        <svg
-                                            class="flex-shrink-0 size-5"
-                                            xmlns="http://www.w3.org/2000/svg"
-                                            width="24"
-                                            height="24"
-                                            viewBox="0 0 24 24"
-                                            fill="none"
-                                            stroke="currentColor"
-                                            stroke-width="2"
-                                            stroke-linecap="round"
-                                            stroke-linejoin="round"
-                                        >
-                                            <path d="m9 18 6-6-6-6"></path>
-                                        </svg>
+                .map(|group| {
+                    let see_all = group
+                        .see_all
+                        .map(|url| {
+                            view! {
+                                <a
+                                    class="inline-flex items-center gap-x-1 text-sm text-blue-600 decoration-2 hover:underline font-medium dark:text-blue-500"
+                                    href=url
+                                >
+                                    {format!("See all in {}", group.source.title())}
+                                </a>
+                            }
+                        });
+                    let hits = group
+                        .hits
+                        .into_iter()
+                        .map(|hit| {
+                            view! {
+                                <a
+                                    class="group flex flex-col bg-white border shadow-sm rounded-xl hover:shadow-md transition dark:bg-slate-900 dark:border-gray-800"
+                                    href=(!hit.url.is_empty()).then_some(hit.url)
+                                >
+                                    <div class="p-4 md:p-5">
+                                        <div class="flex justify-between items-center">
+                                            <div>
+                                                <h3 class="group-hover:text-blue-600 font-semibold text-gray-800 dark:group-hover:text-gray-400 dark:text-gray-200">
+                                                    {hit.title}
+                                                </h3>
+                                                <p class="text-sm text-gray-500">{hit.details}</p>
+                                            </div>
+                                            <div class="ps-3">
+                                                <svg
+                                                    class="flex-shrink-0 size-5"
+                                                    xmlns="http://www.w3.org/2000/svg"
+                                                    width="24"
+                                                    height="24"
+                                                    viewBox="0 0 24 24"
+                                                    fill="none"
+                                                    stroke="currentColor"
+                                                    stroke-width="2"
+                                                    stroke-linecap="round"
+                                                    stroke-linejoin="round"
+                                                >
+                                                    <path d="m9 18 6-6-6-6"></path>
+                                                </svg>
+                                            </div>
+                                        </div>
                                     </div>
-                                </div>
+                                </a>
+                            }
+                        })
+                        .collect_view();
+
+                    view! {
+                        <div class="mb-8">
+                            <div class="mb-3 flex justify-between items-center">
+                                <h2 class="text-lg font-semibold text-gray-800 dark:text-gray-200">
+                                    {group.source.title()}
+                                </h2>
+                                {see_all}
+                            </div>
+                            <div class="grid sm:grid-cols-2 md:grid-cols-3 xl:grid-cols-4 gap-3 sm:gap-6">
+                                {hits}
                             </div>
-                        </a>
+                        </div>
                     }
-                        .into_view()
                 })
                 .collect_view();
             if has_results {
                 view! {
                     <div class="max-w-[85rem] px-4 py-10 sm:px-6 lg:px-8 lg:py-14 mx-auto">
-                        <div class="grid sm:grid-cols-2 md:grid-cols-3 xl:grid-cols-4 gap-3 sm:gap-6">
-                            {results}
-                        </div>
-
+                        {results}
                     </div>
                 }
                     .into_view()
@@ -112,6 +299,175 @@ pub fn SettingsSearch() -> impl IntoView {
     }
 }
 
+/// Returns the settings forms matching all the words in the query.
+pub fn setting_hits(schemas: &Schemas, query: &str) -> Vec<SearchHit> {
+    let params = query
+        .split_whitespace()
+        .map(|s| s.to_lowercase())
+        .collect::<Vec<_>>();
+    if params.is_empty() {
+        return vec![];
+    }
+
+    let mut hits = schemas
+        .schemas
+        .values()
+        .filter_map(|s| {
+            let (title, matches) = s.form.contains_string(&params)?;
+
+            Some(SearchHit {
+                source: SearchSource::Settings,
+                title: title.to_string(),
+                details: matches.to_string(),
+                url: format!("/settings/{}/edit", s.id),
+            })
+        })
+        .collect::<Vec<_>>();
+    hits.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.url.cmp(&b.url)));
+    hits
+}
+
+/// Returns the results shown below the search box. Recently visited pages
+/// and a curated list of common settings are suggested while the query is
+/// empty, otherwise the settings matching the query are returned.
+pub fn quick_results(query: &str, recent: &[RecentPage], schemas: &Schemas) -> Vec<SearchGroup> {
+    let query = query.trim();
+    if !query.is_empty() {
+        return group_results(setting_hits(schemas, query), Some(GROUP_LIMIT), query);
+    }
+
+    let recent = recent
+        .iter()
+        .take(RECENT_PAGES_LIMIT)
+        .map(|page| SearchHit {
+            source: SearchSource::Recent,
+            title: page.title.clone(),
+            details: String::new(),
+            url: page.url.clone(),
+        })
+        .collect::<Vec<_>>();
+    let suggested = SUGGESTED_SCHEMAS
+        .iter()
+        .filter_map(|id| RecentPage::for_schema(schemas.schemas.get(id)?))
+        .filter(|page| !recent.iter().any(|hit| hit.url == page.url))
+        .map(|page| SearchHit {
+            source: SearchSource::Suggested,
+            title: page.title,
+            details: String::new(),
+            url: page.url,
+        })
+        .collect::<Vec<_>>();
+
+    group_results(recent.into_iter().chain(suggested).collect(), None, query)
+}
+
+impl RecentPage {
+    /// Returns the page used to edit a form, or to list the records of a
+    /// schema.
+    pub fn for_schema(schema: &Schema) -> Option<Self> {
+        match schema.typ {
+            SchemaType::List => Some(RecentPage {
+                title: Some(schema.form.title)
+                    .filter(|title| !title.is_empty())
+                    .or_else(|| schema.form.sections.iter().find_map(|s| s.title))?
+                    .to_string(),
+                url: format!("/settings/{}/edit", schema.id),
+            }),
+            SchemaType::Record { .. } | SchemaType::Entry { .. }
+                if !schema.list.title.is_empty() =>
+            {
+                Some(RecentPage {
+                    title: schema.list.title.to_string(),
+                    url: format!("/settings/{}", schema.id),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn load() -> Vec<Self> {
+        LocalStorage::get(RECENT_PAGES_STORAGE_KEY).unwrap_or_default()
+    }
+
+    /// Moves the page to the top of the recently visited pages.
+    pub fn record(self) {
+        let mut pages = Self::load();
+        pages.retain(|page| page.url != self.url);
+        pages.insert(0, self);
+        pages.truncate(RECENT_PAGES_LIMIT);
+        if let Err(err) = LocalStorage::set(RECENT_PAGES_STORAGE_KEY, &pages) {
+            log::error!("Failed to save recent pages to local storage: {}", err);
+        }
+    }
+}
+
+/// Groups search hits by source. Groups are ordered by source and hits keep
+/// the order in which each source returned them. When `limit` is set, each
+/// group is truncated and links to the full results.
+pub fn group_results(hits: Vec<SearchHit>, limit: Option<usize>, query: &str) -> Vec<SearchGroup> {
+    let mut hits = hits;
+    hits.sort_by(|a, b| a.source.cmp(&b.source));
+
+    let mut groups: Vec<SearchGroup> = Vec::new();
+    for hit in hits {
+        match groups.last_mut() {
+            Some(group) if group.source == hit.source => {
+                if limit.is_some_and(|limit| group.hits.len() >= limit) {
+                    group.see_all = Some(group.source.see_all_url(query));
+                } else {
+                    group.hits.push(hit);
+                }
+            }
+            _ => groups.push(SearchGroup {
+                source: hit.source.clone(),
+                hits: vec![hit],
+                see_all: None,
+            }),
+        }
+    }
+
+    groups
+}
+
+impl SearchSource {
+    pub fn id(&self) -> &str {
+        match self {
+            SearchSource::Recent => "recent",
+            SearchSource::Suggested => "suggested",
+            SearchSource::Settings => "settings",
+            SearchSource::Records { schema_id, .. } => schema_id.as_str(),
+            SearchSource::Audit => "audit",
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match self {
+            SearchSource::Recent => "Recent".to_string(),
+            SearchSource::Suggested => "Suggested".to_string(),
+            SearchSource::Settings => "Settings".to_string(),
+            SearchSource::Records { name, .. } => name.clone(),
+            SearchSource::Audit => "Audit log".to_string(),
+        }
+    }
+
+    pub fn see_all_url(&self, query: &str) -> String {
+        match self {
+            SearchSource::Records { schema_id, .. } => {
+                UrlBuilder::new(format!("/settings/{schema_id}"))
+                    .with_parameter("filter", query)
+                    .finish()
+            }
+            SearchSource::Recent
+            | SearchSource::Suggested
+            | SearchSource::Settings
+            | SearchSource::Audit => UrlBuilder::new("/settings/search")
+                .with_parameter("query", query)
+                .with_parameter("group", self.id())
+                .finish(),
+        }
+    }
+}
+
 trait ContainsString {
     fn contains_string(&self, query: &[String]) -> Option<&'static str>;
 }
@@ -159,3 +515,136 @@ impl ContainsString for &'static str {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(source: SearchSource, title: &str) -> SearchHit {
+        SearchHit {
+            source,
+            title: title.to_string(),
+            details: String::new(),
+            url: String::new(),
+        }
+    }
+
+    fn records(schema_id: &str) -> SearchSource {
+        SearchSource::Records {
+            schema_id: schema_id.to_string(),
+            name: schema_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn group_heterogeneous_sources() {
+        let hits = vec![
+            hit(SearchSource::Audit, "audit-1"),
+            hit(records("blocked-ip"), "10.0.0.1"),
+            hit(SearchSource::Settings, "Rate limits"),
+            hit(records("allowed-ip"), "192.168.0.1"),
+            hit(SearchSource::Audit, "audit-2"),
+            hit(records("blocked-ip"), "10.0.0.2"),
+            hit(SearchSource::Settings, "Throttle"),
+        ];
+
+        let groups = group_results(hits, None, "10");
+        assert_eq!(
+            groups
+                .iter()
+                .map(|group| (
+                    group.source.id(),
+                    group
+                        .hits
+                        .iter()
+                        .map(|hit| hit.title.as_str())
+                        .collect::<Vec<_>>()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("settings", vec!["Rate limits", "Throttle"]),
+                ("allowed-ip", vec!["192.168.0.1"]),
+                ("blocked-ip", vec!["10.0.0.1", "10.0.0.2"]),
+                ("audit", vec!["audit-1", "audit-2"]),
+            ]
+        );
+        assert!(groups.iter().all(|group| group.see_all.is_none()));
+    }
+
+    #[test]
+    fn group_limit_links_to_all_results() {
+        let hits = (0..4)
+            .map(|idx| hit(records("blocked-ip"), &format!("10.0.0.{idx}")))
+            .chain([hit(SearchSource::Audit, "audit-1")])
+            .collect::<Vec<_>>();
+
+        let groups = group_results(hits, Some(3), "10.0");
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].hits.len(), 3);
+        assert_eq!(groups[0].hits[2].title, "10.0.0.2");
+        assert_eq!(
+            groups[0].see_all.as_deref(),
+            Some("/settings/blocked-ip?filter=10.0")
+        );
+        assert_eq!(groups[1].hits.len(), 1);
+        assert_eq!(groups[1].see_all, None);
+    }
+
+    #[test]
+    fn suggest_recent_and_common_settings() {
+        let schemas = crate::build_schemas();
+        let recent = vec![
+            RecentPage {
+                title: "Listeners".to_string(),
+                url: "/settings/listener".to_string(),
+            },
+            RecentPage {
+                title: "Blocked IP addresses".to_string(),
+                url: "/settings/blocked-ip".to_string(),
+            },
+        ];
+
+        let groups = quick_results("  ", &recent, &schemas);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].source, SearchSource::Recent);
+        assert_eq!(
+            groups[0]
+                .hits
+                .iter()
+                .map(|hit| hit.url.as_str())
+                .collect::<Vec<_>>(),
+            vec!["/settings/listener", "/settings/blocked-ip"]
+        );
+        assert_eq!(groups[1].source, SearchSource::Suggested);
+        let suggested = groups[1]
+            .hits
+            .iter()
+            .map(|hit| hit.url.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(suggested.len(), SUGGESTED_SCHEMAS.len() - 1);
+        assert!(suggested.contains(&"/settings/network/edit"));
+        assert!(!suggested.contains(&"/settings/listener"));
+
+        // Without recent pages only the curated list is suggested
+        let groups = quick_results("", &[], &schemas);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].hits.len(), SUGGESTED_SCHEMAS.len());
+    }
+
+    #[test]
+    fn switch_to_results_on_input() {
+        let schemas = crate::build_schemas();
+        let recent = vec![RecentPage {
+            title: "Listeners".to_string(),
+            url: "/settings/listener".to_string(),
+        }];
+
+        let groups = quick_results("dkim", &recent, &schemas);
+        assert!(!groups.is_empty());
+        assert!(groups
+            .iter()
+            .all(|group| group.source == SearchSource::Settings));
+        assert!(groups[0].hits.len() <= GROUP_LIMIT);
+        assert!(quick_results("no-such-setting-xyz", &recent, &schemas).is_empty());
+    }
+}
@@ -0,0 +1,447 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use gloo_storage::{LocalStorage, Storage};
+use leptos::*;
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        form::button::Button,
+        messages::{
+            alert::{use_alerts, Alert},
+            modal::{use_modals, Modal},
+        },
+        Color,
+    },
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+        AccessToken,
+    },
+    pages::config::edit::FetchSettings,
+    utils::audit::{log_audit, AuditAction},
+};
+
+use super::{Settings, UpdateSettings};
+
+const SNAPSHOTS_STORAGE_KEY: &str = "webadmin_snapshots";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshots {
+    items: Vec<ConfigSnapshot>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigSnapshot {
+    pub id: String,
+    pub label: String,
+    pub created: DateTime<Utc>,
+    pub settings: BTreeMap<String, String>,
+}
+
+impl Snapshots {
+    pub fn load() -> Self {
+        LocalStorage::get(SNAPSHOTS_STORAGE_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = LocalStorage::set(SNAPSHOTS_STORAGE_KEY, self) {
+            log::warn!("Failed to persist snapshots: {err}");
+        }
+    }
+
+    pub fn add(&mut self, snapshot: ConfigSnapshot) {
+        self.items.push(snapshot);
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool {
+        let len = self.items.len();
+        self.items.retain(|snapshot| snapshot.id != id);
+        self.items.len() != len
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ConfigSnapshot> {
+        self.items.iter().find(|snapshot| snapshot.id == id)
+    }
+
+    /// Returns the snapshots, newest first.
+    pub fn list(&self) -> Vec<&ConfigSnapshot> {
+        let mut items = self.items.iter().collect::<Vec<_>>();
+        items.sort_by(|a, b| b.created.cmp(&a.created));
+        items
+    }
+}
+
+impl ConfigSnapshot {
+    pub fn new(label: impl Into<String>, settings: Settings, created: DateTime<Utc>) -> Self {
+        ConfigSnapshot {
+            id: thread_rng()
+                .sample_iter(Alphanumeric)
+                .take(12)
+                .map(char::from)
+                .collect(),
+            label: label.into(),
+            created,
+            settings: settings.into_iter().collect(),
+        }
+    }
+
+    /// Computes the minimal update that turns `current` back into this
+    /// snapshot: keys added since are deleted and keys that were removed
+    /// or changed are inserted with their snapshot values.
+    pub fn restore_diff(&self, current: &Settings) -> Vec<UpdateSettings> {
+        let mut delete_keys = current
+            .keys()
+            .filter(|key| !self.settings.contains_key(*key))
+            .cloned()
+            .collect::<Vec<_>>();
+        delete_keys.sort_unstable();
+
+        let values = self
+            .settings
+            .iter()
+            .filter(|(key, value)| current.get(*key) != Some(*value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<Vec<_>>();
+
+        let mut updates = Vec::with_capacity(2);
+        if !delete_keys.is_empty() {
+            updates.push(UpdateSettings::Delete { keys: delete_keys });
+        }
+        if !values.is_empty() {
+            updates.push(UpdateSettings::Insert {
+                prefix: None,
+                values,
+                assert_empty: false,
+            });
+        }
+        updates
+    }
+}
+
+pub(crate) async fn fetch_all_settings(auth: &AccessToken) -> http::Result<Settings> {
+    HttpRequest::get("/api/settings/list")
+        .with_authorization(auth)
+        .send::<FetchSettings>()
+        .await
+        .map(|list| list.items)
+}
+
+pub async fn snapshot_settings(
+    auth: &AccessToken,
+    label: impl Into<String>,
+) -> http::Result<ConfigSnapshot> {
+    fetch_all_settings(auth)
+        .await
+        .map(|settings| ConfigSnapshot::new(label, settings, Utc::now()))
+}
+
+/// Restores a snapshot by applying its diff against the current settings
+/// in a single request. Returns the number of changed keys.
+pub async fn restore_snapshot(
+    auth: &AccessToken,
+    snapshot: &ConfigSnapshot,
+) -> http::Result<usize> {
+    let current = fetch_all_settings(auth).await?;
+    let updates = snapshot.restore_diff(&current);
+    let total = updates.iter().map(UpdateSettings::total_changes).sum();
+
+    if !updates.is_empty() {
+        HttpRequest::post("/api/settings")
+            .with_authorization(auth)
+            .with_body(updates)
+            .unwrap()
+            .send::<Option<String>>()
+            .await?;
+    }
+
+    Ok(total)
+}
+
+#[component]
+pub fn SettingsSnapshots() -> impl IntoView {
+    let auth = use_authorization();
+    let alert = use_alerts();
+    let modal = use_modals();
+    let snapshots = create_rw_signal(Snapshots::load());
+    let label = create_rw_signal(String::new());
+    let (pending, set_pending) = create_signal(false);
+
+    let take_snapshot = create_action(move |label: &String| {
+        let label = label.clone();
+        let auth = auth.get();
+
+        async move {
+            set_pending.set(true);
+            match snapshot_settings(&auth, label).await {
+                Ok(snapshot) => {
+                    let total = snapshot.settings.len();
+                    snapshots.update(|snapshots| {
+                        snapshots.add(snapshot);
+                        snapshots.save();
+                    });
+                    alert.set(Alert::success(format!(
+                        "Snapshot of {total} settings saved"
+                    )));
+                }
+                Err(err) => {
+                    alert.set(Alert::from(err));
+                }
+            }
+            set_pending.set(false);
+        }
+    });
+    let restore = create_action(move |snapshot: &ConfigSnapshot| {
+        let snapshot = snapshot.clone();
+        let auth = auth.get();
+
+        async move {
+            set_pending.set(true);
+            match restore_snapshot(&auth, &snapshot).await {
+                Ok(total) => {
+                    log_audit(
+                        AuditAction::ConfigUpdate,
+                        &auth.username,
+                        &format!("Restored snapshot {} ({total} changes)", snapshot.id),
+                        None,
+                        true,
+                    );
+                    alert.set(if total > 0 {
+                        Alert::success(format!(
+                            "Snapshot restored with {total} {}",
+                            if total == 1 { "change" } else { "changes" }
+                        ))
+                        .with_details("Reload the configuration to apply the restored settings.")
+                    } else {
+                        Alert::success("Settings already match the snapshot")
+                    });
+                }
+                Err(err) => {
+                    log_audit(
+                        AuditAction::ConfigUpdate,
+                        &auth.username,
+                        &format!("Failed to restore snapshot {}", snapshot.id),
+                        None,
+                        false,
+                    );
+                    alert.set(Alert::from(err));
+                }
+            }
+            set_pending.set(false);
+        }
+    });
+
+    view! {
+        <div class="max-w-4xl px-4 py-10 sm:px-6 lg:px-8 lg:py-14 mx-auto">
+            <div class="bg-white rounded-xl shadow p-4 sm:p-7 dark:bg-slate-900">
+                <div class="mb-8">
+                    <h2 class="text-xl font-bold text-gray-800 dark:text-gray-200">
+                        "Configuration snapshots"
+                    </h2>
+                    <p class="text-sm text-gray-600 dark:text-gray-400">
+                        "Save the current settings and roll back to them later."
+                    </p>
+                </div>
+                <div class="flex gap-x-2">
+                    <input
+                        type="text"
+                        class="py-2 px-3 block w-full border-gray-200 rounded-lg text-sm focus:border-blue-500 focus:ring-blue-500 dark:bg-slate-900 dark:border-gray-700 dark:text-gray-400"
+                        placeholder="Snapshot label"
+                        prop:value=move || label.get()
+                        on:input=move |ev| label.set(event_target_value(&ev))
+                    />
+                    <Button
+                        text="Take snapshot"
+                        color=Color::Blue
+                        on_click=move |_| {
+                            let text = label.get().trim().to_string();
+                            take_snapshot
+                                .dispatch(
+                                    if text.is_empty() {
+                                        Utc::now().format("%Y-%m-%d %H:%M").to_string()
+                                    } else {
+                                        text
+                                    },
+                                );
+                            label.set(String::new());
+                        }
+
+                        disabled=pending
+                    />
+                </div>
+                <ul class="mt-6 divide-y divide-gray-200 dark:divide-gray-700">
+                    <For
+                        each=move || {
+                            snapshots
+                                .get()
+                                .list()
+                                .into_iter()
+                                .cloned()
+                                .collect::<Vec<_>>()
+                        }
+
+                        key=|snapshot| snapshot.id.clone()
+                        children=move |snapshot| {
+                            let description = format!(
+                                "{} settings, taken on {}",
+                                snapshot.settings.len(),
+                                snapshot.created.format("%Y-%m-%d %H:%M:%S UTC"),
+                            );
+                            let label = snapshot.label.clone();
+                            let restored = snapshot.clone();
+                            let deleted = snapshot.id.clone();
+
+                            view! {
+                                <li class="flex items-center justify-between gap-x-2 py-3">
+                                    <div class="text-sm">
+                                        <p class="font-medium text-gray-800 dark:text-gray-200">
+                                            {label}
+                                        </p>
+                                        <p class="text-gray-500">{description}</p>
+                                    </div>
+                                    <div class="inline-flex gap-x-2">
+                                        <Button
+                                            text="Restore"
+                                            color=Color::Yellow
+                                            on_click=move |_| {
+                                                let snapshot = restored.clone();
+                                                modal
+                                                    .set(
+                                                        Modal::with_title("Restore snapshot")
+                                                            .with_message(
+                                                                format!(
+                                                                    "All settings will be reverted to the snapshot {:?}. Changes made since then will be lost.",
+                                                                    snapshot.label,
+                                                                ),
+                                                            )
+                                                            .with_button("Restore")
+                                                            .with_dangerous_callback(move || {
+                                                                restore.dispatch(snapshot.clone());
+                                                            }),
+                                                    );
+                                            }
+
+                                            disabled=pending
+                                        />
+                                        <Button
+                                            text="Delete"
+                                            color=Color::Red
+                                            on_click=move |_| {
+                                                snapshots
+                                                    .update(|snapshots| {
+                                                        if snapshots.remove(&deleted) {
+                                                            snapshots.save();
+                                                        }
+                                                    });
+                                            }
+
+                                            disabled=pending
+                                        />
+                                    </div>
+                                </li>
+                            }
+                        }
+                    />
+
+                </ul>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(values: &[(&str, &str)]) -> Settings {
+        values
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn restore_diff_is_minimal() {
+        let snapshot = ConfigSnapshot::new(
+            "before",
+            settings(&[
+                ("server.hostname", "mx.example.org"),
+                ("queue.throttle.rcpt.enable", "true"),
+                ("queue.throttle.rcpt.rate", "5/1s"),
+                ("store.db.type", "rocksdb"),
+            ]),
+            Utc::now(),
+        );
+        let current = settings(&[
+            ("server.hostname", "mx.example.org"),
+            ("queue.throttle.rcpt.enable", "false"),
+            ("store.db.type", "rocksdb"),
+            ("store.pg.type", "postgresql"),
+            ("store.pg.host", "localhost"),
+        ]);
+
+        assert_eq!(
+            snapshot.restore_diff(&current),
+            vec![
+                UpdateSettings::Delete {
+                    keys: vec!["store.pg.host".to_string(), "store.pg.type".to_string()],
+                },
+                UpdateSettings::Insert {
+                    prefix: None,
+                    values: vec![
+                        ("queue.throttle.rcpt.enable".to_string(), "true".to_string()),
+                        ("queue.throttle.rcpt.rate".to_string(), "5/1s".to_string()),
+                    ],
+                    assert_empty: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn restore_diff_of_unchanged_settings_is_empty() {
+        let values = settings(&[("server.hostname", "mx.example.org")]);
+        let snapshot = ConfigSnapshot::new("current", values.clone(), Utc::now());
+
+        assert_eq!(snapshot.restore_diff(&values), vec![]);
+        assert_eq!(
+            snapshot.restore_diff(&Settings::new()),
+            vec![UpdateSettings::Insert {
+                prefix: None,
+                values: vec![("server.hostname".to_string(), "mx.example.org".to_string())],
+                assert_empty: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn list_snapshots_newest_first() {
+        let now = Utc::now();
+        let mut snapshots = Snapshots::default();
+        snapshots.add(ConfigSnapshot::new(
+            "old",
+            Settings::new(),
+            now - chrono::Duration::hours(1),
+        ));
+        snapshots.add(ConfigSnapshot::new("new", Settings::new(), now));
+
+        let labels = snapshots
+            .list()
+            .into_iter()
+            .map(|snapshot| snapshot.label.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec!["new", "old"]);
+
+        let id = snapshots.list()[0].id.clone();
+        assert!(snapshots.remove(&id));
+        assert!(snapshots.get(&id).is_none());
+    }
+}
@@ -0,0 +1,284 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use leptos::*;
+
+use super::UpdateSettings;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StagedChanges {
+    items: Vec<StagedItem>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StagedItem {
+    pub schema_id: &'static str,
+    pub title: &'static str,
+    pub record_id: Option<String>,
+    pub updates: Vec<UpdateSettings>,
+}
+
+pub fn init_staged_changes() {
+    provide_context(create_rw_signal(StagedChanges::default()));
+}
+
+pub fn use_staged_changes() -> RwSignal<StagedChanges> {
+    expect_context::<RwSignal<StagedChanges>>()
+}
+
+impl StagedChanges {
+    /// Stages the updates produced by a sub-form, replacing any changes
+    /// previously staged for the same schema and record.
+    pub fn stage(&mut self, item: StagedItem) {
+        if let Some(existing) = self
+            .items
+            .iter_mut()
+            .find(|i| i.schema_id == item.schema_id && i.record_id == item.record_id)
+        {
+            *existing = item;
+        } else {
+            self.items.push(item);
+        }
+    }
+
+    pub fn unstage(&mut self, schema_id: &str, record_id: Option<&str>) {
+        self.items
+            .retain(|i| i.schema_id != schema_id || i.record_id.as_deref() != record_id);
+    }
+
+    pub fn is_staged(&self, schema_id: &str, record_id: Option<&str>) -> bool {
+        self.items
+            .iter()
+            .any(|i| i.schema_id == schema_id && i.record_id.as_deref() == record_id)
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn items(&self) -> &[StagedItem] {
+        &self.items
+    }
+
+    /// Coalesces all staged changes into a single update vector, keeping
+    /// the updates of each sub-form in the order they were staged so that a
+    /// key deleted and then re-created ends up stored. Consecutive deletions
+    /// are merged into a single request.
+    pub fn build_update(&self) -> Vec<UpdateSettings> {
+        let mut updates: Vec<UpdateSettings> = Vec::new();
+
+        for update in self.items.iter().flat_map(|item| &item.updates) {
+            match (updates.last_mut(), update) {
+                (
+                    Some(UpdateSettings::Delete { keys }),
+                    UpdateSettings::Delete { keys: new_keys },
+                ) => {
+                    for key in new_keys {
+                        if !keys.contains(key) {
+                            keys.push(key.clone());
+                        }
+                    }
+                }
+                (Some(last), update) if last == update => {}
+                _ => updates.push(update.clone()),
+            }
+        }
+
+        updates
+    }
+
+    pub fn summary(&self) -> Vec<String> {
+        self.items
+            .iter()
+            .map(|item| {
+                let total = item.updates.iter().map(UpdateSettings::total_changes).sum::<usize>();
+                let title = if let Some(record_id) = &item.record_id {
+                    format!("{} ({record_id})", item.title)
+                } else {
+                    item.title.to_string()
+                };
+                format!(
+                    "{title}: {total} {}",
+                    if total == 1 { "change" } else { "changes" }
+                )
+            })
+            .collect()
+    }
+}
+
+impl UpdateSettings {
+    pub fn total_changes(&self) -> usize {
+        match self {
+            UpdateSettings::Delete { keys } => keys.len(),
+            UpdateSettings::Clear { .. } => 1,
+            UpdateSettings::Insert { values, .. } => values.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(values: &[(&str, &str)]) -> UpdateSettings {
+        UpdateSettings::Insert {
+            prefix: None,
+            values: values
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            assert_empty: false,
+        }
+    }
+
+    #[test]
+    fn stage_multiple_sub_forms() {
+        let mut staged = StagedChanges::default();
+        staged.stage(StagedItem {
+            schema_id: "smtp-in-connect",
+            title: "Connect stage",
+            record_id: None,
+            updates: vec![insert(&[("session.connect.greeting", "hi")])],
+        });
+        staged.stage(StagedItem {
+            schema_id: "smtp-in-ehlo",
+            title: "EHLO stage",
+            record_id: None,
+            updates: vec![
+                UpdateSettings::Delete {
+                    keys: vec!["session.ehlo.reject-non-fqdn".to_string()],
+                },
+                insert(&[("session.ehlo.require", "true"), ("session.ehlo.timeout", "5m")]),
+            ],
+        });
+        assert_eq!(staged.len(), 2);
+        assert!(staged.is_staged("smtp-in-ehlo", None));
+        assert_eq!(
+            staged.summary(),
+            vec![
+                "Connect stage: 1 change".to_string(),
+                "EHLO stage: 3 changes".to_string()
+            ]
+        );
+
+        // Restaging the same sub-form replaces the previous changes
+        staged.stage(StagedItem {
+            schema_id: "smtp-in-connect",
+            title: "Connect stage",
+            record_id: None,
+            updates: vec![insert(&[("session.connect.greeting", "hello")])],
+        });
+        assert_eq!(staged.len(), 2);
+
+        staged.unstage("smtp-in-ehlo", None);
+        assert_eq!(staged.len(), 1);
+        staged.clear();
+        assert!(staged.is_empty());
+    }
+
+    #[test]
+    fn coalesce_into_single_update() {
+        let mut staged = StagedChanges::default();
+        staged.stage(StagedItem {
+            schema_id: "smtp-in-connect",
+            title: "Connect stage",
+            record_id: None,
+            updates: vec![
+                UpdateSettings::Delete {
+                    keys: vec!["a".to_string()],
+                },
+                insert(&[("b", "1")]),
+            ],
+        });
+        staged.stage(StagedItem {
+            schema_id: "smtp-in-ehlo",
+            title: "EHLO stage",
+            record_id: None,
+            updates: vec![
+                UpdateSettings::Clear {
+                    prefix: "c.".to_string(),
+                    filter: None,
+                },
+                UpdateSettings::Delete {
+                    keys: vec!["a".to_string(), "d".to_string()],
+                },
+                insert(&[("c.0", "x")]),
+            ],
+        });
+
+        assert_eq!(
+            staged.build_update(),
+            vec![
+                UpdateSettings::Delete {
+                    keys: vec!["a".to_string()],
+                },
+                insert(&[("b", "1")]),
+                UpdateSettings::Clear {
+                    prefix: "c.".to_string(),
+                    filter: None,
+                },
+                UpdateSettings::Delete {
+                    keys: vec!["a".to_string(), "d".to_string()],
+                },
+                insert(&[("c.0", "x")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn keep_order_of_each_sub_form() {
+        let mut staged = StagedChanges::default();
+        staged.stage(StagedItem {
+            schema_id: "store",
+            title: "Store",
+            record_id: Some("old".to_string()),
+            updates: vec![UpdateSettings::Delete {
+                keys: vec!["store.old.type".to_string()],
+            }],
+        });
+        staged.stage(StagedItem {
+            schema_id: "store",
+            title: "Store",
+            record_id: Some("new".to_string()),
+            updates: vec![
+                UpdateSettings::Delete {
+                    keys: vec!["store.new.type".to_string()],
+                },
+                insert(&[("store.new.type", "rocksdb")]),
+            ],
+        });
+        staged.stage(StagedItem {
+            schema_id: "directory",
+            title: "Directory",
+            record_id: Some("default".to_string()),
+            updates: vec![UpdateSettings::Delete {
+                keys: vec!["store.new.type".to_string()],
+            }],
+        });
+
+        // The insert is not moved before the deletions that follow it
+        assert_eq!(
+            staged.build_update(),
+            vec![
+                UpdateSettings::Delete {
+                    keys: vec!["store.old.type".to_string(), "store.new.type".to_string()],
+                },
+                insert(&[("store.new.type", "rocksdb")]),
+                UpdateSettings::Delete {
+                    keys: vec!["store.new.type".to_string()],
+                },
+            ]
+        );
+    }
+}
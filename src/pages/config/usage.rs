@@ -0,0 +1,478 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use ahash::{AHashMap, AHashSet};
+use humansize::{format_size, DECIMAL};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::Color,
+    core::{
+        http::{self, HttpRequest},
+        oauth::use_authorization,
+    },
+    pages::{
+        config::{edit::FetchSettings, Settings, SettingsValues},
+        queue::messages::Message,
+        List,
+    },
+};
+
+pub const QUEUE_SCHEMA: &str = "smtp-out-queue";
+pub const QUOTA_SCHEMA: &str = "smtp-out-quota";
+
+const QUOTA_PREFIX: &str = "queue.quota";
+const PAGE_SIZE: u32 = 100;
+const MAX_PAGES: u32 = 10;
+const REFRESH_INTERVAL: Duration = Duration::from_secs(15);
+const WARNING_THRESHOLD: u64 = 75;
+const CRITICAL_THRESHOLD: u64 = 90;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueueUsage {
+    pub messages: u64,
+    pub size: u64,
+    pub quotas: AHashMap<String, UsageCount>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UsageCount {
+    pub messages: u64,
+    pub size: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Gauge {
+    pub label: String,
+    pub used: u64,
+    pub limit: Option<u64>,
+    pub is_size: bool,
+}
+
+impl Gauge {
+    pub fn new(label: impl Into<String>, used: u64, limit: Option<u64>) -> Self {
+        Gauge {
+            label: label.into(),
+            used,
+            limit,
+            is_size: false,
+        }
+    }
+
+    pub fn size(mut self) -> Self {
+        self.is_size = true;
+        self
+    }
+
+    /// Returns the usage as a percentage of the limit, which can exceed 100
+    /// when the limit was lowered below the current usage. Returns `None`
+    /// for unbounded gauges.
+    pub fn percentage(&self) -> Option<u64> {
+        self.limit
+            .filter(|limit| *limit > 0)
+            .map(|limit| self.used.saturating_mul(100) / limit)
+    }
+
+    pub fn color(&self) -> Color {
+        match self.percentage() {
+            None => Color::Gray,
+            Some(pct) if pct >= CRITICAL_THRESHOLD => Color::Red,
+            Some(pct) if pct >= WARNING_THRESHOLD => Color::Yellow,
+            Some(_) => Color::Green,
+        }
+    }
+
+    pub fn percentage_label(&self) -> String {
+        self.percentage()
+            .map(|pct| format!("{pct}%"))
+            .unwrap_or_else(|| "—".to_string())
+    }
+
+    pub fn usage_label(&self) -> String {
+        let format = |value: u64| {
+            if self.is_size {
+                format_size(value, DECIMAL)
+            } else {
+                value.to_string()
+            }
+        };
+        format!(
+            "{} / {}",
+            format(self.used),
+            self.limit.map(format).unwrap_or_else(|| "—".to_string())
+        )
+    }
+
+    fn bar_class(&self) -> &'static str {
+        match self.color() {
+            Color::Red => "h-full bg-red-600 dark:bg-red-500",
+            Color::Yellow => "h-full bg-yellow-500",
+            Color::Green => "h-full bg-teal-500",
+            Color::Gray | Color::Blue => "h-full bg-gray-400 dark:bg-neutral-500",
+        }
+    }
+}
+
+impl QueueUsage {
+    /// Computes the queue usage from the listed messages. `total` is the
+    /// number of messages reported by the server, which can exceed the
+    /// listed ones when the queue is larger than the fetched pages.
+    ///
+    /// Each quota is charged with its busiest bucket, messages being grouped
+    /// by the values of the quota's keys. Match conditions are not evaluated,
+    /// so quotas restricted by one may show a higher usage than the server
+    /// enforces.
+    pub fn from_messages(messages: &[Message], total: u64, quotas: &Settings) -> Self {
+        let mut usage = QueueUsage {
+            messages: total,
+            size: messages.iter().map(|message| message.size as u64).sum(),
+            quotas: AHashMap::new(),
+        };
+
+        for id in quota_ids(quotas) {
+            let keys = quotas
+                .array_values(&format!("{id}.key"))
+                .into_iter()
+                .map(|(_, key)| key)
+                .collect::<Vec<_>>();
+            let mut buckets: AHashMap<String, UsageCount> = AHashMap::new();
+            for message in messages {
+                let sender_domain = message
+                    .return_path
+                    .rsplit_once('@')
+                    .map(|(_, domain)| domain)
+                    .unwrap_or_default();
+                let mut message_buckets = AHashSet::new();
+                for domain in &message.domains {
+                    for rcpt in &domain.recipients {
+                        let bucket = keys
+                            .iter()
+                            .map(|key| match *key {
+                                "sender" => message.return_path.as_str(),
+                                "sender_domain" => sender_domain,
+                                "rcpt" => rcpt.address.as_str(),
+                                "rcpt_domain" => domain.name.as_str(),
+                                _ => "",
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\0");
+                        message_buckets.insert(bucket);
+                    }
+                }
+                for bucket in message_buckets {
+                    let count = buckets.entry(bucket).or_default();
+                    count.messages += 1;
+                    count.size += message.size as u64;
+                }
+            }
+
+            usage.quotas.insert(
+                id.to_string(),
+                UsageCount {
+                    messages: buckets
+                        .values()
+                        .map(|count| count.messages)
+                        .max()
+                        .unwrap_or_default(),
+                    size: buckets
+                        .values()
+                        .map(|count| count.size)
+                        .max()
+                        .unwrap_or_default(),
+                },
+            );
+        }
+
+        usage
+    }
+}
+
+fn quota_ids(quotas: &Settings) -> Vec<&str> {
+    let mut ids = quotas
+        .keys()
+        .filter_map(|key| key.split_once('.').map(|(id, _)| id))
+        .collect::<Vec<_>>();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// Builds the gauges shown on the queue or quota settings page. Queue
+/// totals have no configured limit, while each quota is compared against
+/// its maximum number of messages and total size.
+pub fn build_gauges(schema_id: &str, usage: &QueueUsage, quotas: &Settings) -> Vec<Gauge> {
+    match schema_id {
+        QUEUE_SCHEMA => vec![
+            Gauge::new("Queued messages", usage.messages, None),
+            Gauge::new("Queue size", usage.size, None).size(),
+        ],
+        QUOTA_SCHEMA => {
+            let ids = quota_ids(quotas);
+            let limit = |id: &str, key: &str| {
+                quotas
+                    .get(&format!("{id}.{key}"))
+                    .and_then(|value| value.trim().parse::<u64>().ok())
+            };
+            let mut gauges = Vec::with_capacity(ids.len() * 2);
+            for id in ids {
+                let used = usage.quotas.get(id).copied().unwrap_or_default();
+                gauges.push(Gauge::new(
+                    format!("{id}: messages"),
+                    used.messages,
+                    limit(id, "messages"),
+                ));
+                gauges.push(Gauge::new(format!("{id}: size"), used.size, limit(id, "size")).size());
+            }
+            gauges
+        }
+        _ => vec![],
+    }
+}
+
+#[component]
+pub fn QueueUsageGauges(#[prop(into)] schema_id: MaybeSignal<&'static str>) -> impl IntoView {
+    let auth = use_authorization();
+    let tick = create_rw_signal(0u32);
+
+    if let Ok(handle) = set_interval_with_handle(
+        move || tick.update(|tick| *tick = tick.wrapping_add(1)),
+        REFRESH_INTERVAL,
+    ) {
+        on_cleanup(move || handle.clear());
+    }
+
+    let gauges = create_resource(
+        move || (schema_id.get(), tick.get()),
+        move |(schema_id, _)| {
+            let auth = auth.get_untracked();
+
+            async move {
+                let mut messages = Vec::new();
+                let mut total = 0;
+                for page in 1..=MAX_PAGES {
+                    let list = HttpRequest::get("/api/queue/messages")
+                        .with_authorization(&auth)
+                        .with_parameter("page", page.to_string())
+                        .with_parameter("limit", PAGE_SIZE.to_string())
+                        .with_parameter("values", "1")
+                        .send::<List<Message>>()
+                        .await?;
+                    let is_last = list.items.len() < PAGE_SIZE as usize;
+                    total = list.total;
+                    messages.extend(list.items);
+                    if is_last || messages.len() as u64 >= total {
+                        break;
+                    }
+                }
+                let quotas = if schema_id == QUOTA_SCHEMA {
+                    HttpRequest::get("/api/settings/list")
+                        .with_authorization(&auth)
+                        .with_parameter("prefix", QUOTA_PREFIX)
+                        .send::<FetchSettings>()
+                        .await?
+                        .items
+                } else {
+                    Settings::default()
+                };
+
+                let usage = QueueUsage::from_messages(&messages, total, &quotas);
+                Ok::<_, http::Error>(build_gauges(schema_id, &usage, &quotas))
+            }
+        },
+    );
+
+    view! {
+        <Transition>
+            {move || {
+                let gauges = match gauges.get() {
+                    Some(Ok(gauges)) if !gauges.is_empty() => gauges,
+                    Some(Err(err)) => {
+                        log::warn!("Failed to fetch queue usage: {err:?}");
+                        return None;
+                    }
+                    _ => return None,
+                };
+
+                Some(
+                    view! {
+                        <div class="grid sm:grid-cols-2 lg:grid-cols-4 gap-4 mb-4">
+                            {gauges
+                                .into_iter()
+                                .map(|gauge| {
+                                    let width = format!(
+                                        "width: {}%",
+                                        gauge.percentage().unwrap_or_default().min(100),
+                                    );
+                                    view! {
+                                        <div class="flex flex-col gap-y-2 p-4 bg-white border border-gray-200 rounded-xl dark:bg-neutral-800 dark:border-neutral-700">
+                                            <div class="flex justify-between text-xs uppercase tracking-wide text-gray-500 dark:text-neutral-500">
+                                                <span>{gauge.label.clone()}</span>
+                                                <span>{gauge.percentage_label()}</span>
+                                            </div>
+                                            <div class="flex w-full h-2 bg-gray-200 rounded-full overflow-hidden dark:bg-neutral-700">
+                                                <div class=gauge.bar_class() style=width></div>
+                                            </div>
+                                            <span class="text-sm font-medium text-gray-800 dark:text-neutral-200">
+                                                {gauge.usage_label()}
+                                            </span>
+                                        </div>
+                                    }
+                                })
+                                .collect_view()}
+                        </div>
+                    },
+                )
+            }}
+
+        </Transition>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::*;
+    use crate::pages::queue::messages::{Domain, Recipient, Status};
+
+    fn message(return_path: &str, rcpts: &[&str], size: usize) -> Message {
+        let mut domains: Vec<Domain> = Vec::new();
+        for rcpt in rcpts {
+            let name = rcpt.rsplit_once('@').unwrap().1;
+            let recipient = Recipient {
+                address: rcpt.to_string(),
+                status: Status::Scheduled,
+                orcpt: None,
+            };
+            match domains.iter_mut().find(|domain| domain.name == name) {
+                Some(domain) => domain.recipients.push(recipient),
+                None => domains.push(Domain {
+                    name: name.to_string(),
+                    status: Status::Scheduled,
+                    recipients: vec![recipient],
+                    retry_num: 0,
+                    next_retry: None,
+                    next_notify: None,
+                    expires: DateTime::default(),
+                }),
+            }
+        }
+        Message {
+            id: 0,
+            return_path: return_path.to_string(),
+            domains,
+            created: DateTime::default(),
+            size,
+            priority: 0,
+            env_id: None,
+            blob_hash: String::new(),
+        }
+    }
+
+    #[test]
+    fn usage_from_messages() {
+        let messages = [
+            message("a@foo.org", &["x@bar.org", "y@bar.org"], 100),
+            message("b@foo.org", &["x@bar.org"], 200),
+            message("a@foo.org", &["z@baz.org"], 400),
+        ];
+        let quotas = Settings::from_iter([
+            ("sender.key.0000".to_string(), "sender".to_string()),
+            ("sender.messages".to_string(), "10".to_string()),
+            ("domain.key.0000".to_string(), "sender_domain".to_string()),
+            ("rcpt.key.0000".to_string(), "rcpt".to_string()),
+            ("all.size".to_string(), "1000".to_string()),
+        ]);
+
+        let usage = QueueUsage::from_messages(&messages, 5, &quotas);
+        assert_eq!(usage.messages, 5);
+        assert_eq!(usage.size, 700);
+        for (id, messages, size) in [
+            ("sender", 2, 500),
+            ("domain", 3, 700),
+            ("rcpt", 2, 400),
+            ("all", 3, 700),
+        ] {
+            assert_eq!(
+                usage.quotas.get(id),
+                Some(&UsageCount { messages, size }),
+                "{id}"
+            );
+        }
+    }
+
+    #[test]
+    fn gauge_percentage_and_color() {
+        for (used, limit, pct, color) in [
+            (0, 100, 0, Color::Green),
+            (74, 100, 74, Color::Green),
+            (75, 100, 75, Color::Yellow),
+            (89, 100, 89, Color::Yellow),
+            (90, 100, 90, Color::Red),
+            (150, 100, 150, Color::Red),
+            (1, 3, 33, Color::Green),
+        ] {
+            let gauge = Gauge::new("test", used, Some(limit));
+            assert_eq!(gauge.percentage(), Some(pct), "{used}/{limit}");
+            assert_eq!(gauge.color(), color, "{used}/{limit}");
+            assert_eq!(gauge.percentage_label(), format!("{pct}%"));
+        }
+        assert_eq!(Gauge::new("test", 5, Some(10)).usage_label(), "5 / 10");
+    }
+
+    #[test]
+    fn unbounded_gauge() {
+        for limit in [None, Some(0)] {
+            let gauge = Gauge::new("test", 42, limit);
+            assert_eq!(gauge.percentage(), None);
+            assert_eq!(gauge.color(), Color::Gray);
+            assert_eq!(gauge.percentage_label(), "—");
+        }
+        assert_eq!(Gauge::new("test", 42, None).usage_label(), "42 / —");
+    }
+
+    #[test]
+    fn build_quota_gauges() {
+        let usage = QueueUsage {
+            messages: 12,
+            size: 3000,
+            quotas: AHashMap::from_iter([(
+                "sender".to_string(),
+                UsageCount {
+                    messages: 8,
+                    size: 2000,
+                },
+            )]),
+        };
+        let quotas = Settings::from_iter([
+            ("sender.messages".to_string(), "10".to_string()),
+            ("sender.enable".to_string(), "true".to_string()),
+            ("domain.size".to_string(), "1000000".to_string()),
+            ("domain.enable".to_string(), "true".to_string()),
+        ]);
+
+        let gauges = build_gauges(QUOTA_SCHEMA, &usage, &quotas);
+        assert_eq!(
+            gauges
+                .iter()
+                .map(|g| (g.label.as_str(), g.used, g.limit, g.color()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("domain: messages", 0, None, Color::Gray),
+                ("domain: size", 0, Some(1000000), Color::Green),
+                ("sender: messages", 8, Some(10), Color::Yellow),
+                ("sender: size", 2000, None, Color::Gray),
+            ]
+        );
+
+        let gauges = build_gauges(QUEUE_SCHEMA, &usage, &Settings::default());
+        assert_eq!(gauges.len(), 2);
+        assert!(gauges.iter().all(|g| g.percentage().is_none()));
+    }
+}
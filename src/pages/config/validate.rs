@@ -0,0 +1,476 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{
+        badge::Badge,
+        messages::load_error::{LoadError, Reload},
+        skeleton::SkeletonTable,
+        Color,
+    },
+    core::{form::FormData, oauth::use_authorization},
+    pages::{
+        config::{
+            lint::{lint_config, LintSeverity},
+            report::split_records,
+            snapshot::fetch_all_settings,
+        },
+        maybe_plural,
+    },
+};
+
+use super::{Schema, SchemaType, Schemas, Settings, SettingsValues};
+
+const LINT_SECTION: &str = "Cross-section checks";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum IssueSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    pub severity: IssueSeverity,
+    pub record: Option<String>,
+    pub field: String,
+    pub message: String,
+    pub route: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionReport {
+    pub title: String,
+    pub issues: Vec<ValidationIssue>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub sections_checked: usize,
+    pub sections: Vec<SectionReport>,
+}
+
+impl IssueSeverity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            IssueSeverity::Error => "Error",
+            IssueSeverity::Warning => "Warning",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            IssueSeverity::Error => Color::Red,
+            IssueSeverity::Warning => Color::Yellow,
+        }
+    }
+}
+
+impl SectionReport {
+    pub fn errors(&self) -> usize {
+        self.count(IssueSeverity::Error)
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.count(IssueSeverity::Warning)
+    }
+
+    fn count(&self, severity: IssueSeverity) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == severity)
+            .count()
+    }
+}
+
+impl ValidationReport {
+    /// Groups issues by section title. Sections without issues are only
+    /// counted, and sections are listed alphabetically with errors first
+    /// within each section.
+    pub fn new(
+        sections_checked: usize,
+        issues: impl IntoIterator<Item = (String, ValidationIssue)>,
+    ) -> Self {
+        let mut sections: BTreeMap<String, Vec<ValidationIssue>> = BTreeMap::new();
+        for (title, issue) in issues {
+            sections.entry(title).or_default().push(issue);
+        }
+
+        ValidationReport {
+            sections_checked,
+            sections: sections
+                .into_iter()
+                .map(|(title, mut issues)| {
+                    issues.sort_by_key(|issue| issue.severity);
+                    SectionReport { title, issues }
+                })
+                .collect(),
+        }
+    }
+
+    pub fn total_errors(&self) -> usize {
+        self.sections.iter().map(|section| section.errors()).sum()
+    }
+
+    pub fn total_warnings(&self) -> usize {
+        self.sections.iter().map(|section| section.warnings()).sum()
+    }
+}
+
+/// Loads every record of a schema from the full configuration and runs the
+/// field validators on it. List schemas are only checked when at least one
+/// of their fields is set.
+pub fn validate_schema(schema: &Arc<Schema>, settings: &Settings) -> Vec<ValidationIssue> {
+    let records = match schema.typ {
+        SchemaType::Record { prefix, .. } | SchemaType::Entry { prefix } => {
+            let prefix = format!("{prefix}.");
+            split_records(
+                schema,
+                settings
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        key.strip_prefix(&prefix)
+                            .map(|key| (key.to_string(), value.clone()))
+                    })
+                    .collect(),
+            )
+        }
+        SchemaType::List => {
+            let values = schema
+                .fields
+                .keys()
+                .flat_map(|id| settings.array_values(id))
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<Settings>();
+            if values.is_empty() {
+                vec![]
+            } else {
+                vec![values]
+            }
+        }
+    };
+
+    let mut issues = Vec::new();
+    for record in records {
+        let record_id = record.get("_id").cloned();
+        let mut data = FormData::from_settings(schema.clone(), Some(record));
+        data.is_update = true;
+        if data.validate_form() {
+            continue;
+        }
+
+        let route = match &record_id {
+            Some(id) => format!("/settings/{}/{id}/edit", schema.id),
+            None => format!("/settings/{}/edit", schema.id),
+        };
+        let mut errors = data.errors.into_iter().collect::<Vec<_>>();
+        errors.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (field_id, error) in errors {
+            issues.push(ValidationIssue {
+                severity: IssueSeverity::Error,
+                record: record_id.clone(),
+                field: schema
+                    .fields
+                    .get(field_id.as_str())
+                    .map(|field| field.label_form)
+                    .filter(|label| !label.is_empty())
+                    .unwrap_or(field_id.as_str())
+                    .to_string(),
+                message: error.error,
+                route: route.clone(),
+            });
+        }
+    }
+
+    issues
+}
+
+/// Validates every section of the configuration and adds the lint findings,
+/// which check settings across sections, under the section they point to.
+pub fn validate_all(settings: &Settings, schemas: &Schemas) -> ValidationReport {
+    let mut checked = 0;
+    let mut issues = Vec::new();
+
+    for schema in schemas.schemas.values() {
+        if schema.form.sections.is_empty() {
+            continue;
+        }
+        checked += 1;
+        let title = section_title(schema);
+        issues.extend(
+            validate_schema(schema, settings)
+                .into_iter()
+                .map(|issue| (title.clone(), issue)),
+        );
+    }
+
+    for finding in lint_config(settings, schemas) {
+        let title = finding
+            .route
+            .strip_prefix("/settings/")
+            .and_then(|route| route.split('/').next())
+            .and_then(|id| schemas.schemas.get(id))
+            .map(|schema| section_title(schema))
+            .unwrap_or_else(|| LINT_SECTION.to_string());
+        issues.push((
+            title,
+            ValidationIssue {
+                severity: if finding.severity == LintSeverity::Critical {
+                    IssueSeverity::Error
+                } else {
+                    IssueSeverity::Warning
+                },
+                record: None,
+                field: finding.rule,
+                message: finding.message,
+                route: finding.route,
+            },
+        ));
+    }
+
+    ValidationReport::new(checked, issues)
+}
+
+fn section_title(schema: &Schema) -> String {
+    [schema.form.title, schema.list.title]
+        .into_iter()
+        .find(|title| !title.is_empty())
+        .or_else(|| {
+            schema
+                .form
+                .sections
+                .iter()
+                .find_map(|section| section.title)
+        })
+        .unwrap_or(schema.id)
+        .to_string()
+}
+
+#[component]
+pub fn SettingsValidate() -> impl IntoView {
+    let auth = use_authorization();
+    let schemas = expect_context::<Arc<Schemas>>();
+    let reload = Reload::new();
+
+    let report = create_resource(
+        move || reload.track(),
+        move |_| {
+            let auth = auth.get_untracked();
+            let schemas = schemas.clone();
+
+            async move {
+                fetch_all_settings(&auth)
+                    .await
+                    .map(|settings| validate_all(&settings, &schemas))
+            }
+        },
+    );
+
+    view! {
+        <div class="max-w-4xl px-4 py-10 sm:px-6 lg:px-8 lg:py-14 mx-auto">
+            <div class="bg-white rounded-xl shadow p-4 sm:p-7 dark:bg-slate-900">
+                <div class="mb-8">
+                    <h2 class="text-xl font-bold text-gray-800 dark:text-gray-200">
+                        "Validate configuration"
+                    </h2>
+                    <p class="text-sm text-gray-600 dark:text-gray-400">
+                        "Runs the validators of every settings section against the stored configuration."
+                    </p>
+                </div>
+                <Transition fallback=SkeletonTable>
+                    {move || match report.get() {
+                        None => None,
+                        Some(Err(err)) => {
+                            Some(view! { <LoadError error=err reload=reload/> }.into_view())
+                        }
+                        Some(Ok(report)) => {
+                            let summary = format!(
+                                "Checked {}: {}, {}.",
+                                maybe_plural(report.sections_checked, "section", "sections"),
+                                maybe_plural(report.total_errors(), "error", "errors"),
+                                maybe_plural(report.total_warnings(), "warning", "warnings"),
+                            );
+                            let sections = report
+                                .sections
+                                .into_iter()
+                                .map(|section| {
+                                    let counts = format!(
+                                        "{}, {}",
+                                        maybe_plural(section.errors(), "error", "errors"),
+                                        maybe_plural(section.warnings(), "warning", "warnings"),
+                                    );
+                                    let issues = section
+                                        .issues
+                                        .into_iter()
+                                        .map(|issue| {
+                                            let field = match &issue.record {
+                                                Some(record) => format!("{record} / {}", issue.field),
+                                                None => issue.field.clone(),
+                                            };
+                                            view! {
+                                                <li class="flex items-center gap-x-2 py-2">
+                                                    <Badge color=issue
+                                                        .severity
+                                                        .color()>{issue.severity.label()}</Badge>
+                                                    <span class="text-sm font-medium text-gray-800 dark:text-gray-200">
+                                                        {field}
+                                                    </span>
+                                                    <span class="text-sm text-gray-600 dark:text-gray-400">
+                                                        {issue.message}
+                                                    </span>
+                                                    <a
+                                                        href=issue.route
+                                                        class="ms-auto text-sm text-blue-600 hover:underline dark:text-blue-500"
+                                                    >
+                                                        "Fix"
+                                                    </a>
+                                                </li>
+                                            }
+                                        })
+                                        .collect_view();
+                                    view! {
+                                        <div class="py-3">
+                                            <div class="flex items-center justify-between">
+                                                <h3 class="text-sm font-semibold text-gray-800 dark:text-gray-200">
+                                                    {section.title}
+                                                </h3>
+                                                <span class="text-xs text-gray-500">{counts}</span>
+                                            </div>
+                                            <ul class="divide-y divide-gray-100 dark:divide-gray-800">
+                                                {issues}
+                                            </ul>
+                                        </div>
+                                    }
+                                })
+                                .collect_view();
+                            Some(
+                                view! {
+                                    <p class="mb-4 text-sm text-gray-600 dark:text-gray-400">
+                                        {summary}
+                                    </p>
+                                    <div class="divide-y divide-gray-200 dark:divide-gray-700">
+                                        {sections}
+                                    </div>
+                                }
+                                    .into_view(),
+                            )
+                        }
+                    }}
+
+                </Transition>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::schema::{Transformer, Type, Validator};
+
+    fn issue(severity: IssueSeverity, field: &str) -> ValidationIssue {
+        ValidationIssue {
+            severity,
+            record: None,
+            field: field.to_string(),
+            message: "Invalid value".to_string(),
+            route: "/settings".to_string(),
+        }
+    }
+
+    #[test]
+    fn aggregate_section_reports() {
+        let report = ValidationReport::new(
+            5,
+            [
+                (
+                    "TLS".to_string(),
+                    issue(IssueSeverity::Warning, "protocols"),
+                ),
+                ("Listeners".to_string(), issue(IssueSeverity::Error, "bind")),
+                (
+                    "TLS".to_string(),
+                    issue(IssueSeverity::Error, "certificate"),
+                ),
+                ("TLS".to_string(), issue(IssueSeverity::Error, "key")),
+            ],
+        );
+
+        assert_eq!(report.sections_checked, 5);
+        assert_eq!(
+            report
+                .sections
+                .iter()
+                .map(|section| (section.title.as_str(), section.errors(), section.warnings()))
+                .collect::<Vec<_>>(),
+            vec![("Listeners", 1, 0), ("TLS", 2, 1)]
+        );
+        assert_eq!(
+            report.sections[1]
+                .issues
+                .iter()
+                .map(|issue| issue.field.as_str())
+                .collect::<Vec<_>>(),
+            vec!["certificate", "key", "protocols"]
+        );
+        assert_eq!(report.total_errors(), 3);
+        assert_eq!(report.total_warnings(), 1);
+
+        let empty = ValidationReport::new(3, []);
+        assert!(empty.sections.is_empty());
+        assert_eq!((empty.total_errors(), empty.total_warnings()), (0, 0));
+    }
+
+    #[test]
+    fn validate_records_of_a_schema() {
+        let schemas = Schemas::builder()
+            .new_schema("listener")
+            .prefix("server.listener")
+            .suffix("protocol")
+            .new_id_field()
+            .build()
+            .new_field("protocol")
+            .build()
+            .new_field("bind")
+            .label("Bind address")
+            .typ(Type::Array)
+            .input_check([Transformer::Trim], [Validator::IsSocketAddr])
+            .build()
+            .new_form_section()
+            .fields(["_id", "protocol", "bind"])
+            .build()
+            .build()
+            .build();
+        let schema = schemas.get("listener");
+        let settings = [
+            ("server.listener.smtp.protocol", "smtp"),
+            ("server.listener.smtp.bind", "[::]:25"),
+            ("server.listener.imap.protocol", "imap"),
+            ("server.listener.imap.bind.0", "[::]:143"),
+            ("server.listener.imap.bind.1", "not-an-address"),
+            ("server.hostname", "mx.example.org"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect::<Settings>();
+
+        let issues = validate_schema(&schema, &settings);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].record.as_deref(), Some("imap"));
+        assert_eq!(issues[0].field, "Bind address");
+        assert_eq!(issues[0].route, "/settings/listener/imap/edit");
+
+        let report = validate_all(&settings, &schemas);
+        assert_eq!(report.sections_checked, 1);
+        assert_eq!(report.total_errors(), 1);
+        assert_eq!(report.sections[0].title, "listener");
+    }
+}
@@ -40,6 +40,7 @@ use crate::{
         oauth::use_authorization,
         url::UrlBuilder,
     },
+    pages::config::bookmarks::BookmarkedSettings,
 };
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -497,6 +498,7 @@ pub fn Dashboard() -> impl IntoView {
             </div>
         </div>
         <Show when=move || { section.get() == Section::Overview }>
+            <BookmarkedSettings/>
             <CardSimple>
                 <CardSimpleItem
                     title="Total Users"
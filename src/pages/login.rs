@@ -25,6 +25,10 @@ use crate::{
         schema::{Builder, Schemas, Transformer, Type, Validator},
         AccessToken, Permissions,
     },
+    utils::{
+        audit::{log_audit_request, AuditAction},
+        security::{check_rate_limit_request, RequestContext},
+    },
     STATE_LOGIN_NAME_KEY, STATE_STORAGE_KEY,
 };
 
@@ -50,7 +54,24 @@ pub fn Login() -> impl IntoView {
             let base_url = base_url.clone();
 
             async move {
-                match oauth_authenticate(&base_url, &username, &password).await {
+                let request = RequestContext::new("POST", "/api/oauth");
+                if let Err(err) = check_rate_limit_request(&username, Some(&request)) {
+                    alert.set(Alert::error(err));
+                    return;
+                }
+
+                let result = oauth_authenticate(&base_url, &username, &password).await;
+                if !matches!(result, AuthenticationResult::TotpRequired) {
+                    log_audit_request(
+                        AuditAction::Login,
+                        &username,
+                        &format!("Login attempt user={username}"),
+                        None,
+                        matches!(result, AuthenticationResult::Success(_)),
+                        request,
+                    );
+                }
+                match result {
                     AuthenticationResult::Success(response) => {
                         let permissions = Permissions::new(response.permissions);
                         let default_url = permissions.default_url(response.is_enterprise);
@@ -20,7 +20,11 @@ use crate::{
         oauth::use_authorization,
         Permission,
     },
-    pages::config::ReloadSettings,
+    pages::config::{
+        bookmarks::use_bookmark_refresh,
+        restart::{RESTART_MESSAGE, RESTART_URL},
+        ReloadSettings,
+    },
 };
 
 #[derive(Debug, Clone, Copy)]
@@ -54,8 +58,8 @@ const ACTIONS: &[Action] = &[
         title: "Restart server",
         description: "Restarts the server. This will interrupt any active connections.",
         icon: "power",
-        url: "/api/restart",
-        success_message: "Restarting server, try reloading this page in a few seconds.",
+        url: RESTART_URL,
+        success_message: RESTART_MESSAGE,
         permission: Permission::Restart,
     },
     Action {
@@ -97,6 +101,7 @@ const ACTIONS: &[Action] = &[
 pub fn Maintenance() -> impl IntoView {
     let auth = use_authorization();
     let alert = use_alerts();
+    let bookmark_refresh = use_bookmark_refresh();
     let (pending, set_pending) = create_signal(false);
 
     let execute = create_action(move |idx: &usize| {
@@ -114,6 +119,7 @@ pub fn Maintenance() -> impl IntoView {
                 {
                     Ok(result) => {
                         set_pending.set(false);
+                        bookmark_refresh.trigger();
                         if result.errors.is_empty() && result.warnings.is_empty() {
                             alert.set(Alert::success(action.success_message).without_timeout());
                         } else {
@@ -1,14 +1,27 @@
 use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
-use std::sync::Mutex;
 use lazy_static::lazy_static;
-use std::collections::VecDeque;
+use regex::{Captures, Regex};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use crate::core::http::HttpRequest;
+
+use super::{lock_or_recover, logging::TARGET_AUDIT, security::RequestContext};
+#[cfg(feature = "local-security")]
+use super::clock;
+
+#[cfg(feature = "local-security")]
 const MAX_AUDIT_LOGS: usize = 1000;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AuditAction {
     ConfigUpdate,
+    ConfigPropose,
+    ConfigApprove,
+    ConfigReject,
+    ConfigExport,
     FileUpload,
     Login,
     Logout,
@@ -17,7 +30,37 @@ pub enum AuditAction {
     AutoSaveToggle,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 审计详情的脱敏策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactionPolicy {
+    /// 原样保存
+    None,
+    /// 脱敏敏感字段（密码、密钥、令牌等）的取值以及形似令牌的长字符串
+    Standard,
+    /// 在 Standard 的基础上脱敏所有 `名称=取值` 形式的取值
+    Strict,
+}
+
+const REDACTED: &str = "[REDACTED]";
+
+impl AuditAction {
+    /// 各操作的脱敏策略。登录相关的详情可能包含凭据，因此采用严格策略。
+    pub fn redaction_policy(&self) -> RedactionPolicy {
+        match self {
+            AuditAction::Login | AuditAction::Logout => RedactionPolicy::Strict,
+            AuditAction::PreviewToggle | AuditAction::AutoSaveToggle => RedactionPolicy::None,
+            AuditAction::ConfigUpdate
+            | AuditAction::ConfigPropose
+            | AuditAction::ConfigApprove
+            | AuditAction::ConfigReject
+            | AuditAction::ConfigExport
+            | AuditAction::FileUpload
+            | AuditAction::ResetConfig => RedactionPolicy::Standard,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AuditLog {
     pub timestamp: DateTime<Utc>,
     pub action: AuditAction,
@@ -25,10 +68,361 @@ pub struct AuditLog {
     pub details: String,
     pub ip_address: Option<String>,
     pub success: bool,
+    #[serde(default)]
+    pub changes: Vec<SettingChange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<RequestContext>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettingChange {
+    pub key: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyHistoryEntry {
+    pub timestamp: DateTime<Utc>,
+    pub user: String,
+    pub change: SettingChange,
+}
+
+lazy_static! {
+    static ref SENSITIVE_VALUE: Regex = Regex::new(
+        r"(?i)\b([\w.-]*(?:password|passwd|secret|token|api[_-]?key|private[_-]?key|credential)[\w.-]*)(\s*[=:]\s*)(\S+)"
+    ).unwrap();
+    static ref BEARER_VALUE: Regex = Regex::new(r"(?i)\b(bearer|basic)(\s+)(\S+)").unwrap();
+    static ref ANY_VALUE: Regex = Regex::new(r"\b([\w.-]+)(\s*=\s*)(\S+)").unwrap();
+    static ref TOKEN_LIKE: Regex = Regex::new(r"[A-Za-z0-9+/_-]{24,}={0,2}").unwrap();
+}
+
+#[cfg(feature = "local-security")]
 lazy_static! {
-    static ref AUDIT_LOGS: Mutex<VecDeque<AuditLog>> = Mutex::new(VecDeque::with_capacity(MAX_AUDIT_LOGS));
+    static ref AUDIT_TRAIL: Mutex<AuditTrail> = Mutex::new(AuditTrail::default());
+}
+
+const DEFAULT_BATCH_SIZE: usize = 20;
+const DEFAULT_MAX_RETRIES: usize = 3;
+const MAX_PENDING_LOGS: usize = 1000;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+const AUDIT_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 审计日志输出目标。`log_audit` 会把每条日志分发给所有已注册的输出目标。
+pub trait AuditSink: Send {
+    fn emit(&mut self, log: &AuditLog);
+
+    /// 立即发送尚未发送的日志
+    fn flush(&mut self) {}
+}
+
+/// 浏览器内存中的日志缓冲区，默认注册，供审计页面读取
+#[cfg(feature = "local-security")]
+#[derive(Clone, Default)]
+pub struct MemorySink {
+    logs: Arc<Mutex<VecDeque<AuditLog>>>,
+}
+
+#[cfg(feature = "local-security")]
+impl MemorySink {
+    pub fn logs(&self) -> Vec<AuditLog> {
+        lock_or_recover(&self.logs).iter().cloned().collect()
+    }
+
+    /// 分页获取日志（最新的在前），只克隆请求的那一页
+    pub fn page(&self, offset: usize, limit: usize) -> Vec<AuditLog> {
+        newest_first_page(&lock_or_recover(&self.logs), offset, limit)
+    }
+
+    pub fn len(&self) -> usize {
+        lock_or_recover(&self.logs).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 删除早于 `cutoff` 的日志
+    pub fn retain_after(&self, cutoff: DateTime<Utc>) {
+        lock_or_recover(&self.logs).retain(|log| log.timestamp > cutoff);
+    }
+}
+
+#[cfg(feature = "local-security")]
+impl AuditSink for MemorySink {
+    fn emit(&mut self, log: &AuditLog) {
+        let mut logs = lock_or_recover(&self.logs);
+        if logs.len() >= MAX_AUDIT_LOGS {
+            logs.pop_front();
+        }
+        logs.push_back(log.clone());
+    }
+}
+
+/// 已注册的审计日志输出目标，内存缓冲区始终位于其中。
+/// 全局实例供应用使用，测试可以创建独立的实例。
+#[cfg(feature = "local-security")]
+pub struct AuditTrail {
+    memory: MemorySink,
+    sinks: Vec<Box<dyn AuditSink>>,
+}
+
+#[cfg(feature = "local-security")]
+impl Default for AuditTrail {
+    fn default() -> Self {
+        let memory = MemorySink::default();
+        Self {
+            sinks: vec![Box::new(memory.clone())],
+            memory,
+        }
+    }
+}
+
+#[cfg(feature = "local-security")]
+impl AuditTrail {
+    pub fn register_sink(&mut self, sink: impl AuditSink + 'static) {
+        self.sinks.push(Box::new(sink));
+    }
+
+    /// 把日志分发给所有输出目标
+    pub fn emit(&mut self, log: &AuditLog) {
+        for sink in self.sinks.iter_mut() {
+            sink.emit(log);
+        }
+    }
+
+    pub fn flush(&mut self) {
+        for sink in self.sinks.iter_mut() {
+            sink.flush();
+        }
+    }
+
+    pub fn memory(&self) -> &MemorySink {
+        &self.memory
+    }
+}
+
+/// 发送结果回调，请求完成（收到 2xx 响应或失败）后调用
+pub type AuditCompletion = Box<dyn FnOnce(Result<(), String>) + Send>;
+
+/// 批量发送审计日志的传输层。发送可以是异步的，完成后必须调用 `done`。
+pub trait AuditTransport: Send + Sync + 'static {
+    fn send(&self, batch: Vec<AuditLog>, done: AuditCompletion);
+
+    /// 等待 `delay` 后调用 `retry`，用于发送失败后的退避重试
+    fn schedule_retry(&self, delay: Duration, retry: Box<dyn FnOnce() + Send>) {
+        leptos::set_timeout(retry, delay);
+    }
+}
+
+/// 通过 HTTP POST 将日志批次以 JSON 数组发送到远程收集端（如 SIEM）
+pub struct HttpTransport {
+    pub url: String,
+}
+
+impl AuditTransport for HttpTransport {
+    fn send(&self, batch: Vec<AuditLog>, done: AuditCompletion) {
+        let request = match HttpRequest::post(self.url.clone()).with_body(batch) {
+            Ok(request) => request,
+            Err(err) => {
+                done(Err(format!("{err:?}")));
+                return;
+            }
+        };
+        leptos::spawn_local(async move {
+            // send_raw 仅在 2xx 响应时返回 Ok
+            done(
+                request
+                    .send_raw()
+                    .await
+                    .map(|_| ())
+                    .map_err(|err| format!("{err:?}")),
+            );
+        });
+    }
+}
+
+/// 远程输出目标：日志先缓存，达到批次大小或调用 `flush` 时发送。
+/// 同一时间只有一个批次在发送，收到成功响应后才从缓存中移除；
+/// 发送失败时按指数退避重试，重试耗尽后保留该批次等待下一次发送。
+pub struct HttpSink<T: AuditTransport> {
+    transport: Arc<T>,
+    queue: Arc<Mutex<SinkQueue>>,
+}
+
+struct SinkQueue {
+    pending: VecDeque<AuditLog>,
+    /// 正在发送的批次长度（位于 `pending` 开头）
+    in_flight: usize,
+    failures: usize,
+    /// 正在等待退避重试，期间不发送新的批次
+    backing_off: bool,
+    flushing: bool,
+    batch_size: usize,
+    max_retries: usize,
+}
+
+impl HttpSink<HttpTransport> {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self::with_transport(HttpTransport { url: url.into() })
+    }
+}
+
+impl<T: AuditTransport> HttpSink<T> {
+    pub fn with_transport(transport: T) -> Self {
+        Self {
+            transport: Arc::new(transport),
+            queue: Arc::new(Mutex::new(SinkQueue {
+                pending: VecDeque::new(),
+                in_flight: 0,
+                failures: 0,
+                backing_off: false,
+                flushing: false,
+                batch_size: DEFAULT_BATCH_SIZE,
+                max_retries: DEFAULT_MAX_RETRIES,
+            })),
+        }
+    }
+
+    pub fn with_batch_size(self, batch_size: usize) -> Self {
+        lock_or_recover(&self.queue).batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_max_retries(self, max_retries: usize) -> Self {
+        lock_or_recover(&self.queue).max_retries = max_retries;
+        self
+    }
+
+    pub fn pending(&self) -> usize {
+        lock_or_recover(&self.queue).pending.len()
+    }
+
+    /// 发送下一个批次。调用 transport 时不持有锁，以便其同步调用 `done`。
+    fn dispatch(queue: &Arc<Mutex<SinkQueue>>, transport: &Arc<T>) {
+        let batch = {
+            let mut queue = lock_or_recover(queue);
+            let len = queue.pending.len().min(queue.batch_size);
+            if queue.in_flight > 0
+                || queue.backing_off
+                || len == 0
+                || (!queue.flushing && queue.pending.len() < queue.batch_size)
+            {
+                return;
+            }
+            queue.in_flight = len;
+            queue.pending.iter().take(len).cloned().collect::<Vec<_>>()
+        };
+
+        let queue_ = queue.clone();
+        let transport_ = transport.clone();
+        transport.send(
+            batch,
+            Box::new(move |result| {
+                let delay = {
+                    let mut queue = lock_or_recover(&queue_);
+                    let len = std::mem::take(&mut queue.in_flight);
+                    let delay = match result {
+                        Ok(()) => {
+                            queue.pending.drain(..len);
+                            queue.failures = 0;
+                            None
+                        }
+                        Err(err) => {
+                            queue.failures += 1;
+                            log::warn!(
+                                target: TARGET_AUDIT,
+                                "Audit log forwarding failed: attempt={} error={err}",
+                                queue.failures
+                            );
+                            if queue.failures > queue.max_retries {
+                                // 保留该批次，等待下一次发送
+                                queue.failures = 0;
+                                queue.flushing = false;
+                                return;
+                            }
+                            queue.backing_off = true;
+                            Some(retry_delay(queue.failures))
+                        }
+                    };
+                    if queue.pending.is_empty() {
+                        queue.flushing = false;
+                    }
+                    delay
+                };
+
+                match delay {
+                    Some(delay) => {
+                        let queue = queue_.clone();
+                        let transport = transport_.clone();
+                        transport_.schedule_retry(
+                            delay,
+                            Box::new(move || {
+                                lock_or_recover(&queue).backing_off = false;
+                                Self::dispatch(&queue, &transport);
+                            }),
+                        );
+                    }
+                    None => Self::dispatch(&queue_, &transport_),
+                }
+            }),
+        );
+    }
+}
+
+/// 第 `attempt` 次失败后的重试间隔，每次加倍，不超过上限
+fn retry_delay(attempt: usize) -> Duration {
+    let factor = u32::try_from(attempt.saturating_sub(1))
+        .ok()
+        .and_then(|shift| 1u32.checked_shl(shift))
+        .unwrap_or(u32::MAX);
+    RETRY_BASE_DELAY
+        .checked_mul(factor)
+        .map_or(MAX_RETRY_DELAY, |delay| delay.min(MAX_RETRY_DELAY))
+}
+
+impl<T: AuditTransport> AuditSink for HttpSink<T> {
+    fn emit(&mut self, log: &AuditLog) {
+        {
+            let mut queue = lock_or_recover(&self.queue);
+            // 正在发送的日志不会被丢弃
+            if queue.pending.len() >= MAX_PENDING_LOGS && queue.pending.len() > queue.in_flight {
+                let in_flight = queue.in_flight;
+                queue.pending.remove(in_flight);
+            }
+            queue.pending.push_back(log.clone());
+        }
+        Self::dispatch(&self.queue, &self.transport);
+    }
+
+    fn flush(&mut self) {
+        lock_or_recover(&self.queue).flushing = true;
+        Self::dispatch(&self.queue, &self.transport);
+    }
+}
+
+/// 注册额外的审计日志输出目标
+#[cfg(feature = "local-security")]
+pub fn register_audit_sink(sink: impl AuditSink + 'static) {
+    lock_or_recover(&AUDIT_TRAIL).register_sink(sink);
+}
+
+/// 发送所有输出目标中缓存的日志
+#[cfg(feature = "local-security")]
+pub fn flush_audit_sinks() {
+    lock_or_recover(&AUDIT_TRAIL).flush();
+}
+
+/// 构建时设置了 `AUDIT_COLLECTOR_URL` 时，把审计日志转发到该收集端，
+/// 并定期以及在页面隐藏时发送缓存的日志
+pub fn init_audit_forwarding() {
+    let Some(url) = option_env!("AUDIT_COLLECTOR_URL").filter(|url| !url.is_empty()) else {
+        return;
+    };
+    register_audit_sink(HttpSink::new(url));
+    _ = leptos::set_interval_with_handle(flush_audit_sinks, AUDIT_FLUSH_INTERVAL);
+    _ = leptos::window_event_listener(leptos::ev::pagehide, |_| flush_audit_sinks());
 }
 
 /// 记录审计日志
@@ -39,32 +433,235 @@ pub fn log_audit(
     ip_address: Option<String>,
     success: bool,
 ) {
-    let log = AuditLog {
-        timestamp: Utc::now(),
+    log_audit_changes(action, user, details, ip_address, success, Vec::new());
+}
+
+/// 记录包含设置变更明细的审计日志
+#[cfg(feature = "local-security")]
+pub fn log_audit_changes(
+    action: AuditAction,
+    user: &str,
+    details: &str,
+    ip_address: Option<String>,
+    success: bool,
+    changes: Vec<SettingChange>,
+) {
+    record_audit(action, user, details, ip_address, success, changes, None);
+}
+
+/// 记录由HTTP请求触发的审计日志，保存请求方法和路径
+#[cfg(feature = "local-security")]
+pub fn log_audit_request(
+    action: AuditAction,
+    user: &str,
+    details: &str,
+    ip_address: Option<String>,
+    success: bool,
+    request: RequestContext,
+) {
+    record_audit(
         action,
-        user: user.to_string(),
-        details: details.to_string(),
+        user,
+        details,
         ip_address,
         success,
-    };
+        Vec::new(),
+        Some(request),
+    );
+}
 
-    let mut logs = AUDIT_LOGS.lock().unwrap();
-    if logs.len() >= MAX_AUDIT_LOGS {
-        logs.pop_front();
+#[cfg(feature = "local-security")]
+fn record_audit(
+    action: AuditAction,
+    user: &str,
+    details: &str,
+    ip_address: Option<String>,
+    success: bool,
+    changes: Vec<SettingChange>,
+    request: Option<RequestContext>,
+) {
+    let log = audit_entry(action, user, details, ip_address, success, changes, request);
+    lock_or_recover(&AUDIT_TRAIL).emit(&log);
+}
+
+/// 构建一条脱敏后的审计日志
+#[cfg(feature = "local-security")]
+fn audit_entry(
+    action: AuditAction,
+    user: &str,
+    details: &str,
+    ip_address: Option<String>,
+    success: bool,
+    changes: Vec<SettingChange>,
+    request: Option<RequestContext>,
+) -> AuditLog {
+    let details = redact_details(details, action.redaction_policy());
+    let endpoint = request
+        .as_ref()
+        .map(|request| format!(" request={request}"))
+        .unwrap_or_default();
+    if success {
+        log::debug!(target: TARGET_AUDIT, "Audit event: action={action:?} user={user}{endpoint}");
+    } else {
+        log::info!(
+            target: TARGET_AUDIT,
+            "Failed audit event: action={action:?} user={user}{endpoint}"
+        );
+    }
+    AuditLog {
+        timestamp: clock::now(),
+        action,
+        user: user.to_string(),
+        details,
+        ip_address,
+        success,
+        changes,
+        request,
     }
-    logs.push_back(log);
+}
+
+/// 按策略脱敏审计详情，替换形似密钥、令牌或密码的内容，其余文本保持不变
+pub fn redact_details(details: &str, policy: RedactionPolicy) -> String {
+    let redact_value = |caps: &Captures| format!("{}{}{REDACTED}", &caps[1], &caps[2]);
+
+    let details = match policy {
+        RedactionPolicy::None => return details.to_string(),
+        RedactionPolicy::Standard => SENSITIVE_VALUE.replace_all(details, redact_value),
+        RedactionPolicy::Strict => ANY_VALUE.replace_all(details, redact_value),
+    };
+    let details = BEARER_VALUE.replace_all(&details, redact_value);
+    TOKEN_LIKE
+        .replace_all(&details, |caps: &Captures| {
+            let value = &caps[0];
+            if value.bytes().any(|ch| ch.is_ascii_digit())
+                && value.bytes().any(|ch| ch.is_ascii_alphabetic())
+            {
+                REDACTED.to_string()
+            } else {
+                value.to_string()
+            }
+        })
+        .into_owned()
 }
 
 /// 获取审计日志
+#[cfg(feature = "local-security")]
 pub fn get_audit_logs() -> Vec<AuditLog> {
-    AUDIT_LOGS.lock().unwrap().iter().cloned().collect()
+    lock_or_recover(&AUDIT_TRAIL).memory().logs()
+}
+
+/// 分页获取审计日志（最新的在前），只克隆请求的那一页
+#[cfg(feature = "local-security")]
+pub fn get_audit_logs_page(offset: usize, limit: usize) -> Vec<AuditLog> {
+    lock_or_recover(&AUDIT_TRAIL).memory().page(offset, limit)
+}
+
+/// 审计日志总数
+#[cfg(feature = "local-security")]
+pub fn audit_log_count() -> usize {
+    lock_or_recover(&AUDIT_TRAIL).memory().len()
+}
+
+#[cfg(feature = "local-security")]
+fn newest_first_page(logs: &VecDeque<AuditLog>, offset: usize, limit: usize) -> Vec<AuditLog> {
+    logs.iter().rev().skip(offset).take(limit).cloned().collect()
+}
+
+/// 审计日志查询条件，空条件匹配所有日志
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditQuery {
+    /// 只保留这些操作，为空时不限制
+    pub actions: Vec<AuditAction>,
+    pub success: Option<bool>,
+    /// 在用户、详情和变更的设置项中查找，不区分大小写
+    pub text: String,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, log: &AuditLog) -> bool {
+        (self.actions.is_empty() || self.actions.contains(&log.action))
+            && self.matches_facet_filters(log)
+    }
+
+    /// 除操作类型之外的条件，用于计算各操作的数量
+    fn matches_facet_filters(&self, log: &AuditLog) -> bool {
+        let text = self.text.trim().to_lowercase();
+        self.success.map_or(true, |success| log.success == success)
+            && (text.is_empty()
+                || log.user.to_lowercase().contains(&text)
+                || log.details.to_lowercase().contains(&text)
+                || log
+                    .changes
+                    .iter()
+                    .any(|change| change.key.to_lowercase().contains(&text)))
+    }
+}
+
+/// 按条件筛选的审计日志（最新的在前）及各操作类型的数量
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditQueryResult {
+    pub logs: Vec<AuditLog>,
+    /// 各操作在其余条件下匹配的日志数，不受所选操作的影响
+    pub facets: HashMap<AuditAction, usize>,
+}
+
+/// 查询审计日志并同时统计各操作的数量
+pub fn query_audit_logs(filter: &AuditQuery) -> AuditQueryResult {
+    query_logs(get_audit_logs().iter().rev(), filter)
+}
+
+/// 各操作类型在当前条件下的数量，用于在筛选项旁显示
+pub fn audit_facets(filter: &AuditQuery) -> HashMap<AuditAction, usize> {
+    query_audit_logs(filter).facets
+}
+
+fn query_logs<'x>(
+    logs: impl Iterator<Item = &'x AuditLog>,
+    filter: &AuditQuery,
+) -> AuditQueryResult {
+    let mut result = AuditQueryResult::default();
+    for log in logs.filter(|log| filter.matches_facet_filters(log)) {
+        *result.facets.entry(log.action.clone()).or_default() += 1;
+        if filter.actions.is_empty() || filter.actions.contains(&log.action) {
+            result.logs.push(log.clone());
+        }
+    }
+    result
+}
+
+/// 获取单个设置项最近的变更历史，按时间倒序排列。
+/// 表达式和数组的子键（如 `key.0.if`）也视为该设置项的变更。
+pub fn key_history(logs: &[AuditLog], key: &str, limit: usize) -> Vec<KeyHistoryEntry> {
+    let prefix = format!("{key}.");
+    let mut logs = logs
+        .iter()
+        .enumerate()
+        .filter(|(_, log)| log.success)
+        .collect::<Vec<_>>();
+    logs.sort_by(|(a_idx, a), (b_idx, b)| {
+        b.timestamp.cmp(&a.timestamp).then_with(|| b_idx.cmp(a_idx))
+    });
+
+    logs.into_iter()
+        .flat_map(|(_, log)| {
+            log.changes
+                .iter()
+                .filter(|change| change.key == key || change.key.starts_with(&prefix))
+                .map(|change| KeyHistoryEntry {
+                    timestamp: log.timestamp,
+                    user: log.user.clone(),
+                    change: change.clone(),
+                })
+        })
+        .take(limit)
+        .collect()
 }
 
 /// 清理过期的审计日志
+#[cfg(feature = "local-security")]
 pub fn cleanup_audit_logs(max_age_days: i64) {
-    let mut logs = AUDIT_LOGS.lock().unwrap();
-    let cutoff = Utc::now() - chrono::Duration::days(max_age_days);
-    logs.retain(|log| log.timestamp > cutoff);
+    let cutoff = clock::now() - chrono::Duration::days(max_age_days);
+    lock_or_recover(&AUDIT_TRAIL).memory().retain_after(cutoff);
 }
 
 /// 导出审计日志
@@ -73,22 +670,141 @@ pub fn export_audit_logs() -> String {
     serde_json::to_string_pretty(&logs).unwrap_or_default()
 }
 
-#[cfg(test)]
+/// 导出指定时间范围内（包含两端）的审计日志，保持原有顺序
+pub fn export_audit_logs_between(since: DateTime<Utc>, until: DateTime<Utc>) -> String {
+    let logs = audit_logs_between(&get_audit_logs(), since, until);
+    serde_json::to_string_pretty(&logs).unwrap_or_default()
+}
+
+/// 筛选时间范围内（包含两端）的审计日志，保持原有顺序
+pub fn audit_logs_between(
+    logs: &[AuditLog],
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Vec<AuditLog> {
+    logs.iter()
+        .filter(|log| log.timestamp >= since && log.timestamp <= until)
+        .cloned()
+        .collect()
+}
+
+/// 审计由服务端负责时使用的空实现：日志不在浏览器中保存或转发。
+#[cfg(not(feature = "local-security"))]
+mod disabled {
+    use super::{AuditAction, AuditLog, AuditSink, RequestContext, SettingChange};
+
+    pub fn register_audit_sink(_sink: impl AuditSink + 'static) {}
+
+    pub fn flush_audit_sinks() {}
+
+    pub fn log_audit_changes(
+        _action: AuditAction,
+        _user: &str,
+        _details: &str,
+        _ip_address: Option<String>,
+        _success: bool,
+        _changes: Vec<SettingChange>,
+    ) {
+    }
+
+    pub fn log_audit_request(
+        _action: AuditAction,
+        _user: &str,
+        _details: &str,
+        _ip_address: Option<String>,
+        _success: bool,
+        _request: RequestContext,
+    ) {
+    }
+
+    pub fn get_audit_logs() -> Vec<AuditLog> {
+        Vec::new()
+    }
+
+    pub fn get_audit_logs_page(_offset: usize, _limit: usize) -> Vec<AuditLog> {
+        Vec::new()
+    }
+
+    pub fn audit_log_count() -> usize {
+        0
+    }
+
+    pub fn cleanup_audit_logs(_max_age_days: i64) {}
+}
+
+#[cfg(not(feature = "local-security"))]
+pub use disabled::*;
+
+#[cfg(all(test, not(feature = "local-security")))]
+mod disabled_tests {
+    use super::*;
+
+    struct NoopSink;
+
+    impl AuditSink for NoopSink {
+        fn emit(&mut self, _log: &AuditLog) {}
+    }
+
+    #[test]
+    fn stubs_match_signatures() {
+        let _: fn(AuditAction, &str, &str, Option<String>, bool) = log_audit;
+        let _: fn(AuditAction, &str, &str, Option<String>, bool, Vec<SettingChange>) =
+            log_audit_changes;
+        let _: fn(AuditAction, &str, &str, Option<String>, bool, RequestContext) =
+            log_audit_request;
+        let _: fn() -> Vec<AuditLog> = get_audit_logs;
+        let _: fn(usize, usize) -> Vec<AuditLog> = get_audit_logs_page;
+        let _: fn() -> usize = audit_log_count;
+        let _: fn(i64) = cleanup_audit_logs;
+        let _: fn() = flush_audit_sinks;
+        let _: fn(NoopSink) = register_audit_sink;
+    }
+
+    #[test]
+    fn stubs_keep_no_state() {
+        register_audit_sink(NoopSink);
+        log_audit(AuditAction::ConfigUpdate, "user", "Saved", None, true);
+        flush_audit_sinks();
+        cleanup_audit_logs(30);
+        assert!(get_audit_logs().is_empty());
+        assert!(get_audit_logs_page(0, 10).is_empty());
+        assert_eq!(audit_log_count(), 0);
+        assert_eq!(export_audit_logs(), "[]");
+        assert_eq!(export_audit_logs_between(Utc::now(), Utc::now()), "[]");
+    }
+}
+
+#[cfg(all(test, feature = "local-security"))]
 mod tests {
     use super::*;
 
+    fn entry(user: &str, request: Option<RequestContext>) -> AuditLog {
+        audit_entry(
+            AuditAction::ConfigUpdate,
+            user,
+            "Saved",
+            None,
+            true,
+            Vec::new(),
+            request,
+        )
+    }
+
     #[test]
     fn test_audit_logging() {
-        log_audit(
+        let mut trail = AuditTrail::default();
+        trail.emit(&audit_entry(
             AuditAction::ConfigUpdate,
             "test_user",
             "Updated layout configuration",
             Some("127.0.0.1".to_string()),
             true,
-        );
+            Vec::new(),
+            None,
+        ));
 
-        let logs = get_audit_logs();
-        assert!(!logs.is_empty());
+        let logs = trail.memory().logs();
+        assert_eq!(logs.len(), 1);
         assert_eq!(logs[0].user, "test_user");
         assert_eq!(logs[0].action, AuditAction::ConfigUpdate);
     }
@@ -96,17 +812,521 @@ mod tests {
     #[test]
     fn test_audit_log_rotation() {
         // 填充超过最大限制的日志
+        let mut trail = AuditTrail::default();
         for i in 0..MAX_AUDIT_LOGS + 1 {
-            log_audit(
-                AuditAction::ConfigUpdate,
-                &format!("user_{}", i),
-                "Test log",
-                None,
-                true,
-            );
+            trail.emit(&entry(&format!("user_{}", i), None));
         }
 
-        let logs = get_audit_logs();
+        let logs = trail.memory().logs();
         assert_eq!(logs.len(), MAX_AUDIT_LOGS);
+        assert_eq!(logs[0].user, "user_1");
+    }
+
+    fn audit_log(details: &str) -> AuditLog {
+        AuditLog {
+            timestamp: Utc::now(),
+            action: AuditAction::ConfigUpdate,
+            user: "sink_user".to_string(),
+            details: details.to_string(),
+            ip_address: None,
+            success: true,
+            changes: Vec::new(),
+            request: None,
+        }
+    }
+
+    struct RecordingSink(Arc<Mutex<Vec<String>>>);
+
+    impl AuditSink for RecordingSink {
+        fn emit(&mut self, log: &AuditLog) {
+            self.0.lock().unwrap().push(log.user.clone());
+        }
+    }
+
+    #[derive(Default)]
+    struct MockTransport {
+        batches: Arc<Mutex<Vec<Vec<String>>>>,
+        attempts: Arc<Mutex<usize>>,
+        delays: Arc<Mutex<Vec<Duration>>>,
+        failures: Mutex<usize>,
+    }
+
+    impl AuditTransport for MockTransport {
+        fn send(&self, batch: Vec<AuditLog>, done: AuditCompletion) {
+            *self.attempts.lock().unwrap() += 1;
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                drop(failures);
+                done(Err("connection refused".to_string()));
+                return;
+            }
+            drop(failures);
+            self.batches
+                .lock()
+                .unwrap()
+                .push(batch.iter().map(|log| log.details.clone()).collect());
+            done(Ok(()));
+        }
+
+        fn schedule_retry(&self, delay: Duration, retry: Box<dyn FnOnce() + Send>) {
+            self.delays.lock().unwrap().push(delay);
+            retry();
+        }
+    }
+
+    /// 模拟异步请求：回调在测试中手动触发
+    #[derive(Default)]
+    struct DeferredTransport {
+        requests: Arc<Mutex<Vec<(Vec<String>, AuditCompletion)>>>,
+    }
+
+    impl DeferredTransport {
+        fn complete(
+            requests: &Mutex<Vec<(Vec<String>, AuditCompletion)>>,
+            result: Result<(), String>,
+        ) -> Vec<String> {
+            let (batch, done) = requests.lock().unwrap().remove(0);
+            done(result);
+            batch
+        }
+    }
+
+    impl AuditTransport for DeferredTransport {
+        fn send(&self, batch: Vec<AuditLog>, done: AuditCompletion) {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((batch.iter().map(|log| log.details.clone()).collect(), done));
+        }
+
+        fn schedule_retry(&self, _delay: Duration, retry: Box<dyn FnOnce() + Send>) {
+            retry();
+        }
+    }
+
+    #[test]
+    fn test_audit_sink_fan_out() {
+        let first = Arc::new(Mutex::new(Vec::new()));
+        let second = Arc::new(Mutex::new(Vec::new()));
+        let mut trail = AuditTrail::default();
+        trail.register_sink(RecordingSink(first.clone()));
+        trail.register_sink(RecordingSink(second.clone()));
+
+        trail.emit(&entry("fan_out_user", None));
+
+        for sink in [first, second] {
+            assert_eq!(*sink.lock().unwrap(), vec!["fan_out_user"]);
+        }
+        assert_eq!(trail.memory().len(), 1);
+    }
+
+    #[test]
+    fn test_http_sink_batching() {
+        let transport = MockTransport::default();
+        let batches = transport.batches.clone();
+        let mut sink = HttpSink::with_transport(transport).with_batch_size(3);
+
+        sink.emit(&audit_log("1"));
+        sink.emit(&audit_log("2"));
+        assert!(batches.lock().unwrap().is_empty());
+        assert_eq!(sink.pending(), 2);
+
+        sink.emit(&audit_log("3"));
+        sink.emit(&audit_log("4"));
+        assert_eq!(*batches.lock().unwrap(), vec![vec!["1", "2", "3"]]);
+        assert_eq!(sink.pending(), 1);
+
+        sink.flush();
+        assert_eq!(batches.lock().unwrap()[1], vec!["4"]);
+        assert_eq!(sink.pending(), 0);
+    }
+
+    #[test]
+    fn test_http_sink_retries() {
+        let transport = MockTransport {
+            failures: Mutex::new(2),
+            ..Default::default()
+        };
+        let batches = transport.batches.clone();
+        let attempts = transport.attempts.clone();
+        let delays = transport.delays.clone();
+        let mut sink = HttpSink::with_transport(transport)
+            .with_batch_size(2)
+            .with_max_retries(1);
+
+        // 首次发送及一次退避重试均失败，批次保留
+        sink.emit(&audit_log("1"));
+        sink.emit(&audit_log("2"));
+        assert_eq!(*attempts.lock().unwrap(), 2);
+        assert_eq!(*delays.lock().unwrap(), vec![RETRY_BASE_DELAY]);
+        assert!(batches.lock().unwrap().is_empty());
+        assert_eq!(sink.pending(), 2);
+
+        sink.flush();
+        assert_eq!(*attempts.lock().unwrap(), 3);
+        assert_eq!(*batches.lock().unwrap(), vec![vec!["1", "2"]]);
+        assert_eq!(sink.pending(), 0);
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        assert_eq!(
+            (1..=8).map(retry_delay).collect::<Vec<_>>(),
+            [1, 2, 4, 8, 16, 32, 60, 60]
+                .into_iter()
+                .map(Duration::from_secs)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(retry_delay(usize::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_http_sink_backs_off_before_retrying() {
+        /// 退避期间不发送新的批次，直到手动触发重试
+        #[derive(Default)]
+        struct PausedTransport {
+            attempts: Arc<Mutex<usize>>,
+            retries: Arc<Mutex<Vec<Box<dyn FnOnce() + Send>>>>,
+        }
+
+        impl AuditTransport for PausedTransport {
+            fn send(&self, _batch: Vec<AuditLog>, done: AuditCompletion) {
+                *self.attempts.lock().unwrap() += 1;
+                done(Err("offline".to_string()));
+            }
+
+            fn schedule_retry(&self, _delay: Duration, retry: Box<dyn FnOnce() + Send>) {
+                self.retries.lock().unwrap().push(retry);
+            }
+        }
+
+        let transport = PausedTransport::default();
+        let attempts = transport.attempts.clone();
+        let retries = transport.retries.clone();
+        let mut sink = HttpSink::with_transport(transport)
+            .with_batch_size(1)
+            .with_max_retries(1);
+
+        sink.emit(&audit_log("1"));
+        sink.emit(&audit_log("2"));
+        sink.flush();
+        assert_eq!(*attempts.lock().unwrap(), 1);
+        assert_eq!(sink.pending(), 2);
+
+        let retry = retries.lock().unwrap().remove(0);
+        retry();
+        assert_eq!(*attempts.lock().unwrap(), 2);
+        assert_eq!(sink.pending(), 2);
+    }
+
+    #[test]
+    fn test_http_sink_waits_for_response() {
+        let transport = DeferredTransport::default();
+        let requests = transport.requests.clone();
+        let mut sink = HttpSink::with_transport(transport)
+            .with_batch_size(2)
+            .with_max_retries(1);
+
+        // 批次在收到响应前保留，且同一时间只发送一个批次
+        sink.emit(&audit_log("1"));
+        sink.emit(&audit_log("2"));
+        sink.emit(&audit_log("3"));
+        sink.emit(&audit_log("4"));
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        assert_eq!(sink.pending(), 4);
+
+        // 网络错误触发重试，重试耗尽后批次保留
+        let failed = DeferredTransport::complete(&requests, Err("offline".to_string()));
+        assert_eq!(sink.pending(), 4);
+        let retried = DeferredTransport::complete(&requests, Err("offline".to_string()));
+        assert_eq!(failed, retried);
+        assert!(requests.lock().unwrap().is_empty());
+        assert_eq!(sink.pending(), 4);
+
+        // 仅在成功后移除，并继续发送下一批次
+        sink.flush();
+        assert_eq!(
+            DeferredTransport::complete(&requests, Ok(())),
+            vec!["1", "2"]
+        );
+        assert_eq!(sink.pending(), 2);
+        assert_eq!(
+            DeferredTransport::complete(&requests, Ok(())),
+            vec!["3", "4"]
+        );
+        assert_eq!(sink.pending(), 0);
+        assert!(requests.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_recover_from_poisoned_locks() {
+        let trail = Arc::new(Mutex::new(AuditTrail::default()));
+        let memory = lock_or_recover(&trail).memory().clone();
+        let trail_ = trail.clone();
+        let _ = std::thread::spawn(move || {
+            let _trail = trail_.lock().unwrap();
+            let _logs = memory.logs.lock().unwrap();
+            panic!("poison the audit locks");
+        })
+        .join();
+        assert!(trail.is_poisoned());
+
+        let mut trail = lock_or_recover(&trail);
+        trail.emit(&entry("poisoned_user", None));
+        assert_eq!(trail.memory().logs()[0].user, "poisoned_user");
+        trail.flush();
+        trail
+            .memory()
+            .retain_after(clock::now() - chrono::Duration::days(30));
+        assert_eq!(trail.memory().len(), 1);
+    }
+
+    #[test]
+    fn test_audit_request_context() {
+        let log = entry(
+            "request_user",
+            Some(RequestContext::new("post", "/api/settings")),
+        );
+        assert_eq!(
+            log.request,
+            Some(RequestContext {
+                method: "POST".to_string(),
+                path: "/api/settings".to_string(),
+            })
+        );
+        assert!(serde_json::to_string_pretty(&[log])
+            .unwrap()
+            .contains("\"path\": \"/api/settings\""));
+
+        let log = entry("plain_user", None);
+        assert!(log.request.is_none());
+        assert!(!serde_json::to_string(&log).unwrap().contains("request"));
+    }
+
+    #[test]
+    fn test_audit_facets() {
+        let entry = |action: AuditAction, user: &str, success: bool| AuditLog {
+            action,
+            user: user.to_string(),
+            success,
+            ..audit_log("Saved")
+        };
+        let logs = vec![
+            entry(AuditAction::ConfigUpdate, "alice", true),
+            entry(AuditAction::ConfigUpdate, "bob", false),
+            entry(AuditAction::ConfigUpdate, "alice", true),
+            entry(AuditAction::Login, "alice", true),
+            entry(AuditAction::Login, "bob", false),
+            entry(AuditAction::FileUpload, "bob", true),
+        ];
+
+        let successful = AuditQuery {
+            success: Some(true),
+            ..Default::default()
+        };
+        let result = query_logs(logs.iter(), &successful);
+        assert_eq!(result.logs.len(), 4);
+        assert_eq!(
+            result.facets,
+            HashMap::from([
+                (AuditAction::ConfigUpdate, 2),
+                (AuditAction::Login, 1),
+                (AuditAction::FileUpload, 1),
+            ])
+        );
+
+        let filter = AuditQuery {
+            actions: vec![AuditAction::Login],
+            success: Some(true),
+            text: "ALICE".to_string(),
+        };
+        let result = query_logs(logs.iter(), &filter);
+        assert_eq!(result.logs, vec![logs[3].clone()]);
+        assert_eq!(
+            result.facets,
+            HashMap::from([(AuditAction::ConfigUpdate, 2), (AuditAction::Login, 1)])
+        );
+        assert!(filter.matches(&logs[3]));
+        assert!(!filter.matches(&logs[0]));
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_audit_logs_page() {
+        let logs = (0..10)
+            .map(|i| AuditLog {
+                user: format!("user_{i}"),
+                ..audit_log("Saved")
+            })
+            .collect::<VecDeque<_>>();
+        let users = |offset, limit| {
+            newest_first_page(&logs, offset, limit)
+                .into_iter()
+                .map(|log| log.user)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(users(0, 3), vec!["user_9", "user_8", "user_7"]);
+        assert_eq!(users(4, 2), vec!["user_5", "user_4"]);
+        assert_eq!(users(8, 5), vec!["user_1", "user_0"]);
+        assert!(users(10, 5).is_empty());
+        assert!(users(25, 5).is_empty());
+
+        let mut trail = AuditTrail::default();
+        for user in ["first_user", "page_user"] {
+            trail.emit(&entry(user, None));
+        }
+        assert_eq!(trail.memory().len(), 2);
+        assert_eq!(
+            trail
+                .memory()
+                .page(0, 1)
+                .into_iter()
+                .map(|log| log.user)
+                .collect::<Vec<_>>(),
+            vec!["page_user"]
+        );
+    }
+
+    #[test]
+    fn test_audit_logs_between() {
+        let start = Utc::now();
+        let logs = (0..4)
+            .map(|i| AuditLog {
+                timestamp: start + chrono::Duration::minutes(i),
+                user: format!("user_{i}"),
+                ..audit_log("Saved")
+            })
+            .collect::<Vec<_>>();
+        let users = |logs: Vec<AuditLog>| logs.into_iter().map(|log| log.user).collect::<Vec<_>>();
+
+        assert_eq!(
+            users(audit_logs_between(
+                &logs,
+                start + chrono::Duration::minutes(1),
+                start + chrono::Duration::minutes(2)
+            )),
+            vec!["user_1", "user_2"]
+        );
+        assert_eq!(
+            users(audit_logs_between(&logs, start, start + chrono::Duration::minutes(3))),
+            vec!["user_0", "user_1", "user_2", "user_3"]
+        );
+        assert!(audit_logs_between(
+            &logs,
+            start + chrono::Duration::seconds(10),
+            start + chrono::Duration::seconds(50)
+        )
+        .is_empty());
+        assert!(audit_logs_between(&logs, start + chrono::Duration::minutes(2), start).is_empty());
+    }
+
+    #[test]
+    fn test_redact_details() {
+        let standard = RedactionPolicy::Standard;
+        assert_eq!(
+            redact_details("Generated token aB3dE5fG7hJ9kL1mN3pQ5rS7tU9v for client", standard),
+            "Generated token [REDACTED] for client"
+        );
+        assert_eq!(
+            redact_details("Updated api_key=abc123 and password: hunter2", standard),
+            "Updated api_key=[REDACTED] and password: [REDACTED]"
+        );
+        assert_eq!(
+            redact_details("Authorization: Bearer abc.def.ghi", standard),
+            "Authorization: Bearer [REDACTED]"
+        );
+        assert_eq!(
+            redact_details("Login attempt user=admin", RedactionPolicy::Strict),
+            "Login attempt user=[REDACTED]"
+        );
+        assert_eq!(
+            redact_details("Updated api_key=abc123", RedactionPolicy::None),
+            "Updated api_key=abc123"
+        );
+    }
+
+    #[test]
+    fn test_redact_details_keeps_benign_text() {
+        for details in [
+            "Updated layout config: title=Stalwart Management",
+            "Successfully uploaded new logo",
+            "Saved server.listener.smtp.bind and authentication.fallback-admin",
+            "Exported settings_configuration_backup_file",
+        ] {
+            assert_eq!(redact_details(details, RedactionPolicy::Standard), details);
+        }
+    }
+
+    #[test]
+    fn test_key_history() {
+        let at = |secs: i64| DateTime::<Utc>::from_timestamp(1_700_000_000 + secs, 0).unwrap();
+        let change = |key: &str, old: Option<&str>, new: Option<&str>| SettingChange {
+            key: key.to_string(),
+            old: old.map(str::to_string),
+            new: new.map(str::to_string),
+        };
+        let log = |secs, user: &str, success, changes| AuditLog {
+            timestamp: at(secs),
+            action: AuditAction::ConfigUpdate,
+            user: user.to_string(),
+            details: String::new(),
+            ip_address: None,
+            success,
+            changes,
+            request: None,
+        };
+        let logs = vec![
+            log(
+                10,
+                "alice",
+                true,
+                vec![change("server.hostname", None, Some("mx1"))],
+            ),
+            log(
+                20,
+                "bob",
+                true,
+                vec![
+                    change("server.hostname", Some("mx1"), Some("mx2")),
+                    change("server.hostname-alias", None, Some("mx")),
+                ],
+            ),
+            log(
+                30,
+                "carol",
+                false,
+                vec![change("server.hostname", Some("mx2"), Some("mx3"))],
+            ),
+            log(
+                15,
+                "dave",
+                true,
+                vec![change("session.rcpt.relay.0.if", None, Some("true"))],
+            ),
+            log(
+                40,
+                "erin",
+                true,
+                vec![change("server.hostname", Some("mx2"), None)],
+            ),
+        ];
+
+        let history = key_history(&logs, "server.hostname", 10);
+        assert_eq!(
+            history
+                .iter()
+                .map(|entry| (entry.user.as_str(), entry.change.new.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![("erin", None), ("bob", Some("mx2")), ("alice", Some("mx1"))]
+        );
+        assert_eq!(history[0].timestamp, at(40));
+        assert_eq!(key_history(&logs, "server.hostname", 1).len(), 1);
+
+        // Nested keys belong to the setting
+        let history = key_history(&logs, "session.rcpt.relay", 10);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].change.key, "session.rcpt.relay.0.if");
+        assert!(key_history(&logs, "server.host", 10).is_empty());
+    }
+}
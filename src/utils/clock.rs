@@ -0,0 +1,89 @@
+use std::{cell::RefCell, sync::Arc, sync::Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+
+use super::lock_or_recover;
+
+/// 时间来源。审计、安全等依赖时间的模块通过 `now()` 读取当前时间，
+/// 测试中可替换为可控的时钟，无需真实等待。
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// 系统时钟
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// 可手动设置和推进的时钟，用于测试
+#[derive(Debug)]
+pub struct FakeClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl FakeClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *lock_or_recover(&self.now) = now;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *lock_or_recover(&self.now) += duration;
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<Utc> {
+        *lock_or_recover(&self.now)
+    }
+}
+
+thread_local! {
+    static CLOCK: RefCell<Option<Arc<dyn Clock>>> = const { RefCell::new(None) };
+}
+
+/// 替换当前线程使用的时钟，传入 `None` 恢复为系统时钟
+pub fn set_clock(clock: Option<Arc<dyn Clock>>) {
+    CLOCK.with(|current| *current.borrow_mut() = clock);
+}
+
+/// 在当前线程安装一个从 `start` 开始的可控时钟并返回它
+pub fn install_fake_clock(start: DateTime<Utc>) -> Arc<FakeClock> {
+    let clock = Arc::new(FakeClock::new(start));
+    set_clock(Some(clock.clone()));
+    clock
+}
+
+pub fn now() -> DateTime<Utc> {
+    CLOCK
+        .with(|clock| clock.borrow().as_ref().map(|clock| clock.now()))
+        .unwrap_or_else(|| SystemClock.now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fake_clock_controls_now() {
+        let start = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        let clock = install_fake_clock(start);
+        assert_eq!(now(), start);
+
+        clock.advance(Duration::seconds(90));
+        assert_eq!(now(), start + Duration::seconds(90));
+
+        set_clock(None);
+        assert!(now() > start + Duration::days(365));
+    }
+}
@@ -0,0 +1,265 @@
+use std::cell::{Cell, RefCell};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use chrono_humanize::HumanTime;
+use humansize::{format_size, FormatSizeOptions, DECIMAL};
+
+use super::clock;
+
+const INVARIANT_LANGUAGE: &str = "en-US";
+
+/// 格式化数字、大小和时间时使用的区域设置
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatLocale {
+    pub language: String,
+    /// 与 UTC 的偏移（秒），东区为正
+    pub utc_offset: i32,
+}
+
+thread_local! {
+    static DETERMINISTIC_NOW: Cell<Option<DateTime<Utc>>> = const { Cell::new(None) };
+    static AMBIENT_OVERRIDE: RefCell<Option<FormatLocale>> = const { RefCell::new(None) };
+}
+
+impl FormatLocale {
+    /// 与运行环境无关的区域设置：英文数字格式及 UTC 时区
+    pub fn invariant() -> Self {
+        Self {
+            language: INVARIANT_LANGUAGE.to_string(),
+            utc_offset: 0,
+        }
+    }
+
+    /// 浏览器当前的语言和时区
+    pub fn ambient() -> Self {
+        AMBIENT_OVERRIDE
+            .with(|locale| locale.borrow().clone())
+            .unwrap_or_else(browser_locale)
+    }
+
+    fn primary_language(&self) -> &str {
+        self.language.split(['-', '_']).next().unwrap_or_default()
+    }
+
+    pub fn decimal_separator(&self) -> char {
+        match self.primary_language() {
+            "de" | "es" | "fr" | "it" | "nl" | "pt" | "ru" | "pl" | "tr" | "sv" | "da" => ',',
+            _ => '.',
+        }
+    }
+
+    pub fn group_separator(&self) -> char {
+        match self.primary_language() {
+            "de" | "es" | "it" | "nl" | "pt" | "tr" | "da" => '.',
+            "fr" | "ru" | "pl" | "sv" => '\u{202f}',
+            _ => ',',
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn browser_locale() -> FormatLocale {
+    let language = js_sys::Reflect::get(&js_sys::global(), &"navigator".into())
+        .and_then(|navigator| js_sys::Reflect::get(&navigator, &"language".into()))
+        .ok()
+        .and_then(|language| language.as_string())
+        .unwrap_or_else(|| INVARIANT_LANGUAGE.to_string());
+    // getTimezoneOffset 返回 UTC 减本地时间的分钟数
+    let utc_offset = -(js_sys::Date::new_0().get_timezone_offset() as i32) * 60;
+    FormatLocale {
+        language,
+        utc_offset,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn browser_locale() -> FormatLocale {
+    FormatLocale::invariant()
+}
+
+/// 启用或关闭确定性格式化模式。启用后忽略浏览器的语言和时区，
+/// 统一使用不变区域设置，相对时间以给定的固定时刻为“现在”。
+pub fn set_deterministic_formatting(now: Option<DateTime<Utc>>) {
+    DETERMINISTIC_NOW.with(|fixed| fixed.set(now));
+}
+
+/// 覆盖浏览器的区域设置，传入 `None` 恢复为浏览器设置
+pub fn set_ambient_locale(locale: Option<FormatLocale>) {
+    AMBIENT_OVERRIDE.with(|ambient| *ambient.borrow_mut() = locale);
+}
+
+pub fn is_deterministic() -> bool {
+    DETERMINISTIC_NOW.with(|fixed| fixed.get().is_some())
+}
+
+pub fn current_locale() -> FormatLocale {
+    if is_deterministic() {
+        FormatLocale::invariant()
+    } else {
+        FormatLocale::ambient()
+    }
+}
+
+/// 相对时间格式化使用的当前时刻
+pub fn now() -> DateTime<Utc> {
+    DETERMINISTIC_NOW
+        .with(|fixed| fixed.get())
+        .unwrap_or_else(clock::now)
+}
+
+/// 按区域设置添加千位分隔符
+pub fn format_number(number: u64) -> String {
+    let separator = current_locale().group_separator();
+    let digits = number.to_string();
+    let mut result = String::with_capacity(digits.len() + digits.len() / 3);
+    for (pos, ch) in digits.chars().enumerate() {
+        if pos > 0 && (digits.len() - pos) % 3 == 0 {
+            result.push(separator);
+        }
+        result.push(ch);
+    }
+    result
+}
+
+/// 格式化字节数，小数点按区域设置替换
+///
+/// 指定小数位数时不足的位数补零，未指定时使用 humansize 的默认精度。
+pub fn format_bytes(bytes: u64, decimal_places: Option<usize>) -> String {
+    let options = match decimal_places {
+        Some(places) => FormatSizeOptions::from(DECIMAL)
+            .decimal_places(places)
+            .decimal_zeroes(places),
+        None => FormatSizeOptions::from(DECIMAL),
+    };
+    let separator = current_locale().decimal_separator();
+    format_size(bytes, options).replace('.', &separator.to_string())
+}
+
+/// 格式化带符号的数字，整数部分按区域设置添加千位分隔符，无法解析时返回 `None`
+pub fn format_signed_number(value: &str) -> Option<String> {
+    let value = value.trim();
+    let (sign, digits) = match value.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", value.strip_prefix('+').unwrap_or(value)),
+    };
+    let (integer, fraction) = match digits.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (digits, None),
+    };
+    if fraction.is_some_and(|fraction| !fraction.bytes().all(|ch| ch.is_ascii_digit())) {
+        return None;
+    }
+
+    let mut result = format!("{sign}{}", format_number(integer.parse::<u64>().ok()?));
+    if let Some(fraction) = fraction.filter(|fraction| !fraction.is_empty()) {
+        result.push(current_locale().decimal_separator());
+        result.push_str(fraction);
+    }
+    Some(result)
+}
+
+/// 以当前区域设置的时区格式化时间
+pub fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+    let offset = FixedOffset::east_opt(current_locale().utc_offset)
+        .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+    timestamp
+        .with_timezone(&offset)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+/// 相对于当前时刻格式化时间，如 “5 minutes ago”
+pub fn format_relative(timestamp: DateTime<Utc>) -> String {
+    HumanTime::from(timestamp - now()).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locale(language: &str, utc_offset: i32) -> FormatLocale {
+        FormatLocale {
+            language: language.to_string(),
+            utc_offset,
+        }
+    }
+
+    fn format_all(timestamp: DateTime<Utc>) -> Vec<String> {
+        vec![
+            format_number(1234567),
+            format_bytes(1_234_000, None),
+            format_timestamp(timestamp),
+            format_relative(timestamp),
+        ]
+    }
+
+    #[test]
+    fn ambient_locale_changes_output() {
+        let timestamp = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        set_deterministic_formatting(None);
+
+        set_ambient_locale(Some(locale("en-US", 0)));
+        assert_eq!(format_number(1234567), "1,234,567");
+        assert_eq!(format_bytes(1_234_000, None), "1.23 MB");
+        assert_eq!(format_timestamp(timestamp), "2023-11-14 22:13:20");
+
+        set_ambient_locale(Some(locale("de-DE", 3600)));
+        assert_eq!(format_number(1234567), "1.234.567");
+        assert_eq!(format_bytes(1_234_000, None), "1,23 MB");
+        assert_eq!(format_timestamp(timestamp), "2023-11-14 23:13:20");
+
+        set_ambient_locale(None);
+    }
+
+    #[test]
+    fn format_signed_numbers() {
+        set_ambient_locale(Some(locale("en-US", 0)));
+        assert_eq!(format_signed_number("-1234567").as_deref(), Some("-1,234,567"));
+        assert_eq!(format_signed_number("+1234.5").as_deref(), Some("1,234.5"));
+        assert_eq!(format_signed_number("-0.75").as_deref(), Some("-0.75"));
+        assert_eq!(format_signed_number("abc"), None);
+        assert_eq!(format_signed_number("1.2.3"), None);
+
+        set_ambient_locale(Some(locale("de-DE", 0)));
+        assert_eq!(format_signed_number("-1234.5").as_deref(), Some("-1.234,5"));
+        set_ambient_locale(None);
+    }
+
+    #[test]
+    fn configurable_size_precision() {
+        set_ambient_locale(Some(locale("en-US", 0)));
+        assert_eq!(format_bytes(1_234_000, Some(0)), "1 MB");
+        assert_eq!(format_bytes(1_234_000, Some(2)), "1.23 MB");
+        assert_eq!(format_bytes(1_000_000, Some(2)), "1.00 MB");
+        assert_eq!(format_bytes(1_000_000, None), "1 MB");
+
+        set_ambient_locale(Some(locale("fr-FR", 0)));
+        assert_eq!(format_bytes(1_500_000, Some(2)), "1,50 MB");
+        set_ambient_locale(None);
+    }
+
+    #[test]
+    fn deterministic_mode_ignores_ambient_locale() {
+        let timestamp = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        set_deterministic_formatting(Some(timestamp + chrono::Duration::minutes(5)));
+
+        set_ambient_locale(Some(locale("fr-FR", 3600)));
+        let french = format_all(timestamp);
+        set_ambient_locale(Some(locale("en-AU", 36000)));
+        let australian = format_all(timestamp);
+
+        assert_eq!(french, australian);
+        assert_eq!(
+            french,
+            vec![
+                "1,234,567".to_string(),
+                "1.23 MB".to_string(),
+                "2023-11-14 22:13:20".to_string(),
+                "5 minutes ago".to_string(),
+            ]
+        );
+
+        set_ambient_locale(None);
+        set_deterministic_formatting(None);
+    }
+}
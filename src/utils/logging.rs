@@ -0,0 +1,10 @@
+use log::Level;
+
+pub const TARGET_SECURITY: &str = "webadmin::security";
+pub const TARGET_AUDIT: &str = "webadmin::audit";
+pub const TARGET_CONFIG: &str = "webadmin::config";
+
+/// 初始化日志输出到浏览器控制台
+pub fn init_logging() {
+    _ = console_log::init_with_level(Level::Debug);
+}
@@ -0,0 +1,15 @@
+pub mod audit;
+pub mod clock;
+pub mod locale;
+pub mod logging;
+pub mod security;
+pub mod telemetry;
+pub mod validation;
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// 获取互斥锁，即使持锁线程曾经 panic 导致锁中毒也继续使用其中的数据，
+/// 避免一次 panic 让后续所有调用都失败。
+pub fn lock_or_recover<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -1,18 +1,53 @@
-use std::collections::HashMap;
-use std::sync::Mutex;
-use std::time::{Duration, Instant};
-use lazy_static::lazy_static;
+use chrono::{DateTime, Utc};
 use rand::{thread_rng, Rng};
-use rand::distributions::Alphanumeric;
 use serde::{Serialize, Deserialize};
+use subtle::ConstantTimeEq;
+
+#[cfg(feature = "local-security")]
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+#[cfg(feature = "local-security")]
+use gloo_storage::{LocalStorage, Storage};
+#[cfg(feature = "local-security")]
+use lazy_static::lazy_static;
+#[cfg(feature = "local-security")]
+use super::{clock, lock_or_recover, logging::TARGET_SECURITY};
 
+#[cfg(feature = "local-security")]
 lazy_static! {
-    static ref RATE_LIMITS: Mutex<HashMap<String, Vec<Instant>>> = Mutex::new(HashMap::new());
-    static ref CSRF_TOKENS: Mutex<HashMap<String, Instant>> = Mutex::new(HashMap::new());
+    static ref RATE_LIMITS: Mutex<RateLimiter> = Mutex::new(RateLimiter::default());
+    static ref CSRF_TOKENS: Mutex<HashMap<String, DateTime<Utc>>> = Mutex::new(HashMap::new());
 }
 
+// 由 init_rate_limits 开启，测试中不读写本地存储
+#[cfg(feature = "local-security")]
+static PERSIST_RATE_LIMITS: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "local-security")]
+const RATE_LIMIT_STORAGE_KEY: &str = "webadmin_rate_limits";
+#[cfg(feature = "local-security")]
 const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
 const MAX_REQUESTS_PER_WINDOW: usize = 60;
+// 同时跟踪的标识符数量上限，防止大量不同标识符导致内存无限增长
+#[cfg(feature = "local-security")]
+const MAX_TRACKED_IDENTIFIERS: usize = 1024;
+// CSRF令牌允许的时钟回拨幅度，超出该幅度的"未来"创建时间视为时钟偏移并作废
+#[cfg(feature = "local-security")]
+const MAX_CLOCK_SKEW: Duration = Duration::from_secs(5);
+pub const CSRF_COOKIE_NAME: &str = "webadmin_csrf";
+pub const CSRF_HEADER_NAME: &str = "X-CSRF-Token";
+const DEFAULT_CSRF_TOKEN_LENGTH: usize = 32;
+// 令牌长度与字符集的安全下限（16 个字符 × 至少 16 种字符 ≥ 64 位熵）
+const MIN_CSRF_TOKEN_LENGTH: usize = 16;
+const MIN_CSRF_ALPHABET_SIZE: usize = 16;
+const CSRF_ALPHANUMERIC: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+#[cfg(feature = "local-security")]
 const CSRF_TOKEN_EXPIRY: Duration = Duration::from_secs(3600); // 1 hour
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -32,74 +67,511 @@ impl Default for SecurityConfig {
     }
 }
 
+/// CSRF令牌的长度和字符集配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsrfConfig {
+    length: usize,
+    alphabet: Vec<char>,
+}
+
+impl Default for CsrfConfig {
+    fn default() -> Self {
+        Self {
+            length: DEFAULT_CSRF_TOKEN_LENGTH,
+            alphabet: CSRF_ALPHANUMERIC.chars().collect(),
+        }
+    }
+}
+
+impl CsrfConfig {
+    /// 创建配置，长度低于安全下限或字符集过小时返回错误
+    pub fn new(length: usize, alphabet: &str) -> Result<Self, String> {
+        let mut chars = alphabet.chars().collect::<Vec<_>>();
+        chars.sort_unstable();
+        chars.dedup();
+
+        if length < MIN_CSRF_TOKEN_LENGTH {
+            return Err(format!(
+                "CSRF token length must be at least {MIN_CSRF_TOKEN_LENGTH} characters"
+            ));
+        }
+        if chars.len() < MIN_CSRF_ALPHABET_SIZE {
+            return Err(format!(
+                "CSRF token alphabet must contain at least {MIN_CSRF_ALPHABET_SIZE} distinct characters"
+            ));
+        }
+        if chars.iter().any(|ch| !ch.is_ascii_graphic()) {
+            return Err(
+                "CSRF token alphabet must only contain printable ASCII characters".to_string(),
+            );
+        }
+
+        Ok(Self {
+            length,
+            alphabet: chars,
+        })
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+}
+
 /// 生成CSRF令牌
 pub fn generate_csrf_token() -> String {
-    let token: String = thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(32)
-        .map(char::from)
+    generate_csrf_token_with(&mut thread_rng())
+}
+
+/// 使用指定的随机数生成器生成CSRF令牌，便于测试时注入固定种子
+pub fn generate_csrf_token_with<R: Rng>(rng: &mut R) -> String {
+    generate_csrf_token_with_config(rng, &CsrfConfig::default())
+}
+
+/// 按指定配置生成CSRF令牌
+pub fn generate_csrf_token_with_config<R: Rng>(rng: &mut R, config: &CsrfConfig) -> String {
+    let token: String = (0..config.length)
+        .map(|_| config.alphabet[rng.gen_range(0..config.alphabet.len())])
         .collect();
-    
-    let mut tokens = CSRF_TOKENS.lock().unwrap();
-    tokens.insert(token.clone(), Instant::now());
+
+    #[cfg(feature = "local-security")]
+    lock_or_recover(&CSRF_TOKENS).insert(token.clone(), clock::now());
+    token
+}
+
+/// 构造双重提交所用的CSRF Cookie，仅限同站且仅通过HTTPS发送
+pub fn csrf_cookie(token: &str) -> String {
+    format!("{CSRF_COOKIE_NAME}={token}; Path=/; SameSite=Strict; Secure")
+}
+
+/// 将CSRF令牌写入浏览器Cookie
+pub fn set_csrf_cookie(token: &str) {
+    let cookie = csrf_cookie(token);
+    if let Err(err) = js_sys::Reflect::set(&leptos::document(), &"cookie".into(), &cookie.into()) {
+        log::error!("Failed to set CSRF cookie: {err:?}");
+    }
+}
+
+/// 读取浏览器当前的Cookie字符串
+fn document_cookies() -> String {
+    js_sys::Reflect::get(&leptos::document(), &"cookie".into())
+        .ok()
+        .and_then(|cookies| cookies.as_string())
+        .unwrap_or_default()
+}
+
+/// 返回请求要附带的CSRF令牌：优先沿用Cookie中的令牌，否则生成新令牌并写入Cookie
+///
+/// 写入后回读Cookie做双重提交校验，浏览器拒绝保存Cookie时（例如非HTTPS页面）记录警告。
+pub fn csrf_token_for_request() -> String {
+    if let Some(token) = read_csrf_cookie(&document_cookies()) {
+        return token.to_string();
+    }
+
+    let token = generate_csrf_token();
+    set_csrf_cookie(&token);
+    if !verify_double_submit(read_csrf_cookie(&document_cookies()), Some(&token)) {
+        log::warn!("CSRF cookie was not stored by the browser, the header is sent alone");
+    }
     token
 }
 
+/// 从 `document.cookie` 形式的字符串中读取CSRF令牌
+pub fn read_csrf_cookie(cookies: &str) -> Option<&str> {
+    cookies.split(';').find_map(|cookie| {
+        cookie
+            .trim()
+            .strip_prefix(CSRF_COOKIE_NAME)?
+            .strip_prefix('=')
+            .filter(|token| !token.is_empty())
+    })
+}
+
+/// 返回附加到请求上的CSRF请求头，令牌为空时不附加
+pub fn csrf_header(token: &str) -> Option<(&'static str, &str)> {
+    (!token.is_empty()).then_some((CSRF_HEADER_NAME, token))
+}
+
+/// 双重提交校验：Cookie与请求头中的令牌必须同时存在且一致
+pub fn verify_double_submit(cookie: Option<&str>, header: Option<&str>) -> bool {
+    match (cookie, header) {
+        (Some(cookie), Some(header)) if !cookie.is_empty() => {
+            constant_time_eq(cookie.as_bytes(), header.as_bytes())
+        }
+        _ => false,
+    }
+}
+
 /// 验证CSRF令牌
+///
+/// 令牌只能使用一次：验证成功后即从存储中移除，之后再次提交同一令牌将失败。
+/// 为避免时序侧信道，不通过哈希表查找令牌，而是以常量时间逐一比较所有已签发的令牌。
+#[cfg(feature = "local-security")]
 pub fn validate_csrf_token(token: &str) -> bool {
-    let mut tokens = CSRF_TOKENS.lock().unwrap();
-    if let Some(created_at) = tokens.get(token) {
-        if created_at.elapsed() < CSRF_TOKEN_EXPIRY {
-            tokens.remove(token);
-            return true;
+    let mut tokens = lock_or_recover(&CSRF_TOKENS);
+    let mut matched = None;
+    for (issued, created_at) in tokens.iter() {
+        if constant_time_eq(issued.as_bytes(), token.as_bytes()) {
+            matched = Some((issued.clone(), *created_at));
+        }
+    }
+    match matched {
+        Some((issued, created_at)) => {
+            tokens.remove(&issued);
+            let valid = is_unexpired(created_at, clock::now());
+            if !valid {
+                log::warn!(target: TARGET_SECURITY, "CSRF token rejected: reason=expired");
+            }
+            valid
+        }
+        None => {
+            log::warn!(target: TARGET_SECURITY, "CSRF token rejected: reason=unknown");
+            false
         }
     }
-    false
+}
+
+/// 判断令牌是否仍在有效期内，签发时间略晚于当前时间时按时钟回拨处理
+#[cfg(feature = "local-security")]
+fn is_unexpired(created_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    match (now - created_at).to_std() {
+        Ok(elapsed) => elapsed < CSRF_TOKEN_EXPIRY,
+        Err(_) => (created_at - now).to_std().is_ok_and(|ahead| ahead <= MAX_CLOCK_SKEW),
+    }
+}
+
+/// 常量时间比较两个字节串，比较耗时与首个不同字节的位置无关
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
 }
 
 /// 清理过期的CSRF令牌
+#[cfg(feature = "local-security")]
 pub fn cleanup_expired_csrf_tokens() {
-    let mut tokens = CSRF_TOKENS.lock().unwrap();
-    tokens.retain(|_, created_at| created_at.elapsed() < CSRF_TOKEN_EXPIRY);
+    let mut tokens = lock_or_recover(&CSRF_TOKENS);
+    let now = clock::now();
+    tokens.retain(|_, created_at| is_unexpired(*created_at, now));
+}
+
+/// 触发操作的HTTP请求，用于按接口区分限速和审计
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestContext {
+    pub method: String,
+    pub path: String,
+}
+
+impl RequestContext {
+    pub fn new(method: impl AsRef<str>, path: impl Into<String>) -> Self {
+        Self {
+            method: method.as_ref().to_ascii_uppercase(),
+            path: path.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for RequestContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.method, self.path)
+    }
+}
+
+/// 速率限制的桶标识：提供请求上下文时每个接口单独计数
+pub fn rate_limit_key(identifier: &str, request: Option<&RequestContext>) -> String {
+    match request {
+        Some(request) => format!("{identifier}:{request}"),
+        None => identifier.to_string(),
+    }
+}
+
+/// 按请求上下文检查速率限制
+pub fn check_rate_limit_request(
+    identifier: &str,
+    request: Option<&RequestContext>,
+) -> Result<(), String> {
+    check_rate_limit(&rate_limit_key(identifier, request))
 }
 
 /// 检查请求速率限制
+#[cfg(feature = "local-security")]
 pub fn check_rate_limit(identifier: &str) -> Result<(), String> {
-    let mut limits = RATE_LIMITS.lock().unwrap();
-    let now = Instant::now();
-    
-    // 清理过期的请求记录
-    if let Some(requests) = limits.get_mut(identifier) {
-        requests.retain(|&time| now.duration_since(time) < RATE_LIMIT_WINDOW);
-        
+    check_rate_limit_at(identifier, clock::now())
+}
+
+/// 以指定的墙上时钟时间检查请求速率限制
+#[cfg(feature = "local-security")]
+pub fn check_rate_limit_at(identifier: &str, now: DateTime<Utc>) -> Result<(), String> {
+    let mut limiter = lock_or_recover(&RATE_LIMITS);
+    let result = limiter.check_at(identifier, now);
+    persist_rate_limits(&limiter);
+    result
+}
+
+/// 从本地存储恢复速率限制状态，并在之后的每次检查后保存，使限速在页面刷新后仍然有效
+#[cfg(feature = "local-security")]
+pub fn init_rate_limits() {
+    let mut limiter = RateLimiter::load();
+    limiter.restore(clock::now());
+    *lock_or_recover(&RATE_LIMITS) = limiter;
+    PERSIST_RATE_LIMITS.store(true, Ordering::Relaxed);
+}
+
+#[cfg(feature = "local-security")]
+fn persist_rate_limits(limiter: &RateLimiter) {
+    if PERSIST_RATE_LIMITS.load(Ordering::Relaxed) {
+        limiter.save();
+    }
+}
+
+/// 速率限制状态：每个标识符对应窗口内的请求时间戳
+///
+/// 全局限速使用一个共享实例，测试可以创建独立实例以避免相互干扰。
+#[cfg(feature = "local-security")]
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RateLimiter {
+    buckets: HashMap<String, Vec<DateTime<Utc>>>,
+}
+
+#[cfg(feature = "local-security")]
+impl RateLimiter {
+    pub fn load() -> Self {
+        LocalStorage::get(RATE_LIMIT_STORAGE_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = LocalStorage::set(RATE_LIMIT_STORAGE_KEY, self) {
+            log::error!("Failed to save rate limits to local storage: {}", err);
+        }
+    }
+
+    /// 整理从存储中恢复的状态：移除过期的请求，并限制跟踪的标识符数量
+    pub fn restore(&mut self, now: DateTime<Utc>) {
+        self.cleanup(now);
+        if self.buckets.len() > MAX_TRACKED_IDENTIFIERS {
+            self.evict(now);
+        }
+    }
+
+    /// 以指定的墙上时钟时间检查并记录一次请求
+    pub fn check_at(&mut self, identifier: &str, now: DateTime<Utc>) -> Result<(), String> {
+        if !self.buckets.contains_key(identifier) && self.buckets.len() >= MAX_TRACKED_IDENTIFIERS {
+            self.evict(now);
+        }
+        let requests = self.buckets.entry(identifier.to_string()).or_default();
+
+        // 清理过期的请求记录
+        expire_requests(requests, now);
+
         if requests.len() >= MAX_REQUESTS_PER_WINDOW {
+            log::warn!(
+                target: TARGET_SECURITY,
+                "Rate limit exceeded: identifier={identifier} requests={}",
+                requests.len()
+            );
             return Err("Rate limit exceeded".to_string());
         }
-        
+
         requests.push(now);
-    } else {
-        limits.insert(identifier.to_string(), vec![now]);
+        Ok(())
+    }
+
+    /// 移除窗口内已无请求的桶
+    pub fn cleanup(&mut self, now: DateTime<Utc>) {
+        self.buckets.retain(|_, requests| {
+            expire_requests(requests, now);
+            !requests.is_empty()
+        });
+    }
+
+    /// 各桶的标识符、窗口内的请求数及最近一次请求时间，按标识符排序
+    pub fn snapshot(&self, now: DateTime<Utc>) -> Vec<(String, usize, DateTime<Utc>)> {
+        let mut snapshot = self
+            .buckets
+            .iter()
+            .filter_map(|(identifier, requests)| {
+                let requests = requests
+                    .iter()
+                    .filter(|&&time| is_within_window(time, now))
+                    .collect::<Vec<_>>();
+                requests
+                    .iter()
+                    .max()
+                    .map(|&&last| (identifier.clone(), requests.len(), last.min(now)))
+            })
+            .collect::<Vec<_>>();
+        snapshot.sort_by(|a, b| a.0.cmp(&b.0));
+        snapshot
+    }
+
+    /// 当前跟踪的标识符数量
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// 为新的标识符腾出空间：先移除窗口内已无请求的桶，若仍达到上限，
+    /// 再移除最近一次请求最早的桶（近似 LRU）。
+    fn evict(&mut self, now: DateTime<Utc>) {
+        self.cleanup(now);
+
+        while self.buckets.len() >= MAX_TRACKED_IDENTIFIERS {
+            let Some(oldest) = self
+                .buckets
+                .iter()
+                .min_by_key(|(_, requests)| requests.iter().max().copied())
+                .map(|(identifier, _)| identifier.clone())
+            else {
+                break;
+            };
+            self.buckets.remove(&oldest);
+        }
+    }
+}
+
+/// 将晚于当前时间的记录收敛到当前时间，再移除窗口外的记录
+///
+/// 时间戳使用墙上时钟以便持久化，因此需要容忍时钟回拨：无论回拨幅度多大，
+/// "未来"的记录都视为刚刚发生，在一个完整窗口后正常过期，而不是被直接丢弃
+/// 从而清空限速状态。
+#[cfg(feature = "local-security")]
+fn expire_requests(requests: &mut Vec<DateTime<Utc>>, now: DateTime<Utc>) {
+    for time in requests.iter_mut() {
+        if *time > now {
+            *time = now;
+        }
+    }
+    requests.retain(|&time| is_within_window(time, now));
+}
+
+/// 判断请求时间戳是否仍处于限速窗口内，晚于当前时间的记录视为刚刚发生
+#[cfg(feature = "local-security")]
+fn is_within_window(time: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    match (now - time).to_std() {
+        Ok(elapsed) => elapsed < RATE_LIMIT_WINDOW,
+        Err(_) => true,
     }
-    
-    Ok(())
 }
 
 /// 清理过期的速率限制记录
+#[cfg(feature = "local-security")]
 pub fn cleanup_rate_limits() {
-    let mut limits = RATE_LIMITS.lock().unwrap();
-    let now = Instant::now();
-    
-    limits.retain(|_, requests| {
-        requests.retain(|&time| now.duration_since(time) < RATE_LIMIT_WINDOW);
-        !requests.is_empty()
-    });
+    let mut limiter = lock_or_recover(&RATE_LIMITS);
+    limiter.cleanup(clock::now());
+    persist_rate_limits(&limiter);
 }
 
-#[cfg(test)]
+/// 获取当前各速率限制桶的快照：标识符、窗口内的请求数及最近一次请求时间
+///
+/// 仅用于调试页面展示，按标识符排序。
+#[cfg(feature = "local-security")]
+pub fn rate_limit_snapshot() -> Vec<(String, usize, DateTime<Utc>)> {
+    lock_or_recover(&RATE_LIMITS).snapshot(clock::now())
+}
+
+/// 获取当前有效的CSRF令牌数量，不暴露令牌本身
+#[cfg(feature = "local-security")]
+pub fn csrf_token_count() -> usize {
+    let now = clock::now();
+    lock_or_recover(&CSRF_TOKENS)
+        .values()
+        .filter(|created_at| is_unexpired(**created_at, now))
+        .count()
+}
+
+/// 安全检查由服务端负责时使用的空实现：不保存任何状态，令牌总是有效，
+/// 请求从不受限，调用方无需修改。
+#[cfg(not(feature = "local-security"))]
+mod disabled {
+    use chrono::{DateTime, Utc};
+
+    pub fn validate_csrf_token(_token: &str) -> bool {
+        true
+    }
+
+    pub fn cleanup_expired_csrf_tokens() {}
+
+    pub fn check_rate_limit(_identifier: &str) -> Result<(), String> {
+        Ok(())
+    }
+
+    pub fn check_rate_limit_at(_identifier: &str, _now: DateTime<Utc>) -> Result<(), String> {
+        Ok(())
+    }
+
+    pub fn cleanup_rate_limits() {}
+
+    pub fn init_rate_limits() {}
+
+    pub fn rate_limit_snapshot() -> Vec<(String, usize, DateTime<Utc>)> {
+        Vec::new()
+    }
+
+    pub fn csrf_token_count() -> usize {
+        0
+    }
+}
+
+#[cfg(not(feature = "local-security"))]
+pub use disabled::*;
+
+#[cfg(all(test, not(feature = "local-security")))]
+mod disabled_tests {
+    use super::*;
+
+    #[test]
+    fn stubs_match_signatures() {
+        let _: fn() -> String = generate_csrf_token;
+        let _: fn(&str) -> bool = validate_csrf_token;
+        let _: fn() = cleanup_expired_csrf_tokens;
+        let _: fn(&str) -> Result<(), String> = check_rate_limit;
+        let _: fn(&str, DateTime<Utc>) -> Result<(), String> = check_rate_limit_at;
+        let _: fn() = cleanup_rate_limits;
+        let _: fn() = init_rate_limits;
+        let _: fn() -> Vec<(String, usize, DateTime<Utc>)> = rate_limit_snapshot;
+        let _: fn() -> usize = csrf_token_count;
+    }
+
+    #[test]
+    fn stubs_never_block_callers() {
+        let token = generate_csrf_token();
+        assert_eq!(token.len(), 32);
+        assert!(validate_csrf_token(&token));
+        for _ in 0..MAX_REQUESTS_PER_WINDOW * 2 {
+            assert!(check_rate_limit("stub").is_ok());
+        }
+        assert!(rate_limit_snapshot().is_empty());
+        assert_eq!(csrf_token_count(), 0);
+    }
+}
+
+#[cfg(all(test, feature = "local-security"))]
 mod tests {
     use super::*;
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+    use rand::{rngs::StdRng, SeedableRng};
     use std::thread;
 
+    struct CapturingLogger(Mutex<Vec<(Level, String, String)>>);
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            self.0.lock().unwrap().push((
+                record.level(),
+                record.target().to_string(),
+                record.args().to_string(),
+            ));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger(Mutex::new(Vec::new()));
+
     #[test]
     fn test_csrf_token_generation_and_validation() {
         let token = generate_csrf_token();
@@ -107,20 +579,349 @@ mod tests {
         assert!(!validate_csrf_token(&token)); // 令牌应该只能使用一次
     }
 
+    #[test]
+    fn test_csrf_token_rejects_near_misses() {
+        let token = generate_csrf_token();
+        let mut first = token.clone().into_bytes();
+        first[0] = if first[0] == b'a' { b'b' } else { b'a' };
+        let mut last = token.clone().into_bytes();
+        last[31] = if last[31] == b'a' { b'b' } else { b'a' };
+
+        assert!(!validate_csrf_token(std::str::from_utf8(&first).unwrap()));
+        assert!(!validate_csrf_token(std::str::from_utf8(&last).unwrap()));
+        assert!(!validate_csrf_token(&token[..31]));
+        assert!(!validate_csrf_token(""));
+        assert!(validate_csrf_token(&token));
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(constant_time_eq(b"", b""));
+        // 首字节与末字节不同时均应返回false，且比较不会在首个不同字节处提前结束
+        assert!(!constant_time_eq(b"xoken", b"token"));
+        assert!(!constant_time_eq(b"tokex", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokens"));
+    }
+
+    #[test]
+    fn test_csrf_config_custom_length() {
+        let config = CsrfConfig::new(64, "0123456789abcdef").unwrap();
+        let token = generate_csrf_token_with_config(&mut thread_rng(), &config);
+        assert_eq!(token.len(), 64);
+        assert!(token.chars().all(|ch| ch.is_ascii_hexdigit()));
+        assert!(validate_csrf_token(&token));
+
+        assert_eq!(CsrfConfig::default().length(), 32);
+    }
+
+    #[test]
+    fn test_csrf_config_rejects_unsafe_values() {
+        assert!(CsrfConfig::new(8, CSRF_ALPHANUMERIC).is_err());
+        assert!(CsrfConfig::new(MIN_CSRF_TOKEN_LENGTH - 1, CSRF_ALPHANUMERIC).is_err());
+        assert!(CsrfConfig::new(MIN_CSRF_TOKEN_LENGTH, CSRF_ALPHANUMERIC).is_ok());
+        // 重复字符不计入字符集大小
+        assert!(CsrfConfig::new(64, "aaaaaaaaaaaaaaaabbbb").is_err());
+        assert!(CsrfConfig::new(64, "0123456789abcde\n").is_err());
+    }
+
+    #[test]
+    fn test_csrf_token_seeded_rng() {
+        let token = generate_csrf_token_with(&mut StdRng::seed_from_u64(42));
+        assert_eq!(token, generate_csrf_token_with(&mut StdRng::seed_from_u64(42)));
+        assert_ne!(token, generate_csrf_token_with(&mut StdRng::seed_from_u64(43)));
+        assert!(validate_csrf_token(&token));
+    }
+
+    #[test]
+    fn test_csrf_token_default_rng() {
+        let token = generate_csrf_token();
+        assert_eq!(token.len(), 32);
+        assert!(token.chars().all(|ch| ch.is_ascii_alphanumeric()));
+    }
+
+    #[test]
+    fn test_rate_limit_window_wall_clock() {
+        let mut limiter = RateLimiter::default();
+        let identifier = "test_wall_clock";
+        let start = Utc::now();
+
+        for _ in 0..MAX_REQUESTS_PER_WINDOW {
+            assert!(limiter.check_at(identifier, start).is_ok());
+        }
+        assert!(limiter.check_at(identifier, start + chrono::Duration::seconds(59)).is_err());
+        assert!(limiter.check_at(identifier, start + chrono::Duration::seconds(60)).is_ok());
+    }
+
+    #[test]
+    fn test_rate_limit_persistence_round_trip() {
+        let mut limiter = RateLimiter::default();
+        let start = Utc::now();
+        for _ in 0..MAX_REQUESTS_PER_WINDOW {
+            assert!(limiter.check_at("test_persisted", start).is_ok());
+        }
+        assert!(limiter
+            .check_at("test_expired", start - chrono::Duration::minutes(5))
+            .is_ok());
+
+        // 恢复后窗口内的请求仍然有效，过期的桶被移除
+        let json = serde_json::to_string(&limiter).unwrap();
+        let mut restored = serde_json::from_str::<RateLimiter>(&json).unwrap();
+        assert_eq!(restored.snapshot(start), limiter.snapshot(start));
+        restored.restore(start);
+        assert_eq!(
+            restored.snapshot(start),
+            vec![("test_persisted".to_string(), MAX_REQUESTS_PER_WINDOW, start)]
+        );
+        assert!(restored.check_at("test_persisted", start).is_err());
+        assert!(restored
+            .check_at("test_persisted", start + chrono::Duration::seconds(60))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_rate_limit_rejection_is_logged() {
+        if log::set_logger(&LOGGER).is_ok() {
+            log::set_max_level(LevelFilter::Trace);
+        }
+
+        let mut limiter = RateLimiter::default();
+        let identifier = "test_logged_rejection";
+        let now = Utc::now();
+        for _ in 0..MAX_REQUESTS_PER_WINDOW {
+            assert!(limiter.check_at(identifier, now).is_ok());
+        }
+        assert!(limiter.check_at(identifier, now).is_err());
+
+        let records = LOGGER.0.lock().unwrap();
+        assert!(records.iter().any(|(level, target, message)| {
+            *level == Level::Warn
+                && target == TARGET_SECURITY
+                && message.contains(&format!("identifier={identifier}"))
+        }));
+    }
+
+    #[test]
+    fn test_csrf_token_expiry() {
+        let clock = clock::install_fake_clock(Utc::now());
+
+        let token = generate_csrf_token();
+        clock.advance(chrono::Duration::minutes(59));
+        assert!(validate_csrf_token(&token));
+
+        let token = generate_csrf_token();
+        clock.advance(chrono::Duration::hours(1));
+        assert!(!validate_csrf_token(&token));
+
+        clock::set_clock(None);
+    }
+
+    #[test]
+    fn test_rate_limit_window_fake_clock() {
+        let clock = clock::install_fake_clock(Utc::now());
+        let identifier = "test_fake_clock";
+
+        for _ in 0..MAX_REQUESTS_PER_WINDOW {
+            assert!(check_rate_limit(identifier).is_ok());
+        }
+        clock.advance(chrono::Duration::seconds(59));
+        assert!(check_rate_limit(identifier).is_err());
+
+        clock.advance(chrono::Duration::seconds(1));
+        assert!(check_rate_limit(identifier).is_ok());
+
+        clock::set_clock(None);
+    }
+
+    #[test]
+    fn test_rate_limit_clock_skew() {
+        let now = Utc::now();
+        assert!(is_within_window(now, now));
+        assert!(is_within_window(now - chrono::Duration::seconds(59), now));
+        assert!(!is_within_window(now - chrono::Duration::seconds(60), now));
+        // 时钟回拨：记录时间晚于当前时间，视为刚刚发生
+        assert!(is_within_window(now + chrono::Duration::seconds(2), now));
+        assert!(is_within_window(now + chrono::Duration::hours(1), now));
+
+        let mut limiter = RateLimiter::default();
+        let identifier = "test_clock_skew";
+        for _ in 0..MAX_REQUESTS_PER_WINDOW {
+            assert!(limiter.check_at(identifier, now).is_ok());
+        }
+        assert!(limiter.check_at(identifier, now - chrono::Duration::seconds(3)).is_err());
+
+        // 大幅回拨不会清空限速状态，记录被收敛到回拨后的时间并在一个窗口后过期
+        let rewound = now - chrono::Duration::hours(1);
+        assert!(limiter.check_at(identifier, rewound).is_err());
+        assert_eq!(
+            limiter.snapshot(rewound),
+            vec![(identifier.to_string(), MAX_REQUESTS_PER_WINDOW, rewound)]
+        );
+        assert!(limiter.check_at(identifier, rewound + chrono::Duration::seconds(59)).is_err());
+        assert!(limiter.check_at(identifier, rewound + chrono::Duration::seconds(60)).is_ok());
+    }
+
+    #[test]
+    fn test_rate_limit_snapshot() {
+        let mut limiter = RateLimiter::default();
+        let identifier = "test_snapshot";
+        let now = Utc::now();
+        let earlier = now - chrono::Duration::seconds(10);
+
+        assert!(limiter.check_at(identifier, now - chrono::Duration::seconds(120)).is_ok());
+        assert!(limiter.check_at(identifier, earlier).is_ok());
+        assert!(limiter.check_at(identifier, now).is_ok());
+        assert!(limiter.check_at(identifier, earlier).is_ok());
+        assert!(limiter.check_at("test_snapshot_other", now).is_ok());
+
+        let snapshot = limiter.snapshot(now);
+        assert_eq!(
+            snapshot,
+            vec![
+                (identifier.to_string(), 3, now),
+                ("test_snapshot_other".to_string(), 1, now),
+            ]
+        );
+
+        limiter.cleanup(now + chrono::Duration::seconds(60));
+        assert!(limiter.is_empty());
+    }
+
+    #[test]
+    fn test_csrf_token_count() {
+        let token = generate_csrf_token();
+        assert!(csrf_token_count() >= 1);
+        assert!(validate_csrf_token(&token));
+    }
+
+    #[test]
+    fn test_recover_from_poisoned_locks() {
+        let _ = thread::spawn(|| {
+            let _limits = RATE_LIMITS.lock().unwrap();
+            let _tokens = CSRF_TOKENS.lock().unwrap();
+            panic!("poison the security locks");
+        })
+        .join();
+        assert!(RATE_LIMITS.is_poisoned());
+        assert!(CSRF_TOKENS.is_poisoned());
+
+        assert!(check_rate_limit("test_poisoned").is_ok());
+        let token = generate_csrf_token();
+        assert!(validate_csrf_token(&token));
+        cleanup_rate_limits();
+        cleanup_expired_csrf_tokens();
+        assert!(rate_limit_snapshot().iter().any(|(id, _, _)| id == "test_poisoned"));
+    }
+
+    #[test]
+    fn test_rate_limit_identifiers_bounded() {
+        let mut limiter = RateLimiter::default();
+        let at = Utc::now();
+        for i in 0..MAX_TRACKED_IDENTIFIERS * 3 {
+            assert!(limiter.check_at(&format!("test_bounded_{i}"), at).is_ok());
+            assert!(limiter.len() <= MAX_TRACKED_IDENTIFIERS);
+        }
+
+        // 达到上限时淘汰最近一次请求最早的桶
+        let recent = at + chrono::Duration::seconds(1);
+        assert!(limiter.check_at("test_bounded_recent", recent).is_ok());
+        assert!(limiter.check_at("test_bounded_next", recent).is_ok());
+        assert!(limiter
+            .snapshot(recent)
+            .iter()
+            .any(|(id, _, _)| id == "test_bounded_recent"));
+
+        // 过期的桶在需要空间时被优先移除
+        let later = at + chrono::Duration::seconds(60);
+        assert!(limiter.check_at("test_bounded_fresh", later).is_ok());
+        let kept = limiter
+            .snapshot(later)
+            .into_iter()
+            .map(|(id, _, _)| id)
+            .collect::<Vec<_>>();
+        assert_eq!(kept, ["test_bounded_fresh", "test_bounded_next", "test_bounded_recent"]);
+    }
+
+    #[test]
+    fn test_rate_limit_per_endpoint() {
+        let clock = clock::install_fake_clock(Utc::now());
+        let save = RequestContext::new("post", "/api/settings");
+        let reload = RequestContext::new("GET", "/api/reload");
+        assert_eq!(
+            rate_limit_key("test_endpoint", Some(&save)),
+            "test_endpoint:POST /api/settings"
+        );
+        assert_eq!(rate_limit_key("test_endpoint", None), "test_endpoint");
+
+        for _ in 0..MAX_REQUESTS_PER_WINDOW {
+            assert!(check_rate_limit_request("test_endpoint", Some(&save)).is_ok());
+        }
+        assert!(check_rate_limit_request("test_endpoint", Some(&save)).is_err());
+        assert!(check_rate_limit_request("test_endpoint", Some(&reload)).is_ok());
+        assert!(check_rate_limit_request("test_endpoint", None).is_ok());
+
+        // 窗口结束后恢复
+        clock.advance(chrono::Duration::seconds(60));
+        assert!(check_rate_limit_request("test_endpoint", Some(&save)).is_ok());
+
+        clock::set_clock(None);
+    }
+
     #[test]
     fn test_rate_limiting() {
+        let clock = clock::install_fake_clock(Utc::now());
         let identifier = "test_client";
-        
+        let window = chrono::Duration::from_std(RATE_LIMIT_WINDOW).unwrap();
+
         // 测试正常请求
         for _ in 0..MAX_REQUESTS_PER_WINDOW {
             assert!(check_rate_limit(identifier).is_ok());
         }
-        
+
         // 测试超出限制
         assert!(check_rate_limit(identifier).is_err());
-        
-        // 等待窗口期结束
-        thread::sleep(RATE_LIMIT_WINDOW);
-        assert!(check_rate_limit(identifier).is_ok());
+
+        // 推进时钟直到窗口期结束，窗口重置后可再次发出完整数量的请求
+        clock.advance(window);
+        for _ in 0..MAX_REQUESTS_PER_WINDOW {
+            assert!(check_rate_limit(identifier).is_ok());
+        }
+        assert!(check_rate_limit(identifier).is_err());
+
+        clock::set_clock(None);
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod double_submit_tests {
+    use super::*;
+
+    #[test]
+    fn attach_csrf_header() {
+        assert_eq!(csrf_header("abc123"), Some(("X-CSRF-Token", "abc123")));
+        assert_eq!(csrf_header(""), None);
+    }
+
+    #[test]
+    fn csrf_cookie_attributes() {
+        assert_eq!(
+            csrf_cookie("abc123"),
+            "webadmin_csrf=abc123; Path=/; SameSite=Strict; Secure"
+        );
+        assert_eq!(
+            read_csrf_cookie("theme=dark; webadmin_csrf=abc123; other=1"),
+            Some("abc123")
+        );
+        assert_eq!(read_csrf_cookie("webadmin_csrf_old=abc; webadmin_csrf="), None);
+        assert_eq!(read_csrf_cookie(""), None);
+    }
+
+    #[test]
+    fn double_submit_requires_matching_tokens() {
+        assert!(verify_double_submit(Some("abc123"), Some("abc123")));
+        assert!(!verify_double_submit(Some("abc123"), Some("abc124")));
+        assert!(!verify_double_submit(Some("abc123"), None));
+        assert!(!verify_double_submit(None, Some("abc123")));
+        assert!(!verify_double_submit(Some(""), Some("")));
+    }
+}
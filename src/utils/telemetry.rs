@@ -0,0 +1,317 @@
+use std::sync::{Mutex, TryLockError};
+
+use chrono::{DateTime, Duration, Utc};
+use gloo_storage::{LocalStorage, Storage};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use crate::core::http::HttpRequest;
+
+use super::{
+    audit::{redact_details, RedactionPolicy},
+    clock, lock_or_recover,
+};
+
+const ERROR_REPORTING_STORAGE_KEY: &str = "webadmin_error_reporting";
+/// 错误报告的接收地址，在构建时通过 `ERROR_REPORT_URL` 设置。
+/// 服务端没有内置的接收接口，未设置时不提供错误上报。
+const ERROR_REPORT_URL: Option<&str> = option_env!("ERROR_REPORT_URL");
+const REPORT_FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+const REPORT_BATCH_SIZE: usize = 10;
+const MAX_REPORTS_PER_WINDOW: usize = 30;
+const REPORT_WINDOW_SECS: i64 = 300;
+const MAX_CONTEXT_VALUE_LENGTH: usize = 256;
+const SENSITIVE_CONTEXT_KEYS: &[&str] = &[
+    "password",
+    "secret",
+    "token",
+    "api_key",
+    "apikey",
+    "private",
+    "credential",
+    "auth",
+    "cookie",
+];
+
+lazy_static! {
+    static ref REPORTER: Mutex<ErrorReporter> = Mutex::new(ErrorReporter::default());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorSource {
+    /// 未处理的错误（panic）
+    Panic,
+    /// 界面中已处理、但值得运维关注的错误
+    Handled,
+}
+
+/// 上报到后端的前端错误
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorReport {
+    pub timestamp: DateTime<Utc>,
+    pub source: ErrorSource,
+    pub operation: String,
+    pub message: String,
+    pub context: Vec<(String, String)>,
+}
+
+/// 收集错误报告并按批次输出，同时限制单位时间内的上报数量
+#[derive(Debug, Default)]
+pub struct ErrorReporter {
+    pub enabled: bool,
+    pending: Vec<ErrorReport>,
+    window_start: Option<DateTime<Utc>>,
+    window_count: usize,
+}
+
+impl ErrorReport {
+    pub fn new(
+        source: ErrorSource,
+        operation: impl Into<String>,
+        message: impl AsRef<str>,
+        context: &[(&str, &str)],
+    ) -> Self {
+        Self {
+            timestamp: clock::now(),
+            source,
+            operation: operation.into(),
+            message: message.as_ref().to_string(),
+            context: context
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    /// 去除报告中可能包含的敏感信息：敏感名称的上下文取值整体替换，
+    /// 其余文本按审计日志的脱敏规则处理，并截断过长的取值。
+    pub fn sanitized(mut self) -> Self {
+        self.message = redact_details(&self.message, RedactionPolicy::Standard);
+        for (key, value) in &mut self.context {
+            let name = key.to_lowercase();
+            *value = if SENSITIVE_CONTEXT_KEYS
+                .iter()
+                .any(|sensitive| name.contains(sensitive))
+            {
+                "[REDACTED]".to_string()
+            } else {
+                redact_details(value, RedactionPolicy::Standard)
+                    .chars()
+                    .take(MAX_CONTEXT_VALUE_LENGTH)
+                    .collect()
+            };
+        }
+        self
+    }
+}
+
+impl ErrorReporter {
+    /// 记录一条错误报告。达到批次大小时返回待发送的批次；
+    /// 未启用上报或超出速率限制时丢弃该报告。
+    pub fn capture(&mut self, report: ErrorReport) -> Option<Vec<ErrorReport>> {
+        if !self.enabled || !self.within_rate_limit(report.timestamp) {
+            return None;
+        }
+        self.pending.push(report.sanitized());
+        if self.pending.len() >= REPORT_BATCH_SIZE {
+            Some(self.take_pending())
+        } else {
+            None
+        }
+    }
+
+    pub fn take_pending(&mut self) -> Vec<ErrorReport> {
+        std::mem::take(&mut self.pending)
+    }
+
+    fn within_rate_limit(&mut self, now: DateTime<Utc>) -> bool {
+        match self.window_start {
+            Some(start) if now >= start && now - start < report_window() => {}
+            _ => {
+                self.window_start = Some(now);
+                self.window_count = 0;
+            }
+        }
+        self.window_count += 1;
+        self.window_count <= MAX_REPORTS_PER_WINDOW
+    }
+}
+
+fn report_window() -> Duration {
+    Duration::seconds(REPORT_WINDOW_SECS)
+}
+
+/// 读取用户是否选择启用错误上报并安装 panic 钩子。
+/// 缓存的报告定期以及在页面隐藏时发送。
+pub fn init_error_reporting() {
+    lock_or_recover(&REPORTER).enabled = is_error_reporting_enabled();
+    if is_error_reporting_available() {
+        _ = leptos::set_interval_with_handle(flush_error_reports, REPORT_FLUSH_INTERVAL);
+        _ = leptos::window_event_listener(leptos::ev::pagehide, |_| flush_error_reports());
+    }
+
+    std::panic::set_hook(Box::new(|info| {
+        console_error_panic_hook::hook(info);
+        report_error(ErrorReport::new(
+            ErrorSource::Panic,
+            "panic",
+            info.to_string(),
+            &[],
+        ));
+        flush_error_reports();
+    }));
+}
+
+/// 构建时是否配置了错误报告的接收地址
+pub fn is_error_reporting_available() -> bool {
+    report_url().is_some()
+}
+
+pub fn is_error_reporting_enabled() -> bool {
+    is_error_reporting_available()
+        && LocalStorage::get::<bool>(ERROR_REPORTING_STORAGE_KEY).unwrap_or(false)
+}
+
+fn report_url() -> Option<&'static str> {
+    ERROR_REPORT_URL.filter(|url| !url.is_empty())
+}
+
+pub fn set_error_reporting(enabled: bool) {
+    if let Err(err) = LocalStorage::set(ERROR_REPORTING_STORAGE_KEY, enabled) {
+        log::error!("Failed to save error reporting preference: {}", err);
+    }
+    let mut reporter = lock_or_recover(&REPORTER);
+    reporter.enabled = enabled && is_error_reporting_available();
+    if !enabled {
+        reporter.take_pending();
+    }
+}
+
+/// 上报一个已处理的错误
+pub fn report_handled_error(
+    operation: &str,
+    error: impl std::fmt::Debug,
+    context: &[(&str, &str)],
+) {
+    report_error(ErrorReport::new(
+        ErrorSource::Handled,
+        operation,
+        format!("{error:?}"),
+        context,
+    ));
+}
+
+pub fn report_error(report: ErrorReport) {
+    // panic 钩子中锁可能已被占用，此时放弃上报而不是阻塞
+    let batch = match REPORTER.try_lock() {
+        Ok(mut reporter) => reporter.capture(report),
+        Err(TryLockError::Poisoned(err)) => err.into_inner().capture(report),
+        Err(TryLockError::WouldBlock) => None,
+    };
+    if let Some(batch) = batch {
+        send_reports(batch);
+    }
+}
+
+pub fn flush_error_reports() {
+    let batch = match REPORTER.try_lock() {
+        Ok(mut reporter) => reporter.take_pending(),
+        Err(TryLockError::Poisoned(err)) => err.into_inner().take_pending(),
+        Err(TryLockError::WouldBlock) => return,
+    };
+    if !batch.is_empty() {
+        send_reports(batch);
+    }
+}
+
+fn send_reports(batch: Vec<ErrorReport>) {
+    let Some(url) = report_url() else {
+        return;
+    };
+    if let Ok(request) = HttpRequest::post(url).with_body(batch) {
+        leptos::spawn_local(async move {
+            if let Err(err) = request.send_raw().await {
+                log::debug!("Failed to send error reports: {err:?}");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(message: &str, at: DateTime<Utc>) -> ErrorReport {
+        ErrorReport {
+            timestamp: at,
+            ..ErrorReport::new(ErrorSource::Handled, "settings.save", message, &[])
+        }
+    }
+
+    #[test]
+    fn capture_and_batch_reports() {
+        let now = Utc::now();
+        let mut reporter = ErrorReporter::default();
+        assert_eq!(reporter.capture(report("ignored", now)), None);
+        assert!(reporter.take_pending().is_empty());
+
+        reporter.enabled = true;
+        for i in 0..REPORT_BATCH_SIZE - 1 {
+            assert_eq!(reporter.capture(report(&format!("error {i}"), now)), None);
+        }
+        let batch = reporter.capture(report("last", now)).unwrap();
+        assert_eq!(batch.len(), REPORT_BATCH_SIZE);
+        assert_eq!(batch.last().unwrap().message, "last");
+        assert!(reporter.take_pending().is_empty());
+
+        // 超出速率限制的报告被丢弃，新窗口开始后恢复
+        for _ in REPORT_BATCH_SIZE..MAX_REPORTS_PER_WINDOW {
+            reporter.capture(report("within limit", now));
+        }
+        reporter.take_pending();
+        assert_eq!(reporter.capture(report("dropped", now)), None);
+        assert!(reporter.take_pending().is_empty());
+        reporter.capture(report("next window", now + report_window()));
+        assert_eq!(reporter.take_pending().len(), 1);
+    }
+
+    #[test]
+    fn timestamp_reports_with_clock() {
+        let start = DateTime::<Utc>::from_timestamp(1_700_000_000, 0).unwrap();
+        let clock = clock::install_fake_clock(start);
+        let report = || ErrorReport::new(ErrorSource::Panic, "panic", "boom", &[]);
+        assert_eq!(report().timestamp, start);
+
+        clock.advance(report_window());
+        assert_eq!(report().timestamp, start + report_window());
+        clock::set_clock(None);
+    }
+
+    #[test]
+    fn strip_secrets_from_context() {
+        let report = ErrorReport::new(
+            ErrorSource::Handled,
+            "settings.save",
+            "Request failed: password=hunter2",
+            &[
+                ("schema", "listener"),
+                ("api_token", "abc"),
+                ("Authorization", "Basic dXNlcjpwYXNz"),
+                ("value", "secret=s3cr3t"),
+            ],
+        )
+        .sanitized();
+
+        assert_eq!(report.message, "Request failed: password=[REDACTED]");
+        assert_eq!(
+            report.context,
+            vec![
+                ("schema".to_string(), "listener".to_string()),
+                ("api_token".to_string(), "[REDACTED]".to_string()),
+                ("Authorization".to_string(), "[REDACTED]".to_string()),
+                ("value".to_string(), "secret=[REDACTED]".to_string()),
+            ]
+        );
+    }
+}
@@ -1,20 +1,77 @@
+use std::cell::Cell;
+
 use regex::Regex;
 use html_escape::encode_text;
 
+const SVG_MIME_TYPE: &str = "image/svg+xml";
+const IMAGE_TYPES: [&str; 4] = ["image/jpeg", "image/png", SVG_MIME_TYPE, "image/gif"];
+
+thread_local! {
+    static STRICT_SECURITY: Cell<bool> = const { Cell::new(false) };
+}
+
 lazy_static! {
     static ref URL_REGEX: Regex = Regex::new(
         r"^(https?://)?([a-zA-Z0-9]([a-zA-Z0-9-]*[a-zA-Z0-9])?\.)+[a-zA-Z]{2,}(/[a-zA-Z0-9-._~:/?#[\]@!$&'()*+,;=]*)?$"
     ).unwrap();
 }
 
+/// 开启或关闭严格安全模式，开启后不再接受 SVG 图片（默认关闭）
+pub fn set_strict_security(enabled: bool) {
+    STRICT_SECURITY.with(|strict| strict.set(enabled));
+}
+
+/// 是否处于严格安全模式
+pub fn is_strict_security() -> bool {
+    STRICT_SECURITY.with(Cell::get)
+}
+
+/// 当前允许的图片类型，严格安全模式下不包含 SVG
+pub fn accepted_image_types() -> Vec<&'static str> {
+    let strict = is_strict_security();
+    IMAGE_TYPES
+        .into_iter()
+        .filter(|typ| !strict || *typ != SVG_MIME_TYPE)
+        .collect()
+}
+
 /// 验证URL是否有效
 pub fn validate_url(url: &str) -> bool {
-    if url.starts_with("data:image/") {
-        return true;
+    if let Some(data) = url.strip_prefix("data:") {
+        let mime = data.split([';', ',']).next().unwrap_or_default();
+        return mime.starts_with("image/")
+            && !(is_strict_security() && mime.eq_ignore_ascii_case(SVG_MIME_TYPE));
     }
     URL_REGEX.is_match(url)
 }
 
+/// 生成可安全绑定到 `<img src>` 的地址
+///
+/// 仅接受站内相对路径、http(s) 地址及图片 data URL，其余协议（如 `javascript:`）
+/// 一律拒绝；引号、尖括号及空白等字符会做百分号编码。
+pub fn safe_image_src(url: &str) -> Option<String> {
+    let url = url.trim();
+    let is_relative = url.starts_with('/') && !url.starts_with("//") && !url.starts_with("/\\");
+    if !is_relative && !validate_url(url) {
+        return None;
+    }
+
+    let mut encoded = String::with_capacity(url.len());
+    for ch in url.chars() {
+        match ch {
+            '"' | '\'' | '<' | '>' | '`' | '\\' => encoded.push_str(&format!("%{:02X}", ch as u32)),
+            ch if ch.is_whitespace() || ch.is_control() => {
+                let mut buf = [0; 4];
+                for byte in ch.encode_utf8(&mut buf).bytes() {
+                    encoded.push_str(&format!("%{byte:02X}"));
+                }
+            }
+            ch => encoded.push(ch),
+        }
+    }
+    Some(encoded)
+}
+
 /// 清理输入文本，防止XSS攻击
 pub fn sanitize_input(input: &str) -> String {
     encode_text(input).to_string()
@@ -22,10 +79,7 @@ pub fn sanitize_input(input: &str) -> String {
 
 /// 验证文件类型
 pub fn validate_file_type(file_type: &str) -> bool {
-    matches!(
-        file_type,
-        "image/jpeg" | "image/png" | "image/svg+xml" | "image/gif"
-    )
+    accepted_image_types().contains(&file_type)
 }
 
 /// 验证文件大小
@@ -33,6 +87,28 @@ pub fn validate_file_size(size: usize, max_size: usize) -> bool {
     size <= max_size
 }
 
+/// 验证 data URL 解码后的字节数不超过上限，非 data URL 不受此限制
+///
+/// base64 编码按编码长度推算解码后的大小，其余按百分号编码计算。
+pub fn validate_data_url_size(url: &str, max_bytes: usize) -> bool {
+    let Some(data) = url.strip_prefix("data:") else {
+        return true;
+    };
+    let Some((header, payload)) = data.split_once(',') else {
+        return false;
+    };
+
+    let decoded_len = if header.ends_with(";base64") {
+        let payload = payload.trim_end();
+        let padding = payload.bytes().rev().take_while(|&ch| ch == b'=').count().min(2);
+        ((payload.len() / 4) * 3 + (payload.len() % 4).saturating_sub(1)).saturating_sub(padding)
+    } else {
+        payload.len().saturating_sub(payload.matches('%').count() * 2)
+    };
+
+    decoded_len <= max_bytes
+}
+
 /// 验证标题长度
 pub fn validate_title_length(title: &str, max_length: usize) -> bool {
     title.len() <= max_length
@@ -72,12 +148,74 @@ mod tests {
         assert!(!validate_file_type("application/pdf"));
     }
 
+    #[test]
+    fn test_strict_security_rejects_svg() {
+        let svg = "data:image/svg+xml;base64,PHN2Zz48L3N2Zz4=";
+        assert!(!is_strict_security());
+        assert!(validate_url(svg));
+        assert!(validate_file_type("image/svg+xml"));
+
+        set_strict_security(true);
+        assert!(!validate_url(svg));
+        assert!(!validate_url("data:IMAGE/SVG+XML,%3Csvg%3E%3C/svg%3E"));
+        assert!(validate_url("data:image/png;base64,abc123"));
+        assert!(!validate_file_type("image/svg+xml"));
+        assert!(validate_file_type("image/png"));
+        assert_eq!(
+            accepted_image_types(),
+            vec!["image/jpeg", "image/png", "image/gif"]
+        );
+
+        set_strict_security(false);
+        assert!(validate_url(svg));
+        assert!(validate_file_type("image/svg+xml"));
+    }
+
+    #[test]
+    fn test_safe_image_src() {
+        assert_eq!(
+            safe_image_src("https://example.com/logo.png").as_deref(),
+            Some("https://example.com/logo.png")
+        );
+        assert_eq!(safe_image_src("/logo.svg").as_deref(), Some("/logo.svg"));
+        assert_eq!(
+            safe_image_src("/a logo\"onerror=\"x.png").as_deref(),
+            Some("/a%20logo%22onerror=%22x.png")
+        );
+        assert!(safe_image_src("data:image/png;base64,abc123").is_some());
+        assert_eq!(safe_image_src("javascript:alert(1)"), None);
+        assert_eq!(safe_image_src(" JavaScript:alert(1)"), None);
+        assert_eq!(safe_image_src("data:text/html,<script>alert(1)</script>"), None);
+        assert_eq!(safe_image_src("//evil.example/logo.png"), None);
+        assert_eq!(safe_image_src("/\\evil.example/logo.png"), None);
+    }
+
     #[test]
     fn test_validate_file_size() {
         assert!(validate_file_size(1024, 2048));
         assert!(!validate_file_size(2048, 1024));
     }
 
+    #[test]
+    fn test_validate_data_url_size() {
+        // 12 字节解码后的数据
+        let url = "data:image/png;base64,aGVsbG8gd29ybGQh";
+        assert!(validate_data_url_size(url, 12));
+        assert!(!validate_data_url_size(url, 11));
+
+        // 带填充：11 字节
+        let url = "data:image/png;base64,aGVsbG8gd29ybGQ=";
+        assert!(validate_data_url_size(url, 11));
+        assert!(!validate_data_url_size(url, 10));
+
+        let url = "data:image/svg+xml,%3Csvg%3E%3C/svg%3E";
+        assert!(validate_data_url_size(url, 11));
+        assert!(!validate_data_url_size(url, 10));
+
+        assert!(!validate_data_url_size("data:image/png;base64", 1024));
+        assert!(validate_data_url_size("https://example.com/logo.png", 0));
+    }
+
     #[test]
     fn test_validate_title_length() {
         assert!(validate_title_length("Short title", 20));